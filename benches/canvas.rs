@@ -0,0 +1,260 @@
+// The canvas benchmarks, run with
+// cargo bench --features testutil
+// or cargo bench --features testutil -- hit_testing for one group. Scenes
+// come from testutil, so they're the ones the property tests run over.
+use std::{hint::black_box, sync::Arc, thread};
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+#[cfg(feature = "render")]
+use kaleidoscope::render::{render, RenderConfig};
+#[cfg(feature = "spatial")]
+use kaleidoscope::spatial::IndexKind;
+use kaleidoscope::{
+    command::Session,
+    export,
+    storage::{EnumCanvas, SoaCanvas},
+    testutil::Scene,
+    Canvas, Coord, Rectangle,
+};
+
+fn add_remove(c: &mut Criterion) {
+    let shapes = Scene::of(10_000).shapes(1);
+    let full = || {
+        let mut canvas = Canvas::default();
+        for shape in &shapes {
+            canvas.add(shape.clone().into_object());
+        }
+        canvas
+    };
+    let mut group = c.benchmark_group("add_remove");
+    group.sample_size(20);
+    group.bench_function("add 10k shapes", |b| b.iter(|| black_box(full())));
+    group.bench_function("remove 10k shapes from the back", |b| {
+        b.iter_batched(
+            full,
+            |mut canvas| {
+                while let Some(&last) = canvas.ids().last() {
+                    canvas.remove(last);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("remove 10k shapes from the front", |b| {
+        b.iter_batched(
+            full,
+            |mut canvas| {
+                while let Some(&first) = canvas.ids().first() {
+                    canvas.remove(first);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn set_origin_contention(c: &mut Criterion) {
+    let canvas = Arc::new(Scene::of(1_000).canvas(1));
+    let ids = canvas.ids();
+    let mut group = c.benchmark_group("set_origin");
+    group.sample_size(20);
+    for threads in [1, 4, 16] {
+        // Every thread on the same few shapes, or each on its own
+        for (name, pick) in [
+            (
+                "8 shared shapes",
+                (|t, i| (t + i) % 8) as fn(usize, usize) -> usize,
+            ),
+            ("disjoint shapes", |t, i| t * 50 + i % 50),
+        ] {
+            group.bench_with_input(BenchmarkId::new(name, threads), &threads, |b, &threads| {
+                b.iter(|| {
+                    thread::scope(|scope| {
+                        for t in 0..threads {
+                            let (canvas, ids) = (&canvas, &ids);
+                            scope.spawn(move || {
+                                for i in 0..1_000 {
+                                    canvas
+                                        .set_origin(ids[pick(t, i)], Coord::new(i as f32, 0.0))
+                                        .unwrap();
+                                }
+                            });
+                        }
+                    })
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn total_area(c: &mut Criterion) {
+    let canvas = Scene::of(100_000).canvas(1);
+    let ids = canvas.ids();
+    let threads = thread::available_parallelism().map_or(4, |n| n.get());
+    let mut group = c.benchmark_group("total_area");
+    group.bench_function("serial, 100k shapes", |b| {
+        b.iter(|| black_box(canvas.total_area()))
+    });
+    group.bench_function(format!("{threads} threads, 100k shapes"), |b| {
+        b.iter(|| {
+            let chunk = ids.len().div_ceil(threads);
+            let total: f32 = thread::scope(|scope| {
+                let parts: Vec<_> = ids
+                    .chunks(chunk)
+                    .map(|ids| {
+                        let canvas = &canvas;
+                        scope.spawn(move || {
+                            ids.iter()
+                                .filter_map(|&id| canvas.get_area(id))
+                                .sum::<f32>()
+                        })
+                    })
+                    .collect();
+                parts.into_iter().map(|part| part.join().unwrap()).sum()
+            });
+            black_box(total)
+        })
+    });
+    group.finish();
+}
+
+fn storage(c: &mut Criterion) {
+    let shapes = Scene::of(100_000).shapes(1);
+    let mut canvas = Canvas::default();
+    for shape in shapes.clone() {
+        canvas.add(shape.into_object());
+    }
+    let mut enums: EnumCanvas = shapes.iter().cloned().collect();
+    let mut columns: SoaCanvas = shapes.into_iter().collect();
+    let mut group = c.benchmark_group("storage");
+    group.bench_function("Canvas total_area", |b| {
+        b.iter(|| black_box(canvas.total_area()))
+    });
+    group.bench_function("EnumCanvas total_area", |b| {
+        b.iter(|| black_box(enums.total_area()))
+    });
+    group.bench_function("SoaCanvas total_area", |b| {
+        b.iter(|| black_box(columns.total_area()))
+    });
+    let offset = Coord::new(1.0, 0.0);
+    group.bench_function("Canvas translate_all", |b| {
+        b.iter(|| canvas.translate_all(offset).unwrap())
+    });
+    group.bench_function("EnumCanvas translate_all", |b| {
+        b.iter(|| enums.translate_all(offset))
+    });
+    group.bench_function("SoaCanvas translate_all", |b| {
+        b.iter(|| columns.translate_all(offset))
+    });
+    group.finish();
+}
+
+#[cfg(feature = "spatial")]
+fn hit_testing(c: &mut Criterion) {
+    let scene = Scene::of(10_000);
+    let bounds = scene.bounds();
+    let mut canvas = scene.canvas(1);
+    let region = Rectangle::from_corners(Coord::new(-20.0, -20.0), Coord::new(20.0, 20.0));
+    let indexes = [
+        ("no index", None),
+        (
+            "quadtree",
+            Some(IndexKind::Quadtree {
+                bounds: bounds.clone(),
+            }),
+        ),
+        (
+            "spatial hash",
+            Some(IndexKind::SpatialHash { cell_size: 5.0 }),
+        ),
+    ];
+    let mut group = c.benchmark_group("hit_testing");
+    for (name, kind) in indexes {
+        canvas.set_spatial_index(kind);
+        group.bench_function(BenchmarkId::new("hit_test", name), |b| {
+            b.iter(|| black_box(canvas.hit_test(Coord::default())))
+        });
+        group.bench_function(BenchmarkId::new("query_region", name), |b| {
+            b.iter(|| black_box(canvas.query_region(&region)))
+        });
+        group.bench_function(BenchmarkId::new("nearest 10", name), |b| {
+            b.iter(|| black_box(canvas.nearest(Coord::default(), 10).unwrap()))
+        });
+    }
+    group.sample_size(10);
+    group.bench_function("raycast", |b| {
+        b.iter(|| black_box(canvas.raycast(bounds.min(), Coord::new(1.0, 1.0))))
+    });
+    group.bench_function("collisions", |b| b.iter(|| black_box(canvas.collisions())));
+    group.finish();
+}
+#[cfg(not(feature = "spatial"))]
+fn hit_testing(_: &mut Criterion) {}
+
+fn serialization(c: &mut Criterion) {
+    let script: String = (0..1_000)
+        .map(|i| match i % 3 {
+            0 => format!("add circle {} at {} {}\n", 1 + i % 5, i % 100, i / 100),
+            1 => format!("add rectangle 2 {} at {} {}\n", 1 + i % 4, i % 100, i / 100),
+            _ => format!("add triangle 3 4 at {} {}\n", i % 100, i / 100),
+        })
+        .collect();
+    let mut session = Session::new();
+    session.run(&script).unwrap();
+    let frame = Rectangle::from_corners(Coord::new(-10.0, -10.0), Coord::new(110.0, 20.0));
+    let mut group = c.benchmark_group("serialization");
+    group.bench_function("read a 1k shape scene script", |b| {
+        b.iter(|| Session::new().run(black_box(&script)).unwrap())
+    });
+    group.bench_function("write a 1k shape scene script", |b| {
+        b.iter(|| black_box(session.script().unwrap()))
+    });
+    group.bench_function("svg of 1k shapes", |b| {
+        b.iter(|| black_box(export::svg(&session.canvas, &frame)))
+    });
+    group.finish();
+}
+
+#[cfg(feature = "render")]
+fn rendering(c: &mut Criterion) {
+    let scene = Scene::of(10_000);
+    #[allow(unused_mut)]
+    let mut canvas = scene.canvas(1);
+    #[cfg(feature = "spatial")]
+    canvas.set_spatial_index(Some(IndexKind::SpatialHash { cell_size: 5.0 }));
+    let mut counts = vec![1, thread::available_parallelism().map_or(1, |n| n.get())];
+    counts.dedup();
+    let mut group = c.benchmark_group("render 1024x1024, 10k shapes");
+    group.sample_size(10);
+    for threads in counts {
+        let config = RenderConfig {
+            width: 1024,
+            height: 1024,
+            view: scene.bounds(),
+            threads,
+            ..Default::default()
+        };
+        group.bench_with_input(
+            BenchmarkId::new("threads", threads),
+            &config,
+            |b, config| b.iter(|| black_box(render(&canvas, config).unwrap())),
+        );
+    }
+    group.finish();
+}
+#[cfg(not(feature = "render"))]
+fn rendering(_: &mut Criterion) {}
+
+criterion_group!(
+    benches,
+    add_remove,
+    set_origin_contention,
+    total_area,
+    storage,
+    hit_testing,
+    serialization,
+    rendering
+);
+criterion_main!(benches);
@@ -0,0 +1,265 @@
+// Hammers one canvas from many threads at once, then checks nothing was
+// lost or left inconsistent
+// cargo run --release --example stress -- [--readers <n>] [--writers <n>]
+//     [--movers <n>] [--iterations <n>] [--seed <n>]
+// Readers query the canvas, writers add and remove shapes, and movers move
+// two shapes, one with symmetric copies, by reading their origin and
+// writing it back a step on with compare_and_set_origin, retrying when
+// another mover got there first. Every step has to land. They also move a
+// third shape the careless way, with set_origin, to show how many steps
+// that loses.
+use std::{
+    env,
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+    thread,
+    time::Instant,
+};
+
+#[cfg(feature = "spatial")]
+use kaleidoscope::spatial::IndexKind;
+use kaleidoscope::{
+    generator::{random_canvas, random_shape, GeneratorConfig, Rng},
+    prelude::*,
+    storage::ShapeKind,
+    symmetry::{Symmetry, SymmetryGroup},
+};
+
+const USAGE: &str = "usage: stress [--readers <n>] [--writers <n>] [--movers <n>] \
+                     [--iterations <n>] [--seed <n>]";
+
+struct Options {
+    readers: usize,
+    writers: usize,
+    movers: usize,
+    iterations: usize,
+    seed: u64,
+}
+
+fn options() -> Result<Options, String> {
+    let mut options = Options {
+        readers: 4,
+        writers: 2,
+        movers: 4,
+        iterations: 2_000,
+        seed: 1,
+    };
+    let args: Vec<String> = env::args().skip(1).collect();
+    for pair in args.chunks(2) {
+        let [flag, value] = pair else {
+            return Err(USAGE.into());
+        };
+        let number = || value.parse().map_err(|_| format!("bad number {value}"));
+        match flag.as_str() {
+            "--readers" => options.readers = number()?,
+            "--writers" => options.writers = number()?,
+            "--movers" => options.movers = number()?,
+            "--iterations" => options.iterations = number()?,
+            "--seed" => options.seed = number()? as u64,
+            _ => return Err(USAGE.into()),
+        }
+    }
+    Ok(options)
+}
+
+fn main() -> ExitCode {
+    let options = match options() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let failures = stress(&options);
+    for failure in &failures {
+        eprintln!("failed: {failure}");
+    }
+    if failures.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn circle(x: f32, y: f32) -> ShapeObject {
+    Arc::new(ShapeCell::new(Circle {
+        origin: Coord::new(x, y),
+        radius: 1.0,
+    }))
+}
+
+// Moves the shape up one by compare and set, however many tries it takes
+fn step(canvas: &Canvas, id: ShapeId) -> usize {
+    let mut tries = 1;
+    loop {
+        let origin = canvas.get(id).unwrap().lock().unwrap().origin();
+        // Widens the window for another mover to get in, which with few
+        // cores would otherwise hardly ever happen
+        thread::yield_now();
+        let moved = origin + Coord::new(0.0, 1.0);
+        if canvas.compare_and_set_origin(id, origin, moved).unwrap() {
+            return tries;
+        }
+        tries += 1;
+    }
+}
+
+fn stress(options: &Options) -> Vec<String> {
+    let config = GeneratorConfig {
+        count: 1_000,
+        ..GeneratorConfig::default()
+    };
+    let mut canvas = random_canvas(options.seed, &config);
+    #[cfg(feature = "spatial")]
+    canvas.set_spatial_index(Some(IndexKind::SpatialHash { cell_size: 5.0 }));
+    let plain = canvas.add(circle(0.0, 0.0));
+    let careless = canvas.add(circle(-10.0, 0.0));
+    canvas
+        .set_symmetry(Some(Symmetry {
+            group: SymmetryGroup::Dihedral(3),
+            center: Coord::default(),
+        }))
+        .unwrap();
+    let seed = canvas.add(circle(10.0, 0.0));
+    canvas.set_symmetry(None).unwrap();
+    let copies: Vec<ShapeId> = canvas.ids()[canvas.len() - 6..].to_vec();
+    let start = canvas.len();
+
+    let canvas = RwLock::new(canvas);
+    let (added, removed, retries) = (
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+    );
+    let bad_reads = AtomicUsize::new(0);
+    let began = Instant::now();
+    thread::scope(|scope| {
+        for t in 0..options.readers {
+            let (canvas, bad_reads) = (&canvas, &bad_reads);
+            scope.spawn(move || {
+                let mut rng = Rng::new(options.seed ^ 0x1000 ^ t as u64);
+                let bounds = Rectangle {
+                    origin: Coord::default(),
+                    side_a: 100.0,
+                    side_b: 100.0,
+                };
+                for _ in 0..options.iterations {
+                    let canvas = canvas.read().unwrap();
+                    let point = rng.point_in(&bounds);
+                    let region = Rectangle {
+                        origin: point,
+                        side_a: 10.0,
+                        side_b: 10.0,
+                    };
+                    let found = canvas
+                        .hit_test(point)
+                        .into_iter()
+                        .chain(canvas.query_region(&region))
+                        .all(|id| canvas.get(id).is_some());
+                    let area = canvas.total_area();
+                    if !found || !area.is_finite() || area < 0.0 {
+                        bad_reads.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+        for t in 0..options.writers {
+            let (canvas, added, removed) = (&canvas, &added, &removed);
+            scope.spawn(move || {
+                let mut rng = Rng::new(options.seed ^ 0x2000 ^ t as u64);
+                let mut own = Vec::new();
+                for _ in 0..options.iterations {
+                    let mut canvas = canvas.write().unwrap();
+                    if own.is_empty() || rng.below(3) > 0 {
+                        let kind = [ShapeKind::Circle, ShapeKind::Rectangle, ShapeKind::Triangle]
+                            [rng.below(3)];
+                        let origin = Coord::new(rng.range(-50.0..50.0), rng.range(-50.0..50.0));
+                        let shape = random_shape(&mut rng, kind, origin, 1.0..5.0);
+                        own.push(canvas.add(shape.into_object()));
+                        added.fetch_add(1, Ordering::Relaxed);
+                    } else {
+                        let id = own.swap_remove(rng.below(own.len()));
+                        if canvas.remove(id).is_some() {
+                            removed.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                }
+            });
+        }
+        for _ in 0..options.movers {
+            let (canvas, retries) = (&canvas, &retries);
+            scope.spawn(move || {
+                // A batch of steps each time the writers let them in, so
+                // the movers race each other
+                for batch in (0..options.iterations).collect::<Vec<_>>().chunks(100) {
+                    let canvas = canvas.read().unwrap();
+                    for _ in batch {
+                        let tries = step(&canvas, plain) + step(&canvas, seed);
+                        retries.fetch_add(tries - 2, Ordering::Relaxed);
+                        let origin = canvas.get(careless).unwrap().lock().unwrap().origin();
+                        thread::yield_now();
+                        canvas
+                            .set_origin(careless, origin + Coord::new(0.0, 1.0))
+                            .unwrap();
+                    }
+                }
+            });
+        }
+    });
+    let elapsed = began.elapsed();
+
+    let canvas = canvas.into_inner().unwrap();
+    let (added, removed) = (added.into_inner(), removed.into_inner());
+    let steps = (options.movers * options.iterations) as f32;
+    let origin = |id| canvas.get(id).unwrap().lock().unwrap().origin();
+    println!(
+        "{} readers, {} writers and {} movers, {} iterations each, in {elapsed:.2?}",
+        options.readers, options.writers, options.movers, options.iterations
+    );
+    println!(
+        "{added} shapes added and {removed} removed, {} compare and set retries",
+        retries.into_inner()
+    );
+    println!(
+        "careless moves lost {} of {steps} steps",
+        steps - origin(careless).y
+    );
+
+    let mut failures = Vec::new();
+    if canvas.len() != start + added - removed {
+        failures.push(format!(
+            "{} shapes, expected {}",
+            canvas.len(),
+            start + added - removed
+        ));
+    }
+    let mut ids = canvas.ids().to_vec();
+    ids.sort();
+    ids.dedup();
+    if ids.len() != canvas.len() {
+        failures.push("ids repeated".into());
+    }
+    for (name, id, x) in [("plain", plain, 0.0), ("symmetric", seed, 10.0)] {
+        if origin(id) != Coord::new(x, steps) {
+            failures.push(format!(
+                "{name} shape at {:?}, expected ({x}, {steps})",
+                origin(id)
+            ));
+        }
+    }
+    // Copies of the symmetric shape all the same distance from the centre
+    let radius = origin(seed).length();
+    if let Some(&copy) = copies
+        .iter()
+        .find(|&&copy| (origin(copy).length() - radius).abs() > 1e-3 * radius)
+    {
+        failures.push(format!("copy at {:?} out of step", origin(copy)));
+    }
+    if bad_reads.into_inner() > 0 {
+        failures.push("readers saw missing shapes or bad areas".into());
+    }
+    failures
+}
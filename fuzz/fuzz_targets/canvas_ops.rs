@@ -0,0 +1,6 @@
+// Canvas operations read from the bytes, checking the canvas after each
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| kaleidoscope::fuzz::canvas_ops(data));
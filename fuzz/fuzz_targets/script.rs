@@ -0,0 +1,6 @@
+// Scene scripts, run whole and line by line, must read back as written out
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| kaleidoscope::fuzz::script(data));
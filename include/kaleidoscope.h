@@ -0,0 +1,52 @@
+/* C interface to the kaleidoscope canvas, built with the ffi feature.
+ * Kept in step with src/ffi.rs by its tests. */
+#ifndef KALEIDOSCOPE_H
+#define KALEIDOSCOPE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Status codes. Results are written through out pointers only on success. */
+#define KALEIDO_OK 0
+#define KALEIDO_NULL_POINTER 1
+#define KALEIDO_INVALID_SHAPE 2
+#define KALEIDO_UNKNOWN_SHAPE 3
+#define KALEIDO_INVALID_ARGUMENT 4
+#define KALEIDO_IO 5
+#define KALEIDO_POISONED 6
+#define KALEIDO_LOCKED 7
+
+typedef struct KaleidoCanvas KaleidoCanvas;
+
+/* A static string, never freed */
+const char *kaleido_error_message(int32_t code);
+
+KaleidoCanvas *kaleido_canvas_new(void);
+void kaleido_canvas_free(KaleidoCanvas *canvas);
+
+int32_t kaleido_canvas_add_circle(KaleidoCanvas *canvas, float x, float y, float radius, uint64_t *id);
+int32_t kaleido_canvas_add_rectangle(KaleidoCanvas *canvas, float x, float y, float side_a, float side_b, uint64_t *id);
+int32_t kaleido_canvas_add_triangle(KaleidoCanvas *canvas, float x, float y, float base, float height, uint64_t *id);
+int32_t kaleido_canvas_remove(KaleidoCanvas *canvas, uint64_t id);
+
+/* Moves the shape, along with any symmetric copies */
+int32_t kaleido_canvas_set_origin(const KaleidoCanvas *canvas, uint64_t id, float x, float y);
+int32_t kaleido_canvas_translate_all(KaleidoCanvas *canvas, float dx, float dy);
+
+size_t kaleido_canvas_len(const KaleidoCanvas *canvas);
+int32_t kaleido_canvas_area(const KaleidoCanvas *canvas, uint64_t id, float *area);
+float kaleido_canvas_total_area(const KaleidoCanvas *canvas);
+
+/* Only when built with the render feature. Renders the part of the canvas
+ * between the two corners, y up, into a width by height PNG. */
+int32_t kaleido_canvas_save_png(const KaleidoCanvas *canvas, const char *path, uint32_t width, uint32_t height, float min_x, float min_y, float max_x, float max_y);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,547 @@
+use std::time::Instant;
+
+use crate::{
+    arena::ShapeId,
+    builder::{coordinate, dimension},
+    error::{CanvasError, ShapeError},
+    transform::Transform,
+    Canvas, Coord, ShapeObject, Style,
+};
+
+// Spins a whole canvas about a pivot at a fixed angular velocity
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spin {
+    pub pivot: Coord,
+    // Radians per tick, counter-clockwise
+    pub angular_velocity: f32,
+    // Total rotation applied so far
+    pub angle: f32,
+}
+
+impl Spin {
+    pub fn new(pivot: Coord, angular_velocity: f32) -> Spin {
+        Spin {
+            pivot,
+            angular_velocity,
+            angle: 0.0,
+        }
+    }
+
+    pub fn tick(&mut self, canvas: &mut Canvas) -> Result<(), CanvasError> {
+        canvas.rotate_all(self.angular_velocity, self.pivot)?;
+        self.angle += self.angular_velocity;
+        Ok(())
+    }
+
+    // Calls on_frame with the tick number after each tick, stopping at the
+    // first that fails
+    pub fn run(
+        &mut self,
+        canvas: &mut Canvas,
+        ticks: u32,
+        mut on_frame: impl FnMut(&Canvas, u32),
+    ) -> Result<(), CanvasError> {
+        for tick in 0..ticks {
+            self.tick(canvas)?;
+            on_frame(canvas, tick);
+        }
+        Ok(())
+    }
+}
+
+// How the time between two keyframes is mapped onto the way between their
+// values, both from 0 to 1
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    // Jumps to the next value when its keyframe is reached
+    Hold,
+}
+
+impl Easing {
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+            Easing::Hold => {
+                if t < 1.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
+}
+
+// Values that can be interpolated between keyframes
+pub trait Tween: Copy {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+impl Tween for f32 {
+    fn lerp(self, other: f32, t: f32) -> f32 {
+        self + (other - self) * t
+    }
+}
+impl Tween for Coord {
+    fn lerp(self, other: Coord, t: f32) -> Coord {
+        self + (other - self) * t
+    }
+}
+
+// A value at a time in seconds, reached from the keyframe before with its
+// easing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe<T> {
+    pub time: f32,
+    pub value: T,
+    pub easing: Easing,
+}
+
+// Keyframes kept in time order. Before the first and after the last the
+// value is held.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Track<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T> Default for Track<T> {
+    fn default() -> Track<T> {
+        Track {
+            keyframes: Vec::new(),
+        }
+    }
+}
+
+impl<T: Tween> Track<T> {
+    pub fn new() -> Track<T> {
+        Track::default()
+    }
+    // Keyframes at the same time as one already there go after it
+    pub fn key(mut self, time: f32, value: T, easing: Easing) -> Track<T> {
+        let index = self.keyframes.partition_point(|k| k.time <= time);
+        self.keyframes.insert(
+            index,
+            Keyframe {
+                time,
+                value,
+                easing,
+            },
+        );
+        self
+    }
+    pub fn keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+    // Time of the last keyframe
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        let (Some(before), after) = (
+            next.checked_sub(1).map(|i| &self.keyframes[i]),
+            self.keyframes.get(next),
+        ) else {
+            return self.keyframes.first().map(|k| k.value);
+        };
+        Some(match after {
+            Some(after) => {
+                let t = (time - before.time) / (after.time - before.time);
+                before.value.lerp(after.value, after.easing.apply(t))
+            }
+            None => before.value,
+        })
+    }
+}
+
+// Keyframed properties of one shape. The origin moves the shape, the scale
+// and rotation (in radians, counter-clockwise) are about its origin and
+// relative to how it was when animated, and the opacity replaces the one in
+// its style. Properties without keyframes are left alone.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShapeAnimation {
+    pub origin: Track<Coord>,
+    pub scale: Track<f32>,
+    pub rotation: Track<f32>,
+    pub opacity: Track<f32>,
+}
+
+impl ShapeAnimation {
+    pub fn duration(&self) -> f32 {
+        [
+            self.origin.duration(),
+            self.scale.duration(),
+            self.rotation.duration(),
+            self.opacity.duration(),
+        ]
+        .into_iter()
+        .fold(0.0, f32::max)
+    }
+
+    fn validate(&self) -> Result<(), CanvasError> {
+        let times = self
+            .origin
+            .keyframes()
+            .iter()
+            .map(|k| k.time)
+            .chain(self.scale.keyframes().iter().map(|k| k.time))
+            .chain(self.rotation.keyframes().iter().map(|k| k.time))
+            .chain(self.opacity.keyframes().iter().map(|k| k.time));
+        for time in times {
+            if !(time.is_finite() && time >= 0.0) {
+                return Err(ShapeError::InvalidDimension {
+                    name: "keyframe time",
+                    value: time,
+                }
+                .into());
+            }
+        }
+        for key in self.origin.keyframes() {
+            coordinate("origin", key.value)?;
+        }
+        for key in self.scale.keyframes() {
+            dimension("scale", Some(key.value))?;
+        }
+        for key in self.rotation.keyframes() {
+            if !key.value.is_finite() {
+                return Err(ShapeError::InvalidDimension {
+                    name: "rotation",
+                    value: key.value,
+                }
+                .into());
+            }
+        }
+        for key in self.opacity.keyframes() {
+            if !(0.0..=1.0).contains(&key.value) {
+                return Err(CanvasError::InvalidOpacity(key.value));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Plays shape animations on a canvas. Animated shapes are replaced by their
+// image at each step, the way group members are, so rotated or unevenly
+// scaled ones become polygons, and symmetric copies don't follow.
+#[derive(Clone, Default)]
+pub struct Timeline {
+    time: f32,
+    looping: bool,
+    // The shape as it was when animated, which every frame starts from
+    animations: Vec<(ShapeId, ShapeObject, ShapeAnimation)>,
+}
+
+impl Timeline {
+    pub fn new() -> Timeline {
+        Timeline::default()
+    }
+
+    // Replaces any animation the shape already had
+    pub fn animate(
+        &mut self,
+        canvas: &Canvas,
+        id: ShapeId,
+        animation: ShapeAnimation,
+    ) -> Result<(), CanvasError> {
+        animation.validate()?;
+        let base = match self.animations.iter().find(|(other, ..)| *other == id) {
+            Some((_, base, _)) => base.clone(),
+            None => canvas
+                .get(id)
+                .ok_or(CanvasError::UnknownShape(id))?
+                .lock()?
+                .duplicate(),
+        };
+        self.animations.retain(|(other, ..)| *other != id);
+        self.animations.push((id, base, animation));
+        Ok(())
+    }
+    pub fn stop(&mut self, id: ShapeId) {
+        self.animations.retain(|(other, ..)| *other != id);
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+    // When the last keyframe of any animation is reached
+    pub fn duration(&self) -> f32 {
+        self.animations
+            .iter()
+            .map(|(_, _, animation)| animation.duration())
+            .fold(0.0, f32::max)
+    }
+    // Whether time wraps round to the start after the duration
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.time >= self.duration()
+    }
+
+    // Moves time on by dt seconds and updates the canvas to match
+    pub fn advance(&mut self, canvas: &mut Canvas, dt: f32) -> Result<(), CanvasError> {
+        let mut time = self.time + dt;
+        let duration = self.duration();
+        if self.looping && duration > 0.0 {
+            time %= duration;
+        }
+        self.seek(canvas, time)
+    }
+
+    // Puts every animated shape where it is at the given time
+    pub fn seek(&mut self, canvas: &mut Canvas, time: f32) -> Result<(), CanvasError> {
+        self.time = time.max(0.0);
+        for (id, base, animation) in &self.animations {
+            let base = base.lock()?;
+            let start = base.origin();
+            let origin = animation.origin.sample(self.time).unwrap_or(start);
+            let scale = animation.scale.sample(self.time).unwrap_or(1.0);
+            let rotation = animation.rotation.sample(self.time).unwrap_or(0.0);
+            let transform = Transform::translation(origin - start).after(
+                &Transform::rotation(rotation)
+                    .after(&Transform::scale(scale, scale))
+                    .about(start),
+            );
+            canvas.unlocked(*id)?;
+            let shape = canvas.shapes.get_mut(*id).unwrap();
+            *shape = transform.image(&*base);
+            if let Some(opacity) = animation.opacity.sample(self.time) {
+                let style = canvas.style(*id).unwrap_or_default();
+                canvas.store_style(*id, Style { opacity, ..style });
+            }
+            canvas.debug_check(canvas.get(*id));
+        }
+        Ok(())
+    }
+}
+
+// Runs a simulation at a fixed rate however unevenly time passes between
+// frames. Elapsed time is banked and spent a whole step at a time; what's
+// left is returned as a fraction of a step, for drawing a blend of the last
+// two states with Tween::lerp.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SimLoop {
+    dt: f32,
+    accumulator: f32,
+    // Bound on steps per update, so a stalled frame can't snowball into
+    // ever longer catch ups. Time past it is dropped.
+    max_steps: u32,
+    steps: u64,
+}
+
+impl SimLoop {
+    // Steps per second
+    pub fn new(rate: f32) -> Result<SimLoop, ShapeError> {
+        let rate = dimension("rate", Some(rate))?;
+        Ok(SimLoop {
+            dt: 1.0 / rate,
+            accumulator: 0.0,
+            max_steps: 8,
+            steps: 0,
+        })
+    }
+    pub fn set_max_steps(&mut self, max_steps: u32) {
+        self.max_steps = max_steps.max(1);
+    }
+
+    // Seconds per step
+    pub fn dt(&self) -> f32 {
+        self.dt
+    }
+    // Steps taken so far
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+    // How far into the next step the banked time reaches, from 0 to 1
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.dt
+    }
+
+    // Banks elapsed seconds, takes the steps they pay for and returns alpha
+    pub fn update(
+        &mut self,
+        canvas: &mut Canvas,
+        elapsed: f32,
+        mut step: impl FnMut(f32, &mut Canvas),
+    ) -> f32 {
+        self.accumulator += elapsed.max(0.0);
+        let mut taken = 0;
+        while self.accumulator >= self.dt {
+            if taken == self.max_steps {
+                self.accumulator %= self.dt;
+                break;
+            }
+            step(self.dt, canvas);
+            self.accumulator -= self.dt;
+            self.steps += 1;
+            taken += 1;
+        }
+        self.alpha()
+    }
+
+    // Steps in real time, calling on_frame with alpha after every update,
+    // until it returns false
+    pub fn run(
+        &mut self,
+        canvas: &mut Canvas,
+        mut step: impl FnMut(f32, &mut Canvas),
+        mut on_frame: impl FnMut(&Canvas, f32) -> bool,
+    ) {
+        let mut last = Instant::now();
+        loop {
+            let now = Instant::now();
+            let alpha = self.update(canvas, (now - last).as_secs_f32(), &mut step);
+            last = now;
+            if !on_frame(canvas, alpha) {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f32::consts::PI, sync::Arc};
+
+    use super::*;
+    use crate::{assert_approx_eq, Rectangle, ShapeCell, Triangle};
+
+    #[test]
+    fn full_turn_returns_to_start() {
+        let mut canvas = Canvas::default();
+        let id = canvas.add(Arc::new(ShapeCell::new(Triangle {
+            origin: Coord::new(3.0, 1.0),
+            base: 2.0,
+            height: 1.0,
+        })));
+        let before = canvas.get(id).unwrap().lock().unwrap().outline();
+
+        let mut spin = Spin::new(Coord::default(), PI / 30.0);
+        let mut frames = 0;
+        spin.run(&mut canvas, 60, |_, _| frames += 1).unwrap();
+        assert_eq!(frames, 60);
+        assert_approx_eq!(spin.angle, 2.0 * PI);
+
+        let after = canvas.get(id).unwrap().lock().unwrap().outline();
+        assert!(before.distance(&after) == 0.0);
+        assert_approx_eq!(canvas.get_area(id).unwrap(), 1.0);
+        let bounds = (before.bounding_box(), after.bounding_box());
+        assert!(bounds.0.min().distance(bounds.1.min()) < 1e-3);
+        assert!(bounds.0.max().distance(bounds.1.max()) < 1e-3);
+    }
+
+    #[test]
+    fn easing_between_keyframes() {
+        let track = Track::new()
+            .key(2.0, 10.0, Easing::Linear)
+            .key(0.0, 0.0, Easing::Linear)
+            .key(4.0, 0.0, Easing::EaseIn)
+            .key(6.0, 5.0, Easing::Hold);
+        assert_eq!(track.duration(), 6.0);
+        assert_eq!(track.sample(-1.0), Some(0.0));
+        assert_eq!(track.sample(1.0), Some(5.0));
+        assert_eq!(track.sample(3.0), Some(7.5));
+        assert_eq!(track.sample(5.9), Some(0.0));
+        assert_eq!(track.sample(9.0), Some(5.0));
+        assert_eq!(Track::<f32>::new().sample(1.0), None);
+        assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+        assert_eq!(Easing::EaseOut.apply(0.5), 0.75);
+    }
+
+    #[test]
+    fn timeline_drives_canvas() {
+        let mut canvas = Canvas::default();
+        let id = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::new(1.0, 1.0),
+            side_a: 2.0,
+            side_b: 2.0,
+        })));
+        let animation = ShapeAnimation {
+            origin: Track::new()
+                .key(0.0, Coord::new(1.0, 1.0), Easing::Linear)
+                .key(1.0, Coord::new(5.0, 1.0), Easing::Linear),
+            scale: Track::new()
+                .key(0.0, 1.0, Easing::Linear)
+                .key(1.0, 2.0, Easing::Linear),
+            rotation: Track::new()
+                .key(0.0, 0.0, Easing::Linear)
+                .key(1.0, PI / 2.0, Easing::Linear),
+            opacity: Track::new()
+                .key(0.0, 1.0, Easing::Linear)
+                .key(1.0, 0.0, Easing::Linear),
+        };
+        let mut timeline = Timeline::new();
+        timeline.animate(&canvas, id, animation.clone()).unwrap();
+        assert_eq!(timeline.duration(), 1.0);
+
+        timeline.advance(&mut canvas, 0.5).unwrap();
+        let origin = canvas.get(id).unwrap().lock().unwrap().origin();
+        assert!(origin.distance(Coord::new(3.0, 1.0)) < 1e-5);
+        assert_approx_eq!(canvas.get_area(id).unwrap(), 4.0 * 1.5 * 1.5);
+        assert_eq!(canvas.style(id).unwrap().opacity, 0.5);
+
+        // Frames are worked out from the original shape, not the last frame
+        timeline.advance(&mut canvas, 0.5).unwrap();
+        assert!(timeline.is_finished());
+        assert_approx_eq!(canvas.get_area(id).unwrap(), 16.0);
+        let bounds = canvas.bounding_box(id).unwrap();
+        assert!(bounds.min().distance(Coord::new(3.0, -1.0)) < 1e-4);
+
+        timeline.set_looping(true);
+        timeline.advance(&mut canvas, 1.25).unwrap();
+        assert_eq!(timeline.time(), 0.25);
+        assert!(!timeline.is_finished());
+
+        let bad = ShapeAnimation {
+            scale: Track::new().key(0.0, 0.0, Easing::Linear),
+            ..animation
+        };
+        assert!(timeline.animate(&canvas, id, bad).is_err());
+        canvas.remove(id);
+        assert!(timeline.advance(&mut canvas, 0.1).is_err());
+    }
+
+    #[test]
+    fn fixed_steps() {
+        let mut canvas = Canvas::default();
+        let mut sim = SimLoop::new(10.0).unwrap();
+        let mut total = 0.0;
+        let alpha = sim.update(&mut canvas, 0.25, |dt, _| total += dt);
+        assert_eq!(sim.steps(), 2);
+        assert_approx_eq!(total, 0.2);
+        assert_approx_eq!(alpha, 0.5);
+        // The banked half step makes up a whole one with the next
+        sim.update(&mut canvas, 0.06, |_, _| {});
+        assert_eq!(sim.steps(), 3);
+
+        // A long stall is capped rather than caught up on
+        sim.set_max_steps(4);
+        sim.update(&mut canvas, 10.0, |_, _| {});
+        assert_eq!(sim.steps(), 7);
+        assert!(sim.alpha() < 1.0);
+        assert!(SimLoop::new(0.0).is_err());
+
+        let mut frames = 0;
+        sim.run(
+            &mut canvas,
+            |_, _| {},
+            |_, alpha| {
+                assert!((0.0..1.0).contains(&alpha));
+                frames += 1;
+                frames < 3
+            },
+        );
+        assert_eq!(frames, 3);
+    }
+}
@@ -0,0 +1,75 @@
+// Float comparison for geometry that goes through trig, square roots or
+// transforms, where results land a few ulps from the exact value. Values
+// are compared relative to the larger of the two, and absolutely below one
+// so that results near zero aren't held to a vanishing tolerance.
+
+pub const EPSILON: f32 = 1e-5;
+
+pub fn within(a: f32, b: f32, epsilon: f32) -> bool {
+    a == b || (a - b).abs() <= epsilon * a.abs().max(b.abs()).max(1.0)
+}
+
+pub fn eq(a: f32, b: f32) -> bool {
+    within(a, b, EPSILON)
+}
+
+// assert_approx_eq!(a, b) or assert_approx_eq!(a, b, epsilon), with an
+// optional message after the epsilon
+#[macro_export]
+macro_rules! assert_approx_eq {
+    ($a:expr, $b:expr $(,)?) => {
+        $crate::assert_approx_eq!($a, $b, $crate::approx::EPSILON)
+    };
+    ($a:expr, $b:expr, $epsilon:expr $(,)?) => {{
+        let (a, b): (f32, f32) = ($a, $b);
+        assert!(
+            $crate::approx::within(a, b, $epsilon),
+            "{} is {a}, not approximately {b}",
+            stringify!($a),
+        );
+    }};
+    ($a:expr, $b:expr, $epsilon:expr, $($message:tt)+) => {{
+        let (a, b): (f32, f32) = ($a, $b);
+        assert!(
+            $crate::approx::within(a, b, $epsilon),
+            "{} is {a}, not approximately {b}: {}",
+            stringify!($a),
+            format_args!($($message)+),
+        );
+    }};
+}
+
+// A shape's area against the expected value, with an optional epsilon
+#[macro_export]
+macro_rules! assert_area_eq {
+    ($shape:expr, $area:expr $(, $epsilon:expr)? $(,)?) => {
+        $crate::assert_approx_eq!($crate::Shape::get_area(&$shape), $area $(, $epsilon)?)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Circle, Coord};
+
+    #[test]
+    fn approximate_comparison() {
+        assert!(eq(0.1 + 0.2, 0.3));
+        assert!(eq(1e6, 1e6 + 5.0));
+        assert!(!eq(1e6, 1e6 + 50.0));
+        assert!(eq(1e-7, 0.0));
+        assert!(!eq(1.0, 1.001));
+        assert!(within(1.0, 1.001, 1e-2));
+        assert!(!eq(f32::NAN, f32::NAN));
+        assert!(eq(f32::INFINITY, f32::INFINITY));
+
+        let circle = Circle {
+            origin: Coord::new(3.0, -1.0),
+            radius: 2.0,
+        };
+        assert_area_eq!(circle, 4.0 * core::f32::consts::PI);
+        assert_approx_eq!((2.0f32).sqrt().powi(2), 2.0);
+        let result = std::panic::catch_unwind(|| assert_approx_eq!(1.0, 1.1, 1e-3, "step {}", 3));
+        assert!(result.is_err());
+    }
+}
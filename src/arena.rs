@@ -0,0 +1,234 @@
+use alloc::vec::Vec;
+
+// Handle to a value in an Arena. It stays valid until that value is removed
+// and is never mistaken for whatever later reuses the slot, since each reuse
+// bumps the slot's generation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ShapeId {
+    index: u32,
+    generation: u32,
+}
+
+impl ShapeId {
+    // Stable for as long as the id is live, for side tables indexed by id
+    pub fn slot(&self) -> usize {
+        self.index as usize
+    }
+
+    // Packed into one integer, for handing across language boundaries
+    pub fn to_bits(self) -> u64 {
+        (self.generation as u64) << 32 | self.index as u64
+    }
+    pub fn from_bits(bits: u64) -> ShapeId {
+        ShapeId {
+            index: bits as u32,
+            generation: (bits >> 32) as u32,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Slot {
+    generation: u32,
+    // Where the value is in the dense arrays, None while the slot is free
+    position: Option<usize>,
+}
+
+// Generational arena keeping values densely packed in insertion order, so
+// iteration never visits removed values and order (painting order, for a
+// canvas) survives removals. Freed slots are reused by later inserts.
+#[derive(Clone, Debug)]
+pub struct Arena<T> {
+    values: Vec<T>,
+    ids: Vec<ShapeId>,
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+    // Bumped by anything that might change which value is where
+    edits: u64,
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Arena<T> {
+        Arena {
+            values: Vec::new(),
+            ids: Vec::new(),
+            slots: Vec::new(),
+            free: Vec::new(),
+            edits: 0,
+        }
+    }
+}
+
+impl<T> Arena<T> {
+    pub fn insert(&mut self, value: T) -> ShapeId {
+        self.edits += 1;
+        let position = self.values.len();
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    position: None,
+                });
+                (self.slots.len() - 1) as u32
+            }
+        };
+        let slot = &mut self.slots[index as usize];
+        slot.position = Some(position);
+        let id = ShapeId {
+            index,
+            generation: slot.generation,
+        };
+        self.values.push(value);
+        self.ids.push(id);
+        id
+    }
+
+    // Keeps the order of the values after it, so takes time linear in them
+    pub fn remove(&mut self, id: ShapeId) -> Option<T> {
+        let position = self.position(id)?;
+        self.edits += 1;
+        let slot = &mut self.slots[id.slot()];
+        slot.position = None;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(id.index);
+        self.ids.remove(position);
+        for later in &self.ids[position..] {
+            if let Some(p) = &mut self.slots[later.slot()].position {
+                *p -= 1;
+            }
+        }
+        Some(self.values.remove(position))
+    }
+
+    // Compacts in one pass, keeping the order of what's left
+    pub fn retain(&mut self, mut keep: impl FnMut(ShapeId, &T) -> bool) {
+        let mut kept = 0;
+        for position in 0..self.values.len() {
+            let id = self.ids[position];
+            let slot = &mut self.slots[id.slot()];
+            if keep(id, &self.values[position]) {
+                self.values.swap(kept, position);
+                self.ids.swap(kept, position);
+                slot.position = Some(kept);
+                kept += 1;
+            } else {
+                slot.position = None;
+                slot.generation = slot.generation.wrapping_add(1);
+                self.free.push(id.index);
+            }
+        }
+        if kept < self.values.len() {
+            self.edits += 1;
+            self.values.truncate(kept);
+            self.ids.truncate(kept);
+        }
+    }
+
+    pub fn get(&self, id: ShapeId) -> Option<&T> {
+        self.position(id).map(|position| &self.values[position])
+    }
+    pub fn get_mut(&mut self, id: ShapeId) -> Option<&mut T> {
+        self.edits += 1;
+        self.position(id).map(|position| &mut self.values[position])
+    }
+    pub fn contains(&self, id: ShapeId) -> bool {
+        self.position(id).is_some()
+    }
+    // How many values come before this one
+    pub fn position(&self, id: ShapeId) -> Option<usize> {
+        let slot = self.slots.get(id.slot())?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        slot.position
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+    // In insertion order
+    pub fn ids(&self) -> &[ShapeId] {
+        &self.ids
+    }
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+    pub fn values_mut(&mut self) -> &mut [T] {
+        self.edits += 1;
+        &mut self.values
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (ShapeId, &T)> {
+        self.ids.iter().copied().zip(&self.values)
+    }
+    // Changes whenever values are added, removed or replaced, so observers
+    // can tell cheaply whether they need to look again
+    pub fn edits(&self) -> u64 {
+        self.edits
+    }
+    // Slots allocated so far, an upper bound on every id's slot
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+    // Bytes allocated for the arena's own arrays, not counting anything the
+    // values point to
+    pub fn heap_bytes(&self) -> usize {
+        use core::mem::size_of;
+        self.values.capacity() * size_of::<T>()
+            + self.ids.capacity() * size_of::<ShapeId>()
+            + self.slots.capacity() * size_of::<Slot>()
+            + self.free.capacity() * size_of::<u32>()
+    }
+}
+
+impl<T> FromIterator<T> for Arena<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(values: I) -> Arena<T> {
+        let mut arena = Arena::default();
+        for value in values {
+            arena.insert(value);
+        }
+        arena
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_survive_removals() {
+        let mut arena: Arena<char> = "abcd".chars().collect();
+        let ids = arena.ids().to_vec();
+
+        assert_eq!(arena.remove(ids[1]), Some('b'));
+        assert_eq!(arena.remove(ids[1]), None);
+        assert_eq!(arena.get(ids[2]), Some(&'c'));
+        assert_eq!(arena.position(ids[3]), Some(2));
+
+        // The freed slot is reused, but the old id doesn't see the new value
+        let e = arena.insert('e');
+        assert_eq!(e.slot(), ids[1].slot());
+        assert_eq!(ShapeId::from_bits(e.to_bits()), e);
+        assert_ne!(e.to_bits(), ids[1].to_bits());
+        assert_eq!(arena.get(ids[1]), None);
+        assert_eq!(arena.values(), ['a', 'c', 'd', 'e']);
+
+        arena.retain(|_, value| *value != 'c');
+        assert_eq!(arena.values(), ['a', 'd', 'e']);
+        assert_eq!(arena.get(ids[3]), Some(&'d'));
+        assert!(!arena.contains(ids[2]));
+        assert_eq!(arena.capacity(), 4);
+
+        // Several at once, with the positions of the rest caught up
+        let mut numbers: Arena<u32> = (0..10).collect();
+        let ids = numbers.ids().to_vec();
+        numbers.retain(|_, n| n % 3 != 0);
+        assert_eq!(numbers.values(), [1, 2, 4, 5, 7, 8]);
+        assert_eq!(numbers.ids(), [1, 2, 4, 5, 7, 8].map(|i| ids[i]));
+        assert_eq!(numbers.position(ids[8]), Some(5));
+        assert_eq!(numbers.get(ids[9]), None);
+    }
+}
@@ -0,0 +1,217 @@
+// A Bevy plugin for showing a canvas in a Bevy app. The canvas lives in the
+// CanvasResource, where systems change it as they would anywhere else, and
+// once a frame every shape on it gets an entity: a CanvasShape with its id,
+// a Transform at its origin, and a Mesh2d of its fill relative to that.
+// Entities follow their shapes as they change and go when they're removed,
+// with a ShapeMessage saying so each time, for systems that would rather
+// react than poll.
+//
+// The meshes go in Assets<Mesh>, so the plugin wants an app that has them,
+// through DefaultPlugins or MeshPlugin; a material to draw them with is up
+// to the app.
+//
+//     App::new()
+//         .add_plugins((DefaultPlugins, KaleidoscopePlugin))
+//         .add_systems(Update, |mut canvas: ResMut<CanvasResource>| { ... })
+use std::collections::HashSet;
+
+use bevy::{
+    asset::RenderAssetUsages,
+    mesh::{Indices, PrimitiveTopology},
+    prelude::*,
+};
+
+use crate::{mesh, Canvas, Shape, ShapeId};
+
+pub struct KaleidoscopePlugin;
+
+impl Plugin for KaleidoscopePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CanvasResource>()
+            .add_message::<ShapeMessage>()
+            .add_systems(PostUpdate, sync_shapes.in_set(CanvasSync));
+    }
+}
+
+// Where sync_shapes runs, for systems to order themselves against
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CanvasSync;
+
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct CanvasResource(pub Canvas);
+
+// On the entity standing for a shape
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CanvasShape {
+    pub id: ShapeId,
+    // The shape's version stamp when the entity last caught up with it
+    version: u64,
+}
+
+#[derive(Message, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShapeMessage {
+    Added { entity: Entity, id: ShapeId },
+    // Moved or reshaped
+    Changed { entity: Entity, id: ShapeId },
+    // The entity's already been despawned
+    Removed { entity: Entity, id: ShapeId },
+}
+
+// The shape's fill, with the origin at 0 0 for the transform to place
+fn fill(shape: &dyn Shape) -> Mesh {
+    let origin = shape.origin();
+    let fill = mesh::Mesh::fill(&shape.outline());
+    let positions: Vec<[f32; 3]> = fill
+        .vertices
+        .iter()
+        .map(|&[x, y]| [x - origin.x, y - origin.y, 0.0])
+        .collect();
+    let normals = vec![[0.0, 0.0, 1.0]; positions.len()];
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_indices(Indices::U32(fill.indices))
+}
+
+fn place(shape: &dyn Shape) -> Transform {
+    let origin = shape.origin();
+    Transform::from_xyz(origin.x, origin.y, 0.0)
+}
+
+// Brings the shape entities into line with the canvas. Shapes whose locks
+// are poisoned are left as they were.
+pub fn sync_shapes(
+    mut commands: Commands,
+    canvas: Res<CanvasResource>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut entities: Query<(Entity, &mut CanvasShape, &mut Transform, &Mesh2d)>,
+    mut messages: MessageWriter<ShapeMessage>,
+) {
+    let mut shown = HashSet::new();
+    for (entity, mut shape, mut transform, mesh) in &mut entities {
+        let id = shape.id;
+        let Some(object) = canvas.get(id) else {
+            commands.entity(entity).despawn();
+            meshes.remove(&mesh.0);
+            messages.write(ShapeMessage::Removed { entity, id });
+            continue;
+        };
+        shown.insert(id);
+        let version = object.version();
+        if version == shape.version {
+            continue;
+        }
+        let Ok(locked) = object.lock() else {
+            continue;
+        };
+        *transform = place(&*locked);
+        meshes.insert(&mesh.0, fill(&*locked)).ok();
+        shape.version = version;
+        messages.write(ShapeMessage::Changed { entity, id });
+    }
+    for &id in canvas.ids() {
+        let Some(object) = canvas.get(id).filter(|_| !shown.contains(&id)) else {
+            continue;
+        };
+        let version = object.version();
+        let Ok(locked) = object.lock() else {
+            continue;
+        };
+        let entity = commands
+            .spawn((
+                CanvasShape { id, version },
+                place(&*locked),
+                Mesh2d(meshes.add(fill(&*locked))),
+            ))
+            .id();
+        messages.write(ShapeMessage::Added { entity, id });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{assert_approx_eq, Circle, Coord, Rectangle, ShapeCell};
+
+    #[test]
+    fn entities_follow_the_canvas() {
+        let mut app = App::new();
+        app.init_resource::<Assets<Mesh>>()
+            .add_plugins(KaleidoscopePlugin);
+        let mut canvas = app.world_mut().resource_mut::<CanvasResource>();
+        let square = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::new(2.0, 1.0),
+            side_a: 2.0,
+            side_b: 2.0,
+        })));
+        let circle = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::default(),
+            radius: 1.0,
+        })));
+        let messages = |app: &App| -> Vec<ShapeMessage> {
+            let messages = app.world().resource::<Messages<ShapeMessage>>();
+            messages.iter_current_update_messages().copied().collect()
+        };
+        let entity = |app: &mut App, id: ShapeId| {
+            let mut query = app.world_mut().query::<(Entity, &CanvasShape)>();
+            let found = query.iter(app.world()).find(|(_, shape)| shape.id == id);
+            found.map(|(entity, _)| entity)
+        };
+
+        app.update();
+        let square_entity = entity(&mut app, square).unwrap();
+        let added = messages(&app);
+        assert_eq!(added.len(), 2);
+        assert!(added.contains(&ShapeMessage::Added {
+            entity: square_entity,
+            id: square,
+        }));
+        let world = app.world();
+        assert_eq!(
+            world.get::<Transform>(square_entity).unwrap().translation,
+            Vec3::new(2.0, 1.0, 0.0)
+        );
+        let handle = &world.get::<Mesh2d>(square_entity).unwrap().0;
+        let mesh = world.resource::<Assets<Mesh>>().get(handle).unwrap();
+        assert_eq!(mesh.indices().unwrap().len(), 6);
+        let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap();
+        let xs = positions.as_float3().unwrap().iter().map(|p| p[0]);
+        assert_approx_eq!(xs.fold(f32::MIN, f32::max), 1.0);
+
+        // Nothing changed, nothing said
+        app.update();
+        assert_eq!(messages(&app), []);
+
+        let canvas = app.world().resource::<CanvasResource>();
+        canvas.set_origin(square, Coord::new(-3.0, 0.0)).unwrap();
+        app.update();
+        assert_eq!(
+            messages(&app),
+            [ShapeMessage::Changed {
+                entity: square_entity,
+                id: square,
+            }]
+        );
+        let transform = app.world().get::<Transform>(square_entity).unwrap();
+        assert_eq!(transform.translation, Vec3::new(-3.0, 0.0, 0.0));
+
+        let circle_entity = entity(&mut app, circle).unwrap();
+        let mut canvas = app.world_mut().resource_mut::<CanvasResource>();
+        canvas.remove(circle);
+        app.update();
+        assert_eq!(
+            messages(&app),
+            [ShapeMessage::Removed {
+                entity: circle_entity,
+                id: circle,
+            }]
+        );
+        assert!(app.world().get_entity(circle_entity).is_err());
+        assert_eq!(app.world().resource::<Assets<Mesh>>().len(), 1);
+    }
+}
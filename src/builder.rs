@@ -0,0 +1,287 @@
+use alloc::vec::Vec;
+
+use crate::{
+    error::ShapeError, Circle, Coord, Ellipse, Polygon, Rectangle, Shape, Square, Triangle,
+};
+
+pub(crate) fn dimension(name: &'static str, value: Option<f32>) -> Result<f32, ShapeError> {
+    match value {
+        None => Err(ShapeError::MissingDimension { name }),
+        Some(value) if value.is_finite() && value > 0.0 => Ok(value),
+        Some(value) => Err(ShapeError::InvalidDimension { name, value }),
+    }
+}
+
+pub(crate) fn coordinate(name: &'static str, point: Coord) -> Result<Coord, ShapeError> {
+    if point.x.is_finite() && point.y.is_finite() {
+        Ok(point)
+    } else {
+        Err(ShapeError::InvalidCoordinate { name })
+    }
+}
+
+// The origin defaults to zero, the dimensions have to be given
+#[derive(Clone, Debug, Default)]
+pub struct CircleBuilder {
+    origin: Coord,
+    radius: Option<f32>,
+}
+
+impl Circle {
+    pub fn builder() -> CircleBuilder {
+        CircleBuilder::default()
+    }
+}
+
+impl CircleBuilder {
+    pub fn origin(mut self, origin: impl Into<Coord>) -> CircleBuilder {
+        self.origin = origin.into();
+        self
+    }
+    pub fn radius(mut self, radius: f32) -> CircleBuilder {
+        self.radius = Some(radius);
+        self
+    }
+    pub fn build(self) -> Result<Circle, ShapeError> {
+        Ok(Circle {
+            origin: coordinate("origin", self.origin)?,
+            radius: dimension("radius", self.radius)?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RectangleBuilder {
+    origin: Coord,
+    side_a: Option<f32>,
+    side_b: Option<f32>,
+}
+
+impl Rectangle {
+    pub fn builder() -> RectangleBuilder {
+        RectangleBuilder::default()
+    }
+}
+
+impl RectangleBuilder {
+    pub fn origin(mut self, origin: impl Into<Coord>) -> RectangleBuilder {
+        self.origin = origin.into();
+        self
+    }
+    pub fn side_a(mut self, side_a: f32) -> RectangleBuilder {
+        self.side_a = Some(side_a);
+        self
+    }
+    pub fn side_b(mut self, side_b: f32) -> RectangleBuilder {
+        self.side_b = Some(side_b);
+        self
+    }
+    pub fn build(self) -> Result<Rectangle, ShapeError> {
+        Ok(Rectangle {
+            origin: coordinate("origin", self.origin)?,
+            side_a: dimension("side_a", self.side_a)?,
+            side_b: dimension("side_b", self.side_b)?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TriangleBuilder {
+    origin: Coord,
+    base: Option<f32>,
+    height: Option<f32>,
+}
+
+impl Triangle {
+    pub fn builder() -> TriangleBuilder {
+        TriangleBuilder::default()
+    }
+}
+
+impl TriangleBuilder {
+    pub fn origin(mut self, origin: impl Into<Coord>) -> TriangleBuilder {
+        self.origin = origin.into();
+        self
+    }
+    pub fn base(mut self, base: f32) -> TriangleBuilder {
+        self.base = Some(base);
+        self
+    }
+    pub fn height(mut self, height: f32) -> TriangleBuilder {
+        self.height = Some(height);
+        self
+    }
+    pub fn build(self) -> Result<Triangle, ShapeError> {
+        Ok(Triangle {
+            origin: coordinate("origin", self.origin)?,
+            base: dimension("base", self.base)?,
+            height: dimension("height", self.height)?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PolygonBuilder {
+    origin: Coord,
+    points: Vec<Coord>,
+}
+
+impl Polygon {
+    pub fn builder() -> PolygonBuilder {
+        PolygonBuilder::default()
+    }
+}
+
+impl PolygonBuilder {
+    pub fn origin(mut self, origin: impl Into<Coord>) -> PolygonBuilder {
+        self.origin = origin.into();
+        self
+    }
+    // Offsets from the origin, as for Polygon::points
+    pub fn point(mut self, point: Coord) -> PolygonBuilder {
+        self.points.push(point);
+        self
+    }
+    pub fn points(mut self, points: impl IntoIterator<Item = Coord>) -> PolygonBuilder {
+        self.points.extend(points);
+        self
+    }
+    pub fn build(self) -> Result<Polygon, ShapeError> {
+        let polygon = Polygon {
+            origin: self.origin,
+            points: self.points,
+        };
+        polygon.validate()?;
+        Ok(polygon)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SquareBuilder {
+    origin: Coord,
+    side: Option<f32>,
+}
+
+impl Square {
+    pub fn builder() -> SquareBuilder {
+        SquareBuilder::default()
+    }
+}
+
+impl SquareBuilder {
+    pub fn origin(mut self, origin: impl Into<Coord>) -> SquareBuilder {
+        self.origin = origin.into();
+        self
+    }
+    pub fn side(mut self, side: f32) -> SquareBuilder {
+        self.side = Some(side);
+        self
+    }
+    pub fn build(self) -> Result<Square, ShapeError> {
+        Ok(Square {
+            origin: coordinate("origin", self.origin)?,
+            side: dimension("side", self.side)?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EllipseBuilder {
+    origin: Coord,
+    radius_x: Option<f32>,
+    radius_y: Option<f32>,
+}
+
+impl Ellipse {
+    pub fn builder() -> EllipseBuilder {
+        EllipseBuilder::default()
+    }
+}
+
+impl EllipseBuilder {
+    pub fn origin(mut self, origin: impl Into<Coord>) -> EllipseBuilder {
+        self.origin = origin.into();
+        self
+    }
+    pub fn radius_x(mut self, radius_x: f32) -> EllipseBuilder {
+        self.radius_x = Some(radius_x);
+        self
+    }
+    pub fn radius_y(mut self, radius_y: f32) -> EllipseBuilder {
+        self.radius_y = Some(radius_y);
+        self
+    }
+    pub fn build(self) -> Result<Ellipse, ShapeError> {
+        Ok(Ellipse {
+            origin: coordinate("origin", self.origin)?,
+            radius_x: dimension("radius_x", self.radius_x)?,
+            radius_y: dimension("radius_y", self.radius_y)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builders_validate_dimensions() {
+        let circle = Circle::builder()
+            .radius(5.0)
+            .origin(Coord::new(1.0, 2.0))
+            .build();
+        assert_eq!(
+            circle,
+            Ok(Circle {
+                origin: Coord::new(1.0, 2.0),
+                radius: 5.0,
+            })
+        );
+        assert_eq!(
+            Circle::builder().radius(-3.0).build(),
+            Err(ShapeError::InvalidDimension {
+                name: "radius",
+                value: -3.0,
+            })
+        );
+        for value in [0.0, f32::NAN, f32::INFINITY] {
+            assert!(Rectangle::builder()
+                .side_a(1.0)
+                .side_b(value)
+                .build()
+                .is_err());
+        }
+        assert!(Square::builder().side(2.0).build().is_ok());
+        assert!(Ellipse::builder()
+            .radius_x(2.0)
+            .radius_y(0.0)
+            .build()
+            .is_err());
+        assert_eq!(
+            Triangle::builder().base(1.0).build(),
+            Err(ShapeError::MissingDimension { name: "height" })
+        );
+        assert_eq!(
+            Circle::builder()
+                .radius(1.0)
+                .origin(Coord::new(f32::NAN, 0.0))
+                .build(),
+            Err(ShapeError::InvalidCoordinate { name: "origin" })
+        );
+
+        let polygon = Polygon::builder()
+            .points([Coord::new(0.0, 0.0), Coord::new(1.0, 0.0)])
+            .point(Coord::new(0.0, 1.0))
+            .build();
+        assert_eq!(polygon.unwrap().points.len(), 3);
+        // Collinear points enclose nothing
+        let line = Polygon::builder()
+            .points([
+                Coord::new(0.0, 0.0),
+                Coord::new(1.0, 1.0),
+                Coord::new(2.0, 2.0),
+            ])
+            .build();
+        assert_eq!(line, Err(ShapeError::DegeneratePolygon));
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    mem::{size_of, size_of_val},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::{arena::ShapeId, geometry::Triangle3, Coord, Rectangle, Shape, ShapeObject};
+
+// Values derived from one version of a shape, each computed on first use
+#[derive(Clone, Debug, Default)]
+pub struct Derived {
+    version: u64,
+    pub bounds: Option<Rectangle>,
+    pub area: Option<f32>,
+    pub triangles: Option<Arc<[Triangle3]>>,
+    pub hull: Option<Arc<[Coord]>>,
+}
+
+// Derived values for every shape of a canvas, by id slot. A shape's entry
+// is dropped as a whole once its version moves on, and since versions are
+// never reused an entry left behind in a reused slot is never a hit either.
+// Nothing but whole entries is written under the lock, so one poisoned by a
+// panic elsewhere is still sound and gets used as it is.
+#[derive(Debug, Default)]
+pub struct DerivedCache {
+    entries: Mutex<Vec<Option<Derived>>>,
+}
+
+impl DerivedCache {
+    // Bytes held by the entries and the triangles and hulls they keep, some
+    // of which may be shared with callers
+    pub fn heap_bytes(&self) -> usize {
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let arrays: usize = entries
+            .iter()
+            .flatten()
+            .map(|entry| {
+                let triangles = entry.triangles.as_ref().map_or(0, |t| size_of_val(&**t));
+                let hull = entry.hull.as_ref().map_or(0, |h| size_of_val(&**h));
+                triangles + hull
+            })
+            .sum();
+        entries.capacity() * size_of::<Option<Derived>>() + arrays
+    }
+
+    // None if the shape's lock is poisoned
+    pub fn get<T: Clone>(
+        &self,
+        id: ShapeId,
+        shape: &ShapeObject,
+        field: fn(&mut Derived) -> &mut Option<T>,
+        compute: impl FnOnce(&dyn Shape) -> T,
+    ) -> Option<T> {
+        let slot = id.slot();
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(Some(entry)) = entries.get_mut(slot) {
+            if entry.version == shape.version() {
+                if let Some(value) = field(entry) {
+                    return Some(value.clone());
+                }
+            }
+        }
+        drop(entries);
+        // Not holding the cache lock while computing, so other shapes can be
+        // looked up meanwhile
+        let (version, value) = {
+            let locked = shape.lock().ok()?;
+            (shape.version(), compute(&*locked))
+        };
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        if entries.len() <= slot {
+            entries.resize(slot + 1, None);
+        }
+        let entry = entries[slot].get_or_insert_with(Derived::default);
+        if entry.version != version {
+            *entry = Derived {
+                version,
+                ..Default::default()
+            };
+        }
+        *field(entry) = Some(value.clone());
+        Some(value)
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{transform::Transform, Coord, Rectangle};
+
+// Which way canvas y runs on screen and in exported images
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum YAxis {
+    // Mathematical convention
+    #[default]
+    Up,
+    // Screen convention, as in SVG and most image formats
+    Down,
+}
+
+// Where the canvas zero sits on a page
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Corner {
+    #[default]
+    BottomLeft,
+    TopLeft,
+    Center,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Axes {
+    pub y: YAxis,
+    pub origin: Corner,
+}
+
+impl Axes {
+    // The canvas area covered by a page of the given size
+    pub fn page(&self, width: f32, height: f32) -> Rectangle {
+        // Distances up the page from the zero to the bottom and top edges
+        let (bottom, top) = match self.origin {
+            Corner::BottomLeft => (0.0, height),
+            Corner::TopLeft => (-height, 0.0),
+            Corner::Center => (-height / 2.0, height / 2.0),
+        };
+        let (min_y, max_y) = match self.y {
+            YAxis::Up => (bottom, top),
+            YAxis::Down => (-top, -bottom),
+        };
+        let min_x = match self.origin {
+            Corner::Center => -width / 2.0,
+            _ => 0.0,
+        };
+        Rectangle::from_corners(Coord::new(min_x, min_y), Coord::new(min_x + width, max_y))
+    }
+}
+
+// What part of the canvas a front end shows. Screen positions are in pixels
+// from the top left corner with y pointing down, and canvas y runs whichever
+// way the given axis says.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    // The canvas point shown in the middle of the screen
+    pub center: Coord,
+    // Screen pixels per canvas unit
+    pub zoom: f32,
+    // Counter-clockwise turn of the view, so shapes appear turned clockwise
+    pub rotation: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Camera {
+        Camera {
+            center: Coord::default(),
+            zoom: 1.0,
+            rotation: 0.0,
+        }
+    }
+}
+
+impl Camera {
+    // Canvas to screen, for a screen of the given size in pixels
+    pub fn to_screen(&self, width: u32, height: u32, y_axis: YAxis) -> Transform {
+        let flip = match y_axis {
+            YAxis::Up => -1.0,
+            YAxis::Down => 1.0,
+        };
+        Transform::translation(Coord::new(width as f32, height as f32) * 0.5)
+            .after(&Transform::scale(self.zoom, flip * self.zoom))
+            .after(&Transform::rotation(-self.rotation))
+            .after(&Transform::translation(-self.center))
+    }
+    // Screen to canvas. None when the zoom is zero.
+    pub fn to_world(&self, width: u32, height: u32, y_axis: YAxis) -> Option<Transform> {
+        self.to_screen(width, height, y_axis).inverse()
+    }
+
+    // Moves the view by a distance given in screen pixels, as when dragging
+    pub fn pan(&mut self, screen_offset: Coord, y_axis: YAxis) {
+        if let Some(to_world) = self.to_world(0, 0, y_axis) {
+            self.center = self.center - to_world.map_vector(screen_offset);
+        }
+    }
+    // Multiplies the zoom, keeping whatever is under the screen point still
+    pub fn zoom_at(
+        &mut self,
+        factor: f32,
+        screen_point: Coord,
+        (width, height): (u32, u32),
+        y_axis: YAxis,
+    ) {
+        let Some(before) = self.to_world(width, height, y_axis) else {
+            return;
+        };
+        let fixed = before.map(screen_point);
+        self.zoom *= factor;
+        if let Some(after) = self.to_world(width, height, y_axis) {
+            self.center = self.center + (fixed - after.map(screen_point));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    fn close(a: Coord, b: Coord) -> bool {
+        a.distance(b) < 1e-3
+    }
+
+    #[test]
+    fn pan_and_zoom() {
+        let mut camera = Camera {
+            center: Coord::new(10.0, 10.0),
+            zoom: 2.0,
+            rotation: 0.0,
+        };
+        let screen = camera.to_screen(200, 100, YAxis::Up);
+        assert!(close(
+            screen.map(Coord::new(10.0, 10.0)),
+            Coord::new(100.0, 50.0)
+        ));
+        // A canvas unit up is two pixels up the screen
+        assert!(close(
+            screen.map(Coord::new(11.0, 11.0)),
+            Coord::new(102.0, 48.0)
+        ));
+        let screen = camera.to_screen(200, 100, YAxis::Down);
+        assert!(close(
+            screen.map(Coord::new(11.0, 11.0)),
+            Coord::new(102.0, 52.0)
+        ));
+
+        // Dragging the canvas right and down moves the view left, and up
+        // the canvas when y is up
+        camera.pan(Coord::new(20.0, 4.0), YAxis::Up);
+        assert!(close(camera.center, Coord::new(0.0, 12.0)));
+        camera.pan(Coord::new(0.0, 4.0), YAxis::Down);
+        assert!(close(camera.center, Coord::new(0.0, 10.0)));
+
+        let cursor = Coord::new(150.0, 20.0);
+        let under = camera.to_world(200, 100, YAxis::Up).unwrap().map(cursor);
+        camera.zoom_at(4.0, cursor, (200, 100), YAxis::Up);
+        assert_eq!(camera.zoom, 8.0);
+        let after = camera.to_world(200, 100, YAxis::Up).unwrap();
+        assert!(close(after.map(cursor), under));
+
+        // Turning the view a quarter left shows canvas +x pointing down
+        camera.rotation = FRAC_PI_2;
+        let screen = camera.to_screen(200, 100, YAxis::Up);
+        let step = screen.map(camera.center + Coord::new(1.0, 0.0)) - screen.map(camera.center);
+        assert!(close(step, Coord::new(0.0, 8.0)));
+    }
+
+    #[test]
+    fn pages() {
+        let corners = |y, origin| {
+            let page = Axes { y, origin }.page(4.0, 2.0);
+            (page.min(), page.max())
+        };
+        let (zero, top) = (Coord::new(0.0, 0.0), Coord::new(4.0, 0.0));
+        assert_eq!(
+            corners(YAxis::Up, Corner::BottomLeft),
+            (zero, Coord::new(4.0, 2.0))
+        );
+        assert_eq!(
+            corners(YAxis::Down, Corner::TopLeft),
+            (zero, Coord::new(4.0, 2.0))
+        );
+        assert_eq!(
+            corners(YAxis::Up, Corner::TopLeft),
+            (Coord::new(0.0, -2.0), top)
+        );
+        assert_eq!(
+            corners(YAxis::Down, Corner::BottomLeft),
+            (Coord::new(0.0, -2.0), top)
+        );
+        assert_eq!(
+            corners(YAxis::Down, Corner::Center),
+            (Coord::new(-2.0, -1.0), Coord::new(2.0, 1.0))
+        );
+    }
+}
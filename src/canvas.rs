@@ -0,0 +1,2912 @@
+#[cfg(feature = "spatial")]
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use crate::{
+    arena::{Arena, ShapeId},
+    builder::{coordinate, dimension},
+    cache::DerivedCache,
+    camera::{Axes, Camera, YAxis},
+    constraint::Constraint,
+    error::{CanvasError, ShapeError},
+    geometry::{self, Outline, Triangle3},
+    group::Group,
+    guide::Guide,
+    layout::{self, Container, PackStrategy},
+    mesh::Mesh,
+    metrics::Counters,
+    param::Dimensions,
+    physics::{Boundary, CollisionResponse, Motion},
+    style::Style,
+    symmetry::{Element, MirrorLine, Orbit, Symmetry},
+    units::{convert_area, Dpi, Length, Unit},
+    Circle, Coord, History, Polygon, Rectangle, Shape, ShapeCell, ShapeObject,
+};
+#[cfg(feature = "spatial")]
+use crate::{
+    cell::latest_version,
+    spatial::{IndexKind, SpatialIndex},
+};
+
+#[derive(Default)]
+pub struct Canvas {
+    pub(crate) shapes: Arena<ShapeObject>,
+    // Applied to shapes as they are added
+    symmetry: Option<Symmetry>,
+    mirror_lines: Vec<MirrorLine>,
+    // Whether reflected copies stay linked to their source shapes
+    live_mirroring: bool,
+    orbits: Vec<Orbit>,
+    pub(crate) groups: Vec<Group>,
+    pub(crate) guides: Vec<Guide>,
+    // Each with the shape it holds, in the order added
+    pub(crate) constraints: Vec<(ShapeId, Constraint)>,
+    // Named values that parametric shapes take their dimensions from
+    pub(crate) params: BTreeMap<String, f32>,
+    pub(crate) parametric: Vec<(ShapeId, Dimensions)>,
+    // How close a move has to land to a guide to be pulled onto it
+    pub(crate) snap_distance: Option<f32>,
+    // Styles other than the default, by id slot
+    pub(crate) styles: Vec<Option<(ShapeId, Style)>>,
+    // Labels and the like, by id slot
+    pub(crate) metadata: Vec<Option<(ShapeId, Metadata)>>,
+    pub(crate) tags: Vec<Option<(ShapeId, Vec<String>)>>,
+    // Shapes left out of drawing and, unless hit_hidden is set, hit tests
+    pub(crate) hidden: Vec<Option<(ShapeId, bool)>>,
+    hit_hidden: bool,
+    // Shapes that stay put, by id slot
+    pub(crate) locked: Vec<Option<(ShapeId, bool)>>,
+    // Velocities and accelerations, by id slot
+    pub(crate) motions: Vec<Option<(ShapeId, Motion)>>,
+    // What happens to moving shapes at the edge of the world
+    pub(crate) boundary: Option<Boundary>,
+    pub(crate) collision_response: Option<CollisionResponse>,
+    pub(crate) counters: Counters,
+    // Memoized geometry of each shape
+    pub(crate) derived: DerivedCache,
+    dpi: Dpi,
+    axes: Axes,
+    #[cfg(feature = "spatial")]
+    spatial: Mutex<Option<IndexState>>,
+}
+
+// A spatial index brought up to date lazily: before a query, shapes whose
+// version has moved on since they were indexed are indexed again. The sweep
+// is skipped altogether when neither the shape list nor any shape has
+// changed since the last one.
+#[cfg(feature = "spatial")]
+struct IndexState {
+    kind: IndexKind,
+    index: Box<dyn SpatialIndex>,
+    // Indexed id and version, by id slot
+    indexed: Vec<Option<(ShapeId, u64)>>,
+    // Arena edits and latest version as of the last sweep
+    swept: Option<(u64, u64)>,
+    // Bounds of everything indexed so far. It only ever grows, so it may
+    // be bigger than the shapes are now.
+    extent: Option<Rectangle>,
+}
+
+#[cfg(feature = "spatial")]
+impl IndexState {
+    fn new(kind: IndexKind) -> IndexState {
+        IndexState {
+            index: kind.build(),
+            kind,
+            indexed: Vec::new(),
+            swept: None,
+            extent: None,
+        }
+    }
+}
+
+// Keys and values the canvas keeps with a shape without looking at them,
+// in key order
+pub type Metadata = BTreeMap<String, String>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    pub id: ShapeId,
+    pub point: Coord,
+    pub distance: f32,
+}
+
+// One shape to paint, for front ends that draw the canvas themselves
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawCommand {
+    pub id: ShapeId,
+    pub outline: Outline,
+    pub style: Style,
+}
+
+impl Canvas {
+    // Returns the id of the added shape. With a symmetry set, its copies
+    // are added straight after it, and with live mirroring on, so are its
+    // reflections in each of the canvas mirror lines.
+    pub fn add(&mut self, shape: ShapeObject) -> ShapeId {
+        let position = self.shapes.len();
+        let id = self.shapes.insert(shape.clone());
+        // A shape whose lock is already poisoned gets no copies
+        if let Some(Ok(orbit)) = self.symmetry.map(|s| Orbit::new(shape, s.elements())) {
+            for (_, image) in &orbit.images {
+                self.shapes.insert(image.clone());
+            }
+            self.orbits.push(orbit);
+        }
+        if self.live_mirroring {
+            let added = self.shapes.ids()[position..].to_vec();
+            for line in self.mirror_lines.clone() {
+                self.reflect_across(&added, &line);
+            }
+        }
+        self.debug_check(&self.shapes.values()[position..]);
+        Counters::count(&self.counters.adds, 1);
+        event!(DEBUG, ?id, shapes = self.shapes.len(), "added shape");
+        id
+    }
+    pub fn get(&self, id: ShapeId) -> Option<&ShapeObject> {
+        self.shapes.get(id)
+    }
+    // Changes to the shape's style, metadata, tags, visibility and lock
+    // count along with changes to the shape itself
+    pub fn history(&self, id: ShapeId) -> Option<History> {
+        Some(self.shapes.get(id)?.history())
+    }
+    // Shapes changed, or added, after the time given, in painting order
+    pub fn changed_since(&self, time: SystemTime) -> Vec<ShapeId> {
+        self.shapes
+            .iter()
+            .filter(|(_, shape)| shape.history().modified > time)
+            .map(|(id, _)| id)
+            .collect()
+    }
+    fn touch(&self, id: ShapeId) {
+        if let Some(shape) = self.shapes.get(id) {
+            shape.touch();
+        }
+    }
+    // Ids of every shape, in the order they were added
+    pub fn ids(&self) -> &[ShapeId] {
+        self.shapes.ids()
+    }
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    // The geometry below is cached, and only recomputed once the shape has
+    // been changed. Shapes whose locks are poisoned give None as missing
+    // ones do.
+    pub fn bounding_box(&self, id: ShapeId) -> Option<Rectangle> {
+        let shape = self.shapes.get(id)?;
+        self.derived
+            .get(id, shape, |d| &mut d.bounds, |s| s.bounding_box())
+    }
+    pub fn get_area(&self, id: ShapeId) -> Option<f32> {
+        let shape = self.shapes.get(id)?;
+        self.derived
+            .get(id, shape, |d| &mut d.area, |s| s.get_area())
+    }
+    // Counter-clockwise triangles covering the shape, circles as polygons
+    pub fn triangles(&self, id: ShapeId) -> Option<Arc<[Triangle3]>> {
+        let shape = self.shapes.get(id)?;
+        self.derived.get(
+            id,
+            shape,
+            |d| &mut d.triangles,
+            |s| geometry::triangulate_points(&geometry::outline_points(s.outline())).into(),
+        )
+    }
+    pub fn convex_hull(&self, id: ShapeId) -> Option<Arc<[Coord]>> {
+        let shape = self.shapes.get(id)?;
+        self.derived.get(
+            id,
+            shape,
+            |d| &mut d.hull,
+            |s| geometry::convex_hull(&geometry::outline_points(s.outline())).into(),
+        )
+    }
+    // Removing a seed shape also removes its symmetric copies. Locked
+    // shapes are left, and give None as missing ones do.
+    pub fn remove(&mut self, id: ShapeId) -> Option<ShapeObject> {
+        self.try_remove(id).ok()
+    }
+    pub fn try_remove(&mut self, id: ShapeId) -> Result<ShapeObject, CanvasError> {
+        self.unlocked(id)?;
+        event!(DEBUG, ?id, "removing shape");
+        let shape = self.shapes.remove(id).unwrap();
+        let mut dropped = vec![id];
+        if let Some(position) = self.orbits.iter().position(|o| o.contains(&shape)) {
+            if Arc::ptr_eq(&self.orbits[position].seed, &shape) {
+                let orbit = self.orbits.remove(position);
+                self.shapes.retain(|copy, s| {
+                    let keep = !orbit.contains(s);
+                    if !keep {
+                        dropped.push(copy);
+                    }
+                    keep
+                });
+            } else {
+                self.orbits[position]
+                    .images
+                    .retain(|(_, image)| !Arc::ptr_eq(image, &shape));
+            }
+        }
+        for id in dropped {
+            self.forget(id);
+        }
+        Counters::count(&self.counters.removes, 1);
+        Ok(shape)
+    }
+    // Everything kept alongside a removed shape, so none of it outlives the
+    // shape or turns up on whatever reuses the slot
+    fn forget(&mut self, id: ShapeId) {
+        self.forget_member(id);
+        self.forget_constraints(id);
+        self.forget_dimensions(id);
+        clear_slot(&mut self.styles, id);
+        clear_slot(&mut self.metadata, id);
+        clear_slot(&mut self.tags, id);
+        clear_slot(&mut self.hidden, id);
+        clear_slot(&mut self.locked, id);
+        clear_slot(&mut self.motions, id);
+    }
+    // Moving any shape with symmetric copies moves the rest of them to match
+    // Snapped to guides, when snapping is on, and then moved as far as
+    // constraints need
+    pub fn set_origin(&self, id: ShapeId, origin: impl Into<Coord>) -> Result<(), CanvasError> {
+        self.place(id, self.snap(origin))?;
+        if !self.constraints.is_empty() {
+            self.settle()?;
+        }
+        Ok(())
+    }
+    // Moves the shape by the offset, snapping where it lands
+    pub fn translate(&self, id: ShapeId, offset: impl Into<Coord>) -> Result<(), CanvasError> {
+        let offset = offset.into();
+        coordinate("offset", offset)?;
+        let origin = self.unlocked(id)?.lock()?.origin();
+        self.set_origin(id, origin + offset)
+    }
+    // set_origin without snapping, for simulation steps and moves of several
+    // shapes that have to keep their spacing
+    pub(crate) fn place(&self, id: ShapeId, origin: Coord) -> Result<(), CanvasError> {
+        let shape = self.unlocked(id)?;
+        coordinate("origin", origin)?;
+        event!(TRACE, ?id, x = origin.x, y = origin.y, "moving shape");
+        Counters::count(&self.counters.moves, 1);
+        match self.orbits.iter().find(|orbit| orbit.contains(shape)) {
+            Some(orbit) => {
+                orbit.set_origin(shape, origin)?;
+                self.debug_check(orbit.members());
+            }
+            None => {
+                shape.lock()?.set_origin(origin);
+                self.debug_check([shape]);
+            }
+        }
+        Ok(())
+    }
+    // Moves the shape only if it's still at the expected origin, checked and
+    // moved under its lock, so threads reading an origin and writing back a
+    // change to it can retry rather than lose each other's updates. False
+    // if it had moved.
+    pub fn compare_and_set_origin(
+        &self,
+        id: ShapeId,
+        expected: impl Into<Coord>,
+        origin: impl Into<Coord>,
+    ) -> Result<bool, CanvasError> {
+        let (expected, origin) = (expected.into(), origin.into());
+        let shape = self.unlocked(id)?;
+        coordinate("origin", origin)?;
+        let moved = match self.orbits.iter().find(|orbit| orbit.contains(shape)) {
+            Some(orbit) => {
+                let moved = orbit.compare_and_set_origin(shape, expected, origin)?;
+                self.debug_check(orbit.members());
+                moved
+            }
+            None => {
+                let mut locked = shape.lock()?;
+                let moved = locked.origin() == expected;
+                if moved {
+                    locked.set_origin(origin);
+                }
+                drop(locked);
+                self.debug_check([shape]);
+                moved
+            }
+        };
+        Ok(moved)
+    }
+    // Sets one of the shape's dimensions by name, as Shape::set_property
+    // does. Symmetric copies aren't reshaped to match.
+    pub fn set_property(&self, id: ShapeId, name: &str, value: f32) -> Result<(), CanvasError> {
+        let shape = self.unlocked(id)?;
+        shape.lock()?.set_property(name, value)?;
+        self.debug_check([shape]);
+        Ok(())
+    }
+    // Shapes without a style of their own get the default one
+    pub fn style(&self, id: ShapeId) -> Option<Style> {
+        self.shapes.get(id)?;
+        Some(slot(&self.styles, id).copied().unwrap_or_default())
+    }
+    // Symmetric copies are styled along with the shape
+    pub fn set_style(&mut self, id: ShapeId, style: Style) -> Result<(), CanvasError> {
+        self.shapes.get(id).ok_or(CanvasError::UnknownShape(id))?;
+        check_style(&style)?;
+        self.store_style(id, style);
+        Ok(())
+    }
+    // Written without checks, for styles known to be valid
+    pub(crate) fn store_style(&mut self, id: ShapeId, style: Style) {
+        for id in self.copies_of(id) {
+            *slot_mut(&mut self.styles, id) = style;
+            self.touch(id);
+        }
+    }
+
+    // None for shapes with nothing set
+    pub fn metadata(&self, id: ShapeId) -> Option<&Metadata> {
+        self.shapes.get(id)?;
+        slot(&self.metadata, id).filter(|metadata| !metadata.is_empty())
+    }
+    pub fn meta(&self, id: ShapeId, key: &str) -> Option<&str> {
+        self.metadata(id)?.get(key).map(String::as_str)
+    }
+    // Symmetric copies carry the same metadata as the shape. Returns the
+    // value the key had.
+    pub fn set_meta(
+        &mut self,
+        id: ShapeId,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Result<Option<String>, CanvasError> {
+        self.shapes.get(id).ok_or(CanvasError::UnknownShape(id))?;
+        let (key, value) = (key.into(), value.into());
+        let old = self.meta(id, &key).map(String::from);
+        for id in self.copies_of(id) {
+            self.metadata_mut(id).insert(key.clone(), value.clone());
+            self.touch(id);
+        }
+        Ok(old)
+    }
+    pub fn remove_meta(&mut self, id: ShapeId, key: &str) -> Option<String> {
+        let old = self.meta(id, key).map(String::from);
+        for id in self.copies_of(id) {
+            if self.metadata_mut(id).remove(key).is_some() {
+                self.touch(id);
+            }
+        }
+        old
+    }
+    // Replaces all of the shape's metadata
+    pub fn set_metadata(&mut self, id: ShapeId, metadata: Metadata) -> Result<(), CanvasError> {
+        self.shapes.get(id).ok_or(CanvasError::UnknownShape(id))?;
+        for id in self.copies_of(id) {
+            *self.metadata_mut(id) = metadata.clone();
+            self.touch(id);
+        }
+        Ok(())
+    }
+    fn metadata_mut(&mut self, id: ShapeId) -> &mut Metadata {
+        slot_mut(&mut self.metadata, id)
+    }
+
+    // None for shapes that aren't on the canvas
+    pub fn tags(&self, id: ShapeId) -> Option<&[String]> {
+        self.shapes.get(id)?;
+        Some(slot(&self.tags, id).map_or(&[], Vec::as_slice))
+    }
+    pub fn has_tag(&self, id: ShapeId, tag: &str) -> bool {
+        self.tags(id)
+            .is_some_and(|tags| tags.iter().any(|t| t == tag))
+    }
+    // Tags the shape and its symmetric copies. False if it already had the
+    // tag.
+    pub fn tag(&mut self, id: ShapeId, tag: impl Into<String>) -> Result<bool, CanvasError> {
+        self.shapes.get(id).ok_or(CanvasError::UnknownShape(id))?;
+        let tag = tag.into();
+        if self.has_tag(id, &tag) {
+            return Ok(false);
+        }
+        for id in self.copies_of(id) {
+            let tags = slot_mut(&mut self.tags, id);
+            if !tags.contains(&tag) {
+                tags.push(tag.clone());
+                self.touch(id);
+            }
+        }
+        Ok(true)
+    }
+    pub fn untag(&mut self, id: ShapeId, tag: &str) -> bool {
+        let had = self.has_tag(id, tag);
+        for id in self.copies_of(id) {
+            let tags = slot_mut(&mut self.tags, id);
+            let before = tags.len();
+            tags.retain(|t| t != tag);
+            if tags.len() != before {
+                self.touch(id);
+            }
+        }
+        had
+    }
+    // In painting order
+    pub fn find_by_tag(&self, tag: &str) -> Vec<ShapeId> {
+        self.shapes
+            .ids()
+            .iter()
+            .copied()
+            .filter(|&id| self.has_tag(id, tag))
+            .collect()
+    }
+    // Moves every unlocked shape with the tag by the offset. Symmetric
+    // copies are tagged along with their seed, and move as a pattern with it
+    // the way set_origin moves them.
+    pub fn move_tagged(&self, tag: &str, offset: impl Into<Coord>) -> Result<(), CanvasError> {
+        let offset = offset.into();
+        coordinate("offset", offset)?;
+        let mut moved: Vec<ShapeId> = Vec::new();
+        for id in self.find_by_tag(tag) {
+            if moved.contains(&id) || self.is_locked(id) {
+                continue;
+            }
+            let origin = self.shapes.get(id).unwrap().lock()?.origin();
+            self.place(id, origin + offset)?;
+            moved.extend(self.copies_of(id));
+        }
+        Ok(())
+    }
+    pub fn set_tagged_visible(&mut self, tag: &str, visible: bool) {
+        for id in self.find_by_tag(tag) {
+            *slot_mut(&mut self.hidden, id) = !visible;
+            self.touch(id);
+        }
+    }
+    // Returns the ids removed, including copies whose seed was tagged.
+    // Locked shapes are left.
+    pub fn remove_tagged(&mut self, tag: &str) -> Vec<ShapeId> {
+        let before: Vec<ShapeId> = self.shapes.ids().to_vec();
+        for id in self.find_by_tag(tag) {
+            self.remove(id);
+        }
+        before
+            .into_iter()
+            .filter(|&id| self.shapes.get(id).is_none())
+            .collect()
+    }
+
+    // Hiding a shape hides its symmetric copies. Hidden shapes stay on the
+    // canvas and in its queries, but aren't drawn.
+    pub fn set_visible(&mut self, id: ShapeId, visible: bool) -> Result<(), CanvasError> {
+        self.shapes.get(id).ok_or(CanvasError::UnknownShape(id))?;
+        for id in self.copies_of(id) {
+            *slot_mut(&mut self.hidden, id) = !visible;
+            self.touch(id);
+        }
+        Ok(())
+    }
+    // False if the shape, or a group it's in, is hidden, or it isn't on the
+    // canvas
+    pub fn is_visible(&self, id: ShapeId) -> bool {
+        self.shapes.get(id).is_some() && !self.hidden_itself(id) && !self.hidden_by_group(id)
+    }
+    // Hidden with set_visible, leaving aside its groups
+    pub(crate) fn hidden_itself(&self, id: ShapeId) -> bool {
+        slot(&self.hidden, id).is_some_and(|&hidden| hidden)
+    }
+    // Ids of the shapes that are drawn, in painting order
+    pub fn visible_ids(&self) -> Vec<ShapeId> {
+        self.shapes
+            .ids()
+            .iter()
+            .copied()
+            .filter(|&id| self.is_visible(id))
+            .collect()
+    }
+    // Whether hit tests find hidden shapes, which by default they don't
+    pub fn set_hit_test_hidden(&mut self, hit: bool) {
+        self.hit_hidden = hit;
+    }
+
+    // Locking a shape locks its symmetric copies. Moving, transforming or
+    // removing a locked shape is an error, and operations over many shapes
+    // leave locked ones where they are.
+    pub fn lock_shape(&mut self, id: ShapeId) -> Result<(), CanvasError> {
+        self.set_locked(id, true)
+    }
+    pub fn unlock_shape(&mut self, id: ShapeId) -> Result<(), CanvasError> {
+        self.set_locked(id, false)
+    }
+    fn set_locked(&mut self, id: ShapeId, locked: bool) -> Result<(), CanvasError> {
+        self.shapes.get(id).ok_or(CanvasError::UnknownShape(id))?;
+        for id in self.copies_of(id) {
+            *slot_mut(&mut self.locked, id) = locked;
+            self.touch(id);
+        }
+        Ok(())
+    }
+    pub fn is_locked(&self, id: ShapeId) -> bool {
+        slot(&self.locked, id).is_some_and(|&locked| locked)
+    }
+    // The shape, if it's there to be changed
+    pub(crate) fn unlocked(&self, id: ShapeId) -> Result<&ShapeObject, CanvasError> {
+        let shape = self.shapes.get(id).ok_or(CanvasError::UnknownShape(id))?;
+        if self.is_locked(id) {
+            return Err(CanvasError::Locked(id));
+        }
+        Ok(shape)
+    }
+    fn locked_shapes(&self) -> Vec<ShapeObject> {
+        self.shapes
+            .iter()
+            .filter(|(id, _)| self.is_locked(*id))
+            .map(|(_, shape)| shape.clone())
+            .collect()
+    }
+
+    // The shape and its symmetric copies, in painting order
+    pub(crate) fn copies_of(&self, id: ShapeId) -> Vec<ShapeId> {
+        let Some(shape) = self.shapes.get(id) else {
+            return Vec::new();
+        };
+        match self.orbits.iter().find(|orbit| orbit.contains(shape)) {
+            Some(orbit) => self
+                .shapes
+                .iter()
+                .filter(|(_, s)| orbit.contains(s))
+                .map(|(id, _)| id)
+                .collect(),
+            None => vec![id],
+        }
+    }
+
+    // How canvas coordinates are laid out in rendered and exported images
+    pub fn set_axes(&mut self, axes: Axes) {
+        self.axes = axes;
+    }
+    pub fn axes(&self) -> Axes {
+        self.axes
+    }
+    // Canvas coordinates are pixels at this resolution
+    pub fn set_dpi(&mut self, dpi: Dpi) -> Result<(), CanvasError> {
+        if !(dpi.0 > 0.0 && dpi.0.is_finite()) {
+            return Err(CanvasError::InvalidDpi(dpi.0));
+        }
+        self.dpi = dpi;
+        Ok(())
+    }
+    pub fn dpi(&self) -> Dpi {
+        self.dpi
+    }
+    // A physical length in canvas coordinates
+    pub fn to_canvas(&self, length: Length) -> f32 {
+        length.to_px(self.dpi)
+    }
+    // In square units
+    pub fn area_in(&self, id: ShapeId, unit: Unit) -> Option<f32> {
+        Some(convert_area(self.get_area(id)?, unit, self.dpi))
+    }
+    pub fn perimeter_in(&self, id: ShapeId, unit: Unit) -> Result<Length, CanvasError> {
+        let shape = self.get(id).ok_or(CanvasError::UnknownShape(id))?;
+        let perimeter = shape.lock()?.perimeter();
+        Ok(Length::px(perimeter).convert(unit, self.dpi))
+    }
+    pub fn total_area(&self) -> f32 {
+        self.ids().iter().filter_map(|&id| self.get_area(id)).sum()
+    }
+    // The centroid of all the shapes taken together, each weighted by its
+    // area. None when there's no area at all.
+    pub fn center_of_mass(&self) -> Option<Coord> {
+        let (sum, area) = self.moments(|_| true);
+        (area > 0.0).then(|| sum * (1.0 / area))
+    }
+    // The area-weighted sum of centroids of the shapes picked, and their area
+    fn moments(&self, pick: impl Fn(ShapeId) -> bool) -> (Coord, f32) {
+        let (mut sum, mut total) = (Coord::default(), 0.0);
+        for &id in self.ids().iter().filter(|&&id| pick(id)) {
+            let (Some(shape), Some(area)) = (self.get(id), self.get_area(id)) else {
+                continue;
+            };
+            let Ok(shape) = shape.lock() else {
+                continue;
+            };
+            sum = sum + shape.outline().centroid() * area;
+            total += area;
+        }
+        (sum, total)
+    }
+
+    // Moves every unlocked shape, keeping symmetry links consistent
+    pub fn translate_all(&mut self, offset: impl Into<Coord>) -> Result<(), CanvasError> {
+        let offset = coordinate("offset", offset.into())?;
+        span!(
+            DEBUG,
+            "translate_all",
+            x = offset.x,
+            y = offset.y,
+            shapes = self.shapes.len()
+        );
+        for (id, shape) in self.shapes.iter() {
+            if self.is_locked(id) {
+                continue;
+            }
+            let mut shape = shape.lock()?;
+            let origin = shape.origin();
+            shape.set_origin(origin + offset);
+            Counters::count(&self.counters.moves, 1);
+        }
+        let translation = Element {
+            angle: 0.0,
+            mirrored: false,
+            offset,
+        };
+        let inverse = translation.inverse();
+        let locked = self.locked_shapes();
+        for orbit in &mut self.orbits {
+            // Locked along with the rest of the orbit
+            if locked.iter().any(|shape| orbit.contains(shape)) {
+                continue;
+            }
+            for (element, _) in &mut orbit.images {
+                *element = translation.after(element).after(&inverse);
+            }
+        }
+        self.debug_check(self.shapes.values());
+        Ok(())
+    }
+
+    // Rotates every unlocked shape about the pivot. Shapes other than
+    // circles are replaced by rotated polygons, keeping their id and any
+    // symmetry links. The symmetry setting and mirror lines are left as
+    // they are. Every rotated shape is made before any is replaced, so a
+    // poisoned lock leaves the whole canvas as it was.
+    pub fn rotate_all(&mut self, angle: f32, pivot: impl Into<Coord>) -> Result<(), CanvasError> {
+        span!(DEBUG, "rotate_all", angle, shapes = self.shapes.len());
+        let rotation = Element::rotation(pivot.into(), angle);
+        let locked = self.locked_shapes();
+        let rotated = self
+            .shapes
+            .values()
+            .iter()
+            .map(|shape| match locked.iter().any(|l| Arc::ptr_eq(l, shape)) {
+                true => Ok(None),
+                false => Ok(Some(rotation.image(&*shape.lock()?))),
+            })
+            .collect::<Result<Vec<_>, CanvasError>>()?;
+        let replaced: Vec<(ShapeObject, ShapeObject)> = self
+            .shapes
+            .values_mut()
+            .iter_mut()
+            .zip(rotated)
+            .filter_map(|(shape, rotated)| {
+                let rotated = rotated?;
+                Some((std::mem::replace(shape, rotated.clone()), rotated))
+            })
+            .collect();
+        let replacement = |old: &ShapeObject| {
+            replaced
+                .iter()
+                .find(|(o, _)| Arc::ptr_eq(o, old))
+                .map_or_else(|| old.clone(), |(_, new)| new.clone())
+        };
+        let inverse = rotation.inverse();
+        for orbit in &mut self.orbits {
+            if locked.iter().any(|shape| orbit.contains(shape)) {
+                continue;
+            }
+            orbit.seed = replacement(&orbit.seed);
+            for (element, image) in &mut orbit.images {
+                // Maps the rotated seed onto the rotated image
+                *element = rotation.after(element).after(&inverse);
+                *image = replacement(image);
+            }
+        }
+        self.debug_check(self.shapes.values());
+        Ok(())
+    }
+    // Moves every unlocked shape by the same offset, as translate_all does,
+    // so the centre of mass lands on the point. Locked shapes still count
+    // towards it, so the rest go further to make up for them. Returns the
+    // offset, which is zero when there's nothing with area to move.
+    pub fn recenter_on(&mut self, point: impl Into<Coord>) -> Result<Coord, CanvasError> {
+        let point = coordinate("point", point.into())?;
+        let (sum, area) = self.moments(|_| true);
+        let (_, movable) = self.moments(|id| !self.is_locked(id));
+        if movable <= 0.0 {
+            return Ok(Coord::default());
+        }
+        let offset = (point * area - sum) * (1.0 / movable);
+        self.translate_all(offset)?;
+        Ok(offset)
+    }
+
+    // Only affects shapes added afterwards
+    // Refused for orders outside 1 to symmetry::MAX_ORDER
+    pub fn set_symmetry(&mut self, symmetry: Option<Symmetry>) -> Result<(), CanvasError> {
+        if let Some(symmetry) = &symmetry {
+            symmetry.validate()?;
+        }
+        self.symmetry = symmetry;
+        Ok(())
+    }
+
+    pub fn add_mirror_line(&mut self, line: MirrorLine) -> usize {
+        self.mirror_lines.push(line);
+        self.mirror_lines.len() - 1
+    }
+    pub fn mirror_lines(&self) -> &[MirrorLine] {
+        &self.mirror_lines
+    }
+    pub fn remove_mirror_line(&mut self, index: usize) -> Result<MirrorLine, CanvasError> {
+        if index >= self.mirror_lines.len() {
+            return Err(CanvasError::UnknownIndex(index));
+        }
+        Ok(self.mirror_lines.remove(index))
+    }
+    // Only affects reflections made afterwards
+    pub fn set_live_mirroring(&mut self, live: bool) {
+        self.live_mirroring = live;
+    }
+
+    // Adds a reflected copy of each shape and returns the new ids. With live
+    // mirroring on, moving a shape or its twin moves the other to match.
+    // Shapes whose locks are poisoned are skipped.
+    pub fn reflect_across(&mut self, ids: &[ShapeId], line: &MirrorLine) -> Vec<ShapeId> {
+        let reflection = Element::reflection(line);
+        let sources: Vec<(ShapeId, ShapeObject)> = ids
+            .iter()
+            .filter_map(|&id| Some((id, self.get(id)?.clone())))
+            .collect();
+        let mut added = Vec::new();
+        for (id, source) in sources {
+            let twin = if !self.live_mirroring {
+                let Ok(source) = source.lock() else {
+                    continue;
+                };
+                reflection.image(&*source)
+            } else if let Some(orbit) = self.orbits.iter_mut().find(|o| o.contains(&source)) {
+                // Join the source's existing orbit so all of it stays in sync
+                let element = orbit.element_of(&source).unwrap();
+                let Ok(twin) = orbit.add_image(reflection.after(&element)) else {
+                    continue;
+                };
+                twin
+            } else {
+                let Ok(orbit) = Orbit::new(source, vec![reflection]) else {
+                    continue;
+                };
+                let twin = orbit.images[0].1.clone();
+                self.orbits.push(orbit);
+                twin
+            };
+            let twin = self.shapes.insert(twin);
+            if let Some(metadata) = self.metadata(id).cloned() {
+                *self.metadata_mut(twin) = metadata;
+            }
+            if let Some(tags) = slot(&self.tags, id).cloned() {
+                *slot_mut(&mut self.tags, twin) = tags;
+            }
+            added.push(twin);
+        }
+        self.debug_check(added.iter().filter_map(|&id| self.get(id)));
+        added
+    }
+
+    // Every pair of overlapping shapes, the one added first first, in the
+    // order they were added. Shapes whose locks are poisoned are left out.
+    pub fn collisions(&self) -> Vec<(ShapeId, ShapeId)> {
+        let ids = self.shapes.ids();
+        // Positions in ids of the shapes with bounds, and the bounds
+        let (positions, boxes): (Vec<usize>, Vec<Rectangle>) = ids
+            .iter()
+            .enumerate()
+            .filter_map(|(position, &id)| Some((position, self.bounding_box(id)?)))
+            .unzip();
+        // Only shapes that might touch something need their exact outline
+        let mut outlines: Vec<Option<Outline>> = vec![None; boxes.len()];
+        let mut outline = |index: usize| -> Option<Outline> {
+            if outlines[index].is_none() {
+                let shape = self.shapes.values()[positions[index]].lock().ok()?;
+                outlines[index] = Some(shape.outline());
+            }
+            outlines[index].clone()
+        };
+
+        // Sweep and prune along x: only shapes whose x extents overlap are
+        // tested against each other exactly
+        let mut order: Vec<usize> = (0..boxes.len()).collect();
+        order.sort_by(|&a, &b| boxes[a].min().x.total_cmp(&boxes[b].min().x));
+        let mut active: Vec<usize> = Vec::new();
+        let mut pairs = Vec::new();
+        for index in order {
+            let min_x = boxes[index].min().x;
+            active.retain(|&other| boxes[other].max().x >= min_x);
+            for &other in &active {
+                if boxes[index].distance_to_box(&boxes[other]) == 0.0
+                    && outline(index)
+                        .zip(outline(other))
+                        .is_some_and(|(a, b)| a.distance(&b) == 0.0)
+                {
+                    pairs.push((index.min(other), index.max(other)));
+                }
+            }
+            active.push(index);
+        }
+        pairs.sort();
+        pairs
+            .into_iter()
+            .map(|(a, b)| (ids[positions[a]], ids[positions[b]]))
+            .collect()
+    }
+
+    // Debug builds check the shapes each mutation touched, so an invalid
+    // value such as a NaN origin is caught where it was introduced rather
+    // than wherever it later surfaces
+    pub(crate) fn debug_check<'a>(&self, shapes: impl IntoIterator<Item = &'a ShapeObject>) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        for shape in shapes {
+            if let Ok(shape) = shape.lock() {
+                if let Err(error) = shape.validate() {
+                    panic!("invalid shape on canvas: {error}");
+                }
+            }
+        }
+    }
+
+    // The index and its record of what's in it, for memory reports
+    #[cfg(feature = "spatial")]
+    pub(crate) fn index_bytes(&self) -> usize {
+        let spatial = self.spatial();
+        spatial.as_ref().map_or(0, |state| {
+            state.index.heap_bytes()
+                + state.indexed.capacity() * std::mem::size_of::<Option<(ShapeId, u64)>>()
+        })
+    }
+    // Picks how query_region and hit_test find candidates. Without an index
+    // every shape's bounds are checked, which is fine for small canvases.
+    #[cfg(feature = "spatial")]
+    pub fn set_spatial_index(&mut self, kind: Option<IndexKind>) {
+        *self
+            .spatial
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner) = kind.map(IndexState::new);
+    }
+    // The index state, started again from empty if a panic mid-sweep left
+    // it poisoned and possibly half updated
+    #[cfg(feature = "spatial")]
+    fn spatial(&self) -> MutexGuard<'_, Option<IndexState>> {
+        self.spatial.lock().unwrap_or_else(|poisoned| {
+            self.spatial.clear_poison();
+            let mut state = poisoned.into_inner();
+            if let Some(stale) = state.as_mut() {
+                *stale = IndexState::new(stale.kind.clone());
+            }
+            state
+        })
+    }
+
+    // Shapes whose bounds touch the region, in the order they were added
+    pub fn candidates(&self, region: &Rectangle) -> Vec<ShapeId> {
+        #[cfg(feature = "spatial")]
+        if let Some(state) = self.spatial().as_mut() {
+            return self.indexed_candidates(state, region);
+        }
+        self.ids()
+            .iter()
+            .copied()
+            .filter(|&id| {
+                self.bounding_box(id)
+                    .is_some_and(|bounds| bounds.distance_to_box(region) == 0.0)
+            })
+            .collect()
+    }
+    #[cfg(feature = "spatial")]
+    fn indexed_candidates(&self, state: &mut IndexState, region: &Rectangle) -> Vec<ShapeId> {
+        self.sweep(state);
+        let mut found = state.index.query_region(region);
+        found.sort_by_key(|&id| self.shapes.position(id));
+        found
+    }
+    #[cfg(feature = "spatial")]
+    fn sweep(&self, state: &mut IndexState) {
+        let now = (self.shapes.edits(), latest_version());
+        if state.swept != Some(now) {
+            state.indexed.resize(self.shapes.capacity(), None);
+            for (id, shape) in self.shapes.iter() {
+                let version = shape.version();
+                let entry = &mut state.indexed[id.slot()];
+                if *entry != Some((id, version)) {
+                    // The slot may still hold a removed shape
+                    if let Some((old, _)) = entry.take() {
+                        state.index.remove(old);
+                    }
+                    // Shapes whose locks are poisoned stay out
+                    if let Some(bounds) = self.bounding_box(id) {
+                        state.index.insert(id, &bounds);
+                        *entry = Some((id, version));
+                        state.extent = Some(match state.extent.take() {
+                            Some(extent) => {
+                                span([extent.min(), extent.max(), bounds.min(), bounds.max()])
+                            }
+                            None => bounds,
+                        });
+                    }
+                }
+            }
+            for entry in &mut state.indexed {
+                if let Some((id, _)) = *entry {
+                    if !self.shapes.contains(id) {
+                        state.index.remove(id);
+                        *entry = None;
+                    }
+                }
+            }
+            state.swept = Some(now);
+        }
+    }
+
+    // Shapes overlapping the region, in the order they were added. These
+    // queries leave out shapes whose locks are poisoned.
+    pub fn query_region(&self, region: &Rectangle) -> Vec<ShapeId> {
+        let window = region.outline();
+        self.candidates(region)
+            .into_iter()
+            .filter(|&id| {
+                self.outline(id)
+                    .is_some_and(|outline| outline.distance(&window) == 0.0)
+            })
+            .collect()
+    }
+    // Shapes containing the point, topmost (last added) first
+    pub fn hit_test(&self, point: impl Into<Coord>) -> Vec<ShapeId> {
+        let point = point.into();
+        Counters::count(&self.counters.hit_tests, 1);
+        let mut hits: Vec<ShapeId> = self
+            .candidates(&Rectangle::from_corners(point, point))
+            .into_iter()
+            .filter(|&id| {
+                (self.hit_hidden || self.is_visible(id))
+                    && self
+                        .outline(id)
+                        .is_some_and(|outline| outline.contains(point))
+            })
+            .collect();
+        hits.reverse();
+        hits
+    }
+
+    // The first shape boundary crossed by a ray, or from inside a shape the
+    // way out of it. With a spatial index only shapes whose bounds lie along
+    // the ray are looked at, a stretch of it at a time, up to the first
+    // stretch with a hit. Refused for a zero or non-finite direction.
+    pub fn raycast(
+        &self,
+        origin: impl Into<Coord>,
+        direction: impl Into<Coord>,
+    ) -> Result<Option<RayHit>, CanvasError> {
+        let origin = coordinate("origin", origin.into())?;
+        let direction = coordinate("direction", direction.into())?;
+        let length = dimension("direction", Some(direction.length()))?;
+        let direction = direction * (1.0 / length);
+        let hit = |id: ShapeId| {
+            let distance = *self
+                .outline(id)?
+                .crossings(origin, direction, f32::INFINITY)
+                .first()?;
+            Some(RayHit {
+                id,
+                point: origin + direction * distance,
+                distance,
+            })
+        };
+        #[cfg(feature = "spatial")]
+        if let Some(state) = self.spatial().as_mut() {
+            return Ok(self.indexed_raycast(state, origin, direction, hit));
+        }
+        Ok(self
+            .ids()
+            .iter()
+            .filter(|&&id| {
+                self.bounding_box(id)
+                    .is_some_and(|bounds| ray_span(&bounds, origin, direction).is_some())
+            })
+            .filter_map(|&id| hit(id))
+            .min_by(|a, b| a.distance.total_cmp(&b.distance)))
+    }
+    // Stretches start at a 64th of the way across everything indexed and
+    // double, so a near hit is found from a few shapes and a miss takes a
+    // handful of queries. Every shape the ray crosses within a stretch has
+    // bounds touching the stretch's box, so a hit no further than the end of
+    // the stretches so far is the first.
+    #[cfg(feature = "spatial")]
+    fn indexed_raycast(
+        &self,
+        state: &mut IndexState,
+        origin: Coord,
+        direction: Coord,
+        hit: impl Fn(ShapeId) -> Option<RayHit>,
+    ) -> Option<RayHit> {
+        self.sweep(state);
+        let (mut start, end) = ray_span(state.extent.as_ref()?, origin, direction)?;
+        let mut step = (end - start) / 64.0;
+        let mut visited = HashSet::new();
+        // With the position, so equal distances go to the shape added first
+        // as they do without an index
+        let mut best: Option<(f32, usize, RayHit)> = None;
+        loop {
+            let stop = (start + step).min(end);
+            let stretch = span([origin + direction * start, origin + direction * stop]);
+            for id in self.indexed_candidates(state, &stretch) {
+                if !visited.insert(id) {
+                    continue;
+                }
+                let (Some(hit), Some(position)) = (hit(id), self.shapes.position(id)) else {
+                    continue;
+                };
+                let closer = best.as_ref().is_none_or(|(distance, first, _)| {
+                    (hit.distance, position) < (*distance, *first)
+                });
+                if closer {
+                    best = Some((hit.distance, position, hit));
+                }
+            }
+            if stop >= end
+                || best
+                    .as_ref()
+                    .is_some_and(|(distance, ..)| *distance <= stop)
+            {
+                return best.map(|(.., hit)| hit);
+            }
+            start = stop;
+            step *= 2.0;
+        }
+    }
+
+    // A copy of the canvas restricted to a window. Shapes crossing the edge
+    // become the polygon of their visible part.
+    pub fn clip_to(&self, rect: &Rectangle) -> Canvas {
+        let (min, max) = (rect.min(), rect.max());
+        let window = rect.outline();
+        let (kept, shapes): (Vec<ShapeId>, Vec<ShapeObject>) = self
+            .shapes
+            .iter()
+            .filter_map(|(id, shape)| {
+                let shape = shape.lock().ok()?;
+                let bounds = shape.bounding_box();
+                let (shape_min, shape_max) = (bounds.min(), bounds.max());
+                if shape_min.x >= min.x
+                    && shape_min.y >= min.y
+                    && shape_max.x <= max.x
+                    && shape_max.y <= max.y
+                {
+                    return Some((id, shape.duplicate()));
+                }
+                let outline = shape.outline();
+                if outline.distance(&window) > 0.0 {
+                    return None;
+                }
+                let clipped = geometry::clip_polygon(&geometry::outline_points(outline), min, max);
+                if clipped.len() < 3 {
+                    return None;
+                }
+                let origin = shape.origin();
+                let polygon = Arc::new(ShapeCell::new(Polygon {
+                    origin,
+                    points: clipped.into_iter().map(|p| p - origin).collect(),
+                })) as ShapeObject;
+                Some((id, polygon))
+            })
+            .unzip();
+        let shapes: Arena<ShapeObject> = shapes.into_iter().collect();
+        // Inserted in the same order, so the new ids line up with the old,
+        // and take the first slots
+        let styles = kept
+            .iter()
+            .zip(shapes.ids())
+            .map(|(old, new)| Some((*new, self.style(*old).unwrap_or_default())))
+            .collect();
+        let metadata = kept
+            .iter()
+            .zip(shapes.ids())
+            .map(|(old, new)| self.metadata(*old).map(|metadata| (*new, metadata.clone())))
+            .collect();
+        let tags = kept
+            .iter()
+            .zip(shapes.ids())
+            .map(|(old, new)| slot(&self.tags, *old).map(|tags| (*new, tags.clone())))
+            .collect();
+        let hidden = kept
+            .iter()
+            .zip(shapes.ids())
+            .map(|(old, new)| slot(&self.hidden, *old).map(|hidden| (*new, *hidden)))
+            .collect();
+        Canvas {
+            shapes,
+            styles,
+            metadata,
+            tags,
+            hidden,
+            guides: self.guides.clone(),
+            snap_distance: self.snap_distance,
+            params: self.params.clone(),
+            dpi: self.dpi,
+            axes: self.axes,
+            ..Default::default()
+        }
+    }
+
+    // The unlocked entries, and the ids of the locked ones
+    fn split_locked<T>(
+        &self,
+        entries: Vec<T>,
+        id: impl Fn(&T) -> ShapeId,
+    ) -> (Vec<T>, Vec<ShapeId>) {
+        let (locked, unlocked): (Vec<T>, Vec<T>) = entries
+            .into_iter()
+            .partition(|entry| self.is_locked(id(entry)));
+        (unlocked, locked.iter().map(id).collect())
+    }
+
+    // Moves shapes so their bounding boxes are laid out inside the frame
+    // without overlapping. Returns the ids that didn't fit, which are left
+    // where they were, along with any locked ones.
+    pub fn pack(
+        &self,
+        ids: &[ShapeId],
+        frame: &Rectangle,
+        strategy: PackStrategy,
+    ) -> Result<Vec<ShapeId>, CanvasError> {
+        let shapes: Vec<(ShapeId, &ShapeObject)> = ids
+            .iter()
+            .filter_map(|&id| Some((id, self.get(id)?)))
+            .collect();
+        let (shapes, mut unplaced) = self.split_locked(shapes, |(id, _)| *id);
+        let boxes = shapes
+            .iter()
+            .map(|(_, shape)| {
+                let shape = shape.lock()?;
+                Ok((shape.origin(), shape.bounding_box()))
+            })
+            .collect::<Result<Vec<(Coord, Rectangle)>, CanvasError>>()?;
+        let sizes: Vec<Coord> = boxes
+            .iter()
+            .map(|(_, bounds)| Coord::new(bounds.side_a, bounds.side_b))
+            .collect();
+        let placed = layout::pack(&sizes, Coord::new(frame.side_a, frame.side_b), strategy);
+
+        for (((id, _), (origin, bounds)), min) in shapes.iter().zip(&boxes).zip(placed) {
+            match min {
+                Some(min) => self.place(*id, frame.min() + min - bounds.min() + *origin)?,
+                None => unplaced.push(*id),
+            }
+        }
+        self.settle()?;
+        Ok(unplaced)
+    }
+
+    // Moves shapes so the circles enclosing them are packed tightly inside
+    // the container without overlapping. Returns the ids that didn't fit,
+    // which are left where they were, along with any locked ones.
+    pub fn pack_circles(
+        &self,
+        ids: &[ShapeId],
+        container: &Container,
+    ) -> Result<Vec<ShapeId>, CanvasError> {
+        let shapes: Vec<(ShapeId, &ShapeObject, Circle)> = ids
+            .iter()
+            .filter_map(|&id| Some((id, self.get(id)?, self.enclosing_circle(&[id])?)))
+            .collect();
+        let (shapes, mut unplaced) = self.split_locked(shapes, |(id, _, _)| *id);
+        let radii: Vec<f32> = shapes.iter().map(|(_, _, circle)| circle.radius).collect();
+        let placed = layout::pack_circles(&radii, container);
+
+        for ((id, shape, circle), center) in shapes.iter().zip(placed) {
+            match center {
+                Some(center) => {
+                    let origin = shape.lock()?.origin();
+                    self.place(*id, center - circle.origin + origin)?;
+                }
+                None => unplaced.push(*id),
+            }
+        }
+        self.settle()?;
+        Ok(unplaced)
+    }
+
+    // Moves shapes so their centroids sit on a grid, in rows of the given
+    // number of columns reading down the page from the top left corner of
+    // their bounding boxes as they were. Cells are sized so no shape's
+    // bounding box reaches into the next, with the spacing between them.
+    pub fn arrange_grid(
+        &self,
+        ids: &[ShapeId],
+        columns: usize,
+        spacing: f32,
+    ) -> Result<(), CanvasError> {
+        if columns == 0 {
+            return Err(ShapeError::InvalidDimension {
+                name: "columns",
+                value: 0.0,
+            }
+            .into());
+        }
+        if !(spacing >= 0.0 && spacing.is_finite()) {
+            return Err(ShapeError::InvalidDimension {
+                name: "spacing",
+                value: spacing,
+            }
+            .into());
+        }
+        let shapes = ids
+            .iter()
+            .map(|&id| Ok((id, self.unlocked(id)?)))
+            .collect::<Result<Vec<_>, CanvasError>>()?;
+        if shapes.is_empty() {
+            return Ok(());
+        }
+        // The grid runs along increasing y, so is laid out upside down when
+        // canvas y points up
+        let flip = match self.axes.y {
+            YAxis::Down => 1.0,
+            YAxis::Up => -1.0,
+        };
+        let (mut left, mut top) = (f32::INFINITY, f32::NEG_INFINITY);
+        let mut offsets = Vec::with_capacity(shapes.len());
+        let reaches = shapes
+            .iter()
+            .map(|(_, shape)| {
+                let shape = shape.lock()?;
+                let outline = shape.outline();
+                let (centroid, bounds) = (outline.centroid(), outline.bounding_box());
+                offsets.push(shape.origin() - centroid);
+                let (low, high) = (centroid - bounds.min(), bounds.max() - centroid);
+                left = left.min(bounds.min().x);
+                Ok(match self.axes.y {
+                    YAxis::Down => {
+                        top = top.max(-bounds.min().y);
+                        (low, high)
+                    }
+                    YAxis::Up => {
+                        top = top.max(bounds.max().y);
+                        (Coord::new(low.x, high.y), Coord::new(high.x, low.y))
+                    }
+                })
+            })
+            .collect::<Result<Vec<(Coord, Coord)>, CanvasError>>()?;
+        let positions = layout::grid(&reaches, columns, spacing);
+        for ((id, _), (offset, position)) in shapes.iter().zip(offsets.into_iter().zip(positions)) {
+            let centroid = Coord::new(left + position.x, flip * (position.y - top));
+            self.place(*id, centroid + offset)?;
+        }
+        self.settle()
+    }
+
+    // Smallest circle containing all of the given shapes
+    pub fn enclosing_circle(&self, ids: &[ShapeId]) -> Option<Circle> {
+        let outlines = self.outlines(ids);
+        let points: Vec<Coord> = outlines
+            .iter()
+            .flat_map(|outline| match outline {
+                Outline::Circle { center, radius } => geometry::circle_points(*center, *radius),
+                Outline::Polygon(points) => points.clone(),
+            })
+            .collect();
+        let (center, radius) = geometry::enclosing_circle(&points)?;
+        // Circles were sampled, so make sure they are fully covered
+        let radius = outlines
+            .iter()
+            .fold(radius, |radius, outline| match outline {
+                Outline::Circle {
+                    center: c,
+                    radius: r,
+                } => radius.max(center.distance(*c) + r),
+                Outline::Polygon(_) => radius,
+            });
+        Some(Circle {
+            origin: center,
+            radius,
+        })
+    }
+    // Axis aligned box around all of the given shapes, None if none of them
+    // are on the canvas
+    pub fn bounding_box_of(&self, ids: &[ShapeId]) -> Option<Rectangle> {
+        let boxes: Vec<Rectangle> = ids.iter().filter_map(|&id| self.bounding_box(id)).collect();
+        let first = boxes.first()?;
+        let (min, max) = boxes[1..]
+            .iter()
+            .fold((first.min(), first.max()), |(min, max), b| {
+                let (b_min, b_max) = (b.min(), b.max());
+                (
+                    Coord::new(min.x.min(b_min.x), min.y.min(b_min.y)),
+                    Coord::new(max.x.max(b_max.x), max.y.max(b_max.y)),
+                )
+            });
+        Some(Rectangle::from_corners(min, max))
+    }
+    // Smallest area rectangle at any rotation containing all of the given
+    // shapes, as a polygon centred on its origin
+    pub fn minimal_bounding_rect(&self, ids: &[ShapeId]) -> Option<Polygon> {
+        let points: Vec<Coord> = self
+            .outlines(ids)
+            .iter()
+            .flat_map(|outline| match outline {
+                // Circumscribed so the rectangle can't cut into the circle
+                Outline::Circle { center, radius } => {
+                    geometry::circle_points(*center, radius / (geometry::ARC_STEP / 2.0).cos())
+                }
+                Outline::Polygon(points) => points.clone(),
+            })
+            .collect();
+        let corners = geometry::minimum_area_rectangle(&points)?;
+        let origin = (corners[0] + corners[2]) * 0.5;
+        Some(Polygon {
+            origin,
+            points: corners.iter().map(|corner| *corner - origin).collect(),
+        })
+    }
+    // Every visible shape, bottom (first added) first, so painting them in
+    // order gives the right overlaps. Drawing leaves out shapes whose locks
+    // are poisoned.
+    pub fn draw_commands(&self) -> Vec<DrawCommand> {
+        self.shapes
+            .iter()
+            .filter(|(id, _)| self.is_visible(*id))
+            .filter_map(|(id, shape)| {
+                Some(DrawCommand {
+                    id,
+                    outline: shape.lock().ok()?.outline(),
+                    style: self.style(id).unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+    // Triangles for every visible shape in paint order, filled or stroked with the
+    // given width, in one mesh for a single draw call
+    pub fn fill_mesh(&self) -> Mesh {
+        self.mesh(Mesh::fill)
+    }
+    pub fn stroke_mesh(&self, width: f32) -> Mesh {
+        self.mesh(|outline| Mesh::stroke(outline, width))
+    }
+    fn mesh(&self, build: impl Fn(&Outline) -> Mesh) -> Mesh {
+        let mut mesh = Mesh::default();
+        for (id, shape) in self.shapes.iter() {
+            if let (true, Ok(shape)) = (self.is_visible(id), shape.lock()) {
+                mesh.extend(&build(&shape.outline()));
+            }
+        }
+        mesh
+    }
+    // Outlines of the given shapes, skipping any missing or poisoned ones
+    pub fn outlines(&self, ids: &[ShapeId]) -> Vec<Outline> {
+        ids.iter().filter_map(|&id| self.outline(id)).collect()
+    }
+    pub(crate) fn outline(&self, id: ShapeId) -> Option<Outline> {
+        Some(self.get(id)?.lock().ok()?.outline())
+    }
+
+    // Shapes under a screen position, for a front end showing the canvas
+    // through the camera on a screen of the given size
+    pub fn hit_test_screen(
+        &self,
+        camera: &Camera,
+        (width, height): (u32, u32),
+        point: Coord,
+    ) -> Vec<ShapeId> {
+        camera
+            .to_world(width, height, self.axes.y)
+            .map_or_else(Vec::new, |to_world| self.hit_test(to_world.map(point)))
+    }
+
+    // The k shapes closest to a point, nearest first
+    pub fn nearest(&self, point: impl Into<Coord>, k: usize) -> Result<Vec<ShapeId>, CanvasError> {
+        let point = point.into();
+        let target = Rectangle::from_corners(point, point);
+        self.k_nearest(k, None, &target, |outline| outline.distance_to(point))
+    }
+    // The k shapes closest to the given one, excluding itself
+    pub fn nearest_to(&self, id: ShapeId, k: usize) -> Result<Vec<ShapeId>, CanvasError> {
+        let shape = self.get(id).ok_or(CanvasError::UnknownShape(id))?;
+        let outline = shape.lock()?.outline();
+        let target = outline.bounding_box();
+        self.k_nearest(k, Some(id), &target, |other| outline.distance(other))
+    }
+
+    // Bounding box gaps are a lower bound on the exact distance, so shapes are
+    // visited in order of their box distance and the exact geometry is only
+    // computed until no remaining box can beat the current k-th best. With a
+    // spatial index, only the shapes within a reach of the target are
+    // looked at, the reach doubling until the k-th best is closer than it.
+    fn k_nearest(
+        &self,
+        k: usize,
+        skip: Option<ShapeId>,
+        target: &Rectangle,
+        exact: impl Fn(&Outline) -> f32,
+    ) -> Result<Vec<ShapeId>, CanvasError> {
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+        #[cfg(feature = "spatial")]
+        let indexed = self.spatial().is_some();
+        #[cfg(not(feature = "spatial"))]
+        let indexed = false;
+
+        let mut found: Vec<(f32, ShapeId)> = Vec::with_capacity(k + 1);
+        let mut visited = HashSet::new();
+        let mut reach = 1.0_f32;
+        loop {
+            let (candidates, everything) = if indexed && reach.is_finite() {
+                let region = Rectangle {
+                    origin: target.origin,
+                    side_a: target.side_a + 2.0 * reach,
+                    side_b: target.side_b + 2.0 * reach,
+                };
+                let candidates = self.candidates(&region);
+                let everything = candidates.len() == self.shapes.len();
+                (candidates, everything)
+            } else {
+                (self.ids().to_vec(), true)
+            };
+            // Those outside the reach but touching its corners wait for the
+            // next round, so everything unvisited is further than the reach
+            let mut candidates: Vec<(f32, ShapeId)> = candidates
+                .into_iter()
+                .filter(|&id| Some(id) != skip && !visited.contains(&id))
+                .map(|id| match self.bounding_box(id) {
+                    Some(bounds) => Ok((target.distance_to_box(&bounds), id)),
+                    None => Err(CanvasError::Poisoned),
+                })
+                .collect::<Result<_, _>>()?;
+            candidates.retain(|&(bound, _)| everything || bound <= reach);
+            candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            for (bound, id) in candidates {
+                visited.insert(id);
+                if found.len() == k && bound > found[k - 1].0 {
+                    break;
+                }
+                let distance = exact(&self.shapes.get(id).unwrap().lock()?.outline());
+                let position = found.partition_point(|(d, _)| *d <= distance);
+                found.insert(position, (distance, id));
+                found.truncate(k);
+            }
+            if everything || (found.len() == k && found[k - 1].0 <= reach) {
+                break;
+            }
+            reach *= 2.0;
+        }
+        Ok(found.into_iter().map(|(_, id)| id).collect())
+    }
+}
+
+// Adds each shape in turn, as add would
+impl FromIterator<ShapeObject> for Canvas {
+    fn from_iter<I: IntoIterator<Item = ShapeObject>>(shapes: I) -> Canvas {
+        let mut canvas = Canvas::default();
+        for shape in shapes {
+            canvas.add(shape);
+        }
+        canvas
+    }
+}
+
+pub(crate) fn check_style(style: &Style) -> Result<(), CanvasError> {
+    dimension("stroke_width", Some(style.stroke_width))?;
+    if let Some((name, size)) = style.fill.and_then(|paint| paint.size()) {
+        dimension(name, Some(size))?;
+    }
+    if let Some(dash) = style.dash {
+        dash.validate()?;
+    }
+    if !(0.0..=1.0).contains(&style.opacity) {
+        return Err(CanvasError::InvalidOpacity(style.opacity));
+    }
+    Ok(())
+}
+
+// The shape's entry in a table kept by id slot, unless the slot is empty or
+// left over from a removed shape
+fn slot<T>(table: &[Option<(ShapeId, T)>], id: ShapeId) -> Option<&T> {
+    match table.get(id.slot()) {
+        Some(Some((owner, value))) if *owner == id => Some(value),
+        _ => None,
+    }
+}
+// The same, made empty first if it's unset or left over
+fn slot_mut<T: Default>(table: &mut Vec<Option<(ShapeId, T)>>, id: ShapeId) -> &mut T {
+    if table.len() <= id.slot() {
+        table.resize_with(id.slot() + 1, || None);
+    }
+    let entry = &mut table[id.slot()];
+    if entry.as_ref().is_none_or(|(owner, _)| *owner != id) {
+        *entry = Some((id, T::default()));
+    }
+    &mut entry.as_mut().unwrap().1
+}
+// The smallest box holding the points
+#[cfg(feature = "spatial")]
+fn span<const N: usize>(points: [Coord; N]) -> Rectangle {
+    let (mut min, mut max) = (points[0], points[0]);
+    for point in points {
+        min = Coord::new(min.x.min(point.x), min.y.min(point.y));
+        max = Coord::new(max.x.max(point.x), max.y.max(point.y));
+    }
+    Rectangle::from_corners(min, max)
+}
+// How far along the ray it enters and leaves the box, the start counting
+// as within it, or None if it misses
+fn ray_span(bounds: &Rectangle, origin: Coord, direction: Coord) -> Option<(f32, f32)> {
+    let (min, max) = (bounds.min(), bounds.max());
+    let (mut enter, mut leave) = (0.0_f32, f32::INFINITY);
+    for (start, step, low, high) in [
+        (origin.x, direction.x, min.x, max.x),
+        (origin.y, direction.y, min.y, max.y),
+    ] {
+        if step == 0.0 {
+            if start < low || start > high {
+                return None;
+            }
+            continue;
+        }
+        let (a, b) = ((low - start) / step, (high - start) / step);
+        enter = enter.max(a.min(b));
+        leave = leave.min(a.max(b));
+    }
+    (enter <= leave).then_some((enter, leave))
+}
+fn clear_slot<T>(table: &mut [Option<(ShapeId, T)>], id: ShapeId) {
+    if let Some(entry) = table.get_mut(id.slot()) {
+        *entry = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f32::consts::PI, thread};
+
+    use super::*;
+    #[cfg(feature = "spatial")]
+    use crate::spatial::IndexKind;
+    use crate::{
+        assert_approx_eq, assert_area_eq,
+        constraint::Constraint,
+        physics::Motion,
+        style::{Color, Dash, LineCap, LineJoin},
+        symmetry::{SymmetryGroup, MAX_ORDER},
+        transform::Transform,
+        ShapeError, Triangle,
+    };
+
+    // Test the canvas api: set_origin, remove, etc
+    #[test]
+    fn update_origin() {
+        let rectangle = Rectangle {
+            origin: Coord::default(),
+            side_a: 2.0,
+            side_b: 4.0,
+        };
+        let mut canvas = Canvas::default();
+        let id = canvas.add(Arc::new(ShapeCell::new(rectangle)));
+
+        assert_eq!(
+            canvas.get(id).unwrap().lock().unwrap().origin(),
+            Coord::default()
+        );
+        canvas.set_origin(id, Coord { x: 2.0, y: 2.0 }).unwrap();
+        assert_eq!(
+            canvas.get(id).unwrap().lock().unwrap().origin(),
+            Coord { x: 2.0, y: 2.0 }
+        );
+
+        // Ids stay valid when other shapes are removed, and stale ones miss
+        let circle = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::default(),
+            radius: 1.0,
+        })));
+        assert!(canvas.remove(id).is_some());
+        assert!(canvas.remove(id).is_none());
+        assert!(matches!(
+            canvas.set_origin(id, Coord::default()),
+            Err(CanvasError::UnknownShape(stale)) if stale == id
+        ));
+        assert!(matches!(
+            canvas.remove_mirror_line(0),
+            Err(CanvasError::UnknownIndex(0))
+        ));
+        assert_approx_eq!(canvas.get_area(circle).unwrap(), PI);
+        assert_eq!(canvas.ids(), [circle]);
+    }
+
+    #[test]
+    fn non_finite_values_rejected() {
+        let mut canvas = Canvas::default();
+        let id = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::default(),
+            radius: 1.0,
+        })));
+        assert!(matches!(
+            canvas.set_origin(id, Coord::new(f32::NAN, 0.0)),
+            Err(CanvasError::Shape(ShapeError::InvalidCoordinate {
+                name: "origin"
+            }))
+        ));
+        // Nothing moves at all for a bad offset, symmetric copies included
+        canvas
+            .set_symmetry(Some(Symmetry {
+                group: SymmetryGroup::Cyclic(2),
+                center: Coord::default(),
+            }))
+            .unwrap();
+        canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(3.0, 0.0),
+            radius: 1.0,
+        })));
+        let before = canvas.report();
+        assert!(matches!(
+            canvas.translate_all((f32::NAN, 1.0)),
+            Err(CanvasError::Shape(ShapeError::InvalidCoordinate {
+                name: "offset"
+            }))
+        ));
+        assert_eq!(canvas.report(), before);
+        canvas.set_origin(canvas.ids()[1], (0.0, 3.0)).unwrap();
+        let copy = canvas
+            .get(canvas.ids()[2])
+            .unwrap()
+            .lock()
+            .unwrap()
+            .origin();
+        assert!(copy.distance(Coord::new(0.0, -3.0)) < 1e-5);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "invalid shape on canvas")]
+    fn invalid_shapes_caught_in_debug() {
+        let mut canvas = Canvas::default();
+        canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::default(),
+            radius: -3.0,
+        })));
+    }
+
+    #[test]
+    fn physical_measurements() {
+        let mut canvas = Canvas::default();
+        canvas.set_dpi(Dpi(254.0)).unwrap();
+        // 10mm square
+        let side = canvas.to_canvas(Length::mm(10.0));
+        assert_approx_eq!(side, 100.0);
+        let id = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::default(),
+            side_a: side,
+            side_b: side,
+        })));
+        assert_approx_eq!(canvas.area_in(id, Unit::Mm).unwrap(), 100.0);
+        let perimeter = canvas.perimeter_in(id, Unit::In).unwrap();
+        assert_eq!(perimeter.unit, Unit::In);
+        assert_approx_eq!(perimeter.value, 40.0 / 25.4);
+        assert!(matches!(
+            canvas.set_dpi(Dpi(0.0)),
+            Err(CanvasError::InvalidDpi(_))
+        ));
+    }
+
+    #[test]
+    fn nearest_shapes() {
+        let circle = Circle {
+            origin: Coord::new(10.0, 0.0),
+            radius: 1.0,
+        };
+        let rectangle = Rectangle {
+            origin: Coord::new(-3.0, 0.0),
+            side_a: 2.0,
+            side_b: 2.0,
+        };
+        let triangle = Triangle {
+            origin: Coord::new(0.0, 20.0),
+            base: 2.0,
+            height: 2.0,
+        };
+        let mut canvas = Canvas::default();
+        let circle = canvas.add(Arc::new(ShapeCell::new(circle)));
+        let rectangle = canvas.add(Arc::new(ShapeCell::new(rectangle)));
+        let triangle = canvas.add(Arc::new(ShapeCell::new(triangle)));
+
+        assert_eq!(
+            canvas.nearest(Coord::default(), 2).unwrap(),
+            vec![rectangle, circle]
+        );
+        assert_eq!(
+            canvas.nearest(Coord::new(-3.0, 0.0), 5).unwrap(),
+            vec![rectangle, circle, triangle]
+        );
+        assert_eq!(canvas.nearest_to(circle, 1).unwrap(), vec![rectangle]);
+        assert!(canvas.nearest(Coord::default(), 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn overlapping_shapes_collide() {
+        let shapes: Vec<ShapeObject> = vec![
+            Arc::new(ShapeCell::new(Circle {
+                origin: Coord::default(),
+                radius: 1.0,
+            })),
+            Arc::new(ShapeCell::new(Rectangle {
+                origin: Coord::new(1.5, 0.0),
+                side_a: 2.0,
+                side_b: 2.0,
+            })),
+            // Bounding box overlaps the circle's but the triangle itself doesn't
+            Arc::new(ShapeCell::new(Triangle {
+                origin: Coord::new(-1.0, 1.5),
+                base: 1.0,
+                height: 1.0,
+            })),
+            Arc::new(ShapeCell::new(Circle {
+                origin: Coord::new(2.0, 0.5),
+                radius: 0.1,
+            })),
+        ];
+        let canvas: Canvas = shapes.into_iter().collect();
+        let ids = canvas.ids();
+
+        assert_eq!(
+            canvas.collisions(),
+            vec![(ids[0], ids[1]), (ids[1], ids[3])]
+        );
+    }
+
+    #[test]
+    fn raycast_hits_first_shape() {
+        let shapes: Vec<ShapeObject> = vec![
+            Arc::new(ShapeCell::new(Rectangle {
+                origin: Coord::new(10.0, 0.0),
+                side_a: 2.0,
+                side_b: 2.0,
+            })),
+            Arc::new(ShapeCell::new(Circle {
+                origin: Coord::new(5.0, 0.0),
+                radius: 1.0,
+            })),
+        ];
+        let mut canvas: Canvas = shapes.into_iter().collect();
+        // Off the ray, and further along it than the others
+        for i in 0..50 {
+            canvas.add(Arc::new(ShapeCell::new(Circle {
+                origin: Coord::new(i as f32 * 3.0, 20.0),
+                radius: 1.0,
+            })));
+        }
+        canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(300.0, 0.0),
+            radius: 1.0,
+        })));
+        let ids = canvas.ids().to_vec();
+
+        let check = |canvas: &Canvas| {
+            let hit = canvas.raycast((0.0, 0.0), (2.0, 0.0)).unwrap();
+            assert_eq!(
+                hit,
+                Some(RayHit {
+                    id: ids[1],
+                    point: Coord::new(4.0, 0.0),
+                    distance: 4.0,
+                })
+            );
+            // From inside the circle, its way out
+            let hit = canvas.raycast((5.0, 0.0), (1.0, 0.0)).unwrap().unwrap();
+            assert_eq!((hit.id, hit.point), (ids[1], Coord::new(6.0, 0.0)));
+            let hit = canvas.raycast((200.0, 0.0), (1.0, 0.0)).unwrap().unwrap();
+            assert_eq!(hit.id, ids[52]);
+            let hit = canvas.raycast((0.0, 20.0), (0.0, -1.0)).unwrap().unwrap();
+            assert_eq!(hit.id, ids[2]);
+            assert_eq!(canvas.raycast((0.0, 0.0), (-1.0, 0.0)).unwrap(), None);
+            assert_eq!(canvas.raycast((0.0, 10.0), (1.0, 0.0)).unwrap(), None);
+        };
+        check(&canvas);
+        #[cfg(feature = "spatial")]
+        for kind in [
+            IndexKind::SpatialHash { cell_size: 4.0 },
+            IndexKind::Quadtree {
+                bounds: Rectangle::from_corners(Coord::new(0.0, -50.0), Coord::new(200.0, 50.0)),
+            },
+        ] {
+            canvas.set_spatial_index(Some(kind));
+            check(&canvas);
+        }
+        assert!(matches!(
+            canvas.raycast(Coord::default(), Coord::default()),
+            Err(CanvasError::Shape(ShapeError::InvalidDimension {
+                name: "direction",
+                ..
+            }))
+        ));
+        assert!(matches!(
+            canvas.raycast(Coord::default(), (f32::NAN, 1.0)),
+            Err(CanvasError::Shape(ShapeError::InvalidCoordinate {
+                name: "direction"
+            }))
+        ));
+    }
+
+    #[test]
+    fn minimal_bounds_of_shapes() {
+        let shapes: Vec<ShapeObject> = vec![
+            Arc::new(ShapeCell::new(Circle {
+                origin: Coord::new(-3.0, 0.0),
+                radius: 1.0,
+            })),
+            Arc::new(ShapeCell::new(Rectangle {
+                origin: Coord::new(3.0, 0.0),
+                side_a: 2.0,
+                side_b: 2.0,
+            })),
+        ];
+        let canvas: Canvas = shapes.into_iter().collect();
+        let ids = canvas.ids();
+
+        let circle = canvas.enclosing_circle(ids).unwrap();
+        // Spanned by the far side of the circle and the square's outer corners
+        assert!(circle.origin.distance(Coord::new(1.0 / 16.0, 0.0)) < 1e-3);
+        assert_approx_eq!(circle.radius, 4.0625);
+
+        let rect = canvas.minimal_bounding_rect(ids).unwrap();
+        assert_approx_eq!(rect.get_area(), 8.0 * 2.0, 3e-3);
+        assert!(canvas.enclosing_circle(&[]).is_none());
+    }
+
+    #[test]
+    fn clip_canvas_to_window() {
+        let shapes: Vec<ShapeObject> = vec![
+            Arc::new(ShapeCell::new(Circle {
+                origin: Coord::default(),
+                radius: 1.0,
+            })),
+            Arc::new(ShapeCell::new(Rectangle {
+                origin: Coord::new(4.0, 0.0),
+                side_a: 4.0,
+                side_b: 2.0,
+            })),
+            Arc::new(ShapeCell::new(Triangle {
+                origin: Coord::new(20.0, 0.0),
+                base: 1.0,
+                height: 1.0,
+            })),
+        ];
+        let mut canvas: Canvas = shapes.into_iter().collect();
+        let outlined = Style {
+            fill: None,
+            stroke: Some(Color::BLACK),
+            stroke_width: 0.5,
+            dash: Some(Dash {
+                on: 1.0,
+                off: 0.5,
+                offset: 0.0,
+            }),
+            cap: LineCap::Round,
+            join: LineJoin::Bevel,
+            opacity: 0.5,
+        };
+        canvas.set_style(canvas.ids()[1], outlined).unwrap();
+        let undashed = Style {
+            dash: Some(Dash {
+                on: 0.0,
+                off: 0.0,
+                offset: 0.0,
+            }),
+            ..outlined
+        };
+        assert!(canvas.set_style(canvas.ids()[1], undashed).is_err());
+        let ids = canvas.ids();
+        let window = Rectangle {
+            origin: Coord::new(2.0, 0.0),
+            side_a: 6.0,
+            side_b: 6.0,
+        };
+
+        let clipped = canvas.clip_to(&window);
+        let clipped_ids = clipped.ids();
+        assert_eq!(clipped.len(), 2);
+        assert_eq!(clipped.get_area(clipped_ids[0]), canvas.get_area(ids[0]));
+        // The window edge at x = 5 cuts a quarter off the rectangle
+        assert_eq!(clipped.get_area(clipped_ids[1]), Some(6.0));
+        assert_eq!(clipped.style(clipped_ids[1]), Some(outlined));
+        assert_eq!(
+            clipped
+                .get(clipped_ids[1])
+                .unwrap()
+                .lock()
+                .unwrap()
+                .origin(),
+            Coord::new(4.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn pack_shapes_into_frame() {
+        let shapes: Vec<ShapeObject> = vec![
+            Arc::new(ShapeCell::new(Circle {
+                origin: Coord::new(50.0, 50.0),
+                radius: 1.0,
+            })),
+            Arc::new(ShapeCell::new(Rectangle {
+                origin: Coord::new(-7.0, 3.0),
+                side_a: 2.0,
+                side_b: 2.0,
+            })),
+            Arc::new(ShapeCell::new(Triangle {
+                origin: Coord::default(),
+                base: 10.0,
+                height: 1.0,
+            })),
+        ];
+        let canvas: Canvas = shapes.into_iter().collect();
+        let ids = canvas.ids();
+        let frame = Rectangle {
+            origin: Coord::new(2.0, 1.0),
+            side_a: 4.0,
+            side_b: 2.0,
+        };
+
+        assert_eq!(
+            canvas.pack(ids, &frame, PackStrategy::MaxRects).unwrap(),
+            vec![ids[2]]
+        );
+        for &id in &ids[..2] {
+            let bounds = canvas.get(id).unwrap().lock().unwrap().bounding_box();
+            assert!(bounds.min().x >= 0.0 && bounds.max().x <= 4.0);
+            assert!(bounds.min().y >= 0.0 && bounds.max().y <= 2.0);
+        }
+
+        // Symmetric copies follow their packed seed
+        let mut canvas = Canvas::default();
+        canvas
+            .set_symmetry(Some(Symmetry {
+                group: SymmetryGroup::Cyclic(2),
+                center: Coord::default(),
+            }))
+            .unwrap();
+        let seed = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::new(-7.0, 3.0),
+            side_a: 2.0,
+            side_b: 2.0,
+        })));
+        let copy = canvas.ids()[1];
+        assert!(canvas
+            .pack(&[seed], &frame, PackStrategy::Shelf)
+            .unwrap()
+            .is_empty());
+        let origin = |id| canvas.get(id).unwrap().lock().unwrap().origin();
+        assert_eq!(origin(seed), Coord::new(1.0, 1.0));
+        assert!(origin(copy).distance(Coord::new(-1.0, -1.0)) < 1e-5);
+    }
+
+    #[test]
+    fn pack_shapes_as_circles() {
+        let mut shapes: Vec<ShapeObject> = (0..12)
+            .map(|i| {
+                Arc::new(ShapeCell::new(Rectangle {
+                    origin: Coord::new(i as f32 * 10.0, 0.0),
+                    side_a: 1.0 + i as f32 % 3.0,
+                    side_b: 1.0,
+                })) as ShapeObject
+            })
+            .collect();
+        shapes.push(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(-40.0, 3.0),
+            radius: 3.0,
+        })));
+        let canvas: Canvas = shapes.into_iter().collect();
+        let ids = canvas.ids();
+        let container = Container::Circle(Circle {
+            origin: Coord::new(1.0, 1.0),
+            radius: 7.0,
+        });
+        assert!(canvas.pack_circles(ids, &container).unwrap().is_empty());
+        assert!(canvas.collisions().is_empty());
+        // The largest in the middle, and all inside
+        assert_eq!(
+            canvas.bounding_box(ids[12]).unwrap().origin,
+            Coord::new(1.0, 1.0)
+        );
+        for &id in ids {
+            let circle = canvas.enclosing_circle(&[id]).unwrap();
+            assert!(circle.origin.distance(Coord::new(1.0, 1.0)) + circle.radius <= 7.0 + 1e-3);
+        }
+
+        let small = Container::Rectangle(Rectangle {
+            origin: Coord::default(),
+            side_a: 4.0,
+            side_b: 4.0,
+        });
+        let circle = canvas.bounding_box(ids[12]).unwrap();
+        assert_eq!(canvas.pack_circles(&ids[12..], &small).unwrap(), [ids[12]]);
+        assert_eq!(canvas.bounding_box(ids[12]), Some(circle));
+    }
+
+    #[test]
+    fn arrange_shapes_in_grid() {
+        let shapes: Vec<ShapeObject> = vec![
+            Arc::new(ShapeCell::new(Circle {
+                origin: Coord::new(0.0, 0.0),
+                radius: 1.0,
+            })),
+            Arc::new(ShapeCell::new(Rectangle {
+                origin: Coord::new(10.0, 10.0),
+                side_a: 4.0,
+                side_b: 2.0,
+            })),
+            Arc::new(ShapeCell::new(Triangle {
+                origin: Coord::new(-5.0, 5.0),
+                base: 2.0,
+                height: 3.0,
+            })),
+            Arc::new(ShapeCell::new(Circle {
+                origin: Coord::new(3.0, -7.0),
+                radius: 2.0,
+            })),
+            Arc::new(ShapeCell::new(Polygon {
+                origin: Coord::default(),
+                points: vec![Coord::default(), Coord::new(6.0, 0.0), Coord::new(0.0, 1.0)],
+            })),
+        ];
+        let mut canvas: Canvas = shapes.into_iter().collect();
+        let ids = canvas.ids().to_vec();
+        let centroids = |canvas: &Canvas| -> Vec<Coord> {
+            canvas
+                .outlines(&ids)
+                .iter()
+                .map(|outline| outline.centroid())
+                .collect()
+        };
+
+        canvas.arrange_grid(&ids, 2, 1.0).unwrap();
+        let c = centroids(&canvas);
+        // Columns and rows line up, evenly spaced, reading down from the top
+        assert_approx_eq!(c[0].x, c[2].x);
+        assert_approx_eq!(c[1].x, c[3].x);
+        assert_approx_eq!(c[4].x, c[0].x);
+        assert_approx_eq!(c[0].y, c[1].y);
+        assert_approx_eq!(c[2].y, c[3].y);
+        assert!(c[2].y < c[0].y);
+        assert_approx_eq!(c[0].y - c[2].y, c[2].y - c[4].y);
+        // Below and right of the old top left corner, with no box reaching
+        // within the spacing of another
+        let boxes: Vec<Rectangle> = ids
+            .iter()
+            .map(|&id| canvas.bounding_box(id).unwrap())
+            .collect();
+        assert!(boxes
+            .iter()
+            .all(|b| b.min().x >= -6.0 - 1e-4 && b.max().y <= 11.0 + 1e-4));
+        for (i, a) in boxes.iter().enumerate() {
+            for b in &boxes[i + 1..] {
+                assert!(a.distance_to_box(b) >= 1.0 - 1e-4);
+            }
+        }
+
+        // With y down the rows run the other way
+        canvas.set_axes(Axes {
+            y: YAxis::Down,
+            ..Axes::default()
+        });
+        canvas.arrange_grid(&ids, 3, 0.0).unwrap();
+        let c = centroids(&canvas);
+        assert!(c[3].y > c[0].y);
+        assert_approx_eq!(c[3].x, c[0].x);
+
+        assert!(canvas.arrange_grid(&ids, 0, 1.0).is_err());
+        assert!(canvas.arrange_grid(&ids, 2, -1.0).is_err());
+        let gone = ids[0];
+        canvas.remove(gone);
+        assert!(matches!(
+            canvas.arrange_grid(&ids, 2, 1.0),
+            Err(CanvasError::UnknownShape(id)) if id == gone
+        ));
+    }
+
+    #[test]
+    fn symmetric_copies_follow_seed() {
+        let mut canvas = Canvas::default();
+        for n in [0, MAX_ORDER + 1, 4_000_000_000] {
+            let huge = Symmetry {
+                group: SymmetryGroup::Dihedral(n),
+                center: Coord::default(),
+            };
+            assert!(matches!(
+                canvas.set_symmetry(Some(huge)),
+                Err(CanvasError::InvalidSymmetryOrder(_))
+            ));
+        }
+        canvas
+            .set_symmetry(Some(Symmetry {
+                group: SymmetryGroup::Dihedral(6),
+                center: Coord::default(),
+            }))
+            .unwrap();
+        let seed = canvas.add(Arc::new(ShapeCell::new(Triangle {
+            origin: Coord::new(5.0, 1.0),
+            base: 1.0,
+            height: 2.0,
+        })));
+        assert_eq!(canvas.len(), 12);
+        for &id in canvas.ids() {
+            assert_approx_eq!(canvas.get_area(id).unwrap(), 1.0);
+        }
+
+        // Moving the seed, or any copy, keeps the pattern symmetric
+        canvas.set_origin(seed, Coord::new(0.0, 3.0)).unwrap();
+        canvas
+            .set_origin(canvas.ids()[7], Coord::new(0.0, 4.0))
+            .unwrap();
+        for shape in canvas.shapes.values() {
+            let origin = shape.lock().unwrap().origin();
+            assert_approx_eq!(origin.length(), 4.0);
+        }
+
+        // Styling any copy styles the whole pattern
+        let style = Style {
+            fill: Some(Color::rgb(200, 0, 0).into()),
+            ..Default::default()
+        };
+        canvas.set_style(canvas.ids()[3], style).unwrap();
+        assert!(canvas
+            .ids()
+            .iter()
+            .all(|&id| canvas.style(id) == Some(style)));
+
+        canvas.set_symmetry(None).unwrap();
+        let circle = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(5.0, 5.0),
+            radius: 1.0,
+        })));
+        assert_eq!(canvas.len(), 13);
+        assert_eq!(canvas.style(circle), Some(Style::default()));
+        assert!(matches!(
+            canvas.set_style(
+                circle,
+                Style {
+                    stroke_width: -1.0,
+                    ..style
+                }
+            ),
+            Err(CanvasError::Shape(ShapeError::InvalidDimension { .. }))
+        ));
+        assert!(matches!(
+            canvas.set_style(
+                circle,
+                Style {
+                    opacity: 1.5,
+                    ..style
+                }
+            ),
+            Err(CanvasError::InvalidOpacity(_))
+        ));
+        canvas.remove(seed);
+        assert_eq!(canvas.len(), 1);
+    }
+
+    #[test]
+    fn removed_copies_leave_nothing_behind() {
+        let mut canvas = Canvas::default();
+        canvas
+            .set_symmetry(Some(Symmetry {
+                group: SymmetryGroup::Cyclic(3),
+                center: Coord::default(),
+            }))
+            .unwrap();
+        let seed = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(5.0, 0.0),
+            radius: 1.0,
+        })));
+        let copy = canvas.ids()[2];
+        canvas.set_symmetry(None).unwrap();
+        let style = Style {
+            opacity: 0.5,
+            ..Default::default()
+        };
+        canvas.set_style(copy, style).unwrap();
+        canvas.set_meta(copy, "label", "copy").unwrap();
+        canvas.tag(copy, "pattern").unwrap();
+        canvas.set_visible(copy, false).unwrap();
+        let motion = Motion {
+            velocity: Coord::new(1.0, 0.0),
+            acceleration: Coord::default(),
+        };
+        canvas.set_motion(copy, motion).unwrap();
+        canvas
+            .constrain(copy, Constraint::Pin(Coord::new(0.0, 5.0)))
+            .unwrap();
+        // A group member held at an offset from the copy
+        let group = canvas.add_group(None, Transform::default()).unwrap();
+        let member = canvas
+            .add_to_group(
+                group,
+                Arc::new(ShapeCell::new(Circle {
+                    origin: Coord::default(),
+                    radius: 1.0,
+                })),
+            )
+            .unwrap();
+        let offset = Coord::new(2.0, 0.0);
+        canvas
+            .constrain(member, Constraint::Offset { from: copy, offset })
+            .unwrap();
+
+        canvas.try_remove(seed).unwrap();
+        assert_eq!(canvas.ids(), [member]);
+        assert_eq!(canvas.group_members(group).unwrap(), [member]);
+        assert!(canvas.constraints.is_empty());
+        for slot in [seed.slot(), copy.slot()] {
+            assert!(canvas.styles.get(slot).is_none_or(Option::is_none));
+            assert!(canvas.metadata.get(slot).is_none_or(Option::is_none));
+            assert!(canvas.tags.get(slot).is_none_or(Option::is_none));
+            assert!(canvas.hidden.get(slot).is_none_or(Option::is_none));
+            assert!(canvas.motions.get(slot).is_none_or(Option::is_none));
+        }
+        // Whatever reuses the slots starts out plain
+        let reused = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::default(),
+            radius: 1.0,
+        })));
+        assert_eq!(canvas.style(reused), Some(Style::default()));
+        assert!(canvas.is_visible(reused));
+        assert_eq!(canvas.motion(reused), Some(Motion::default()));
+    }
+
+    #[test]
+    fn mirrored_twins() {
+        let mut canvas = Canvas::default();
+        let diagonal = MirrorLine {
+            point: Coord::default(),
+            direction: Coord::new(1.0, 1.0),
+        };
+        let square = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::new(3.0, 0.0),
+            side_a: 1.0,
+            side_b: 1.0,
+        })));
+
+        // One-off reflections don't follow the source
+        let copy = canvas.reflect_across(&[square], &diagonal)[0];
+        canvas.set_origin(square, Coord::new(4.0, 0.0)).unwrap();
+        let copy_origin = canvas.get(copy).unwrap().lock().unwrap().origin();
+        assert!(copy_origin.distance(Coord::new(0.0, 3.0)) < 1e-5);
+
+        canvas.set_live_mirroring(true);
+        canvas.add_mirror_line(MirrorLine {
+            point: Coord::new(0.0, 1.0),
+            direction: Coord::new(1.0, 0.0),
+        });
+        let twin = canvas.reflect_across(&[square], &diagonal)[0];
+        let circle = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(2.0, 3.0),
+            radius: 1.0,
+        })));
+        assert_eq!(canvas.len(), 5);
+
+        canvas.set_origin(twin, Coord::new(0.0, 5.0)).unwrap();
+        let origin = canvas.get(square).unwrap().lock().unwrap().origin();
+        assert!(origin.distance(Coord::new(5.0, 0.0)) < 1e-5);
+        canvas.set_origin(circle, Coord::new(2.0, 4.0)).unwrap();
+        let reflection = canvas.ids()[4];
+        let origin = canvas.get(reflection).unwrap().lock().unwrap().origin();
+        assert!(origin.distance(Coord::new(2.0, -2.0)) < 1e-5);
+    }
+
+    #[test]
+    fn metadata_follows_copies() {
+        let mut canvas = Canvas::default();
+        canvas
+            .set_symmetry(Some(Symmetry {
+                group: SymmetryGroup::Cyclic(3),
+                center: Coord::default(),
+            }))
+            .unwrap();
+        let seed = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(4.0, 0.0),
+            radius: 1.0,
+        })));
+        canvas.set_symmetry(None).unwrap();
+        assert_eq!(canvas.metadata(seed), None);
+
+        // Set on a copy, it's set on the whole pattern
+        let copy = canvas.ids()[2];
+        assert_eq!(canvas.set_meta(copy, "label", "petal").unwrap(), None);
+        assert_eq!(
+            canvas.set_meta(seed, "label", "leaf").unwrap().as_deref(),
+            Some("petal")
+        );
+        canvas.set_meta(seed, "source", "scan 3").unwrap();
+        assert!(canvas
+            .ids()
+            .iter()
+            .all(|&id| canvas.meta(id, "label") == Some("leaf")));
+        assert_eq!(
+            canvas.remove_meta(copy, "source").as_deref(),
+            Some("scan 3")
+        );
+        assert_eq!(canvas.metadata(seed).unwrap().len(), 1);
+
+        // Reflected and clipped copies keep it
+        let twin = canvas.reflect_across(
+            &[seed],
+            &MirrorLine {
+                point: Coord::default(),
+                direction: Coord::new(0.0, 1.0),
+            },
+        )[0];
+        assert_eq!(canvas.meta(twin, "label"), Some("leaf"));
+        let clipped = canvas.clip_to(&Rectangle {
+            origin: Coord::new(4.0, 0.0),
+            side_a: 4.0,
+            side_b: 4.0,
+        });
+        assert_eq!(clipped.meta(clipped.ids()[0], "label"), Some("leaf"));
+
+        let square = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::new(0.0, 0.0),
+            side_a: 1.0,
+            side_b: 1.0,
+        })));
+        let metadata = Metadata::from([("label".to_string(), "square".to_string())]);
+        canvas.set_metadata(square, metadata.clone()).unwrap();
+        assert_eq!(canvas.metadata(square), Some(&metadata));
+        // Not passed on to a shape reusing the slot
+        canvas.remove(square);
+        let circle = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(0.0, 0.0),
+            radius: 1.0,
+        })));
+        assert_eq!(canvas.metadata(circle), None);
+        assert!(matches!(
+            canvas.set_meta(square, "label", "gone"),
+            Err(CanvasError::UnknownShape(_))
+        ));
+    }
+
+    #[test]
+    fn tagged_shapes() {
+        let mut canvas = Canvas::default();
+        let circle = |x: f32| {
+            Arc::new(ShapeCell::new(Circle {
+                origin: Coord::new(x, 0.0),
+                radius: 1.0,
+            })) as ShapeObject
+        };
+        let first = canvas.add(circle(-10.0));
+        let plain = canvas.add(circle(-5.0));
+        canvas
+            .set_symmetry(Some(Symmetry {
+                group: SymmetryGroup::Cyclic(2),
+                center: Coord::default(),
+            }))
+            .unwrap();
+        let seed = canvas.add(circle(3.0));
+        canvas.set_symmetry(None).unwrap();
+        let copy = canvas.ids()[3];
+
+        assert!(canvas.tag(first, "background").unwrap());
+        assert!(!canvas.tag(first, "background").unwrap());
+        assert!(canvas.tag(copy, "background").unwrap());
+        canvas.tag(first, "guide").unwrap();
+        assert_eq!(canvas.tags(first).unwrap(), ["background", "guide"]);
+        assert_eq!(canvas.tags(plain).unwrap(), [] as [String; 0]);
+        assert_eq!(canvas.find_by_tag("background"), [first, seed, copy]);
+
+        // The pattern moves as one, under its symmetry
+        canvas
+            .move_tagged("background", Coord::new(0.0, 1.0))
+            .unwrap();
+        let origin = |canvas: &Canvas, id| canvas.get(id).unwrap().lock().unwrap().origin();
+        assert_eq!(origin(&canvas, first), Coord::new(-10.0, 1.0));
+        assert_eq!(origin(&canvas, seed), Coord::new(3.0, 1.0));
+        assert_approx_eq!(origin(&canvas, copy).x, -3.0);
+        assert_approx_eq!(origin(&canvas, copy).y, -1.0);
+        assert_eq!(origin(&canvas, plain), Coord::new(-5.0, 0.0));
+
+        assert!(canvas.untag(first, "guide"));
+        assert!(!canvas.untag(plain, "guide"));
+        assert_eq!(canvas.remove_tagged("background"), [first, seed, copy]);
+        assert_eq!(canvas.ids(), [plain]);
+        assert_eq!(canvas.tags(first), None);
+        assert!(canvas.find_by_tag("background").is_empty());
+    }
+
+    #[test]
+    fn hidden_shapes() {
+        let mut canvas = Canvas::default();
+        let square = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::new(0.0, 0.0),
+            side_a: 2.0,
+            side_b: 2.0,
+        })));
+        canvas
+            .set_symmetry(Some(Symmetry {
+                group: SymmetryGroup::Cyclic(2),
+                center: Coord::default(),
+            }))
+            .unwrap();
+        let seed = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(5.0, 0.0),
+            radius: 1.0,
+        })));
+        canvas.set_symmetry(None).unwrap();
+        let copy = canvas.ids()[2];
+
+        // Hidden along with its copy, but still on the canvas
+        canvas.set_visible(copy, false).unwrap();
+        assert!(!canvas.is_visible(seed));
+        assert_eq!(canvas.visible_ids(), [square]);
+        assert_eq!(canvas.len(), 3);
+        let everywhere = Rectangle {
+            origin: Coord::default(),
+            side_a: 20.0,
+            side_b: 20.0,
+        };
+        assert_eq!(canvas.query_region(&everywhere).len(), 3);
+        let drawn: Vec<ShapeId> = canvas.draw_commands().iter().map(|c| c.id).collect();
+        assert_eq!(drawn, [square]);
+        assert_eq!(
+            canvas.fill_mesh(),
+            Mesh::fill(&canvas.outlines(&[square])[0])
+        );
+
+        // Not hit unless asked for
+        assert!(canvas.hit_test(Coord::new(5.0, 0.0)).is_empty());
+        canvas.set_hit_test_hidden(true);
+        assert_eq!(canvas.hit_test(Coord::new(5.0, 0.0)), [seed]);
+        canvas.set_hit_test_hidden(false);
+
+        canvas.set_visible(seed, true).unwrap();
+        canvas.tag(square, "construction").unwrap();
+        canvas.set_tagged_visible("construction", false);
+        assert_eq!(canvas.visible_ids(), [seed, copy]);
+        canvas.set_tagged_visible("construction", true);
+
+        // Hiding a group hides what's in it and in the groups inside it
+        let layer = canvas.add_group(None, Transform::IDENTITY).unwrap();
+        let inner = canvas.add_group(Some(layer), Transform::IDENTITY).unwrap();
+        let member = canvas
+            .add_to_group(
+                inner,
+                Arc::new(ShapeCell::new(Circle {
+                    origin: Coord::new(0.0, 9.0),
+                    radius: 1.0,
+                })),
+            )
+            .unwrap();
+        canvas.set_group_visible(layer, false).unwrap();
+        assert!(!canvas.is_visible(member));
+        assert!(canvas.group_visible(inner).unwrap());
+        canvas.set_group_visible(layer, true).unwrap();
+        assert!(canvas.is_visible(member));
+        assert!(matches!(
+            canvas.set_visible(ShapeId::from_bits(u64::MAX), false),
+            Err(CanvasError::UnknownShape(_))
+        ));
+    }
+
+    #[test]
+    fn locked_shapes_stay_put() {
+        let mut canvas = Canvas::default();
+        let circle = |x: f32| {
+            Arc::new(ShapeCell::new(Circle {
+                origin: Coord::new(x, 0.0),
+                radius: 1.0,
+            })) as ShapeObject
+        };
+        let reference = canvas.add(circle(-10.0));
+        let free = canvas.add(circle(-5.0));
+        canvas
+            .set_symmetry(Some(Symmetry {
+                group: SymmetryGroup::Cyclic(2),
+                center: Coord::default(),
+            }))
+            .unwrap();
+        let seed = canvas.add(circle(4.0));
+        canvas.set_symmetry(None).unwrap();
+        let copy = canvas.ids()[3];
+        canvas.lock_shape(reference).unwrap();
+        canvas.lock_shape(copy).unwrap();
+        assert!(canvas.is_locked(seed));
+        let origin = |canvas: &Canvas, id| canvas.get(id).unwrap().lock().unwrap().origin();
+
+        assert!(matches!(
+            canvas.set_origin(reference, Coord::new(0.0, 0.0)),
+            Err(CanvasError::Locked(_))
+        ));
+        assert!(matches!(
+            canvas.set_origin(copy, Coord::new(0.0, 9.0)),
+            Err(CanvasError::Locked(_))
+        ));
+        assert!(matches!(
+            canvas.try_remove(seed),
+            Err(CanvasError::Locked(_))
+        ));
+        assert!(canvas.remove(reference).is_none());
+        assert_eq!(canvas.len(), 4);
+
+        // Bulk operations go around them
+        canvas.translate_all(Coord::new(0.0, 1.0)).unwrap();
+        canvas.rotate_all(PI, Coord::default()).unwrap();
+        for tag in [reference, free, seed] {
+            canvas.tag(tag, "all").unwrap();
+        }
+        canvas.move_tagged("all", Coord::new(1.0, 0.0)).unwrap();
+        assert_eq!(origin(&canvas, reference), Coord::new(-10.0, 0.0));
+        assert_eq!(origin(&canvas, seed), Coord::new(4.0, 0.0));
+        assert!(origin(&canvas, copy).distance(Coord::new(-4.0, 0.0)) < 1e-5);
+        assert_approx_eq!(origin(&canvas, free).x, 6.0);
+        assert_approx_eq!(origin(&canvas, free).y, -1.0);
+        let frame = Rectangle {
+            origin: Coord::default(),
+            side_a: 20.0,
+            side_b: 20.0,
+        };
+        assert_eq!(
+            canvas
+                .pack(&[reference, free], &frame, PackStrategy::Shelf)
+                .unwrap(),
+            [reference]
+        );
+        assert!(matches!(
+            canvas.arrange_grid(&[free, seed], 2, 1.0),
+            Err(CanvasError::Locked(_))
+        ));
+        assert_eq!(canvas.remove_tagged("all"), [free]);
+
+        // As are group members
+        let group = canvas.add_group(None, Transform::IDENTITY).unwrap();
+        let member = canvas.add_to_group(group, circle(0.0)).unwrap();
+        canvas.lock_shape(member).unwrap();
+        assert!(matches!(
+            canvas.set_group_transform(group, Transform::translation(Coord::new(1.0, 1.0))),
+            Err(CanvasError::Locked(_))
+        ));
+        canvas.unlock_shape(member).unwrap();
+        canvas
+            .set_group_transform(group, Transform::translation(Coord::new(1.0, 1.0)))
+            .unwrap();
+        assert_eq!(origin(&canvas, member), Coord::new(1.0, 1.0));
+        canvas.unlock_shape(seed).unwrap();
+        assert!(canvas.remove(copy).is_some());
+    }
+
+    #[test]
+    fn rotate_whole_canvas() {
+        let mut canvas = Canvas::default();
+        canvas
+            .set_symmetry(Some(Symmetry {
+                group: SymmetryGroup::Cyclic(2),
+                center: Coord::default(),
+            }))
+            .unwrap();
+        let square = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::new(2.0, 0.0),
+            side_a: 1.0,
+            side_b: 1.0,
+        })));
+        canvas.set_symmetry(None).unwrap();
+        let circle = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(0.0, 3.0),
+            radius: 1.0,
+        })));
+
+        canvas.rotate_all(PI / 2.0, Coord::new(0.0, 1.0)).unwrap();
+        let origin = |id: ShapeId| canvas.get(id).unwrap().lock().unwrap().origin();
+        assert!(origin(square).distance(Coord::new(1.0, 3.0)) < 1e-5);
+        assert!(origin(circle).distance(Coord::new(-2.0, 1.0)) < 1e-5);
+        assert_approx_eq!(canvas.get_area(square).unwrap(), 1.0);
+
+        // The half turn copy still follows the square, about the rotated centre
+        canvas.set_origin(square, Coord::new(1.0, 5.0)).unwrap();
+        let copy = canvas.ids()[1];
+        assert!(origin(copy).distance(Coord::new(1.0, -3.0)) < 1e-5);
+    }
+
+    #[test]
+    fn recenter_on_center_of_mass() {
+        let mut canvas = Canvas::default();
+        assert_eq!(canvas.center_of_mass(), None);
+        let square = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::default(),
+            side_a: 2.0,
+            side_b: 2.0,
+        })));
+        let bar = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::new(5.0, 0.0),
+            side_a: 4.0,
+            side_b: 1.0,
+        })));
+        assert!(
+            canvas
+                .center_of_mass()
+                .unwrap()
+                .distance(Coord::new(2.5, 0.0))
+                < 1e-5
+        );
+
+        let offset = canvas.recenter_on((0.0, 1.0)).unwrap();
+        assert!(offset.distance(Coord::new(-2.5, 1.0)) < 1e-5);
+        assert!(
+            canvas
+                .center_of_mass()
+                .unwrap()
+                .distance(Coord::new(0.0, 1.0))
+                < 1e-5
+        );
+
+        // With the square held, the bar goes twice as far
+        canvas.lock_shape(square).unwrap();
+        let offset = canvas.recenter_on((10.0, 1.0)).unwrap();
+        assert!(offset.distance(Coord::new(20.0, 0.0)) < 1e-4);
+        assert!(
+            canvas
+                .center_of_mass()
+                .unwrap()
+                .distance(Coord::new(10.0, 1.0))
+                < 1e-4
+        );
+        canvas.lock_shape(bar).unwrap();
+        assert_eq!(canvas.recenter_on((0.0, 0.0)).unwrap(), Coord::default());
+    }
+
+    #[test]
+    fn cached_bounds_follow_changes() {
+        let mut canvas = Canvas::default();
+        let circle: ShapeObject = Arc::new(ShapeCell::new(Circle {
+            origin: Coord::default(),
+            radius: 1.0,
+        }));
+        let circle_id = canvas.add(circle.clone());
+        let square_id = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::new(5.0, 0.0),
+            side_a: 2.0,
+            side_b: 2.0,
+        })));
+        let square = canvas.bounding_box(square_id).unwrap();
+
+        // Reading doesn't change the version, writing through any handle does
+        let version = circle.version();
+        assert_area_eq!(*circle.lock().unwrap(), PI);
+        assert_eq!(circle.version(), version);
+        circle.lock().unwrap().set_origin(Coord::new(10.0, 0.0));
+        assert_ne!(circle.version(), version);
+        assert_eq!(
+            canvas.bounding_box(circle_id).unwrap().origin,
+            Coord::new(10.0, 0.0)
+        );
+
+        canvas.remove(circle_id);
+        assert_eq!(canvas.bounding_box(square_id), Some(square));
+        assert_eq!(canvas.bounding_box(circle_id), None);
+    }
+
+    #[test]
+    fn history_counts_changes() {
+        let mut canvas = Canvas::default();
+        let circle = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::default(),
+            radius: 1.0,
+        })));
+        let added = canvas.history(circle).unwrap();
+        assert_eq!(added.created, added.modified);
+        assert_eq!(added.revision, 0);
+
+        let before = SystemTime::now();
+        let square = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::new(5.0, 0.0),
+            side_a: 2.0,
+            side_b: 2.0,
+        })));
+        assert_eq!(canvas.changed_since(before), [square]);
+
+        // One change per guard, however many writes it makes, and changes
+        // beside the shape count without changing its version
+        {
+            let mut shape = canvas.get(circle).unwrap().lock().unwrap();
+            shape.set_origin(Coord::new(1.0, 0.0));
+            shape.set_origin(Coord::new(2.0, 0.0));
+        }
+        let version = canvas.get(circle).unwrap().version();
+        canvas.tag(circle, "round").unwrap();
+        canvas.tag(circle, "round").unwrap();
+        assert_eq!(canvas.get(circle).unwrap().version(), version);
+        let history = canvas.history(circle).unwrap();
+        assert_eq!((history.created, history.revision), (added.created, 2));
+        assert!(history.modified >= before);
+        assert_eq!(canvas.changed_since(before), [circle, square]);
+        canvas.remove(square);
+        assert_eq!(canvas.history(square), None);
+    }
+
+    #[test]
+    fn memoized_geometry() {
+        let mut canvas = Canvas::default();
+        // An arrowhead, so the hull drops the notch
+        let id = canvas.add(Arc::new(ShapeCell::new(Polygon {
+            origin: Coord::default(),
+            points: vec![
+                Coord::new(0.0, 0.0),
+                Coord::new(4.0, 2.0),
+                Coord::new(0.0, 4.0),
+                Coord::new(1.0, 2.0),
+            ],
+        })));
+
+        let triangles = canvas.triangles(id).unwrap();
+        assert_eq!(triangles.len(), 2);
+        assert!(Arc::ptr_eq(&triangles, &canvas.triangles(id).unwrap()));
+        assert_eq!(canvas.convex_hull(id).unwrap().len(), 3);
+        assert_eq!(canvas.get_area(id), Some(6.0));
+
+        // Moving the shape invalidates everything derived from it
+        canvas.set_origin(id, Coord::new(10.0, 0.0)).unwrap();
+        let moved = canvas.triangles(id).unwrap();
+        assert!(!Arc::ptr_eq(&triangles, &moved));
+        assert!(moved.iter().all(|t| t.a.x >= 10.0));
+        assert_eq!(canvas.total_area(), 6.0);
+    }
+
+    #[cfg(feature = "spatial")]
+    #[test]
+    fn spatial_queries() {
+        let bounds = Rectangle {
+            origin: Coord::new(50.0, 50.0),
+            side_a: 100.0,
+            side_b: 100.0,
+        };
+        for kind in [
+            None,
+            Some(IndexKind::Quadtree { bounds }),
+            Some(IndexKind::SpatialHash { cell_size: 4.0 }),
+        ] {
+            let mut canvas = Canvas::default();
+            canvas.set_spatial_index(kind.clone());
+            let shapes: Vec<ShapeId> = (0..100)
+                .map(|i| {
+                    canvas.add(Arc::new(ShapeCell::new(Circle {
+                        origin: Coord::new((i % 10) as f32 * 10.0, (i / 10) as f32 * 10.0),
+                        radius: 1.0,
+                    })))
+                })
+                .collect();
+            let square = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+                origin: Coord::new(10.0, 10.0),
+                side_a: 4.0,
+                side_b: 4.0,
+            })));
+
+            assert_eq!(
+                canvas.hit_test(Coord::new(10.5, 10.5)),
+                vec![square, shapes[11]]
+            );
+            // Inside the bounding box of the circle but not the circle
+            assert!(canvas.hit_test(Coord::new(0.9, 0.9)).is_empty());
+            let region = Rectangle::from_corners(Coord::new(15.0, -5.0), Coord::new(31.0, 0.5));
+            assert_eq!(canvas.query_region(&region), vec![shapes[2], shapes[3]]);
+            // Nearest shapes are the same however the candidates are found,
+            // including from well outside them all
+            assert_eq!(
+                canvas.nearest((42.0, 0.0), 3).unwrap(),
+                [shapes[4], shapes[5], shapes[14]]
+            );
+            assert_eq!(canvas.nearest((500.0, 500.0), 1).unwrap(), [shapes[99]]);
+            assert_eq!(canvas.nearest_to(square, 1).unwrap(), [shapes[11]]);
+            assert_eq!(canvas.nearest((0.0, 0.0), 200).unwrap().len(), 101);
+
+            // Changes made through any handle are picked up, and points can be
+            // given as tuples or arrays
+            canvas.set_origin(shapes[2], (80.0, 80.0)).unwrap();
+            canvas.remove(shapes[3]);
+            assert!(canvas.query_region(&region).is_empty(), "{kind:?}");
+            assert_eq!(canvas.hit_test([80.0, 80.0]), vec![shapes[88], shapes[2]]);
+        }
+    }
+
+    // Multithreaded tests - eg what is in main
+    #[test]
+    fn concurrent_moves_not_lost() {
+        let mut canvas = Canvas::default();
+        let plain = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::default(),
+            radius: 1.0,
+        })));
+        canvas
+            .set_symmetry(Some(Symmetry {
+                group: SymmetryGroup::Cyclic(4),
+                center: Coord::default(),
+            }))
+            .unwrap();
+        let seed = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(10.0, 0.0),
+            radius: 1.0,
+        })));
+        let increment = |id: ShapeId| loop {
+            let origin = canvas.get(id).unwrap().lock().unwrap().origin();
+            let moved = origin + Coord::new(0.0, 1.0);
+            if canvas.compare_and_set_origin(id, origin, moved).unwrap() {
+                break;
+            }
+        };
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..100 {
+                        increment(plain);
+                        increment(seed);
+                    }
+                });
+            }
+        });
+        let origin = |id| canvas.get(id).unwrap().lock().unwrap().origin();
+        assert_eq!(origin(plain), Coord::new(0.0, 800.0));
+        assert_eq!(origin(seed), Coord::new(10.0, 800.0));
+        // The copies moved with every one of them
+        for &id in &canvas.ids()[2..] {
+            assert_approx_eq!(origin(id).length(), origin(seed).length(), 1e-4);
+        }
+
+        assert!(!canvas
+            .compare_and_set_origin(plain, Coord::default(), Coord::new(1.0, 1.0))
+            .unwrap());
+        assert!(canvas
+            .compare_and_set_origin(plain, (0.0, 800.0), (f32::NAN, 0.0))
+            .is_err());
+    }
+
+    #[test]
+    fn poisoned_shapes_are_left_out() {
+        let mut canvas = Canvas::default();
+        let [a, b, c] = [0.0, 1.0, 2.0].map(|x| {
+            canvas.add(Arc::new(ShapeCell::new(Circle {
+                origin: Coord::new(x, 0.0),
+                radius: 0.75,
+            })))
+        });
+        let poisoned = canvas.get(a).unwrap().clone();
+        thread::spawn(move || {
+            let _guard = poisoned.lock().unwrap();
+            panic!("poisoning the shape lock");
+        })
+        .join()
+        .unwrap_err();
+
+        assert_eq!(canvas.collisions(), [(b, c)]);
+        assert_eq!(canvas.hit_test((-0.5, 0.0)), []);
+        assert_eq!(canvas.hit_test((1.0, 0.0)), [b]);
+        assert_eq!(canvas.bounding_box(a), None);
+        assert_eq!(canvas.draw_commands().len(), 2);
+        assert_eq!(canvas.report().rows.len(), 2);
+        #[cfg(feature = "spatial")]
+        canvas.set_spatial_index(Some(IndexKind::SpatialHash { cell_size: 1.0 }));
+        let everything = Rectangle::from_corners(Coord::new(-5.0, -5.0), Coord::new(5.0, 5.0));
+        assert_eq!(canvas.query_region(&everything), [b, c]);
+
+        assert!(matches!(
+            canvas.set_origin(a, (5.0, 5.0)),
+            Err(CanvasError::Poisoned)
+        ));
+        assert!(matches!(
+            canvas.rotate_all(PI, Coord::default()),
+            Err(CanvasError::Poisoned)
+        ));
+        assert_eq!(
+            canvas.get(b).unwrap().lock().unwrap().origin(),
+            Coord::new(1.0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, LockResult, Mutex, MutexGuard, PoisonError, TryLockError,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{metrics::record_lock_wait, Shape};
+
+// A lockable shape with a version stamp that changes whenever the shape is
+// borrowed mutably, so caches can tell when their copy is out of date.
+// Stamps come from one global counter, so they are never reused either by
+// the same shape or by a different one.
+//
+// Apart from the stamp, each cell keeps when the shape was made and last
+// changed, as nanoseconds since the Unix epoch, and its revision, a count
+// of its changes. These go with the shape through storage, which the stamp
+// doesn't, and also count changes the canvas keeps beside it, such as its
+// style, which caches don't care about.
+pub struct ShapeCell<S: ?Sized> {
+    version: AtomicU64,
+    created: AtomicU64,
+    modified: AtomicU64,
+    revision: AtomicU64,
+    shape: Mutex<S>,
+}
+
+// When a shape was made and last changed, and how many times it has been
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct History {
+    pub created: SystemTime,
+    pub modified: SystemTime,
+    pub revision: u64,
+}
+
+pub(crate) fn nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_nanos() as u64)
+}
+
+pub(crate) fn time(nanos: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_nanos(nanos)
+}
+
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn next_version() -> u64 {
+    NEXT_VERSION.fetch_add(1, Ordering::Relaxed)
+}
+
+// The stamp the next change to any shape will get, so unchanged since a
+// previous call means no shape anywhere has changed in between
+pub fn latest_version() -> u64 {
+    NEXT_VERSION.load(Ordering::Acquire)
+}
+
+impl<S> ShapeCell<S> {
+    pub fn new(shape: S) -> ShapeCell<S> {
+        let now = nanos(SystemTime::now());
+        ShapeCell {
+            version: AtomicU64::new(next_version()),
+            created: AtomicU64::new(now),
+            modified: AtomicU64::new(now),
+            revision: AtomicU64::new(0),
+            shape: Mutex::new(shape),
+        }
+    }
+}
+
+impl<S: ?Sized> ShapeCell<S> {
+    // Waits for a lock held elsewhere are timed, for metrics::lock_waits
+    // and, with tracing on, an event
+    pub fn lock(&self) -> LockResult<ShapeGuard<'_, S>> {
+        let wrap = |guard| ShapeGuard {
+            guard,
+            cell: self,
+            changed: false,
+        };
+        let locked = match self.shape.try_lock() {
+            Ok(guard) => Ok(guard),
+            Err(TryLockError::Poisoned(poisoned)) => Err(poisoned),
+            Err(TryLockError::WouldBlock) => {
+                let start = Instant::now();
+                let locked = self.shape.lock();
+                let waited = start.elapsed();
+                record_lock_wait(waited);
+                event!(
+                    DEBUG,
+                    waited_us = waited.as_micros() as u64,
+                    "waited for shape lock"
+                );
+                locked
+            }
+        };
+        locked
+            .map(wrap)
+            .map_err(|poisoned| PoisonError::new(wrap(poisoned.into_inner())))
+    }
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::Acquire)
+    }
+
+    pub fn history(&self) -> History {
+        History {
+            created: time(self.created.load(Ordering::Relaxed)),
+            modified: time(self.modified.load(Ordering::Relaxed)),
+            revision: self.revision.load(Ordering::Relaxed),
+        }
+    }
+    // For shapes read back from storage, or standing in for another
+    pub fn set_history(&self, history: History) {
+        self.created
+            .store(nanos(history.created), Ordering::Relaxed);
+        self.modified
+            .store(nanos(history.modified), Ordering::Relaxed);
+        self.revision.store(history.revision, Ordering::Relaxed);
+    }
+    // Counts a change made beside the shape rather than to it, leaving the
+    // version stamp be
+    pub fn touch(&self) {
+        self.modified
+            .store(nanos(SystemTime::now()), Ordering::Relaxed);
+        self.revision.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// Each guard borrowed mutably counts as one change to the revision, however
+// many times it's borrowed
+pub struct ShapeGuard<'a, S: ?Sized> {
+    guard: MutexGuard<'a, S>,
+    cell: &'a ShapeCell<S>,
+    changed: bool,
+}
+
+impl<S: ?Sized> Deref for ShapeGuard<'_, S> {
+    type Target = S;
+    fn deref(&self) -> &S {
+        &self.guard
+    }
+}
+
+// Stamped before the change is made, but the lock is held until it's done,
+// so anyone who sees the new stamp and locks to recompute sees the change
+impl<S: ?Sized> DerefMut for ShapeGuard<'_, S> {
+    fn deref_mut(&mut self) -> &mut S {
+        self.cell.version.store(next_version(), Ordering::Release);
+        if !self.changed {
+            self.changed = true;
+            self.cell.touch();
+        }
+        &mut self.guard
+    }
+}
+
+pub type ShapeObject = Arc<ShapeCell<dyn Shape + Send + Sync>>;
@@ -0,0 +1,566 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "render")]
+use crate::render::{self, map_box, Image, RenderConfig};
+use crate::{
+    arena::ShapeId, builder::coordinate, camera::Axes, clipboard::Clipboard, error::CanvasError,
+    Canvas, Coord, Rectangle, ShapeObject, Style,
+};
+
+// The metadata key holding a shape's number, which stays the same across
+// saving and loading while its id on the chunk's canvas doesn't
+pub const NUMBER_KEY: &str = "chunked.number";
+
+const INDEX: &str = "index";
+
+// A square of the plane, side chunk_size, counted from the zero
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChunkKey {
+    pub x: i32,
+    pub y: i32,
+}
+
+// Shapes live in the chunk holding their origin, and move chunk when moved
+// into another, which set_origin hands back a new id for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChunkedId {
+    pub chunk: ChunkKey,
+    pub number: u64,
+}
+
+struct Chunk {
+    canvas: Canvas,
+    numbers: BTreeMap<u64, ShapeId>,
+    dirty: bool,
+    used: u64,
+}
+
+// A canvas over the whole plane, too big to hold in memory at once. Space
+// is split into square chunks, each saved to a file of the directory in
+// the clipboard's text format and loaded when something touches it. Only
+// so many stay loaded, the least recently used written back and dropped to
+// make room, so queries and viewport renders stream through the chunks
+// they cover. Changes reach the disk on eviction, flush or drop.
+pub struct ChunkedCanvas {
+    dir: PathBuf,
+    chunk_size: f32,
+    // The furthest any shape, with its stroke, reaches from its origin,
+    // which is how far round a region queries look for chunks. It only
+    // ever grows.
+    reach: f32,
+    next: u64,
+    max_loaded: usize,
+    axes: Axes,
+    saved: BTreeSet<ChunkKey>,
+    loaded: BTreeMap<ChunkKey, Chunk>,
+    clock: u64,
+    index_dirty: bool,
+}
+
+impl ChunkedCanvas {
+    // A directory already holding a chunked canvas keeps the chunk size it
+    // was made with
+    pub fn open(dir: impl AsRef<Path>, chunk_size: f32) -> Result<ChunkedCanvas, CanvasError> {
+        let dir = dir.as_ref().to_path_buf();
+        span!(DEBUG, "open_chunked", dir = %dir.display(), chunk_size);
+        fs::create_dir_all(&dir)?;
+        let mut canvas = ChunkedCanvas {
+            chunk_size: crate::builder::dimension("chunk size", Some(chunk_size))?,
+            reach: 0.0,
+            next: 0,
+            max_loaded: 16,
+            axes: Axes::default(),
+            saved: BTreeSet::new(),
+            loaded: BTreeMap::new(),
+            clock: 0,
+            index_dirty: true,
+            dir,
+        };
+        match fs::read_to_string(canvas.dir.join(INDEX)) {
+            Ok(index) => canvas.read_index(&index)?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error.into()),
+        }
+        for entry in fs::read_dir(&canvas.dir)? {
+            let name = entry?.file_name();
+            if let Some(key) = name.to_str().and_then(parse_file_name) {
+                canvas.saved.insert(key);
+            }
+        }
+        Ok(canvas)
+    }
+
+    fn read_index(&mut self, index: &str) -> Result<(), CanvasError> {
+        let corrupt = || invalid_data(format!("bad index in {}", self.dir.display()));
+        for line in index.lines().skip(1) {
+            let Some((key, value)) = line.split_once(' ') else {
+                return Err(corrupt());
+            };
+            match key {
+                "size" => self.chunk_size = value.parse().map_err(|_| corrupt())?,
+                "reach" => self.reach = value.parse().map_err(|_| corrupt())?,
+                "next" => self.next = value.parse().map_err(|_| corrupt())?,
+                _ => return Err(corrupt()),
+            }
+        }
+        self.index_dirty = false;
+        Ok(())
+    }
+
+    pub fn chunk_size(&self) -> f32 {
+        self.chunk_size
+    }
+    // At least one
+    pub fn set_max_loaded(&mut self, max_loaded: usize) -> Result<(), CanvasError> {
+        self.max_loaded = max_loaded.max(1);
+        while self.loaded.len() > self.max_loaded {
+            self.evict()?;
+        }
+        Ok(())
+    }
+    pub fn loaded_chunks(&self) -> usize {
+        self.loaded.len()
+    }
+    // Those with shapes, loaded or not
+    pub fn chunks(&self) -> Vec<ChunkKey> {
+        let mut keys: BTreeSet<ChunkKey> = self.saved.clone();
+        keys.extend(self.loaded.keys());
+        keys.retain(|key| {
+            self.loaded
+                .get(key)
+                .is_none_or(|chunk| !chunk.canvas.is_empty())
+        });
+        keys.into_iter().collect()
+    }
+    // For rendering the viewport
+    pub fn set_axes(&mut self, axes: Axes) {
+        self.axes = axes;
+    }
+
+    pub fn chunk_of(&self, point: Coord) -> ChunkKey {
+        ChunkKey {
+            x: (point.x / self.chunk_size).floor() as i32,
+            y: (point.y / self.chunk_size).floor() as i32,
+        }
+    }
+
+    pub fn add(&mut self, shape: ShapeObject) -> Result<ChunkedId, CanvasError> {
+        let (origin, bounds) = {
+            let shape = shape.lock()?;
+            (coordinate("origin", shape.origin())?, shape.bounding_box())
+        };
+        self.reach_to(origin, &bounds, 0.0);
+        let id = ChunkedId {
+            chunk: self.chunk_of(origin),
+            number: self.next,
+        };
+        self.next += 1;
+        self.index_dirty = true;
+        let chunk = self.load(id.chunk)?;
+        let shape_id = chunk.canvas.add(shape);
+        chunk
+            .canvas
+            .set_meta(shape_id, NUMBER_KEY, id.number.to_string())?;
+        chunk.numbers.insert(id.number, shape_id);
+        chunk.dirty = true;
+        Ok(id)
+    }
+
+    // A copy, so change shapes through set_origin and set_style, which know
+    // to write them back
+    pub fn get(&mut self, id: ChunkedId) -> Result<Option<ShapeObject>, CanvasError> {
+        let Some((chunk, shape_id)) = self.find(id)? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            chunk.canvas.get(shape_id).unwrap().lock()?.duplicate(),
+        ))
+    }
+    pub fn style(&mut self, id: ChunkedId) -> Result<Option<Style>, CanvasError> {
+        Ok(self
+            .find(id)?
+            .and_then(|(chunk, shape_id)| chunk.canvas.style(shape_id)))
+    }
+    // False when there's no such shape
+    pub fn set_style(&mut self, id: ChunkedId, style: Style) -> Result<bool, CanvasError> {
+        let Some((chunk, shape_id)) = self.find(id)? else {
+            return Ok(false);
+        };
+        chunk.canvas.set_style(shape_id, style)?;
+        chunk.dirty = true;
+        let (origin, bounds) = {
+            let shape = chunk.canvas.get(shape_id).unwrap().lock()?;
+            (shape.origin(), shape.bounding_box())
+        };
+        self.reach_to(origin, &bounds, style.stroke_reach());
+        Ok(true)
+    }
+    // The shape's id, which changes when it moves into another chunk, or
+    // None when there's no such shape
+    pub fn set_origin(
+        &mut self,
+        id: ChunkedId,
+        origin: Coord,
+    ) -> Result<Option<ChunkedId>, CanvasError> {
+        coordinate("origin", origin)?;
+        let to = self.chunk_of(origin);
+        let Some((chunk, shape_id)) = self.find(id)? else {
+            return Ok(None);
+        };
+        let from = chunk.canvas.get(shape_id).unwrap().lock()?.origin();
+        if to == id.chunk {
+            chunk.canvas.set_origin(shape_id, origin)?;
+            chunk.dirty = true;
+            return Ok(Some(id));
+        }
+        let clipboard = chunk.canvas.copy(&[shape_id]);
+        chunk.canvas.try_remove(shape_id)?;
+        chunk.numbers.remove(&id.number);
+        chunk.dirty = true;
+        let chunk = self.load(to)?;
+        let shape_id = chunk.canvas.paste(&clipboard, origin - from)?[0];
+        chunk.numbers.insert(id.number, shape_id);
+        chunk.dirty = true;
+        Ok(Some(ChunkedId { chunk: to, ..id }))
+    }
+    pub fn remove(&mut self, id: ChunkedId) -> Result<Option<ShapeObject>, CanvasError> {
+        let Some((chunk, shape_id)) = self.find(id)? else {
+            return Ok(None);
+        };
+        let shape = chunk.canvas.try_remove(shape_id)?;
+        chunk.numbers.remove(&id.number);
+        chunk.dirty = true;
+        Ok(Some(shape))
+    }
+
+    // In chunk order, and by number within each
+    pub fn query_region(&mut self, region: &Rectangle) -> Result<Vec<ChunkedId>, CanvasError> {
+        let mut found = Vec::new();
+        for key in self.chunks_near(region) {
+            let chunk = self.load(key)?;
+            let mut numbers: Vec<u64> = chunk
+                .canvas
+                .query_region(region)
+                .into_iter()
+                .filter_map(|shape_id| number(&chunk.canvas, shape_id))
+                .collect();
+            numbers.sort_unstable();
+            found.extend(
+                numbers
+                    .into_iter()
+                    .map(|number| ChunkedId { chunk: key, number }),
+            );
+        }
+        Ok(found)
+    }
+
+    // Gathers the shapes in view onto a canvas of their own, in the order
+    // they were added, and renders that
+    #[cfg(feature = "render")]
+    pub fn render(&mut self, config: &RenderConfig) -> Result<Image, CanvasError> {
+        let view = map_box(
+            &config.to_world(self.axes.y),
+            &Rectangle::from_corners(
+                Coord::default(),
+                Coord::new(config.width as f32, config.height as f32),
+            ),
+        );
+        let region = grow(&view, self.reach);
+        let mut copies: Vec<(u64, Clipboard)> = Vec::new();
+        for key in self.chunks_near(&view) {
+            let canvas = &self.load(key)?.canvas;
+            for shape_id in canvas.query_region(&region) {
+                if let Some(number) = number(canvas, shape_id) {
+                    copies.push((number, canvas.copy(&[shape_id])));
+                }
+            }
+        }
+        copies.sort_by_key(|(number, _)| *number);
+        let mut scene = Canvas::default();
+        scene.set_axes(self.axes);
+        for (_, clipboard) in &copies {
+            scene.paste(clipboard, Coord::default())?;
+        }
+        render::render(&scene, config)
+    }
+
+    // Writes every changed chunk and the index
+    pub fn flush(&mut self) -> Result<(), CanvasError> {
+        span!(DEBUG, "flush_chunks", loaded = self.loaded.len());
+        let keys: Vec<ChunkKey> = self.loaded.keys().copied().collect();
+        for key in keys {
+            let chunk = self.loaded.get_mut(&key).unwrap();
+            if chunk.dirty {
+                save(&self.dir, &mut self.saved, key, chunk)?;
+            }
+        }
+        if self.index_dirty {
+            let index = format!(
+                "# chunked 1\nsize {}\nreach {}\nnext {}\n",
+                self.chunk_size, self.reach, self.next
+            );
+            fs::write(self.dir.join(INDEX), index)?;
+            self.index_dirty = false;
+        }
+        Ok(())
+    }
+
+    fn reach_to(&mut self, origin: Coord, bounds: &Rectangle, stroke: f32) {
+        let (min, max) = (bounds.min(), bounds.max());
+        let reach = [
+            origin.x - min.x,
+            origin.y - min.y,
+            max.x - origin.x,
+            max.y - origin.y,
+        ]
+        .into_iter()
+        .fold(0.0, f32::max)
+            + stroke;
+        if reach > self.reach {
+            self.reach = reach;
+            self.index_dirty = true;
+        }
+    }
+
+    // Chunks with shapes whose origins lie near enough to the region for
+    // them to reach into it
+    fn chunks_near(&self, region: &Rectangle) -> Vec<ChunkKey> {
+        let region = grow(region, self.reach);
+        let (min, max) = (self.chunk_of(region.min()), self.chunk_of(region.max()));
+        self.chunks()
+            .into_iter()
+            .filter(|key| (min.x..=max.x).contains(&key.x) && (min.y..=max.y).contains(&key.y))
+            .collect()
+    }
+
+    fn find(&mut self, id: ChunkedId) -> Result<Option<(&mut Chunk, ShapeId)>, CanvasError> {
+        if !self.saved.contains(&id.chunk) && !self.loaded.contains_key(&id.chunk) {
+            return Ok(None);
+        }
+        let chunk = self.load(id.chunk)?;
+        Ok(chunk
+            .numbers
+            .get(&id.number)
+            .copied()
+            .map(|shape_id| (chunk, shape_id)))
+    }
+
+    fn load(&mut self, key: ChunkKey) -> Result<&mut Chunk, CanvasError> {
+        self.clock += 1;
+        if !self.loaded.contains_key(&key) {
+            while self.loaded.len() >= self.max_loaded {
+                self.evict()?;
+            }
+            let mut canvas = Canvas::default();
+            if self.saved.contains(&key) {
+                let path = self.dir.join(file_name(key));
+                span!(DEBUG, "load_chunk", x = key.x, y = key.y);
+                let clipboard: Clipboard = fs::read_to_string(&path)?
+                    .parse()
+                    .map_err(|error| invalid_data(format!("{}: {error}", path.display())))?;
+                canvas.paste(&clipboard, Coord::default())?;
+            }
+            let numbers = canvas
+                .ids()
+                .iter()
+                .filter_map(|&shape_id| Some((number(&canvas, shape_id)?, shape_id)))
+                .collect();
+            self.loaded.insert(
+                key,
+                Chunk {
+                    canvas,
+                    numbers,
+                    dirty: false,
+                    used: 0,
+                },
+            );
+        }
+        let chunk = self.loaded.get_mut(&key).unwrap();
+        chunk.used = self.clock;
+        Ok(chunk)
+    }
+
+    fn evict(&mut self) -> Result<(), CanvasError> {
+        let Some((&key, _)) = self.loaded.iter().min_by_key(|(_, chunk)| chunk.used) else {
+            return Ok(());
+        };
+        let mut chunk = self.loaded.remove(&key).unwrap();
+        if chunk.dirty {
+            save(&self.dir, &mut self.saved, key, &mut chunk)?;
+        }
+        Ok(())
+    }
+}
+
+// Anything not yet written is lost when flushing fails here, so call flush
+// to see the error
+impl Drop for ChunkedCanvas {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+// Empty chunks have their files removed. Written aside and renamed over, so
+// a crash leaves the old file or the new one.
+fn save(
+    dir: &Path,
+    saved: &mut BTreeSet<ChunkKey>,
+    key: ChunkKey,
+    chunk: &mut Chunk,
+) -> Result<(), CanvasError> {
+    let path = dir.join(file_name(key));
+    span!(
+        DEBUG,
+        "save_chunk",
+        x = key.x,
+        y = key.y,
+        shapes = chunk.canvas.len()
+    );
+    if chunk.canvas.is_empty() {
+        if saved.remove(&key) {
+            fs::remove_file(&path)?;
+        }
+    } else {
+        let text = chunk.canvas.copy(chunk.canvas.ids()).to_string();
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, text)?;
+        fs::rename(&temporary, &path)?;
+        saved.insert(key);
+    }
+    chunk.dirty = false;
+    Ok(())
+}
+
+fn number(canvas: &Canvas, shape_id: ShapeId) -> Option<u64> {
+    canvas.meta(shape_id, NUMBER_KEY)?.parse().ok()
+}
+
+fn file_name(key: ChunkKey) -> String {
+    format!("{}_{}.chunk", key.x, key.y)
+}
+fn parse_file_name(name: &str) -> Option<ChunkKey> {
+    let (x, y) = name.strip_suffix(".chunk")?.split_once('_')?;
+    Some(ChunkKey {
+        x: x.parse().ok()?,
+        y: y.parse().ok()?,
+    })
+}
+
+fn invalid_data(message: String) -> CanvasError {
+    io::Error::new(io::ErrorKind::InvalidData, message).into()
+}
+
+fn grow(area: &Rectangle, by: f32) -> Rectangle {
+    Rectangle {
+        side_a: area.side_a + 2.0 * by,
+        side_b: area.side_b + 2.0 * by,
+        ..area.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, sync::Arc};
+
+    use super::*;
+    use crate::{style::Color, Circle, ShapeCell};
+
+    fn circle(x: f32, y: f32) -> ShapeObject {
+        Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(x, y),
+            radius: 2.0,
+        }))
+    }
+
+    #[test]
+    fn streams_chunks_through_memory() {
+        let dir = env::temp_dir().join(format!("kaleidoscope-chunks-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (far, moved) = {
+            let mut canvas = ChunkedCanvas::open(&dir, 10.0).unwrap();
+            canvas.set_max_loaded(2).unwrap();
+            // A row of shapes a chunk apart, and one a long way off
+            for i in 0..10 {
+                canvas.add(circle(i as f32 * 10.0 + 5.0, 5.0)).unwrap();
+            }
+            let far = canvas.add(circle(-1e6, 1e6)).unwrap();
+            assert_eq!(
+                far.chunk,
+                ChunkKey {
+                    x: -100_000,
+                    y: 100_000
+                }
+            );
+            assert_eq!(canvas.loaded_chunks(), 2);
+            assert_eq!(canvas.chunks().len(), 11);
+
+            let red = Style {
+                fill: Some(Color::rgb(255, 0, 0).into()),
+                ..Style::default()
+            };
+            assert!(canvas.set_style(far, red).unwrap());
+            let first = ChunkedId {
+                chunk: ChunkKey { x: 0, y: 0 },
+                number: 0,
+            };
+            // Moving it into the next chunk changes its id
+            let moved = canvas
+                .set_origin(first, Coord::new(14.0, 5.0))
+                .unwrap()
+                .unwrap();
+            assert_eq!(moved.chunk, ChunkKey { x: 1, y: 0 });
+            assert!(canvas.get(first).unwrap().is_none());
+            (far, moved)
+        };
+
+        // Everything comes back from disk
+        let mut canvas = ChunkedCanvas::open(&dir, 1.0).unwrap();
+        assert_eq!(canvas.chunk_size(), 10.0);
+        assert_eq!(canvas.loaded_chunks(), 0);
+        assert_eq!(canvas.chunks().len(), 10);
+        let shape = canvas.get(moved).unwrap().unwrap();
+        assert_eq!(shape.lock().unwrap().origin(), Coord::new(14.0, 5.0));
+        assert_eq!(
+            canvas.style(far).unwrap().unwrap().fill,
+            Some(Color::rgb(255, 0, 0).into())
+        );
+        // Shapes reaching in from neighbouring chunks are found
+        let region = Rectangle::from_corners(Coord::new(16.5, 0.0), Coord::new(23.5, 10.0));
+        let found = canvas.query_region(&region).unwrap();
+        assert_eq!(
+            found.iter().map(|id| id.number).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert!(canvas.remove(far).unwrap().is_some());
+        assert_eq!(canvas.next, 11);
+        let next = canvas.add(circle(0.0, 0.0)).unwrap();
+        assert_eq!(next.number, 11);
+        canvas.flush().unwrap();
+        assert_eq!(canvas.chunks().len(), 10);
+        assert!(!dir.join("-100000_100000.chunk").exists());
+
+        #[cfg(feature = "render")]
+        {
+            use crate::render::{RenderConfig, BACKGROUND, FILL};
+            let image = canvas
+                .render(&RenderConfig {
+                    width: 20,
+                    height: 10,
+                    view: Rectangle::from_corners(Coord::new(10.0, 0.0), Coord::new(30.0, 10.0)),
+                    ..RenderConfig::default()
+                })
+                .unwrap();
+            // The moved shape at 14 and the next along at 15, then a gap
+            // before the one at 25
+            assert_eq!(image.pixel(4, 5), FILL);
+            assert_eq!(image.pixel(15, 5), FILL);
+            assert_eq!(image.pixel(9, 5), BACKGROUND);
+        }
+        drop(canvas);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,511 @@
+use std::{
+    fmt::{self, Write},
+    str::FromStr,
+};
+
+use crate::{
+    arena::ShapeId,
+    builder::coordinate,
+    canvas::{check_style, Metadata},
+    cell::{nanos, time},
+    command::{escape, parse_shape, shape_words, unescape},
+    error::{CanvasError, CommandError},
+    style::{Color, Dash, LineCap, LineJoin, Paint, Style},
+    Canvas, Coord, History, ShapeObject,
+};
+
+// Shapes copied off a canvas with their styles, metadata and tags, to paste
+// back onto it or onto another. The shapes are copies, so later changes on
+// the canvas don't show in the clipboard or the other way round.
+//
+// As text, for pasting between processes, each shape is a line as given to
+// the add command, followed by lines for whatever goes with it:
+//     shape circle 2 at 1 1
+//     style fill solid #ff0000 stroke #000000 width 0.5 opacity 0.8
+//     meta label leaf
+//     tag background
+//     history 1760400000000000000 1760400005000000000 3
+// History lines give when the shape was made and last changed, in
+// nanoseconds since the Unix epoch, and its revision. Pasted shapes keep
+// theirs, so it lasts through chunk files.
+//
+// Style lines only give what differs from the default style. Fills are
+// none, solid <colour>, linear <x> <y> <x> <y>, radial <x> <y> <radius>,
+// stripes <angle> <size> or checks <size>, the gradients and patterns
+// followed by their two colours. Dashes are <on> <off> <offset>.
+#[derive(Clone, Default)]
+pub struct Clipboard {
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone)]
+struct Entry {
+    shape: ShapeObject,
+    style: Style,
+    metadata: Metadata,
+    tags: Vec<String>,
+    // None for shapes read from text without one, which paste as new
+    history: Option<History>,
+}
+
+impl Clipboard {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Moves the copies, which the canvas copied from doesn't see
+    pub(crate) fn translate(&self, offset: Coord) -> Result<(), CanvasError> {
+        for entry in &self.entries {
+            let mut shape = entry.shape.lock()?;
+            let origin = shape.origin();
+            shape.set_origin(origin + offset);
+        }
+        Ok(())
+    }
+    // So the shapes paste as new ones
+    pub(crate) fn forget_history(&mut self) {
+        for entry in &mut self.entries {
+            entry.history = None;
+        }
+    }
+}
+
+impl Canvas {
+    // Missing ids and shapes whose locks are poisoned are skipped.
+    // Symmetric copies and group members are copied as the plain shapes
+    // they are on the canvas.
+    pub fn copy(&self, ids: &[ShapeId]) -> Clipboard {
+        let entries = ids
+            .iter()
+            .filter_map(|&id| {
+                Some(Entry {
+                    shape: self.get(id)?.lock().ok()?.duplicate(),
+                    style: self.style(id)?,
+                    metadata: self.metadata(id).cloned().unwrap_or_default(),
+                    tags: self.tags(id)?.to_vec(),
+                    history: self.history(id),
+                })
+            })
+            .collect();
+        Clipboard { entries }
+    }
+
+    // Adds copies of the clipboard's shapes moved by the offset, in the
+    // order they were copied, and returns their ids. They're added as they
+    // are, without the canvas symmetry applied. Moving them by an offset
+    // other than zero counts as a change to them.
+    pub fn paste(
+        &mut self,
+        clipboard: &Clipboard,
+        offset: impl Into<Coord>,
+    ) -> Result<Vec<ShapeId>, CanvasError> {
+        let offset = offset.into();
+        coordinate("offset", offset)?;
+        let mut pasted = Vec::new();
+        for entry in &clipboard.entries {
+            let shape = entry.shape.lock()?.duplicate();
+            let id = self.shapes.insert(shape.clone());
+            self.store_style(id, entry.style);
+            self.set_metadata(id, entry.metadata.clone())?;
+            for tag in &entry.tags {
+                self.tag(id, tag.clone())?;
+            }
+            if let Some(history) = entry.history {
+                shape.set_history(history);
+            }
+            if offset != Coord::default() {
+                let mut shape = shape.lock()?;
+                let origin = shape.origin();
+                shape.set_origin(origin + offset);
+            }
+            pasted.push(id);
+        }
+        self.debug_check(pasted.iter().filter_map(|&id| self.get(id)));
+        Ok(pasted)
+    }
+}
+
+impl fmt::Display for Clipboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            let shape = entry.shape.lock().map_err(|_| fmt::Error)?;
+            writeln!(f, "shape {}", shape_words(&*shape))?;
+            let style = style_words(&entry.style);
+            if !style.is_empty() {
+                writeln!(f, "style{style}")?;
+            }
+            for (key, value) in &entry.metadata {
+                writeln!(f, "meta {} {}", escape(key), escape(value))?;
+            }
+            for tag in &entry.tags {
+                writeln!(f, "tag {}", escape(tag))?;
+            }
+            if let Some(history) = entry.history {
+                writeln!(
+                    f,
+                    "history {} {} {}",
+                    nanos(history.created),
+                    nanos(history.modified),
+                    history.revision
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Read back from the text above. Errors give the line, counting from 1.
+impl FromStr for Clipboard {
+    type Err = CommandError;
+    fn from_str(text: &str) -> Result<Clipboard, CommandError> {
+        let mut entries: Vec<Entry> = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let entry = entries.last_mut();
+            let read = match (words.as_slice(), entry) {
+                ([], _) => Ok(()),
+                (["shape", kind, rest @ ..], _) => parse_shape(kind, rest).map(|shape| {
+                    entries.push(Entry {
+                        shape,
+                        style: Style::default(),
+                        metadata: Metadata::new(),
+                        tags: Vec::new(),
+                        history: None,
+                    })
+                }),
+                (["style", rest @ ..], Some(entry)) => parse_style(rest).and_then(|style| {
+                    check_style(&style)?;
+                    entry.style = style;
+                    Ok(())
+                }),
+                (["meta", key, value @ ..], Some(entry)) => {
+                    let value: Vec<String> = value.iter().map(|word| unescape(word)).collect();
+                    entry.metadata.insert(unescape(key), value.join(" "));
+                    Ok(())
+                }
+                (["tag", tag], Some(entry)) => {
+                    let tag = unescape(tag);
+                    if !entry.tags.contains(&tag) {
+                        entry.tags.push(tag);
+                    }
+                    Ok(())
+                }
+                (["history", created, modified, revision], Some(entry)) => {
+                    let number = |word: &str| {
+                        word.parse::<u64>()
+                            .map_err(|_| CommandError::Usage(HISTORY_USAGE))
+                    };
+                    entry.history = Some(History {
+                        created: time(number(created)?),
+                        modified: time(number(modified)?),
+                        revision: number(revision)?,
+                    });
+                    Ok(())
+                }
+                (["history", ..], Some(_)) => Err(CommandError::Usage(HISTORY_USAGE)),
+                (["style" | "meta" | "tag" | "history", ..], None) => Err(CommandError::Usage(
+                    "shape <shape> before its style, meta, tags and history",
+                )),
+                ([command, ..], _) => Err(CommandError::UnknownCommand(String::from(*command))),
+            };
+            read.map_err(|error| CommandError::Line(i + 1, Box::new(error)))?;
+        }
+        Ok(Clipboard { entries })
+    }
+}
+
+const HISTORY_USAGE: &str = "history <created> <modified> <revision>";
+
+const STYLE_USAGE: &str = "style [fill <paint>] [stroke <colour>|none] [width <width>] \
+                           [dash <on> <off> <offset>|none] [cap butt|round|square] \
+                           [join miter|round|bevel] [opacity <opacity>]";
+
+// The parts of the style other than the default, each with a leading space
+fn style_words(style: &Style) -> String {
+    let default = Style::default();
+    let mut out = String::new();
+    if style.fill != default.fill {
+        let _ = match style.fill {
+            None => write!(out, " fill none"),
+            Some(Paint::Solid(color)) => write!(out, " fill solid {}", color.to_hex()),
+            Some(Paint::Linear { start, end, colors }) => write!(
+                out,
+                " fill linear {} {} {} {}{}",
+                start.x,
+                start.y,
+                end.x,
+                end.y,
+                pair(colors)
+            ),
+            Some(Paint::Radial {
+                center,
+                radius,
+                colors,
+            }) => write!(
+                out,
+                " fill radial {} {} {radius}{}",
+                center.x,
+                center.y,
+                pair(colors)
+            ),
+            Some(Paint::Stripes {
+                angle,
+                size,
+                colors,
+            }) => write!(out, " fill stripes {angle} {size}{}", pair(colors)),
+            Some(Paint::Checks { size, colors }) => {
+                write!(out, " fill checks {size}{}", pair(colors))
+            }
+        };
+    }
+    if style.stroke != default.stroke {
+        let _ = match style.stroke {
+            Some(color) => write!(out, " stroke {}", color.to_hex()),
+            None => write!(out, " stroke none"),
+        };
+    }
+    if style.stroke_width != default.stroke_width {
+        let _ = write!(out, " width {}", style.stroke_width);
+    }
+    if let Some(Dash { on, off, offset }) = style.dash {
+        let _ = write!(out, " dash {on} {off} {offset}");
+    }
+    if style.cap != default.cap {
+        let cap = match style.cap {
+            LineCap::Butt => "butt",
+            LineCap::Round => "round",
+            LineCap::Square => "square",
+        };
+        let _ = write!(out, " cap {cap}");
+    }
+    if style.join != default.join {
+        let join = match style.join {
+            LineJoin::Miter => "miter",
+            LineJoin::Round => "round",
+            LineJoin::Bevel => "bevel",
+        };
+        let _ = write!(out, " join {join}");
+    }
+    if style.opacity != default.opacity {
+        let _ = write!(out, " opacity {}", style.opacity);
+    }
+    out
+}
+
+fn pair([a, b]: [Color; 2]) -> String {
+    format!(" {} {}", a.to_hex(), b.to_hex())
+}
+
+fn parse_style(mut words: &[&str]) -> Result<Style, CommandError> {
+    let usage = || CommandError::Usage(STYLE_USAGE);
+    let number = |word: &str| word.parse::<f32>().map_err(|_| usage());
+    let color = |word: &str| word.parse::<Color>().map_err(|_| usage());
+    let point = |x: &str, y: &str| Ok::<_, CommandError>(Coord::new(number(x)?, number(y)?));
+    let mut style = Style::default();
+    while !words.is_empty() {
+        words = match words {
+            ["fill", "none", rest @ ..] => {
+                style.fill = None;
+                rest
+            }
+            ["fill", "solid", c, rest @ ..] => {
+                style.fill = Some(Paint::Solid(color(c)?));
+                rest
+            }
+            ["fill", "linear", x0, y0, x1, y1, a, b, rest @ ..] => {
+                style.fill = Some(Paint::Linear {
+                    start: point(x0, y0)?,
+                    end: point(x1, y1)?,
+                    colors: [color(a)?, color(b)?],
+                });
+                rest
+            }
+            ["fill", "radial", x, y, radius, a, b, rest @ ..] => {
+                style.fill = Some(Paint::Radial {
+                    center: point(x, y)?,
+                    radius: number(radius)?,
+                    colors: [color(a)?, color(b)?],
+                });
+                rest
+            }
+            ["fill", "stripes", angle, size, a, b, rest @ ..] => {
+                style.fill = Some(Paint::Stripes {
+                    angle: number(angle)?,
+                    size: number(size)?,
+                    colors: [color(a)?, color(b)?],
+                });
+                rest
+            }
+            ["fill", "checks", size, a, b, rest @ ..] => {
+                style.fill = Some(Paint::Checks {
+                    size: number(size)?,
+                    colors: [color(a)?, color(b)?],
+                });
+                rest
+            }
+            ["stroke", "none", rest @ ..] => {
+                style.stroke = None;
+                rest
+            }
+            ["stroke", c, rest @ ..] => {
+                style.stroke = Some(color(c)?);
+                rest
+            }
+            ["width", width, rest @ ..] => {
+                style.stroke_width = number(width)?;
+                rest
+            }
+            ["dash", "none", rest @ ..] => {
+                style.dash = None;
+                rest
+            }
+            ["dash", on, off, offset, rest @ ..] => {
+                style.dash = Some(Dash {
+                    on: number(on)?,
+                    off: number(off)?,
+                    offset: number(offset)?,
+                });
+                rest
+            }
+            ["cap", cap, rest @ ..] => {
+                style.cap = match *cap {
+                    "butt" => LineCap::Butt,
+                    "round" => LineCap::Round,
+                    "square" => LineCap::Square,
+                    _ => return Err(usage()),
+                };
+                rest
+            }
+            ["join", join, rest @ ..] => {
+                style.join = match *join {
+                    "miter" => LineJoin::Miter,
+                    "round" => LineJoin::Round,
+                    "bevel" => LineJoin::Bevel,
+                    _ => return Err(usage()),
+                };
+                rest
+            }
+            ["opacity", opacity, rest @ ..] => {
+                style.opacity = number(opacity)?;
+                rest
+            }
+            _ => return Err(usage()),
+        };
+    }
+    Ok(style)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        symmetry::{Symmetry, SymmetryGroup},
+        Circle, Polygon, ShapeCell,
+    };
+
+    #[test]
+    fn copy_and_paste_between_canvases() {
+        let mut canvas = Canvas::default();
+        let circle = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(1.0, 1.0),
+            radius: 2.0,
+        })));
+        let polygon = canvas.add(Arc::new(ShapeCell::new(Polygon {
+            origin: Coord::new(5.0, -5.0),
+            points: vec![
+                Coord::new(0.0, 0.0),
+                Coord::new(4.0, 0.0),
+                Coord::new(0.0, 3.0),
+            ],
+        })));
+        let style = Style {
+            fill: Some(Paint::Linear {
+                start: Coord::new(0.0, 0.0),
+                end: Coord::new(4.0, 0.0),
+                colors: [Color::WHITE, Color::rgba(200, 0, 0, 128)],
+            }),
+            stroke: Some(Color::BLACK),
+            stroke_width: 0.5,
+            dash: Some(Dash {
+                on: 2.0,
+                off: 1.0,
+                offset: 0.5,
+            }),
+            cap: LineCap::Round,
+            join: LineJoin::Bevel,
+            opacity: 0.75,
+        };
+        canvas.set_style(polygon, style).unwrap();
+        canvas.set_meta(polygon, "source", "scan 3").unwrap();
+        canvas.tag(polygon, "background").unwrap();
+        canvas.tag(circle, "guide").unwrap();
+
+        let clipboard = canvas.copy(&[polygon, circle]);
+        assert_eq!(clipboard.len(), 2);
+        // A deep copy, unmoved by later changes
+        canvas.set_origin(circle, Coord::new(9.0, 9.0)).unwrap();
+
+        let mut other = Canvas::default();
+        other
+            .set_symmetry(Some(Symmetry {
+                group: SymmetryGroup::Cyclic(4),
+                center: Coord::default(),
+            }))
+            .unwrap();
+        let pasted = other.paste(&clipboard, Coord::new(10.0, 0.0)).unwrap();
+        assert_eq!(other.len(), 2);
+        let origin = |id| other.get(id).unwrap().lock().unwrap().origin();
+        assert_eq!(origin(pasted[0]), Coord::new(15.0, -5.0));
+        assert_eq!(origin(pasted[1]), Coord::new(11.0, 1.0));
+        assert_eq!(other.style(pasted[0]), Some(style));
+        assert_eq!(other.meta(pasted[0], "source"), Some("scan 3"));
+        assert_eq!(other.find_by_tag("guide"), [pasted[1]]);
+
+        // And through text, as between processes
+        let text = clipboard.to_string();
+        let unrecorded: String = text
+            .lines()
+            .filter(|line| !line.starts_with("history "))
+            .map(|line| format!("{line}\n"))
+            .collect();
+        assert!(unrecorded.starts_with(
+            "shape polygon 0 0 4 0 0 3 at 5 -5\n\
+             style fill linear 0 0 4 0 #ffffff #c8000080 stroke #000000 width 0.5 \
+             dash 2 1 0.5 cap round join bevel opacity 0.75\n\
+             meta source scan\\s3\n\
+             tag background\n\
+             shape circle 2 at 1 1\n"
+        ));
+        let read: Clipboard = text.parse().unwrap();
+        assert_eq!(read.to_string(), text);
+        let mut third = Canvas::default();
+        let ids = third.paste(&read, Coord::default()).unwrap();
+        assert_eq!(third.style(ids[0]), Some(style));
+        assert_eq!(third.style(ids[1]), Some(Style::default()));
+        assert_eq!(third.get_area(ids[0]), canvas.get_area(polygon));
+        assert_eq!(third.history(ids[0]), canvas.history(polygon));
+        // Styling, labelling and tagging were changes, and so is pasting
+        // somewhere else
+        let revision = |history: Option<History>| history.unwrap().revision;
+        assert_eq!(revision(canvas.history(polygon)), 3);
+        assert_eq!(revision(other.history(pasted[0])), 4);
+
+        assert!(matches!(
+            "shape circle 1 at 0 0\nstyle width -1".parse::<Clipboard>(),
+            Err(CommandError::Line(2, _))
+        ));
+        assert!(matches!(
+            "tag early".parse::<Clipboard>(),
+            Err(CommandError::Line(1, _))
+        ));
+        assert!(canvas.copy(&[]).is_empty());
+        assert!(other
+            .paste(&Canvas::default().copy(&[]), Coord::new(f32::NAN, 0.0))
+            .is_err());
+    }
+}
@@ -0,0 +1,1175 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+    sync::{Arc, PoisonError, RwLockReadGuard},
+};
+
+#[cfg(feature = "render")]
+use crate::render::{render, Image, RenderConfig};
+use crate::{
+    approx,
+    arena::ShapeId,
+    builder::coordinate,
+    error::{CanvasError, CommandError, ShapeError},
+    geometry::{outline_points, Outline},
+    journal::Journal,
+    macros::Macro,
+    registry::ShapeRegistry,
+    snapshots::Snapshots,
+    symmetry::{Symmetry, SymmetryGroup, MAX_ORDER},
+    transform::Transform,
+    Canvas, Circle, Coord, Polygon, Rectangle, Shape, ShapeCell, ShapeObject, Triangle,
+};
+
+// A canvas driven by one line commands, as typed at the REPL or read from a
+// scene script. Shapes and groups are numbered from 1 in the order they were
+// added, and numbers aren't reused.
+#[derive(Default)]
+pub struct Session {
+    pub canvas: Canvas,
+    shapes: Vec<Option<Entry>>,
+    symmetry: Option<Symmetry>,
+    // None until asked for, so sessions nobody watches don't pile them up
+    events: Option<Vec<Event>>,
+    pub(crate) journal: Option<Journal>,
+    // The macro being recorded, under its name
+    pub(crate) recording: Option<(String, Macro)>,
+    pub(crate) macros: BTreeMap<String, Macro>,
+    pub(crate) snapshots: Snapshots,
+    // Set for sessions run on behalf of others, over the network say
+    restricted: bool,
+}
+
+// A change a command made to the numbered shapes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    Added(usize),
+    // With where the shape is now
+    Moved(usize, Coord),
+    Removed(usize),
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Added(number) => write!(f, "added {number}"),
+            Event::Moved(number, Coord { x, y }) => write!(f, "moved {number} to {x} {y}"),
+            Event::Removed(number) => write!(f, "removed {number}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    id: ShapeId,
+    // What it was added under, for writing the session back out
+    symmetry: Option<Symmetry>,
+    group: Option<usize>,
+}
+
+pub const HELP: &str = "\
+add circle <radius> at <x> <y>
+add rectangle <width> <height> at <x> <y>
+add triangle <base> <height> at <x> <y>
+add polygon <x> <y> <x> <y> <x> <y>... at <x> <y>
+add <kind> <number>... at <x> <y>
+    for kinds added to the shape registry
+    followed by in <group> to add to a group, placed in its coordinates
+move <n> to <x> <y>
+group at <x> <y>
+move group <group> to <x> <y>
+symmetry cyclic|dihedral <n> [at <x> <y>]
+symmetry off
+skip <n>
+    leaves the next n shape numbers unused, as removed shapes do
+remove <n>
+meta <n> [<key> [<value>]]
+    sets or shows metadata, \\s standing for a space in keys or values
+tag <n> <tag>
+hide|show <n>
+lock|unlock <n>
+    locked shapes can't be moved or removed
+prop <n> [<name> [<value>]]
+    shows or sets the shape's dimensions by name, radius or side_a, say
+hide|show group <group>
+record <name>
+    records the commands that follow as a macro, until stop
+stop
+replay <name> [<n>...]
+    runs the macro again, with the shapes given standing in for those it used
+snapshot <name>
+snapshots
+restore <name>
+    puts the canvas back as it was at the snapshot
+diff <name> <name>
+area [<n>]
+hit <x> <y>
+list
+render <path.png> [<width> <height>]
+help";
+
+// The most numbers one skip leaves unused, so a typo can't allocate the
+// machine away
+const MAX_SKIP: usize = 1 << 20;
+
+// What a command had to say, and whether it changed the session, which is
+// what gets journaled
+#[derive(Default)]
+struct Reply {
+    output: String,
+    changed: bool,
+}
+
+const CHANGED: Reply = Reply {
+    output: String::new(),
+    changed: true,
+};
+
+impl Reply {
+    fn read(output: String) -> Reply {
+        Reply {
+            output,
+            changed: false,
+        }
+    }
+    fn change(output: String) -> Reply {
+        Reply {
+            output,
+            changed: true,
+        }
+    }
+}
+
+impl Session {
+    pub fn new() -> Session {
+        Session::default()
+    }
+
+    // Refuses commands that read or write files, render to a path being the
+    // only one, and skip, which only the session's own snapshot scripts
+    // need. Those are replayed into fresh sessions, restore's and the
+    // journal's, never into a restricted one.
+    pub fn restrict(&mut self) {
+        self.restricted = true;
+    }
+
+    pub fn shape(&self, number: usize) -> Option<ShapeId> {
+        let entry = self.shapes.get(number.checked_sub(1)?)?;
+        entry.map(|entry| entry.id)
+    }
+
+    // Runs one command, returning what it has to say. Blank lines and those
+    // starting with # do nothing.
+    pub fn execute(&mut self, line: &str) -> Result<String, CommandError> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Reply { output, changed } = self.command(&words)?;
+        if let Some(mut journal) = self.journal.take_if(|_| changed) {
+            let recorded = journal.record(self, line);
+            self.journal = Some(journal);
+            recorded?;
+        }
+        self.record_line(line);
+        Ok(output)
+    }
+
+    fn command(&mut self, words: &[&str]) -> Result<Reply, CommandError> {
+        match words {
+            [] => Ok(Reply::default()),
+            [first, ..] if first.starts_with('#') => Ok(Reply::default()),
+            ["add", kind, rest @ .., "in", group] => {
+                let shape = parse_shape(kind, rest)?;
+                let group = self.group(group, "add ... in <group>")?;
+                let id = self.canvas.add_to_group(group, shape)?;
+                self.push(id, Some(group)).map(Reply::change)
+            }
+            ["add", kind, rest @ ..] => {
+                let shape = parse_shape(kind, rest)?;
+                let id = self.canvas.add(shape);
+                self.push(id, None).map(Reply::change)
+            }
+            ["add", ..] => Err(CommandError::Usage(
+                "add circle|rectangle|triangle|polygon ...",
+            )),
+            ["move", "group", group, "to", x, y] => {
+                let usage = "move group <group> to <x> <y>";
+                let group = self.group(group, usage)?;
+                let to = point(x, y, usage)?;
+                self.canvas
+                    .set_group_transform(group, Transform::translation(to))?;
+                for number in 1..=self.shapes.len() {
+                    if self.shapes[number - 1].is_some_and(|entry| entry.group == Some(group)) {
+                        self.moved(number)?;
+                    }
+                }
+                Ok(CHANGED)
+            }
+            ["move", n, "to", x, y] => {
+                let id = self.number(n, "move <n> to <x> <y>")?;
+                let to = point(x, y, "move <n> to <x> <y>")?;
+                let group = self.entry(id).and_then(|entry| entry.group);
+                match (group, self.canvas.local_shape(id)) {
+                    // Members move within their group
+                    (Some(group), Some(local)) => {
+                        self.canvas.unlocked(id)?;
+                        coordinate("origin", to)?;
+                        local.lock()?.set_origin(to);
+                        self.canvas.refresh_group(group)?;
+                    }
+                    _ => self.canvas.set_origin(id, to)?,
+                }
+                self.moved(n.parse().unwrap())?;
+                Ok(CHANGED)
+            }
+            ["move", ..] => Err(CommandError::Usage("move <n> to <x> <y>")),
+            ["remove", n] => {
+                let id = self.number(n, "remove <n>")?;
+                self.canvas.try_remove(id)?;
+                let number: usize = n.parse().unwrap();
+                self.shapes[number - 1] = None;
+                self.record(Event::Removed(number));
+                Ok(CHANGED)
+            }
+            ["remove", ..] => Err(CommandError::Usage("remove <n>")),
+            ["meta", n, rest @ ..] => {
+                let usage = "meta <n> [<key> [<value>]]";
+                let id = self.number(n, usage)?;
+                match rest {
+                    [] => {
+                        let metadata = self.canvas.metadata(id).into_iter().flatten();
+                        let lines: Vec<String> = metadata
+                            .map(|(key, value)| format!("{} {}", escape(key), escape(value)))
+                            .collect();
+                        Ok(Reply::read(lines.join("\n")))
+                    }
+                    [key] => Ok(Reply::read(escape(
+                        self.canvas.meta(id, &unescape(key)).unwrap_or(""),
+                    ))),
+                    [key, value @ ..] => {
+                        let value: Vec<String> = value.iter().map(|word| unescape(word)).collect();
+                        self.canvas.set_meta(id, unescape(key), value.join(" "))?;
+                        Ok(CHANGED)
+                    }
+                }
+            }
+            ["meta", ..] => Err(CommandError::Usage("meta <n> [<key> [<value>]]")),
+            ["tag", n, tag] => {
+                let id = self.number(n, "tag <n> <tag>")?;
+                self.canvas.tag(id, unescape(tag))?;
+                Ok(CHANGED)
+            }
+            ["tag", ..] => Err(CommandError::Usage("tag <n> <tag>")),
+            [command @ ("hide" | "show"), "group", group] => {
+                let group = self.group(group, "hide|show group <group>")?;
+                self.canvas.set_group_visible(group, *command == "show")?;
+                Ok(CHANGED)
+            }
+            [command @ ("hide" | "show"), n] => {
+                let id = self.number(n, "hide|show <n>")?;
+                self.canvas.set_visible(id, *command == "show")?;
+                Ok(CHANGED)
+            }
+            ["hide" | "show", ..] => Err(CommandError::Usage("hide|show [group] <n>")),
+            ["lock", n] => {
+                let id = self.number(n, "lock <n>")?;
+                self.canvas.lock_shape(id)?;
+                Ok(CHANGED)
+            }
+            ["unlock", n] => {
+                let id = self.number(n, "unlock <n>")?;
+                self.canvas.unlock_shape(id)?;
+                Ok(CHANGED)
+            }
+            ["lock" | "unlock", ..] => Err(CommandError::Usage("lock|unlock <n>")),
+            ["prop", n, rest @ ..] => {
+                let usage = "prop <n> [<name> [<value>]]";
+                let id = self.number(n, usage)?;
+                let shape = self
+                    .canvas
+                    .get(id)
+                    .ok_or(CanvasError::UnknownShape(id))?
+                    .lock()?;
+                let value = |name: &str| {
+                    shape
+                        .get_property(name)
+                        .ok_or_else(|| ShapeError::UnknownProperty(name.into()))
+                };
+                match rest {
+                    [] => {
+                        let lines: Vec<String> = shape
+                            .properties()
+                            .iter()
+                            .map(|name| format!("{name} {}", shape.get_property(name).unwrap()))
+                            .collect();
+                        Ok(Reply::read(lines.join("\n")))
+                    }
+                    [name] => Ok(Reply::read(value(name)?.to_string())),
+                    [name, value] => {
+                        let value = number(value, usage)?;
+                        drop(shape);
+                        self.canvas.set_property(id, name, value)?;
+                        Ok(CHANGED)
+                    }
+                    _ => Err(CommandError::Usage(usage)),
+                }
+            }
+            ["record", name] => {
+                self.start_recording(*name);
+                Ok(Reply::default())
+            }
+            ["record", ..] => Err(CommandError::Usage("record <name>")),
+            ["stop"] => self
+                .stop_recording()
+                .map(|name| Reply::read(format!("recorded {name}")))
+                .ok_or(CommandError::Usage("stop, after record <name>")),
+            ["stop", ..] => Err(CommandError::Usage("stop")),
+            ["replay", name, args @ ..] => {
+                let usage = "replay <name> [<n>...]";
+                let args = args
+                    .iter()
+                    .map(|arg| arg.parse().map_err(|_| CommandError::Usage(usage)))
+                    .collect::<Result<Vec<usize>, _>>()?;
+                // Each line replayed is journaled as it's run
+                self.replay(name, &args).map(Reply::read)
+            }
+            ["replay"] => Err(CommandError::Usage("replay <name> [<n>...]")),
+            ["area"] => Ok(Reply::read(self.canvas.total_area().to_string())),
+            ["area", n] => {
+                let id = self.number(n, "area [<n>]")?;
+                Ok(Reply::read(
+                    self.canvas.get_area(id).unwrap_or_default().to_string(),
+                ))
+            }
+            ["area", ..] => Err(CommandError::Usage("area [<n>]")),
+            ["hit", x, y] => {
+                let at = point(x, y, "hit <x> <y>")?;
+                let numbers: Vec<String> = self
+                    .hit(at)
+                    .into_iter()
+                    .map(|number| number.to_string())
+                    .collect();
+                Ok(Reply::read(numbers.join(" ")))
+            }
+            ["hit", ..] => Err(CommandError::Usage("hit <x> <y>")),
+            ["list"] => {
+                let mut out = String::new();
+                for (i, id) in self.shapes.iter().enumerate() {
+                    let Some(entry) = id else {
+                        continue;
+                    };
+                    let Some(shape) = self.canvas.get(entry.id) else {
+                        continue;
+                    };
+                    let shape = shape.lock()?;
+                    let Coord { x, y } = shape.origin();
+                    let _ = writeln!(
+                        out,
+                        "{} {} at {x} {y}, area {}",
+                        i + 1,
+                        shape.name(),
+                        shape.get_area()
+                    );
+                }
+                out.pop();
+                Ok(Reply::read(out))
+            }
+            ["render", ..] if self.restricted => Err(CommandError::Restricted("render")),
+            ["render", path, size @ ..] => self.render(path, size).map(Reply::read),
+            ["render", ..] => Err(CommandError::Usage("render <path.png> [<width> <height>]")),
+            ["skip", ..] if self.restricted => Err(CommandError::Restricted("skip")),
+            ["skip", n] => {
+                let usage = "skip <n>, with n at most 1048576";
+                let n: usize = n.parse().map_err(|_| CommandError::Usage(usage))?;
+                if n > MAX_SKIP {
+                    return Err(CommandError::Usage(usage));
+                }
+                self.shapes.extend((0..n).map(|_| None));
+                Ok(CHANGED)
+            }
+            ["skip", ..] => Err(CommandError::Usage("skip <n>")),
+            ["group", "at", x, y] => {
+                let at = point(x, y, "group at <x> <y>")?;
+                let group = self.canvas.add_group(None, Transform::translation(at))?;
+                Ok(Reply::change(format!("added group {}", group + 1)))
+            }
+            ["group", ..] => Err(CommandError::Usage("group at <x> <y>")),
+            ["symmetry", "off"] => {
+                self.set_symmetry(None)?;
+                Ok(CHANGED)
+            }
+            ["symmetry", kind, n, center @ ..] => {
+                let usage = "symmetry cyclic|dihedral <n> [at <x> <y>], n from 1 to 360";
+                let n = match n.parse() {
+                    Ok(n @ 1..=MAX_ORDER) => n,
+                    _ => return Err(CommandError::Usage(usage)),
+                };
+                let group = match *kind {
+                    "cyclic" => SymmetryGroup::Cyclic(n),
+                    "dihedral" => SymmetryGroup::Dihedral(n),
+                    _ => return Err(CommandError::Usage(usage)),
+                };
+                let center = match center {
+                    [] => Coord::default(),
+                    ["at", x, y] => point(x, y, usage)?,
+                    _ => return Err(CommandError::Usage(usage)),
+                };
+                coordinate("center", center)?;
+                self.set_symmetry(Some(Symmetry { group, center }))?;
+                Ok(CHANGED)
+            }
+            ["symmetry", ..] => Err(CommandError::Usage(
+                "symmetry cyclic|dihedral <n> [at <x> <y>]",
+            )),
+            ["snapshot", name] => {
+                self.take_snapshot(name)?;
+                Ok(Reply::default())
+            }
+            ["snapshot", ..] => Err(CommandError::Usage("snapshot <name>")),
+            ["snapshots"] => Ok(Reply::read(self.list_snapshots().join("\n"))),
+            ["restore", name] => {
+                // Which rewrites the journal itself
+                self.restore(name)?;
+                Ok(Reply::default())
+            }
+            ["restore", ..] => Err(CommandError::Usage("restore <name>")),
+            ["diff", a, b] => {
+                let changes = self.diff_snapshots(a, b)?;
+                let lines: Vec<String> = changes.iter().map(|change| change.to_string()).collect();
+                Ok(Reply::read(lines.join("\n")))
+            }
+            ["diff", ..] => Err(CommandError::Usage("diff <name> <name>")),
+            ["help"] => Ok(Reply::read(String::from(HELP))),
+            [command, ..] => Err(CommandError::UnknownCommand(String::from(*command))),
+        }
+    }
+
+    // Runs a script of commands, one per line, stopping at the first that
+    // fails. The outputs are joined with newlines.
+    pub fn run(&mut self, script: &str) -> Result<String, CommandError> {
+        let mut out = String::new();
+        for (i, line) in script.lines().enumerate() {
+            let output = self
+                .execute(line)
+                .map_err(|error| CommandError::Line(i + 1, Box::new(error)))?;
+            if !output.is_empty() {
+                out.push_str(&output);
+                out.push('\n');
+            }
+        }
+        out.pop();
+        Ok(out)
+    }
+
+    // A script that rebuilds the session as it is now, with shapes numbered
+    // afresh from 1. Shapes other than circles, rectangles and triangles are
+    // written as polygons through their outlines, and groups by where their
+    // transforms take the origin.
+    pub fn script(&self) -> Result<String, CommandError> {
+        self.write_script(false)
+    }
+    // The same, but skipping the numbers of removed shapes so the rest keep
+    // theirs, for scripts that later commands will be run after
+    pub(crate) fn snapshot(&self) -> Result<String, CommandError> {
+        self.write_script(true)
+    }
+
+    fn write_script(&self, keep_numbers: bool) -> Result<String, CommandError> {
+        let mut out = String::new();
+        for group in 0..self.canvas.groups.len() {
+            let Coord { x, y } = self.canvas.group_transform(group)?.offset;
+            let _ = writeln!(out, "group at {x} {y}");
+            if !self.canvas.group_visible(group)? {
+                let _ = writeln!(out, "hide group {}", group + 1);
+            }
+        }
+        let set_symmetry = |out: &mut String, symmetry| {
+            let _ = match symmetry {
+                None => writeln!(out, "symmetry off"),
+                Some(Symmetry { group, center }) => {
+                    let (kind, n) = match group {
+                        SymmetryGroup::Cyclic(n) => ("cyclic", n),
+                        SymmetryGroup::Dihedral(n) => ("dihedral", n),
+                    };
+                    writeln!(out, "symmetry {kind} {n} at {} {}", center.x, center.y)
+                }
+            };
+        };
+        let (mut symmetry, mut skipped, mut number) = (None, 0, 0);
+        for entry in &self.shapes {
+            let shape = entry.and_then(|entry| match entry.group {
+                Some(_) => self.canvas.local_shape(entry.id),
+                None => self.canvas.get(entry.id),
+            });
+            let (Some(entry), Some(shape)) = (entry, shape) else {
+                skipped += 1;
+                continue;
+            };
+            if keep_numbers && skipped > 0 {
+                let _ = writeln!(out, "skip {skipped}");
+                number += skipped;
+                skipped = 0;
+            }
+            number += 1;
+            if entry.group.is_none() && entry.symmetry != symmetry {
+                symmetry = entry.symmetry;
+                set_symmetry(&mut out, symmetry);
+            }
+            let _ = write!(out, "add {}", shape_words(&*shape.lock()?));
+            let _ = match entry.group {
+                Some(group) => writeln!(out, " in {}", group + 1),
+                None => writeln!(out),
+            };
+            for (key, value) in self.canvas.metadata(entry.id).into_iter().flatten() {
+                let _ = writeln!(out, "meta {number} {} {}", escape(key), escape(value));
+            }
+            for tag in self.canvas.tags(entry.id).into_iter().flatten() {
+                let _ = writeln!(out, "tag {number} {}", escape(tag));
+            }
+            if self.canvas.hidden_itself(entry.id) {
+                let _ = writeln!(out, "hide {number}");
+            }
+            if self.canvas.is_locked(entry.id) {
+                let _ = writeln!(out, "lock {number}");
+            }
+        }
+        if keep_numbers && skipped > 0 {
+            let _ = writeln!(out, "skip {skipped}");
+        }
+        // For shapes added after the script
+        if self.symmetry != symmetry {
+            set_symmetry(&mut out, self.symmetry);
+        }
+        Ok(out)
+    }
+
+    // Numbers of the shapes covering the point, topmost first. A shape's
+    // symmetric copies count as the shape.
+    pub fn hit(&self, point: Coord) -> Vec<usize> {
+        let mut numbers: Vec<usize> = Vec::new();
+        for id in self.canvas.hit_test(point) {
+            let number = self.shapes.iter().position(|entry| {
+                entry.is_some_and(|entry| self.canvas.copies_of(entry.id).contains(&id))
+            });
+            if let Some(number) = number.map(|i| i + 1) {
+                if !numbers.contains(&number) {
+                    numbers.push(number);
+                }
+            }
+        }
+        numbers
+    }
+
+    // Shapes added from now on get symmetric copies under it
+    pub fn set_symmetry(&mut self, symmetry: Option<Symmetry>) -> Result<(), CanvasError> {
+        self.canvas.set_symmetry(symmetry)?;
+        self.symmetry = symmetry;
+        Ok(())
+    }
+
+    // Takes on the other session's canvas and shape numbers, keeping the
+    // rest of this one
+    pub(crate) fn replace_canvas(&mut self, other: Session) {
+        self.canvas = other.canvas;
+        self.shapes = other.shapes;
+        self.symmetry = other.symmetry;
+    }
+
+    // What the next shape added will be numbered
+    pub(crate) fn next_number(&self) -> usize {
+        self.shapes.len() + 1
+    }
+
+    fn push(&mut self, id: ShapeId, group: Option<usize>) -> Result<String, CommandError> {
+        let symmetry = group.is_none().then_some(self.symmetry).flatten();
+        self.shapes.push(Some(Entry {
+            id,
+            symmetry,
+            group,
+        }));
+        self.record(Event::Added(self.shapes.len()));
+        Ok(format!("added shape {}", self.shapes.len()))
+    }
+
+    // Starts keeping events for take_events
+    pub fn record_events(&mut self) {
+        self.events.get_or_insert_with(Vec::new);
+    }
+    // Those since the last call, oldest first
+    pub fn take_events(&mut self) -> Vec<Event> {
+        self.events.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    fn record(&mut self, event: Event) {
+        if let Some(events) = &mut self.events {
+            events.push(event);
+        }
+    }
+    fn moved(&mut self, number: usize) -> Result<(), CommandError> {
+        let id = self
+            .shape(number)
+            .ok_or(CommandError::UnknownNumber(number))?;
+        if let Some(shape) = self.canvas.get(id) {
+            let origin = shape.lock()?.origin();
+            self.record(Event::Moved(number, origin));
+        }
+        Ok(())
+    }
+
+    fn entry(&self, id: ShapeId) -> Option<Entry> {
+        self.shapes
+            .iter()
+            .flatten()
+            .find(|entry| entry.id == id)
+            .copied()
+    }
+
+    // Groups are numbered from 1 here, though from 0 on the canvas
+    fn group(&self, text: &str, usage: &'static str) -> Result<usize, CommandError> {
+        match text.parse::<usize>() {
+            Ok(group) if (1..=self.canvas.groups.len()).contains(&group) => Ok(group - 1),
+            _ => Err(CommandError::Usage(usage)),
+        }
+    }
+
+    fn number(&self, text: &str, usage: &'static str) -> Result<ShapeId, CommandError> {
+        let number: usize = text.parse().map_err(|_| CommandError::Usage(usage))?;
+        self.shape(number)
+            .ok_or(CommandError::UnknownNumber(number))
+    }
+
+    #[cfg(feature = "render")]
+    fn render(&self, path: &str, size: &[&str]) -> Result<String, CommandError> {
+        let usage = "render <path.png> [<width> <height>]";
+        if !path.ends_with(".png") {
+            return Err(CommandError::Usage(usage));
+        }
+        let (width, height) = match size {
+            [] => (512, 512),
+            [width, height] => (
+                width.parse().map_err(|_| CommandError::Usage(usage))?,
+                height.parse().map_err(|_| CommandError::Usage(usage))?,
+            ),
+            _ => return Err(CommandError::Usage(usage)),
+        };
+        self.image(width, height)?.save_png(path)?;
+        Ok(format!("wrote {path}"))
+    }
+
+    // The view is fitted round every shape with a margin
+    #[cfg(feature = "render")]
+    pub fn image(&self, width: u32, height: u32) -> Result<Image, CommandError> {
+        let mut config = RenderConfig {
+            width,
+            height,
+            ..RenderConfig::default()
+        };
+        let bounds = self
+            .canvas
+            .ids()
+            .iter()
+            .filter_map(|&id| self.canvas.bounding_box(id))
+            .reduce(|a, b| {
+                Rectangle::from_corners(
+                    Coord::new(a.min().x.min(b.min().x), a.min().y.min(b.min().y)),
+                    Coord::new(a.max().x.max(b.max().x), a.max().y.max(b.max().y)),
+                )
+            });
+        if let Some(bounds) = bounds {
+            let side = bounds.side_a.max(bounds.side_b) * 1.1;
+            config.view = Rectangle {
+                side_a: side,
+                side_b: side,
+                ..bounds
+            };
+        }
+        Ok(render(&self.canvas, &config)?)
+    }
+    #[cfg(not(feature = "render"))]
+    fn render(&self, _: &str, _: &[&str]) -> Result<String, CommandError> {
+        Err(CommandError::Unsupported("render"))
+    }
+}
+
+fn number(text: &str, usage: &'static str) -> Result<f32, CommandError> {
+    text.parse().map_err(|_| CommandError::Usage(usage))
+}
+fn point(x: &str, y: &str, usage: &'static str) -> Result<Coord, CommandError> {
+    Ok(Coord::new(number(x, usage)?, number(y, usage)?))
+}
+
+// The shape as it's given to add: circle 2 at 1 1, say. Shapes other than
+// circles, rectangles, triangles and registered kinds are given as polygons
+// through their outlines, as are those named one of the three but drawn
+// rotated or sheared, a Transformed<Rectangle> say, whose outline the
+// shorter form wouldn't give back.
+pub(crate) fn shape_words(shape: &dyn Shape) -> String {
+    let origin = shape.origin();
+    let registered = registry().encode(shape);
+    if let Some((kind, numbers)) = registered {
+        let mut words = String::from(kind);
+        for number in numbers {
+            let _ = write!(words, " {number}");
+        }
+        let _ = write!(words, " at {} {}", origin.x, origin.y);
+        return words;
+    }
+    let outline = shape.outline();
+    let bounds = outline.bounding_box();
+    let (side_a, side_b) = (bounds.side_a, bounds.side_b);
+    let compact = match (shape.name(), &outline) {
+        ("circle", &Outline::Circle { radius, .. }) => {
+            let plain = Circle { origin, radius };
+            same_outline(&plain.outline(), &outline).then(|| format!("circle {radius}"))
+        }
+        ("rectangle" | "square", _) => {
+            let plain = Rectangle {
+                origin,
+                side_a,
+                side_b,
+            };
+            same_outline(&plain.outline(), &outline).then(|| format!("rectangle {side_a} {side_b}"))
+        }
+        ("triangle", _) => {
+            let plain = Triangle {
+                origin,
+                base: side_a,
+                height: side_b,
+            };
+            same_outline(&plain.outline(), &outline).then(|| format!("triangle {side_a} {side_b}"))
+        }
+        _ => None,
+    };
+    let mut words = compact.unwrap_or_else(|| {
+        let mut points = String::from("polygon");
+        for point in outline_points(outline) {
+            let Coord { x, y } = point - origin;
+            let _ = write!(points, " {x} {y}");
+        }
+        points
+    });
+    let _ = write!(words, " at {} {}", origin.x, origin.y);
+    words
+}
+
+// Vertex for vertex, to rounding
+fn same_outline(a: &Outline, b: &Outline) -> bool {
+    let same = |a: Coord, b: Coord| approx::eq(a.x, b.x) && approx::eq(a.y, b.y);
+    match (a, b) {
+        (
+            Outline::Circle { center, radius },
+            Outline::Circle {
+                center: c,
+                radius: r,
+            },
+        ) => same(*center, *c) && approx::eq(*radius, *r),
+        (Outline::Polygon(a), Outline::Polygon(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(&a, &b)| same(a, b))
+        }
+        _ => false,
+    }
+}
+
+// Keys and values are written a word each, so the whitespace in them is
+// written as \s, \t, \n, \r or \u{<hex>}, and the empty string as a lone
+// backslash
+pub(crate) fn escape(text: &str) -> String {
+    if text.is_empty() {
+        return String::from("\\");
+    }
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            ' ' => out.push_str("\\s"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_whitespace() => {
+                let _ = write!(out, "\\u{{{:x}}}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+pub(crate) fn unescape(word: &str) -> String {
+    let mut out = String::new();
+    let mut chars = word.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => out.push(' '),
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('u') => {
+                let code: String = chars.by_ref().skip(1).take_while(|&c| c != '}').collect();
+                out.extend(u32::from_str_radix(&code, 16).ok().and_then(char::from_u32));
+            }
+            Some(c) => out.push(c),
+            None => {}
+        }
+    }
+    out
+}
+
+fn registry() -> RwLockReadGuard<'static, ShapeRegistry> {
+    ShapeRegistry::global()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+pub(crate) fn parse_shape(kind: &str, words: &[&str]) -> Result<ShapeObject, CommandError> {
+    let registry = registry();
+    if registry.contains(kind) {
+        let usage = "add <kind> <number>... at <x> <y>";
+        let [numbers @ .., "at", x, y] = words else {
+            return Err(CommandError::Usage(usage));
+        };
+        let numbers = numbers
+            .iter()
+            .map(|word| number(word, usage))
+            .collect::<Result<Vec<f32>, _>>()?;
+        let origin = point(x, y, usage)?;
+        // Unless it was unregistered since, which leaves the kinds built in
+        if let Some(shape) = registry.decode(kind, &numbers, origin) {
+            return Ok(shape?);
+        }
+    }
+    let (usage, sizes) = match kind {
+        "circle" => ("add circle <radius> at <x> <y>", 1),
+        "rectangle" => ("add rectangle <width> <height> at <x> <y>", 2),
+        "triangle" => ("add triangle <base> <height> at <x> <y>", 2),
+        "polygon" => ("add polygon <x> <y> <x> <y> <x> <y>... at <x> <y>", 0),
+        _ => return Err(CommandError::UnknownCommand(format!("add {kind}"))),
+    };
+    let [size @ .., "at", x, y] = words else {
+        return Err(CommandError::Usage(usage));
+    };
+    // Polygons take any number of points, two numbers each
+    let fits = match sizes {
+        0 => size.len() % 2 == 0,
+        sizes => size.len() == sizes,
+    };
+    if !fits {
+        return Err(CommandError::Usage(usage));
+    }
+    let size = size
+        .iter()
+        .map(|word| number(word, usage))
+        .collect::<Result<Vec<f32>, _>>()?;
+    let origin = point(x, y, usage)?;
+    Ok(match kind {
+        "circle" => Arc::new(ShapeCell::new(
+            Circle::builder().radius(size[0]).origin(origin).build()?,
+        )),
+        "polygon" => Arc::new(ShapeCell::new(
+            Polygon::builder()
+                .points(size.chunks(2).map(|pair| Coord::new(pair[0], pair[1])))
+                .origin(origin)
+                .build()?,
+        )),
+        "rectangle" => Arc::new(ShapeCell::new(
+            Rectangle::builder()
+                .side_a(size[0])
+                .side_b(size[1])
+                .origin(origin)
+                .build()?,
+        )),
+        _ => Arc::new(ShapeCell::new(
+            Triangle::builder()
+                .base(size[0])
+                .height(size[1])
+                .origin(origin)
+                .build()?,
+        )),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CanvasError;
+
+    #[test]
+    fn run_commands() {
+        let mut session = Session::new();
+        assert_eq!(
+            session.execute("add circle 5 at 0 0").unwrap(),
+            "added shape 1"
+        );
+        assert_eq!(
+            session.execute("add rectangle 2 4 at 1 1").unwrap(),
+            "added shape 2"
+        );
+        session
+            .execute("  # comments and blank lines are skipped")
+            .unwrap();
+        session.execute("").unwrap();
+        session.execute("move 2 to 5 5").unwrap();
+        assert_eq!(session.execute("area 2").unwrap(), "8");
+        assert_eq!(
+            session.execute("list").unwrap(),
+            format!(
+                "1 circle at 0 0, area {}\n2 rectangle at 5 5, area 8",
+                Circle {
+                    origin: Coord::default(),
+                    radius: 5.0
+                }
+                .get_area()
+            )
+        );
+        session.execute("remove 1").unwrap();
+        assert_eq!(session.execute("area").unwrap(), "8");
+        assert_eq!(
+            session.execute("add triangle 2 2 at 0 0").unwrap(),
+            "added shape 3"
+        );
+
+        assert!(matches!(
+            session.execute("area 1"),
+            Err(CommandError::UnknownNumber(1))
+        ));
+        assert!(matches!(
+            session.execute("move 2 to five 5"),
+            Err(CommandError::Usage(_))
+        ));
+        assert!(matches!(
+            session.execute("add circle -1 at 0 0"),
+            Err(CommandError::Canvas(_))
+        ));
+        assert!(matches!(
+            session.execute("spin 2"),
+            Err(CommandError::UnknownCommand(_))
+        ));
+        assert_eq!(session.execute("help").unwrap(), HELP);
+
+        for order in ["0", "361", "4000000000"] {
+            assert!(matches!(
+                session.execute(&format!("symmetry dihedral {order}")),
+                Err(CommandError::Usage(_))
+            ));
+        }
+
+        // Skips are bounded, and refused outright once restricted
+        assert!(matches!(
+            session.execute("skip 99999999999"),
+            Err(CommandError::Usage(_))
+        ));
+        session.execute("skip 2").unwrap();
+        session.restrict();
+        assert!(matches!(
+            session.execute("skip 1"),
+            Err(CommandError::Restricted("skip"))
+        ));
+        assert_eq!(
+            session.execute("add circle 1 at 0 0").unwrap(),
+            "added shape 6"
+        );
+    }
+
+    #[test]
+    fn script_round_trip() {
+        let mut session = Session::new();
+        session
+            .run(
+                "add circle 2 at 1 1\n\
+                 add triangle 3 4 at 0 0\n\
+                 add rectangle 1 1 at 9 9\n\
+                 add polygon 0 0 4 0 0 3 at 5 -5\n\
+                 remove 3",
+            )
+            .unwrap();
+        let script = session.script().unwrap();
+        assert_eq!(
+            script,
+            "add circle 2 at 1 1\n\
+             add triangle 3 4 at 0 0\n\
+             add polygon 0 0 4 0 0 3 at 5 -5\n"
+        );
+        let mut copy = Session::new();
+        copy.run(&script).unwrap();
+        assert_eq!(copy.script().unwrap(), script);
+        assert_eq!(copy.canvas.total_area(), session.canvas.total_area());
+
+        // Metadata goes with the shapes, under their new numbers
+        session
+            .run("meta 4 label big\\sred triangle\nmeta 4 note \\\nmeta 1 path a\\\\b")
+            .unwrap();
+        assert_eq!(
+            session.execute("meta 4 label").unwrap(),
+            "big\\sred\\striangle"
+        );
+        let script = session.script().unwrap();
+        assert!(script.ends_with(
+            "add polygon 0 0 4 0 0 3 at 5 -5\n\
+             meta 3 label big\\sred\\striangle\n\
+             meta 3 note \\\n"
+        ));
+        let mut copy = Session::new();
+        copy.run(&script).unwrap();
+        let (shape, polygon) = (copy.shape(1).unwrap(), copy.shape(3).unwrap());
+        assert_eq!(copy.canvas.meta(shape, "path"), Some("a\\b"));
+        assert_eq!(copy.canvas.meta(polygon, "label"), Some("big red triangle"));
+        assert_eq!(copy.canvas.meta(polygon, "note"), Some(""));
+        session.execute("tag 2 background").unwrap();
+        let script = session.script().unwrap();
+        assert!(script.contains("add triangle 3 4 at 0 0\ntag 2 background\n"));
+        let mut copy = Session::new();
+        copy.run(&script).unwrap();
+        assert_eq!(
+            copy.canvas.find_by_tag("background"),
+            [copy.shape(2).unwrap()]
+        );
+        session.execute("hide 2").unwrap();
+        session.execute("lock 1").unwrap();
+        assert!(session
+            .script()
+            .unwrap()
+            .contains("add circle 2 at 1 1\nmeta 1 path a\\\\b\nlock 1\n"));
+        assert!(session
+            .script()
+            .unwrap()
+            .contains("tag 2 background\nhide 2\n"));
+        assert!(matches!(
+            session.execute("move 1 to 0 0"),
+            Err(CommandError::Canvas(CanvasError::Locked(_)))
+        ));
+        assert!(session.execute("remove 1").is_err());
+        assert!(session.shape(1).is_some());
+        session.execute("unlock 1").unwrap();
+        session.execute("show 2").unwrap();
+        assert_eq!(escape("tab\there\u{a0}"), "tab\\there\\u{a0}");
+        assert_eq!(unescape(&escape("tab\there\u{a0}")), "tab\there\u{a0}");
+
+        match session.run("list\nadd polygon 0 0 1 at 0 0") {
+            Err(CommandError::Line(2, error)) => {
+                assert!(matches!(*error, CommandError::Usage(_)))
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn transformed_shapes_round_trip() {
+        let rectangle = Rectangle {
+            origin: Coord::new(1.0, 2.0),
+            side_a: 4.0,
+            side_b: 2.0,
+        };
+        // Only moved, so the short form still gives it back
+        let moved = rectangle
+            .clone()
+            .transformed(Transform::translation(Coord::new(1.0, 0.0)));
+        assert_eq!(shape_words(&moved), "rectangle 4 2 at 2 2");
+
+        let rotated = rectangle.transformed(Transform::rotation(0.5).about(Coord::new(1.0, 2.0)));
+        let words = shape_words(&rotated);
+        assert!(words.starts_with("polygon "), "{words}");
+        let (kind, rest) = words.split_once(' ').unwrap();
+        let parsed = parse_shape(kind, &rest.split(' ').collect::<Vec<_>>()).unwrap();
+        let parsed = parsed.lock().unwrap();
+        assert!(same_outline(&parsed.outline(), &rotated.outline()));
+        assert_eq!(parsed.origin(), rotated.origin());
+    }
+
+    #[test]
+    fn scene_with_groups_and_symmetry() {
+        let scene = "\
+group at 10 0
+symmetry cyclic 4 at 0 0
+add circle 1 at 5 0
+add rectangle 2 2 at 1 1 in 1
+symmetry off
+add triangle 2 2 at -5 -5
+";
+        let mut session = Session::new();
+        assert_eq!(
+            session.run(scene).unwrap(),
+            "added group 1\nadded shape 1\nadded shape 2\nadded shape 3"
+        );
+        // Four circles, one rectangle in the group and the triangle
+        assert_eq!(session.canvas.len(), 6);
+        let member = session.shape(2).unwrap();
+        assert_eq!(
+            session.canvas.get(member).unwrap().lock().unwrap().origin(),
+            Coord::new(11.0, 1.0)
+        );
+
+        session.record_events();
+        session.execute("move group 1 to 0 10").unwrap();
+        session.execute("move 2 to 2 2").unwrap();
+        assert_eq!(
+            session.take_events(),
+            [
+                Event::Moved(2, Coord::new(1.0, 11.0)),
+                Event::Moved(2, Coord::new(2.0, 12.0))
+            ]
+        );
+        assert_eq!(
+            Event::Moved(2, Coord::new(2.0, 12.0)).to_string(),
+            "moved 2 to 2 12"
+        );
+        assert_eq!(
+            session.canvas.get(member).unwrap().lock().unwrap().origin(),
+            Coord::new(2.0, 12.0)
+        );
+        let script = session.script().unwrap();
+        assert_eq!(
+            script,
+            "\
+group at 0 10
+symmetry cyclic 4 at 0 0
+add circle 1 at 5 0
+add rectangle 2 2 at 2 2 in 1
+symmetry off
+add triangle 2 2 at -5 -5
+"
+        );
+        let mut copy = Session::new();
+        copy.run(&script).unwrap();
+        assert_eq!(copy.canvas.len(), 6);
+        // Over a copy of the circle, then over nothing, then over the group
+        // member drawn on top of another copy
+        assert_eq!(session.execute("hit 0 -5").unwrap(), "1");
+        assert_eq!(session.execute("hit 3 3").unwrap(), "");
+        session.execute("move group 1 to -2 3").unwrap();
+        assert_eq!(session.hit(Coord::new(0.0, 5.0)), [2, 1]);
+        // Hidden shapes aren't hit, and stay hidden through a script
+        session.execute("hide group 1").unwrap();
+        assert_eq!(session.hit(Coord::new(0.0, 5.0)), [1]);
+        let script = session.script().unwrap();
+        assert!(script.starts_with("group at -2 3\nhide group 1\n"));
+        let mut copy = Session::new();
+        copy.run(&script).unwrap();
+        assert_eq!(copy.hit(Coord::new(0.0, 5.0)), [1]);
+
+        assert!(matches!(
+            session.execute("add circle 1 at 0 0 in 2"),
+            Err(CommandError::Usage(_))
+        ));
+        assert!(matches!(
+            session.execute("symmetry cyclic 0"),
+            Err(CommandError::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn properties_by_name() {
+        let mut session = Session::new();
+        session.execute("add rectangle 2 4 at 0 0").unwrap();
+        assert_eq!(session.execute("prop 1").unwrap(), "side_a 2\nside_b 4");
+        session.execute("prop 1 side_a 3").unwrap();
+        assert_eq!(session.execute("prop 1 side_a").unwrap(), "3");
+        assert_eq!(session.execute("area 1").unwrap(), "12");
+        assert!(session.execute("prop 1 radius").is_err());
+        assert!(session.execute("prop 1 side_b 0").is_err());
+        session.execute("lock 1").unwrap();
+        assert!(matches!(
+            session.execute("prop 1 side_b 1"),
+            Err(CommandError::Canvas(CanvasError::Locked(_)))
+        ));
+    }
+}
@@ -0,0 +1,292 @@
+use crate::{
+    approx,
+    arena::ShapeId,
+    builder::{coordinate, dimension},
+    error::CanvasError,
+    transform::Transform,
+    Canvas, Coord, Rectangle,
+};
+
+// Rules a shape is held to. Those that move shapes are enforced after every
+// set_origin; aspect ratios, which reshape them, and everything after moves
+// made some other way, by solve.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Constraint {
+    // The origin held at the point
+    Pin(Coord),
+    // The bounding box kept inside the rectangle, moving the shape in by the
+    // least it takes. Boxes too big to fit are lined up with its bottom left.
+    Inside(Rectangle),
+    // Width over height of the bounding box, kept by stretching the shape up
+    // or down about the box's centre. Shapes other than circles become
+    // polygons when stretched, as do circles held to a ratio other than one.
+    Aspect(f32),
+    // The origin kept at the offset from the other shape's
+    Offset { from: ShapeId, offset: Coord },
+}
+
+// Rounds of moves settle makes before giving up on constraints that fight
+// each other
+const PASSES: usize = 16;
+
+impl Canvas {
+    // Applies the constraint straight away and returns its index into
+    // constraints(). Constraints go when their shape is removed, or the
+    // shape an offset is from.
+    pub fn constrain(&mut self, id: ShapeId, constraint: Constraint) -> Result<usize, CanvasError> {
+        self.shapes.get(id).ok_or(CanvasError::UnknownShape(id))?;
+        match &constraint {
+            Constraint::Pin(point) => {
+                coordinate("pin", *point)?;
+            }
+            Constraint::Inside(rect) => {
+                coordinate("container origin", rect.origin)?;
+                dimension("container width", Some(rect.side_a))?;
+                dimension("container height", Some(rect.side_b))?;
+            }
+            Constraint::Aspect(ratio) => {
+                dimension("aspect ratio", Some(*ratio))?;
+            }
+            Constraint::Offset { from, offset } => {
+                self.shapes
+                    .get(*from)
+                    .ok_or(CanvasError::UnknownShape(*from))?;
+                if *from == id {
+                    return Err(CanvasError::SelfConstraint(id));
+                }
+                coordinate("offset", *offset)?;
+            }
+        }
+        self.constraints.push((id, constraint));
+        self.solve()?;
+        Ok(self.constraints.len() - 1)
+    }
+    pub fn constraints(&self) -> &[(ShapeId, Constraint)] {
+        &self.constraints
+    }
+    // Those holding the shape, as indices into constraints()
+    pub fn constraints_on(&self, id: ShapeId) -> Vec<usize> {
+        (0..self.constraints.len())
+            .filter(|&i| self.constraints[i].0 == id)
+            .collect()
+    }
+    // Later constraints move down an index
+    pub fn remove_constraint(
+        &mut self,
+        index: usize,
+    ) -> Result<(ShapeId, Constraint), CanvasError> {
+        if index >= self.constraints.len() {
+            return Err(CanvasError::UnknownIndex(index));
+        }
+        Ok(self.constraints.remove(index))
+    }
+    pub(crate) fn forget_constraints(&mut self, id: ShapeId) {
+        self.constraints.retain(|(shape, constraint)| {
+            *shape != id && !matches!(constraint, Constraint::Offset { from, .. } if *from == id)
+        });
+    }
+
+    // Enforces every constraint and returns the indices of any left unmet,
+    // which happens when they contradict each other or hold a locked shape.
+    // Symmetric copies and group members aren't stretched for their aspect
+    // ratios.
+    pub fn solve(&mut self) -> Result<Vec<usize>, CanvasError> {
+        let aspects: Vec<(ShapeId, f32)> = self
+            .constraints
+            .iter()
+            .filter_map(|(id, constraint)| match constraint {
+                Constraint::Aspect(ratio) => Some((*id, *ratio)),
+                _ => None,
+            })
+            .collect();
+        for (id, ratio) in aspects {
+            if self.is_locked(id) || self.copies_of(id).len() > 1 || self.is_member(id) {
+                continue;
+            }
+            let shape = self.shapes.get(id).unwrap();
+            let stretched = {
+                let shape = shape.lock()?;
+                let bounds = shape.bounding_box();
+                let height = bounds.side_a / ratio;
+                if approx::eq(bounds.side_b, height) {
+                    continue;
+                }
+                Transform::scale(1.0, height / bounds.side_b)
+                    .about(bounds.origin)
+                    .image(&*shape)
+            };
+            stretched.set_history(shape.history());
+            stretched.touch();
+            *self.shapes.get_mut(id).unwrap() = stretched;
+        }
+        self.settle()?;
+        self.unmet()
+    }
+
+    // Moves shapes until the moving constraints hold, or for so many rounds
+    pub(crate) fn settle(&self) -> Result<(), CanvasError> {
+        for _ in 0..PASSES {
+            let mut moved = false;
+            for (id, constraint) in &self.constraints {
+                if self.is_locked(*id) {
+                    continue;
+                }
+                let (origin, bounds) = {
+                    let shape = self.shapes.get(*id).unwrap().lock()?;
+                    (shape.origin(), shape.bounding_box())
+                };
+                let target = match constraint {
+                    Constraint::Pin(point) => *point,
+                    Constraint::Inside(rect) => origin + inward(&bounds, rect),
+                    Constraint::Aspect(_) => continue,
+                    Constraint::Offset { from, offset } => {
+                        self.shapes.get(*from).unwrap().lock()?.origin() + *offset
+                    }
+                };
+                if !same(origin, target) {
+                    self.place(*id, target)?;
+                    moved = true;
+                }
+            }
+            if !moved {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn unmet(&self) -> Result<Vec<usize>, CanvasError> {
+        let mut unmet = Vec::new();
+        for (i, (id, constraint)) in self.constraints.iter().enumerate() {
+            let shape = self.shapes.get(*id).unwrap().lock()?;
+            let bounds = shape.bounding_box();
+            let met = match constraint {
+                Constraint::Pin(point) => same(shape.origin(), *point),
+                Constraint::Inside(rect) => same(inward(&bounds, rect), Coord::default()),
+                Constraint::Aspect(ratio) => approx::eq(bounds.side_a / ratio, bounds.side_b),
+                Constraint::Offset { from, offset } => {
+                    let from = self.shapes.get(*from).unwrap().lock()?.origin();
+                    same(shape.origin(), from + *offset)
+                }
+            };
+            if !met {
+                unmet.push(i);
+            }
+        }
+        Ok(unmet)
+    }
+}
+
+fn same(a: Coord, b: Coord) -> bool {
+    approx::eq(a.x, b.x) && approx::eq(a.y, b.y)
+}
+
+// The least move putting the box inside the container
+fn inward(bounds: &Rectangle, container: &Rectangle) -> Coord {
+    let (min, max) = (bounds.min(), bounds.max());
+    let (low, high) = (container.min(), container.max());
+    let axis = |min: f32, max: f32, low: f32, high: f32| {
+        if min < low || max - min > high - low {
+            low - min
+        } else if max > high {
+            high - max
+        } else {
+            0.0
+        }
+    };
+    Coord::new(
+        axis(min.x, max.x, low.x, high.x),
+        axis(min.y, max.y, low.y, high.y),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{assert_approx_eq, Circle, ShapeCell, ShapeObject};
+
+    fn square(x: f32, y: f32, side: f32) -> ShapeObject {
+        Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::new(x, y),
+            side_a: side,
+            side_b: side,
+        }))
+    }
+
+    #[test]
+    fn constraints_hold_through_moves() {
+        let mut canvas = Canvas::default();
+        let anchor = canvas.add(square(0.0, 0.0, 2.0));
+        let follower = canvas.add(square(3.0, 3.0, 2.0));
+        let boxed = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(50.0, 0.0),
+            radius: 1.0,
+        })));
+        let origin = |canvas: &Canvas, id| canvas.get(id).unwrap().lock().unwrap().origin();
+
+        canvas
+            .constrain(
+                follower,
+                Constraint::Offset {
+                    from: anchor,
+                    offset: Coord::new(10.0, 0.0),
+                },
+            )
+            .unwrap();
+        assert_eq!(origin(&canvas, follower), Coord::new(10.0, 0.0));
+        let container = Rectangle::from_corners(Coord::new(0.0, 0.0), Coord::new(20.0, 20.0));
+        canvas
+            .constrain(boxed, Constraint::Inside(container.clone()))
+            .unwrap();
+        assert_eq!(origin(&canvas, boxed), Coord::new(19.0, 1.0));
+
+        // Moving the anchor brings the follower along, and the circle can't
+        // be moved out
+        canvas.set_origin(anchor, (2.0, 5.0)).unwrap();
+        assert_eq!(origin(&canvas, follower), Coord::new(12.0, 5.0));
+        canvas.set_origin(boxed, (-5.0, 10.0)).unwrap();
+        assert_eq!(origin(&canvas, boxed), Coord::new(1.0, 10.0));
+
+        // Moves made otherwise wait for solve
+        canvas.translate_all((100.0, 0.0)).unwrap();
+        assert_eq!(canvas.solve().unwrap(), Vec::<usize>::new());
+        assert_eq!(origin(&canvas, boxed), Coord::new(19.0, 10.0));
+        assert_eq!(origin(&canvas, follower), Coord::new(112.0, 5.0));
+
+        // Stretched to square, keeping the width
+        let wide = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::new(0.0, 0.0),
+            side_a: 4.0,
+            side_b: 1.0,
+        })));
+        canvas.constrain(wide, Constraint::Aspect(1.0)).unwrap();
+        let bounds = canvas.get(wide).unwrap().lock().unwrap().bounding_box();
+        assert_approx_eq!(bounds.side_a, 4.0);
+        assert_approx_eq!(bounds.side_b, 4.0);
+
+        // A pin outside the container can't be met
+        let pin = canvas
+            .constrain(boxed, Constraint::Pin(Coord::new(-10.0, 0.0)))
+            .unwrap();
+        // The pin has the last word, so it's the container that's unmet
+        assert_eq!(canvas.solve().unwrap(), vec![1]);
+        assert_eq!(canvas.constraints_on(boxed), vec![1, pin]);
+        canvas.remove_constraint(pin).unwrap();
+
+        assert!(matches!(
+            canvas.constrain(
+                anchor,
+                Constraint::Offset {
+                    from: anchor,
+                    offset: Coord::default()
+                }
+            ),
+            Err(CanvasError::SelfConstraint(_))
+        ));
+        assert!(canvas.constrain(anchor, Constraint::Aspect(0.0)).is_err());
+        canvas.remove(anchor);
+        assert_eq!(canvas.constraints().len(), 2);
+    }
+}
@@ -0,0 +1,352 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::{
+    arena::ShapeId,
+    canvas::check_style,
+    error::CanvasError,
+    export,
+    palette::{Assignment, Palette},
+    Canvas, Coord, Rectangle, Style,
+};
+
+// The metadata key naming the shared style a shape was given, so that
+// redefining the style restyles it
+pub const STYLE_KEY: &str = "document.style";
+
+// Named canvases, each a page of its own size, with styles and palettes
+// shared between them
+#[derive(Default)]
+pub struct Document {
+    artboards: Vec<Artboard>,
+    styles: BTreeMap<String, Style>,
+    palettes: BTreeMap<String, Palette>,
+}
+
+pub struct Artboard {
+    pub name: String,
+    pub canvas: Canvas,
+    // The page, in canvas units, laid out by the canvas axes
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Artboard {
+    // The canvas area the page covers
+    pub fn frame(&self) -> Rectangle {
+        self.canvas.axes().page(self.width, self.height)
+    }
+}
+
+impl Document {
+    pub fn new() -> Document {
+        Document::default()
+    }
+
+    // In the order added, which is the page order
+    pub fn artboards(&self) -> &[Artboard] {
+        &self.artboards
+    }
+    pub fn add_artboard(
+        &mut self,
+        name: impl Into<String>,
+        width: f32,
+        height: f32,
+    ) -> Result<&mut Canvas, CanvasError> {
+        let name = name.into();
+        if self.artboard(&name).is_some() {
+            return Err(CanvasError::NameTaken(name));
+        }
+        crate::builder::dimension("width", Some(width))?;
+        crate::builder::dimension("height", Some(height))?;
+        self.artboards.push(Artboard {
+            name,
+            canvas: Canvas::default(),
+            width,
+            height,
+        });
+        Ok(&mut self.artboards.last_mut().unwrap().canvas)
+    }
+    pub fn artboard(&self, name: &str) -> Option<&Artboard> {
+        self.artboards.iter().find(|artboard| artboard.name == name)
+    }
+    pub fn artboard_mut(&mut self, name: &str) -> Option<&mut Artboard> {
+        self.artboards
+            .iter_mut()
+            .find(|artboard| artboard.name == name)
+    }
+    pub fn canvas(&self, name: &str) -> Result<&Canvas, CanvasError> {
+        self.artboard(name)
+            .map(|artboard| &artboard.canvas)
+            .ok_or_else(|| CanvasError::UnknownName(name.into()))
+    }
+    pub fn canvas_mut(&mut self, name: &str) -> Result<&mut Canvas, CanvasError> {
+        self.artboard_mut(name)
+            .map(|artboard| &mut artboard.canvas)
+            .ok_or_else(|| CanvasError::UnknownName(name.into()))
+    }
+    pub fn remove_artboard(&mut self, name: &str) -> Result<Artboard, CanvasError> {
+        let index = self
+            .artboards
+            .iter()
+            .position(|artboard| artboard.name == name)
+            .ok_or_else(|| CanvasError::UnknownName(name.into()))?;
+        Ok(self.artboards.remove(index))
+    }
+
+    pub fn style(&self, name: &str) -> Option<Style> {
+        self.styles.get(name).copied()
+    }
+    // Redefining a style restyles every shape given it, on every artboard,
+    // even those whose style was changed on the canvas since
+    pub fn define_style(
+        &mut self,
+        name: impl Into<String>,
+        style: Style,
+    ) -> Result<(), CanvasError> {
+        check_style(&style)?;
+        let name = name.into();
+        for artboard in &mut self.artboards {
+            let canvas = &mut artboard.canvas;
+            for id in canvas.ids().to_vec() {
+                if canvas.meta(id, STYLE_KEY) == Some(name.as_str()) {
+                    canvas.store_style(id, style);
+                }
+            }
+        }
+        self.styles.insert(name, style);
+        Ok(())
+    }
+    // Styles the shape and its symmetric copies, and links them to the
+    // style by metadata under STYLE_KEY
+    pub fn apply_style(
+        &mut self,
+        artboard: &str,
+        id: ShapeId,
+        name: &str,
+    ) -> Result<(), CanvasError> {
+        let style = self
+            .style(name)
+            .ok_or_else(|| CanvasError::UnknownName(name.into()))?;
+        let canvas = self.canvas_mut(artboard)?;
+        canvas.set_style(id, style)?;
+        canvas.set_meta(id, STYLE_KEY, name)?;
+        Ok(())
+    }
+
+    pub fn palette(&self, name: &str) -> Option<&Palette> {
+        self.palettes.get(name)
+    }
+    pub fn define_palette(&mut self, name: impl Into<String>, palette: Palette) {
+        self.palettes.insert(name.into(), palette);
+    }
+    pub fn apply_palette(
+        &mut self,
+        artboard: &str,
+        name: &str,
+        assignment: Assignment,
+    ) -> Result<(), CanvasError> {
+        let palette = self
+            .palettes
+            .get(name)
+            .ok_or_else(|| CanvasError::UnknownName(name.into()))?;
+        let canvas = &mut self
+            .artboards
+            .iter_mut()
+            .find(|board| board.name == artboard)
+            .ok_or_else(|| CanvasError::UnknownName(artboard.into()))?
+            .canvas;
+        canvas.apply_palette(palette, assignment);
+        Ok(())
+    }
+
+    // Pastes copies of the shapes onto the other artboard, moved by the
+    // offset, with their styles, metadata and tags, and returns their ids
+    // there. Links to shared styles come along in the metadata.
+    pub fn copy_to(
+        &mut self,
+        from: &str,
+        ids: &[ShapeId],
+        to: &str,
+        offset: impl Into<Coord>,
+    ) -> Result<Vec<ShapeId>, CanvasError> {
+        let clipboard = self.canvas(from)?.copy(ids);
+        self.canvas_mut(to)?.paste(&clipboard, offset)
+    }
+
+    pub fn svg(&self, artboard: &str) -> Result<String, CanvasError> {
+        let artboard = self
+            .artboard(artboard)
+            .ok_or_else(|| CanvasError::UnknownName(artboard.into()))?;
+        Ok(export::svg(&artboard.canvas, &artboard.frame()))
+    }
+    // A page for each artboard, in order
+    pub fn pdf(&self) -> Vec<u8> {
+        let frames: Vec<Rectangle> = self.artboards.iter().map(Artboard::frame).collect();
+        let pages: Vec<(&Canvas, Rectangle)> = self
+            .artboards
+            .iter()
+            .zip(frames)
+            .map(|(artboard, frame)| (&artboard.canvas, frame))
+            .collect();
+        export::pdf(&pages)
+    }
+    pub fn save_pdf(&self, path: impl AsRef<Path>) -> Result<(), CanvasError> {
+        span!(DEBUG, "save_pdf", path = %path.as_ref().display());
+        Ok(fs::write(path, self.pdf())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        camera::{Axes, YAxis},
+        style::{Color, Paint},
+        Circle, ShapeCell, ShapeObject,
+    };
+
+    fn circle(x: f32, y: f32) -> ShapeObject {
+        Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(x, y),
+            radius: 1.0,
+        }))
+    }
+
+    #[test]
+    fn artboards_share_styles() {
+        let mut document = Document::new();
+        let cover = document
+            .add_artboard("cover", 20.0, 10.0)
+            .unwrap()
+            .add(circle(5.0, 5.0));
+        document.add_artboard("back", 20.0, 10.0).unwrap();
+        assert!(matches!(
+            document.add_artboard("cover", 1.0, 1.0),
+            Err(CanvasError::NameTaken(_))
+        ));
+        assert!(document.add_artboard("empty", 0.0, 1.0).is_err());
+
+        let red = Style {
+            fill: Some(Color::rgb(255, 0, 0).into()),
+            ..Style::default()
+        };
+        assert!(matches!(
+            document.apply_style("cover", cover, "accent"),
+            Err(CanvasError::UnknownName(_))
+        ));
+        document.define_style("accent", red).unwrap();
+        document.apply_style("cover", cover, "accent").unwrap();
+        let copied = document
+            .copy_to("cover", &[cover], "back", (1.0, 0.0))
+            .unwrap();
+        let back = document.canvas("back").unwrap();
+        assert_eq!(back.style(copied[0]), Some(red));
+        assert_eq!(back.meta(copied[0], STYLE_KEY), Some("accent"));
+
+        // Redefining restyles both artboards
+        let blue = Style {
+            fill: Some(Color::rgb(0, 0, 255).into()),
+            ..red
+        };
+        document.define_style("accent", blue).unwrap();
+        assert_eq!(document.canvas("cover").unwrap().style(cover), Some(blue));
+        assert_eq!(
+            document.canvas("back").unwrap().style(copied[0]),
+            Some(blue)
+        );
+
+        document.define_palette("mono", Palette::named("mono").unwrap());
+        document
+            .apply_palette("back", "mono", Assignment::ByIndex)
+            .unwrap();
+        assert_eq!(
+            document
+                .canvas("back")
+                .unwrap()
+                .style(copied[0])
+                .unwrap()
+                .fill,
+            Some(Palette::named("mono").unwrap().color(0).into())
+        );
+        assert!(document
+            .apply_palette("cover", "none", Assignment::ByIndex)
+            .is_err());
+
+        assert_eq!(document.remove_artboard("empty").ok().map(|a| a.name), None);
+        assert_eq!(document.remove_artboard("back").unwrap().name, "back");
+        assert_eq!(document.artboards().len(), 1);
+    }
+
+    #[test]
+    fn exports_svg_and_pdf() {
+        let mut document = Document::new();
+        let canvas = document.add_artboard("first", 20.0, 10.0).unwrap();
+        let shape = canvas.add(circle(5.0, 2.0));
+        canvas
+            .set_style(
+                shape,
+                Style {
+                    fill: Some(Color::rgb(255, 0, 0).into()),
+                    stroke: Some(Color::rgba(0, 0, 0, 128)),
+                    ..Style::default()
+                },
+            )
+            .unwrap();
+        let hidden = canvas.add(circle(1.0, 1.0));
+        canvas.set_visible(hidden, false).unwrap();
+        let canvas = document.add_artboard("second", 10.0, 10.0).unwrap();
+        canvas.set_axes(Axes {
+            y: YAxis::Down,
+            ..Axes::default()
+        });
+        let checked = canvas.add(circle(3.0, -4.0));
+        canvas
+            .set_style(
+                checked,
+                Style {
+                    fill: Some(Paint::Checks {
+                        size: 0.5,
+                        colors: [Color::BLACK, Color::rgb(255, 255, 255)],
+                    }),
+                    ..Style::default()
+                },
+            )
+            .unwrap();
+
+        // y up, so the circle is drawn 2 up from the bottom, 8 down
+        let svg = document.svg("first").unwrap();
+        assert!(svg.starts_with(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"20\" height=\"10\" \
+             viewBox=\"0 0 20 10\">\n<g transform=\"matrix(1 0 0 -1 0 10)\">\n"
+        ));
+        assert!(svg.contains(
+            "<circle cx=\"5\" cy=\"2\" r=\"1\" fill=\"#ff0000\" stroke=\"#000000\" \
+             stroke-opacity=\"0.5019608\""
+        ));
+        assert_eq!(svg.matches("<circle").count(), 1);
+        assert!(svg.ends_with("</g>\n</svg>\n"));
+        let svg = document.svg("second").unwrap();
+        assert!(svg.contains("<g transform=\"matrix(1 0 0 1 0 10)\">"));
+        assert!(svg.contains("<pattern id=\"paint0\" patternUnits=\"userSpaceOnUse\""));
+        assert!(svg.contains("<circle cx=\"3\" cy=\"-4\" r=\"1\" fill=\"url(#paint0)\"/>"));
+        assert!(document.svg("third").is_err());
+
+        let pdf = String::from_utf8(document.pdf()).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.contains("/Count 2"));
+        // 96 canvas units an inch, 72 points
+        assert!(pdf.contains("/MediaBox [0 0 15 7.5]"));
+        assert!(pdf.contains("/MediaBox [0 0 7.5 7.5]"));
+        assert!(pdf.contains("0.75 0 0 -0.75 0 0 cm"));
+        assert!(pdf.contains("/G0 << /ca 1 /CA 0.5019608 >>"));
+        assert!(pdf.contains("1 0 0 rg\n0 0 0 RG 1 w 0 J 0 j\n6 2 m\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        // The cross reference table points at each object
+        let start: usize = pdf.lines().rev().nth(1).unwrap().parse().unwrap();
+        assert!(pdf[start..].starts_with("xref\n0 7\n"));
+        let offset: usize = pdf[start..].lines().nth(5).unwrap()[..10].parse().unwrap();
+        assert!(pdf[offset..].starts_with("3 0 obj\n<< /Type /Page "));
+    }
+}
@@ -0,0 +1,262 @@
+use alloc::string::String;
+use core::{error::Error, fmt};
+#[cfg(feature = "std")]
+use std::{io, sync::PoisonError};
+
+#[cfg(feature = "std")]
+use crate::arena::ShapeId;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShapeError {
+    // A size that isn't a positive finite number
+    InvalidDimension { name: &'static str, value: f32 },
+    // A position with a NaN or infinite coordinate
+    InvalidCoordinate { name: &'static str },
+    MissingDimension { name: &'static str },
+    // Polygons need at least three points enclosing some area
+    DegeneratePolygon,
+    // A transform that flattens shapes, so has no inverse
+    SingularTransform,
+    // A name the shape has no property for
+    UnknownProperty(String),
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShapeError::InvalidDimension { name, value } => {
+                write!(f, "{name} must be positive and finite, got {value}")
+            }
+            ShapeError::InvalidCoordinate { name } => write!(f, "{name} must be finite"),
+            ShapeError::MissingDimension { name } => write!(f, "{name} was not set"),
+            ShapeError::DegeneratePolygon => write!(f, "polygon encloses no area"),
+            ShapeError::SingularTransform => write!(f, "transform is not invertible"),
+            ShapeError::UnknownProperty(name) => write!(f, "no property named {name:?}"),
+        }
+    }
+}
+
+impl Error for ShapeError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseColorError {
+    pub input: String,
+}
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid colour {:?}, expected #rgb, #rgba, #rrggbb or #rrggbbaa",
+            self.input
+        )
+    }
+}
+
+impl Error for ParseColorError {}
+
+// Where an expression stopped making sense, counting characters from 0
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseExprError {
+    pub input: String,
+    pub position: usize,
+    pub expected: &'static str,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for ParseExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid expression {:?}, expected {} at {}",
+            self.input, self.expected, self.position
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for ParseExprError {}
+
+// A rhai script that failed to parse or stopped with an error, with the
+// line it was on, counting from 1, when it's known
+#[cfg(feature = "rhai")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScriptError {
+    pub message: String,
+    pub line: Option<usize>,
+}
+
+#[cfg(feature = "rhai")]
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.message),
+            None => self.message.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "rhai")]
+impl Error for ScriptError {}
+
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum CanvasError {
+    // The shape was removed, or the id is from another canvas
+    UnknownShape(ShapeId),
+    // Locked shapes can't be moved, transformed or removed
+    Locked(ShapeId),
+    // A constraint holding a shape relative to itself
+    SelfConstraint(ShapeId),
+    // An index past the end, for storage and lists addressed by position
+    UnknownIndex(usize),
+    // A thread panicked while holding a shape's lock
+    Poisoned,
+    InvalidRenderConfig {
+        name: &'static str,
+    },
+    // Resolutions must be positive and finite
+    InvalidDpi(f32),
+    // Opacities run from 0 to 1
+    InvalidOpacity(f32),
+    // Symmetry orders run from 1 to symmetry::MAX_ORDER
+    InvalidSymmetryOrder(u32),
+    EmptyImage,
+    // No artboard, style or palette of that name in the document
+    UnknownName(String),
+    // Artboard names are unique within a document
+    NameTaken(String),
+    // lyon couldn't tessellate a shape
+    #[cfg(feature = "lyon")]
+    Tessellation(lyon_tessellation::TessellationError),
+    Shape(ShapeError),
+    Io(io::Error),
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for CanvasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CanvasError::UnknownShape(id) => write!(f, "no shape with id {id:?}"),
+            CanvasError::Locked(id) => write!(f, "shape {id:?} is locked"),
+            CanvasError::SelfConstraint(id) => {
+                write!(f, "shape {id:?} can't be constrained relative to itself")
+            }
+            CanvasError::UnknownIndex(index) => write!(f, "nothing at index {index}"),
+            CanvasError::Poisoned => write!(f, "shape lock poisoned by a panic"),
+            CanvasError::InvalidRenderConfig { name } => {
+                write!(f, "render {name} must be positive and finite")
+            }
+            CanvasError::InvalidDpi(dpi) => write!(f, "invalid resolution {dpi} dpi"),
+            CanvasError::InvalidSymmetryOrder(n) => write!(
+                f,
+                "symmetry order {n} is outside 1 to {}",
+                crate::symmetry::MAX_ORDER
+            ),
+            CanvasError::InvalidOpacity(opacity) => {
+                write!(f, "opacity must be between 0 and 1, got {opacity}")
+            }
+            CanvasError::EmptyImage => write!(f, "image has no pixels"),
+            CanvasError::UnknownName(name) => write!(f, "nothing named {name:?}"),
+            CanvasError::NameTaken(name) => write!(f, "{name:?} is already taken"),
+            #[cfg(feature = "lyon")]
+            CanvasError::Tessellation(error) => error.fmt(f),
+            CanvasError::Shape(error) => error.fmt(f),
+            CanvasError::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for CanvasError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CanvasError::Shape(error) => Some(error),
+            CanvasError::Io(error) => Some(error),
+            #[cfg(feature = "lyon")]
+            CanvasError::Tessellation(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<ShapeError> for CanvasError {
+    fn from(error: ShapeError) -> CanvasError {
+        CanvasError::Shape(error)
+    }
+}
+#[cfg(feature = "std")]
+impl From<io::Error> for CanvasError {
+    fn from(error: io::Error) -> CanvasError {
+        CanvasError::Io(error)
+    }
+}
+#[cfg(feature = "lyon")]
+impl From<lyon_tessellation::TessellationError> for CanvasError {
+    fn from(error: lyon_tessellation::TessellationError) -> CanvasError {
+        CanvasError::Tessellation(error)
+    }
+}
+#[cfg(feature = "std")]
+impl<T> From<PoisonError<T>> for CanvasError {
+    fn from(_: PoisonError<T>) -> CanvasError {
+        CanvasError::Poisoned
+    }
+}
+
+// A line for a command Session that couldn't be run
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum CommandError {
+    UnknownCommand(String),
+    // Arguments not matching the command's usage, which is given
+    Usage(&'static str),
+    // No live shape has that number
+    UnknownNumber(usize),
+    // A command this build leaves out, for want of a feature
+    Unsupported(&'static str),
+    // A command a restricted session won't run, for touching the filesystem
+    Restricted(&'static str),
+    Canvas(CanvasError),
+    // Where a script stopped, counting lines from 1
+    Line(usize, Box<CommandError>),
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::UnknownCommand(command) => write!(f, "unknown command {command:?}"),
+            CommandError::Usage(usage) => write!(f, "usage: {usage}"),
+            CommandError::UnknownNumber(number) => write!(f, "no shape numbered {number}"),
+            CommandError::Unsupported(command) => {
+                write!(f, "{command} is not available in this build")
+            }
+            CommandError::Restricted(command) => {
+                write!(f, "{command} is not allowed in this session")
+            }
+            CommandError::Canvas(error) => error.fmt(f),
+            CommandError::Line(line, error) => write!(f, "line {line}: {error}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for CommandError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CommandError::Canvas(error) => Some(error),
+            CommandError::Line(_, error) => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: Into<CanvasError>> From<E> for CommandError {
+    fn from(error: E) -> CommandError {
+        CommandError::Canvas(error.into())
+    }
+}
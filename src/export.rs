@@ -0,0 +1,381 @@
+// Vector output: an SVG of a canvas frame, and a PDF with a page for each of
+// a list of canvas frames. Both draw what draw_commands gives, so hidden
+// shapes are left out, and lay the frame out the way the canvas axes say.
+// Canvas units are pixels at the canvas resolution, so pages come out at
+// the frame's physical size.
+use std::fmt::Write;
+
+use crate::{
+    camera::YAxis,
+    canvas::DrawCommand,
+    geometry::Outline,
+    style::{Color, LineCap, LineJoin, Paint, Style},
+    Canvas, Coord, Rectangle,
+};
+
+// From the base of the circle's quarter arcs to their control points, as a
+// share of the radius
+const KAPPA: f32 = 0.552_284_8;
+
+// Everything is drawn in canvas coordinates inside a group mapping them onto
+// the page, so gradients and patterns line up with the canvas as when
+// rendered
+pub fn svg(canvas: &Canvas, frame: &Rectangle) -> String {
+    let scale = 96.0 / canvas.dpi().0;
+    let (width, height) = (frame.side_a, frame.side_b);
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+         viewBox=\"0 0 {width} {height}\">",
+        width * scale,
+        height * scale
+    );
+    let (min, max) = (frame.min(), frame.max());
+    let _ = match canvas.axes().y {
+        YAxis::Up => writeln!(
+            out,
+            "<g transform=\"matrix(1 0 0 -1 {} {})\">",
+            negate(min.x),
+            max.y
+        ),
+        YAxis::Down => writeln!(
+            out,
+            "<g transform=\"matrix(1 0 0 1 {} {})\">",
+            negate(min.x),
+            negate(min.y)
+        ),
+    };
+    for (i, command) in canvas.draw_commands().iter().enumerate() {
+        let style = &command.style;
+        let fill = match style.fill {
+            None => String::from(" fill=\"none\""),
+            Some(Paint::Solid(color)) => paint_attributes("fill", color),
+            Some(paint) => {
+                let _ = writeln!(out, "{}", svg_paint(i, &paint));
+                format!(" fill=\"url(#paint{i})\"")
+            }
+        };
+        let _ = match &command.outline {
+            Outline::Circle { center, radius } => write!(
+                out,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"{radius}\"",
+                center.x, center.y
+            ),
+            Outline::Polygon(points) => {
+                let mut path = String::new();
+                for (j, point) in points.iter().enumerate() {
+                    let _ = write!(
+                        path,
+                        "{}{} {} ",
+                        if j == 0 { "M" } else { "L" },
+                        point.x,
+                        point.y
+                    );
+                }
+                write!(out, "<path d=\"{path}Z\"")
+            }
+        };
+        out.push_str(&fill);
+        if let Some(stroke) = style.stroke {
+            out.push_str(&paint_attributes("stroke", stroke));
+            let _ = write!(out, " stroke-width=\"{}\"", style.stroke_width);
+            if let Some(dash) = style.dash {
+                let _ = write!(
+                    out,
+                    " stroke-dasharray=\"{} {}\" stroke-dashoffset=\"{}\"",
+                    dash.on, dash.off, dash.offset
+                );
+            }
+            let _ = write!(
+                out,
+                " stroke-linecap=\"{}\" stroke-linejoin=\"{}\"",
+                cap_name(style.cap),
+                join_name(style.join)
+            );
+        }
+        if style.opacity < 1.0 {
+            let _ = write!(out, " opacity=\"{}\"", style.opacity);
+        }
+        out.push_str("/>\n");
+    }
+    out.push_str("</g>\n</svg>\n");
+    out
+}
+
+// Written as 0 rather than -0 for points on the axes
+fn negate(value: f32) -> f32 {
+    0.0 - value
+}
+
+fn paint_attributes(name: &str, color: Color) -> String {
+    let hex = Color { a: 255, ..color }.to_hex();
+    match color.a {
+        255 => format!(" {name}=\"{hex}\""),
+        a => format!(" {name}=\"{hex}\" {name}-opacity=\"{}\"", a as f32 / 255.0),
+    }
+}
+
+fn stop(offset: f32, color: Color) -> String {
+    let hex = Color { a: 255, ..color }.to_hex();
+    format!(
+        "<stop offset=\"{offset}\" stop-color=\"{hex}\" stop-opacity=\"{}\"/>",
+        color.a as f32 / 255.0
+    )
+}
+
+// A gradient or pattern in canvas units, for a fill to refer to by id
+fn svg_paint(i: usize, paint: &Paint) -> String {
+    let rect = |x: f32, y: f32, width: f32, height: f32, color: Color| {
+        format!(
+            "<rect x=\"{x}\" y=\"{y}\" width=\"{width}\" height=\"{height}\"{}/>",
+            paint_attributes("fill", color)
+        )
+    };
+    match *paint {
+        Paint::Solid(_) => String::new(),
+        Paint::Linear { start, end, colors } => format!(
+            "<linearGradient id=\"paint{i}\" gradientUnits=\"userSpaceOnUse\" \
+             x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\">{}{}</linearGradient>",
+            start.x,
+            start.y,
+            end.x,
+            end.y,
+            stop(0.0, colors[0]),
+            stop(1.0, colors[1])
+        ),
+        Paint::Radial {
+            center,
+            radius,
+            colors,
+        } => format!(
+            "<radialGradient id=\"paint{i}\" gradientUnits=\"userSpaceOnUse\" \
+             cx=\"{}\" cy=\"{}\" r=\"{radius}\">{}{}</radialGradient>",
+            center.x,
+            center.y,
+            stop(0.0, colors[0]),
+            stop(1.0, colors[1])
+        ),
+        // Bands across the pattern's y, turned to run along the angle
+        Paint::Stripes {
+            angle,
+            size,
+            colors,
+        } => format!(
+            "<pattern id=\"paint{i}\" patternUnits=\"userSpaceOnUse\" width=\"{}\" \
+             height=\"{}\" patternTransform=\"rotate({})\">{}{}</pattern>",
+            2.0 * size,
+            2.0 * size,
+            angle.to_degrees(),
+            rect(0.0, 0.0, 2.0 * size, 2.0 * size, colors[0]),
+            rect(0.0, size, 2.0 * size, size, colors[1])
+        ),
+        Paint::Checks { size, colors } => format!(
+            "<pattern id=\"paint{i}\" patternUnits=\"userSpaceOnUse\" width=\"{}\" \
+             height=\"{}\">{}{}{}</pattern>",
+            2.0 * size,
+            2.0 * size,
+            rect(0.0, 0.0, 2.0 * size, 2.0 * size, colors[0]),
+            rect(size, 0.0, size, size, colors[1]),
+            rect(0.0, size, size, size, colors[1])
+        ),
+    }
+}
+
+fn cap_name(cap: LineCap) -> &'static str {
+    match cap {
+        LineCap::Butt => "butt",
+        LineCap::Round => "round",
+        LineCap::Square => "square",
+    }
+}
+fn join_name(join: LineJoin) -> &'static str {
+    match join {
+        LineJoin::Miter => "miter",
+        LineJoin::Round => "round",
+        LineJoin::Bevel => "bevel",
+    }
+}
+
+// One page a frame, each the frame's size at its canvas's resolution. PDF
+// has no flat equivalent of the gradients and patterns, so those are
+// filled with their first colour.
+pub fn pdf(pages: &[(&Canvas, Rectangle)]) -> Vec<u8> {
+    // The catalog and page tree, then a page and its contents for each
+    let mut objects = vec![
+        String::from("<< /Type /Catalog /Pages 2 0 R >>"),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..pages.len())
+                .map(|page| format!("{} 0 R", 3 + 2 * page))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        ),
+    ];
+    for (page, (canvas, frame)) in pages.iter().enumerate() {
+        let (contents, opacities) = page_contents(canvas, frame);
+        let points = |side: f32| side * 72.0 / canvas.dpi().0;
+        let states: String = opacities
+            .iter()
+            .enumerate()
+            .map(|(i, (fill, stroke))| format!(" /G{i} << /ca {fill} /CA {stroke} >>"))
+            .collect();
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents {} 0 R \
+             /Resources << /ExtGState <<{states} >> >> >>",
+            points(frame.side_a),
+            points(frame.side_b),
+            4 + 2 * page
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{contents}endstream",
+            contents.len()
+        ));
+    }
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        let _ = write!(out, "{} 0 obj\n{object}\nendobj\n", i + 1);
+    }
+    let xref = out.len();
+    let _ = write!(out, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(out, "{offset:010} 00000 n ");
+    }
+    let _ = write!(
+        out,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+        objects.len() + 1
+    );
+    out.into_bytes()
+}
+
+// The page's content stream, and the fill and stroke opacities it refers to
+// as graphics states /G0, /G1 and so on
+fn page_contents(canvas: &Canvas, frame: &Rectangle) -> (String, Vec<(f32, f32)>) {
+    let scale = 72.0 / canvas.dpi().0;
+    let (min, max) = (frame.min(), frame.max());
+    let mut out = String::new();
+    // Canvas coordinates onto the page, whose y runs up
+    let _ = match canvas.axes().y {
+        YAxis::Up => writeln!(
+            out,
+            "{scale} 0 0 {scale} {} {} cm",
+            negate(min.x * scale),
+            negate(min.y * scale)
+        ),
+        YAxis::Down => writeln!(
+            out,
+            "{scale} 0 0 {} {} {} cm",
+            -scale,
+            negate(min.x * scale),
+            max.y * scale
+        ),
+    };
+    let mut opacities: Vec<(f32, f32)> = Vec::new();
+    for DrawCommand { outline, style, .. } in canvas.draw_commands() {
+        let fill = style.fill.map(|paint| paint.color());
+        let (op, opacity) = match (fill, style.stroke) {
+            (None, None) => continue,
+            (Some(fill), None) => ("f", (fill.a, 255)),
+            (None, Some(stroke)) => ("S", (255, stroke.a)),
+            (Some(fill), Some(stroke)) => ("B", (fill.a, stroke.a)),
+        };
+        out.push_str("q\n");
+        let opacity = (
+            opacity.0 as f32 / 255.0 * style.opacity,
+            opacity.1 as f32 / 255.0 * style.opacity,
+        );
+        if opacity != (1.0, 1.0) {
+            let state = opacities
+                .iter()
+                .position(|&known| known == opacity)
+                .unwrap_or_else(|| {
+                    opacities.push(opacity);
+                    opacities.len() - 1
+                });
+            let _ = writeln!(out, "/G{state} gs");
+        }
+        if let Some(color) = fill {
+            let _ = writeln!(out, "{} rg", rgb(color));
+        }
+        if let Some(color) = style.stroke {
+            stroke_state(&mut out, color, &style);
+        }
+        path(&mut out, &outline);
+        let _ = writeln!(out, "{op}\nQ");
+    }
+    (out, opacities)
+}
+
+fn rgb(color: Color) -> String {
+    let channel = |c: u8| c as f32 / 255.0;
+    format!(
+        "{} {} {}",
+        channel(color.r),
+        channel(color.g),
+        channel(color.b)
+    )
+}
+
+fn stroke_state(out: &mut String, color: Color, style: &Style) {
+    let cap = match style.cap {
+        LineCap::Butt => 0,
+        LineCap::Round => 1,
+        LineCap::Square => 2,
+    };
+    let join = match style.join {
+        LineJoin::Miter => 0,
+        LineJoin::Round => 1,
+        LineJoin::Bevel => 2,
+    };
+    let _ = writeln!(
+        out,
+        "{} RG {} w {cap} J {join} j",
+        rgb(color),
+        style.stroke_width
+    );
+    if let Some(dash) = style.dash {
+        let _ = writeln!(out, "[{} {}] {} d", dash.on, dash.off, dash.offset);
+    }
+}
+
+fn path(out: &mut String, outline: &Outline) {
+    match outline {
+        Outline::Circle { center, radius } => {
+            let k = KAPPA * radius;
+            let point = |x: f32, y: f32| Coord::new(center.x + x, center.y + y);
+            let start = point(*radius, 0.0);
+            let _ = writeln!(out, "{} {} m", start.x, start.y);
+            // A quarter at a time, counter-clockwise from the right
+            for [(x1, y1), (x2, y2), (x3, y3)] in [
+                [(*radius, k), (k, *radius), (0.0, *radius)],
+                [(-k, *radius), (-radius, k), (-radius, 0.0)],
+                [(-radius, -k), (-k, -radius), (0.0, -radius)],
+                [(k, -radius), (*radius, -k), (*radius, 0.0)],
+            ] {
+                let (a, b, c) = (point(x1, y1), point(x2, y2), point(x3, y3));
+                let _ = writeln!(out, "{} {} {} {} {} {} c", a.x, a.y, b.x, b.y, c.x, c.y);
+            }
+        }
+        Outline::Polygon(points) => {
+            for (i, point) in points.iter().enumerate() {
+                let op = if i == 0 { "m" } else { "l" };
+                let _ = writeln!(out, "{} {} {op}", point.x, point.y);
+            }
+        }
+    }
+    out.push_str("h\n");
+}
+
+impl Canvas {
+    pub fn to_svg(&self, frame: &Rectangle) -> String {
+        svg(self, frame)
+    }
+    pub fn to_pdf(&self, frame: &Rectangle) -> Vec<u8> {
+        pdf(&[(self, frame.clone())])
+    }
+}
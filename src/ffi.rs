@@ -0,0 +1,313 @@
+// C interface for embedding the canvas in other programs, declared in
+// include/kaleidoscope.h. The canvas is an opaque handle from
+// kaleido_canvas_new, released with kaleido_canvas_free. Functions that can
+// fail return one of the KALEIDO_ codes below and write any result through
+// an out pointer, which is left alone on failure. Shape ids are u64s.
+#![allow(clippy::missing_safety_doc)]
+
+use std::{ffi::CStr, os::raw::c_char, sync::Arc};
+
+use crate::{
+    error::CanvasError, Canvas, Circle, Coord, Rectangle, Shape, ShapeCell, ShapeError, ShapeId,
+    Triangle,
+};
+
+pub const KALEIDO_OK: i32 = 0;
+pub const KALEIDO_NULL_POINTER: i32 = 1;
+pub const KALEIDO_INVALID_SHAPE: i32 = 2;
+pub const KALEIDO_UNKNOWN_SHAPE: i32 = 3;
+pub const KALEIDO_INVALID_ARGUMENT: i32 = 4;
+pub const KALEIDO_IO: i32 = 5;
+pub const KALEIDO_POISONED: i32 = 6;
+pub const KALEIDO_LOCKED: i32 = 7;
+
+fn code(error: &CanvasError) -> i32 {
+    match error {
+        CanvasError::UnknownShape(_) | CanvasError::UnknownIndex(_) => KALEIDO_UNKNOWN_SHAPE,
+        CanvasError::Poisoned => KALEIDO_POISONED,
+        CanvasError::Locked(_) => KALEIDO_LOCKED,
+        CanvasError::Shape(_) => KALEIDO_INVALID_SHAPE,
+        CanvasError::Io(_) => KALEIDO_IO,
+        CanvasError::InvalidRenderConfig { .. }
+        | CanvasError::InvalidDpi(_)
+        | CanvasError::InvalidOpacity(_)
+        | CanvasError::InvalidSymmetryOrder(_)
+        | CanvasError::EmptyImage
+        | CanvasError::SelfConstraint(_)
+        | CanvasError::UnknownName(_)
+        | CanvasError::NameTaken(_) => KALEIDO_INVALID_ARGUMENT,
+        #[cfg(feature = "lyon")]
+        CanvasError::Tessellation(_) => KALEIDO_INVALID_SHAPE,
+    }
+}
+
+fn status(result: Result<(), CanvasError>) -> i32 {
+    result.map_or_else(|e| code(&e), |()| KALEIDO_OK)
+}
+
+#[no_mangle]
+pub extern "C" fn kaleido_error_message(code: i32) -> *const c_char {
+    let message: &'static CStr = match code {
+        KALEIDO_OK => c"ok",
+        KALEIDO_NULL_POINTER => c"null pointer",
+        KALEIDO_INVALID_SHAPE => c"invalid shape",
+        KALEIDO_UNKNOWN_SHAPE => c"no such shape",
+        KALEIDO_INVALID_ARGUMENT => c"invalid argument",
+        KALEIDO_IO => c"io error",
+        KALEIDO_POISONED => c"shape lock poisoned",
+        KALEIDO_LOCKED => c"shape is locked",
+        _ => c"unknown error code",
+    };
+    message.as_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn kaleido_canvas_new() -> *mut Canvas {
+    Box::into_raw(Box::default())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn kaleido_canvas_free(canvas: *mut Canvas) {
+    if !canvas.is_null() {
+        drop(Box::from_raw(canvas));
+    }
+}
+
+unsafe fn add(
+    canvas: *mut Canvas,
+    shape: Result<impl Shape + Send + Sync + 'static, ShapeError>,
+    id: *mut u64,
+) -> i32 {
+    let (Some(canvas), false) = (canvas.as_mut(), id.is_null()) else {
+        return KALEIDO_NULL_POINTER;
+    };
+    match shape {
+        Ok(shape) => {
+            *id = canvas.add(Arc::new(ShapeCell::new(shape))).to_bits();
+            KALEIDO_OK
+        }
+        Err(_) => KALEIDO_INVALID_SHAPE,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn kaleido_canvas_add_circle(
+    canvas: *mut Canvas,
+    x: f32,
+    y: f32,
+    radius: f32,
+    id: *mut u64,
+) -> i32 {
+    let circle = Circle::builder()
+        .origin(Coord::new(x, y))
+        .radius(radius)
+        .build();
+    add(canvas, circle, id)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn kaleido_canvas_add_rectangle(
+    canvas: *mut Canvas,
+    x: f32,
+    y: f32,
+    side_a: f32,
+    side_b: f32,
+    id: *mut u64,
+) -> i32 {
+    let rectangle = Rectangle::builder()
+        .origin(Coord::new(x, y))
+        .side_a(side_a)
+        .side_b(side_b)
+        .build();
+    add(canvas, rectangle, id)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn kaleido_canvas_add_triangle(
+    canvas: *mut Canvas,
+    x: f32,
+    y: f32,
+    base: f32,
+    height: f32,
+    id: *mut u64,
+) -> i32 {
+    let triangle = Triangle::builder()
+        .origin(Coord::new(x, y))
+        .base(base)
+        .height(height)
+        .build();
+    add(canvas, triangle, id)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn kaleido_canvas_remove(canvas: *mut Canvas, id: u64) -> i32 {
+    let Some(canvas) = canvas.as_mut() else {
+        return KALEIDO_NULL_POINTER;
+    };
+    match canvas.remove(ShapeId::from_bits(id)) {
+        Some(_) => KALEIDO_OK,
+        None => KALEIDO_UNKNOWN_SHAPE,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn kaleido_canvas_set_origin(
+    canvas: *const Canvas,
+    id: u64,
+    x: f32,
+    y: f32,
+) -> i32 {
+    let Some(canvas) = canvas.as_ref() else {
+        return KALEIDO_NULL_POINTER;
+    };
+    status(canvas.set_origin(ShapeId::from_bits(id), Coord::new(x, y)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn kaleido_canvas_translate_all(
+    canvas: *mut Canvas,
+    dx: f32,
+    dy: f32,
+) -> i32 {
+    let Some(canvas) = canvas.as_mut() else {
+        return KALEIDO_NULL_POINTER;
+    };
+    status(canvas.translate_all(Coord::new(dx, dy)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn kaleido_canvas_len(canvas: *const Canvas) -> usize {
+    canvas.as_ref().map_or(0, Canvas::len)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn kaleido_canvas_area(
+    canvas: *const Canvas,
+    id: u64,
+    area: *mut f32,
+) -> i32 {
+    let (Some(canvas), false) = (canvas.as_ref(), area.is_null()) else {
+        return KALEIDO_NULL_POINTER;
+    };
+    match canvas.get_area(ShapeId::from_bits(id)) {
+        Some(value) => {
+            *area = value;
+            KALEIDO_OK
+        }
+        None => KALEIDO_UNKNOWN_SHAPE,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn kaleido_canvas_total_area(canvas: *const Canvas) -> f32 {
+    canvas.as_ref().map_or(0.0, Canvas::total_area)
+}
+
+// Renders the part of the canvas between the two corners to a PNG file
+#[cfg(feature = "render")]
+#[no_mangle]
+pub unsafe extern "C" fn kaleido_canvas_save_png(
+    canvas: *const Canvas,
+    path: *const c_char,
+    width: u32,
+    height: u32,
+    min_x: f32,
+    min_y: f32,
+    max_x: f32,
+    max_y: f32,
+) -> i32 {
+    use crate::render::{render, RenderConfig};
+
+    let (Some(canvas), false) = (canvas.as_ref(), path.is_null()) else {
+        return KALEIDO_NULL_POINTER;
+    };
+    let Ok(path) = CStr::from_ptr(path).to_str() else {
+        return KALEIDO_INVALID_ARGUMENT;
+    };
+    let config = RenderConfig {
+        width,
+        height,
+        view: Rectangle::from_corners(Coord::new(min_x, min_y), Coord::new(max_x, max_y)),
+        ..Default::default()
+    };
+    status(render(canvas, &config).and_then(|image| image.save_png(path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use super::*;
+
+    #[test]
+    fn drive_canvas_through_ffi() {
+        unsafe {
+            let canvas = kaleido_canvas_new();
+            let (mut circle, mut square, mut area) = (0, 0, 0.0);
+            assert_eq!(
+                kaleido_canvas_add_circle(canvas, 0.0, 0.0, 1.0, &mut circle),
+                KALEIDO_OK
+            );
+            assert_eq!(
+                kaleido_canvas_add_rectangle(canvas, 5.0, 0.0, 2.0, 2.0, &mut square),
+                KALEIDO_OK
+            );
+            let mut unset = 7;
+            assert_eq!(
+                kaleido_canvas_add_triangle(canvas, 0.0, 0.0, f32::NAN, 1.0, &mut unset),
+                KALEIDO_INVALID_SHAPE
+            );
+            assert_eq!(unset, 7);
+
+            assert_eq!(kaleido_canvas_area(canvas, square, &mut area), KALEIDO_OK);
+            assert_eq!(area, 4.0);
+            assert_eq!(
+                kaleido_canvas_set_origin(canvas, square, 1.0, 1.0),
+                KALEIDO_OK
+            );
+            assert_eq!(
+                kaleido_canvas_set_origin(canvas, square, f32::INFINITY, 1.0),
+                KALEIDO_INVALID_SHAPE
+            );
+            assert_eq!(kaleido_canvas_remove(canvas, square), KALEIDO_OK);
+            assert_eq!(kaleido_canvas_remove(canvas, square), KALEIDO_UNKNOWN_SHAPE);
+            assert_eq!(kaleido_canvas_len(canvas), 1);
+            assert_eq!(
+                kaleido_canvas_translate_all(ptr::null_mut(), 1.0, 1.0),
+                KALEIDO_NULL_POINTER
+            );
+            assert_eq!(
+                CStr::from_ptr(kaleido_error_message(KALEIDO_UNKNOWN_SHAPE)),
+                c"no such shape"
+            );
+            kaleido_canvas_free(canvas);
+        }
+    }
+
+    // Every export is declared in the header, and nothing else is
+    #[test]
+    fn header_matches_exports() {
+        let names = |source: &str, marker: &str| {
+            let mut names: Vec<String> = source
+                .split(marker)
+                .skip(1)
+                .map(|rest| rest.split('(').next().unwrap().trim().to_string())
+                .collect();
+            names.sort();
+            names
+        };
+        let header = include_str!("../include/kaleidoscope.h");
+        let exports = names(include_str!("ffi.rs"), "extern \"C\" fn ");
+        let declared: Vec<String> = names(header, "kaleido_")
+            .into_iter()
+            .map(|name| format!("kaleido_{name}"))
+            .collect();
+        assert_eq!(exports, declared);
+        for constant in ["OK", "NULL_POINTER", "INVALID_SHAPE", "UNKNOWN_SHAPE", "IO"] {
+            assert!(
+                header.contains(&format!("#define KALEIDO_{constant} ")),
+                "{constant}"
+            );
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::{fs, io, path::Path};
+
+use crate::{arena::ShapeId, clipboard::Clipboard, error::CanvasError, Canvas, Coord};
+
+const HEADER: &str = "# fragment\n";
+
+// Shapes picked out of a canvas to act on together, in painting order
+pub struct Selection<'a> {
+    canvas: &'a Canvas,
+    ids: Vec<ShapeId>,
+}
+
+impl Canvas {
+    // Ids that aren't on the canvas are left out
+    pub fn select(&self, ids: &[ShapeId]) -> Selection<'_> {
+        let ids = self
+            .ids()
+            .iter()
+            .copied()
+            .filter(|id| ids.contains(id))
+            .collect();
+        Selection { canvas: self, ids }
+    }
+    pub fn select_tagged(&self, tag: &str) -> Selection<'_> {
+        Selection {
+            canvas: self,
+            ids: self.find_by_tag(tag),
+        }
+    }
+
+    // Adds the fragment's shapes as new ones, with the lower corner of
+    // their bounding box at the offset, and returns their ids
+    pub fn import_fragment(
+        &mut self,
+        path: impl AsRef<Path>,
+        offset: impl Into<Coord>,
+    ) -> Result<Vec<ShapeId>, CanvasError> {
+        let path = path.as_ref();
+        let invalid =
+            |message: String| CanvasError::Io(io::Error::new(io::ErrorKind::InvalidData, message));
+        let text = fs::read_to_string(path)?;
+        let clipboard: Clipboard = text
+            .strip_prefix(HEADER)
+            .ok_or_else(|| invalid(format!("{} isn't a fragment", path.display())))?
+            .parse()
+            .map_err(|error| invalid(format!("{}: {error}", path.display())))?;
+        self.paste(&clipboard, offset)
+    }
+}
+
+impl Selection<'_> {
+    pub fn ids(&self) -> &[ShapeId] {
+        &self.ids
+    }
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    // Writes the shapes to the file with their styles, metadata and tags,
+    // as a fragment for import_fragment to add to any canvas. It's the
+    // clipboard's text under a header line, with the shapes placed from
+    // the lower corner of their bounding box and their history left out,
+    // so it reads the same whoever wrote it and wherever from.
+    pub fn export(&self, path: impl AsRef<Path>) -> Result<(), CanvasError> {
+        let mut clipboard = self.canvas.copy(&self.ids);
+        if let Some(bounds) = self.canvas.bounding_box_of(&self.ids) {
+            clipboard.translate(Coord::default() - bounds.min())?;
+        }
+        clipboard.forget_history();
+        fs::write(path, format!("{HEADER}{clipboard}"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, sync::Arc};
+
+    use super::*;
+    use crate::{
+        style::{Color, Paint},
+        Circle, Rectangle, ShapeCell, Style,
+    };
+
+    #[test]
+    fn fragments_round_trip() {
+        let mut canvas = Canvas::default();
+        let circle = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(11.0, 21.0),
+            radius: 1.0,
+        })));
+        let square = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::new(14.0, 21.0),
+            side_a: 2.0,
+            side_b: 2.0,
+        })));
+        canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(50.0, 50.0),
+            radius: 3.0,
+        })));
+        let red = Style {
+            fill: Some(Paint::Solid(Color::rgb(255, 0, 0))),
+            ..Style::default()
+        };
+        canvas.set_style(square, red).unwrap();
+        canvas.set_meta(square, "motif", "petal").unwrap();
+        canvas.tag(circle, "motif").unwrap();
+        canvas.tag(square, "motif").unwrap();
+
+        let selection = canvas.select_tagged("motif");
+        assert_eq!(selection.ids(), [circle, square]);
+        let path = env::temp_dir().join(format!("kaleidoscope-{}.fragment", std::process::id()));
+        selection.export(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("# fragment\nshape circle 1 at 1 1\ntag motif\n"));
+        assert!(!text.contains("history"));
+
+        let mut other = Canvas::default();
+        let ids = other.import_fragment(&path, (100.0, 0.0)).unwrap();
+        let origin = |id| other.get(id).unwrap().lock().unwrap().origin();
+        assert_eq!(origin(ids[0]), Coord::new(101.0, 1.0));
+        assert_eq!(origin(ids[1]), Coord::new(104.0, 1.0));
+        assert_eq!(other.style(ids[1]), Some(red));
+        assert_eq!(other.meta(ids[1], "motif"), Some("petal"));
+        assert_eq!(other.find_by_tag("motif"), ids);
+
+        fs::write(&path, "shape circle 1 at 0 0\n").unwrap();
+        assert!(matches!(
+            other.import_fragment(&path, (0.0, 0.0)),
+            Err(CanvasError::Io(_))
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,211 @@
+// Fuzz targets. Each takes arbitrary bytes and must never panic; the canvas
+// target also checks the canvas is consistent after every operation. The
+// cargo-fuzz crate in fuzz/ runs them under libFuzzer, with
+// cargo +nightly fuzz run script
+// and the tests here give them a brief run over random bytes and byte
+// level mutations of valid scripts, from the seeded generator.
+#[cfg(test)]
+use std::panic;
+
+#[cfg(test)]
+use crate::generator::Rng;
+use crate::{
+    command::Session,
+    storage::ShapeEnum,
+    symmetry::{Symmetry, SymmetryGroup},
+    Canvas, Circle, Coord, Polygon, Rectangle, ShapeId, Triangle,
+};
+
+// Valid and nearly valid scripts for mutation to start from
+#[cfg(test)]
+const CORPUS: [&str; 4] = [
+    "add circle 5 at 0 0\nadd rectangle 2 4 at 1 1\nmove 2 to 5 5\narea 2\nlist\nhit 5 5",
+    "group at 10 0\nsymmetry dihedral 3 at 1 1\nadd triangle 3 4 at 0 0\nadd circle 1 at 2 2 in 1\nmove group 1 to 0 0\nremove 1",
+    "add polygon 0 0 4 0 0 3 at 5 -5\nskip 2\nadd circle 1 at 0 0\nremove 4\nsymmetry off\narea",
+    "symmetry cyclic 6\nadd rectangle 1 1 at 4 0\nmove 1 to 0 4\nhit 0 4\n# done",
+];
+
+// Runs the bytes, as text, as a script and line by line. Whatever was
+// built must survive being written out and read back.
+pub fn script(data: &[u8]) {
+    let text = String::from_utf8_lossy(data);
+    let _ = Session::new().run(&text);
+    let mut session = Session::new();
+    for line in text.lines() {
+        let _ = session.execute(line);
+    }
+    let script = session.script().expect("scripts write out");
+    let mut copy = Session::new();
+    copy.run(&script)
+        .unwrap_or_else(|error| panic!("{error} reading back\n{script}"));
+    assert_eq!(copy.canvas.len(), session.canvas.len(), "{script}");
+}
+
+// Arguments taken from the input, zeros once it runs out
+struct Bytes<'a>(std::slice::Iter<'a, u8>);
+
+impl Bytes<'_> {
+    fn byte(&mut self) -> u8 {
+        self.0.next().copied().unwrap_or(0)
+    }
+    fn index(&mut self, len: usize) -> usize {
+        self.byte() as usize % len
+    }
+    // Mostly small, sometimes zero or negative
+    fn number(&mut self) -> f32 {
+        (self.byte() as i8) as f32 / 4.0
+    }
+    fn point(&mut self) -> Coord {
+        Coord::new(self.number(), self.number())
+    }
+}
+
+// Reads bytes as canvas operations, each a byte picking the operation and
+// some more for its arguments, checking the canvas after each
+pub fn canvas_ops(data: &[u8]) {
+    let mut bytes = Bytes(data.iter());
+    let mut canvas = Canvas::default();
+    let mut ids: Vec<ShapeId> = Vec::new();
+    while !bytes.0.as_slice().is_empty() {
+        let op = bytes.byte();
+        let origin = bytes.point();
+        match op % 8 {
+            0 => {
+                let size = (bytes.number(), bytes.number());
+                let shape: Result<ShapeEnum, _> = match bytes.byte() % 4 {
+                    0 => Circle::builder()
+                        .radius(size.0)
+                        .origin(origin)
+                        .build()
+                        .map(ShapeEnum::from),
+                    1 => Rectangle::builder()
+                        .side_a(size.0)
+                        .side_b(size.1)
+                        .origin(origin)
+                        .build()
+                        .map(ShapeEnum::from),
+                    2 => Triangle::builder()
+                        .base(size.0)
+                        .height(size.1)
+                        .origin(origin)
+                        .build()
+                        .map(ShapeEnum::from),
+                    _ => Polygon::builder()
+                        .points([
+                            Coord::default(),
+                            Coord::new(size.0, 0.0),
+                            Coord::new(0.0, size.1),
+                        ])
+                        .origin(origin)
+                        .build()
+                        .map(ShapeEnum::from),
+                };
+                if let Ok(shape) = shape {
+                    ids.push(canvas.add(shape.into_object()));
+                }
+            }
+            // Ids may be stale, which must be harmless
+            1 if !ids.is_empty() => {
+                canvas.remove(ids[bytes.index(ids.len())]);
+            }
+            2 if !ids.is_empty() => {
+                let _ = canvas.set_origin(ids[bytes.index(ids.len())], origin);
+            }
+            3 => {
+                let _ = canvas.translate_all(origin);
+            }
+            4 => {
+                let _ = canvas.rotate_all(bytes.number(), origin);
+            }
+            5 => {
+                let n = bytes.index(8) as u32;
+                let group = match bytes.byte() % 3 {
+                    0 => None,
+                    1 => Some(SymmetryGroup::Cyclic(n)),
+                    _ => Some(SymmetryGroup::Dihedral(n)),
+                };
+                let _ = canvas.set_symmetry(group.map(|group| Symmetry {
+                    group,
+                    center: origin,
+                }));
+            }
+            6 => {
+                let hits = canvas.hit_test(origin);
+                assert!(hits.iter().all(|id| canvas.get(*id).is_some()));
+            }
+            _ => {
+                for (a, b) in canvas.collisions() {
+                    assert!(canvas.get(a).is_some() && canvas.get(b).is_some());
+                }
+            }
+        }
+        check(&canvas);
+    }
+}
+
+fn check(canvas: &Canvas) {
+    assert_eq!(canvas.len(), canvas.ids().len());
+    let mut ids = canvas.ids().to_vec();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), canvas.len(), "ids repeated");
+    for &id in canvas.ids() {
+        let shape = canvas.get(id).expect("listed ids resolve").lock().unwrap();
+        shape.validate().expect("shapes stay valid");
+    }
+    let area = canvas.total_area();
+    assert!(area.is_finite() && area >= 0.0, "total area {area}");
+}
+
+// Random bytes, or a corpus script with a few bytes changed, removed or
+// repeated
+#[cfg(test)]
+fn input(rng: &mut Rng) -> Vec<u8> {
+    if rng.below(4) == 0 {
+        return (0..rng.below(256)).map(|_| rng.next_u64() as u8).collect();
+    }
+    let mut data = CORPUS[rng.below(CORPUS.len())].as_bytes().to_vec();
+    for _ in 0..1 + rng.below(8) {
+        let at = rng.below(data.len().max(1));
+        match rng.below(3) {
+            0 if at < data.len() => {
+                data[at] = b" \n-0123456789.abcdefghijklmnopqrstuvwxyz"[rng.below(39)]
+            }
+            1 if at < data.len() => {
+                data.remove(at);
+            }
+            _ => {
+                let end = (at + rng.below(16)).min(data.len());
+                let repeat = data[at..end].to_vec();
+                data.splice(at..at, repeat);
+            }
+        }
+    }
+    data
+}
+
+#[cfg(test)]
+fn fuzz(cases: u64) {
+    for seed in 0..cases {
+        let data = input(&mut Rng::new(seed));
+        for (name, target) in [("script", script as fn(&[u8])), ("canvas_ops", canvas_ops)] {
+            if panic::catch_unwind(|| target(&data)).is_err() {
+                panic!(
+                    "{name} panicked for seed {seed} on {:?}",
+                    String::from_utf8_lossy(&data)
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn fuzz_briefly() {
+    fuzz(300);
+}
+
+#[test]
+#[ignore]
+fn fuzz_at_length() {
+    fuzz(100_000);
+}
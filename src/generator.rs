@@ -0,0 +1,458 @@
+use std::{f32::consts::PI, ops::Range};
+
+use crate::{
+    builder::{coordinate, dimension},
+    error::ShapeError,
+    geometry::Outline,
+    storage::{ShapeEnum, ShapeKind},
+    Canvas, Circle, Coord, Ellipse, Polygon, Rectangle, Shape, ShapeId, Square, Triangle,
+};
+
+// SplitMix64, small and fast with good enough statistics for scene
+// generation. The same seed always produces the same sequence.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+    // Uniform in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+    pub fn range(&mut self, range: Range<f32>) -> f32 {
+        range.start + (range.end - range.start) * self.next_f32()
+    }
+    // Uniform in 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+    // Standard normal, by the Box-Muller transform
+    pub fn normal(&mut self) -> f32 {
+        let u = 1.0 - self.next_f32();
+        let v = self.next_f32();
+        (-2.0 * u.ln()).sqrt() * (2.0 * PI * v).cos()
+    }
+    pub fn point_in(&mut self, bounds: &Rectangle) -> Coord {
+        let (min, max) = (bounds.min(), bounds.max());
+        Coord::new(self.range(min.x..max.x), self.range(min.y..max.y))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Placement {
+    Uniform {
+        bounds: Rectangle,
+    },
+    // Normally distributed around cluster centres placed uniformly in bounds
+    Clustered {
+        bounds: Rectangle,
+        clusters: usize,
+        spread: f32,
+    },
+    // Uniform in angle, so denser towards the centre
+    Radial {
+        center: Coord,
+        radius: f32,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeneratorConfig {
+    pub count: usize,
+    // Relative likelihood of each kind being picked
+    pub weights: Vec<(ShapeKind, f32)>,
+    // Range for each dimension: radius, sides, base and height
+    pub size: Range<f32>,
+    pub placement: Placement,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> GeneratorConfig {
+        GeneratorConfig {
+            count: 100,
+            weights: vec![
+                (ShapeKind::Circle, 1.0),
+                (ShapeKind::Rectangle, 1.0),
+                (ShapeKind::Triangle, 1.0),
+            ],
+            size: 1.0..5.0,
+            placement: Placement::Uniform {
+                bounds: Rectangle {
+                    origin: Coord::default(),
+                    side_a: 100.0,
+                    side_b: 100.0,
+                },
+            },
+        }
+    }
+}
+
+pub fn random_canvas(seed: u64, config: &GeneratorConfig) -> Canvas {
+    let mut canvas = Canvas::default();
+    for shape in random_shapes(seed, config) {
+        canvas.add(shape.into_object());
+    }
+    canvas
+}
+
+// The shapes random_canvas would add, by value
+pub fn random_shapes(seed: u64, config: &GeneratorConfig) -> Vec<ShapeEnum> {
+    let mut rng = Rng::new(seed);
+    let cluster_centers: Vec<Coord> = match &config.placement {
+        Placement::Clustered {
+            bounds, clusters, ..
+        } => (0..(*clusters).max(1))
+            .map(|_| rng.point_in(bounds))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut shapes = Vec::with_capacity(config.count);
+    for _ in 0..config.count {
+        let origin = match &config.placement {
+            Placement::Uniform { bounds } => rng.point_in(bounds),
+            Placement::Clustered { spread, .. } => {
+                let center = cluster_centers[rng.below(cluster_centers.len())];
+                center + Coord::new(rng.normal(), rng.normal()) * *spread
+            }
+            Placement::Radial { center, radius } => {
+                let angle = rng.range(0.0..2.0 * PI);
+                *center + Coord::new(rng.range(0.0..*radius), 0.0).rotate(angle)
+            }
+        };
+        let Some(kind) = pick_kind(&mut rng, &config.weights) else {
+            break;
+        };
+        shapes.push(random_shape(&mut rng, kind, origin, config.size.clone()));
+    }
+    shapes
+}
+
+fn pick_kind(rng: &mut Rng, weights: &[(ShapeKind, f32)]) -> Option<ShapeKind> {
+    let total: f32 = weights.iter().map(|(_, w)| w.max(0.0)).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mut choice = rng.range(0.0..total);
+    for (kind, weight) in weights {
+        choice -= weight.max(0.0);
+        if choice < 0.0 {
+            return Some(*kind);
+        }
+    }
+    weights
+        .iter()
+        .rev()
+        .find(|(_, w)| *w > 0.0)
+        .map(|(k, _)| *k)
+}
+
+pub fn random_shape(rng: &mut Rng, kind: ShapeKind, origin: Coord, size: Range<f32>) -> ShapeEnum {
+    match kind {
+        ShapeKind::Circle => ShapeEnum::Circle(Circle {
+            origin,
+            radius: rng.range(size),
+        }),
+        ShapeKind::Rectangle => ShapeEnum::Rectangle(Rectangle {
+            origin,
+            side_a: rng.range(size.clone()),
+            side_b: rng.range(size),
+        }),
+        ShapeKind::Triangle => ShapeEnum::Triangle(Triangle {
+            origin,
+            base: rng.range(size.clone()),
+            height: rng.range(size),
+        }),
+        ShapeKind::Square => ShapeEnum::Square(Square {
+            origin,
+            side: rng.range(size),
+        }),
+        ShapeKind::Ellipse => ShapeEnum::Ellipse(Ellipse {
+            origin,
+            radius_x: rng.range(size.clone()),
+            radius_y: rng.range(size),
+        }),
+        // Star shaped, so always simple: vertices in angle order, each at a
+        // random distance from the origin
+        ShapeKind::Polygon => {
+            let sides = 3 + rng.below(6);
+            let points = (0..sides)
+                .map(|i| {
+                    let angle = 2.0 * PI * (i as f32 + rng.range(0.0..0.8)) / sides as f32;
+                    Coord::new(rng.range(size.clone()), 0.0).rotate(angle)
+                })
+                .collect();
+            ShapeEnum::Polygon(Polygon { origin, points })
+        }
+    }
+}
+
+// Where place_random may put shapes. Spacing is the least gap left between
+// a new shape and any other, so a positive spacing rules out overlaps
+// whether or not they're asked for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Constraints {
+    // Each shape lies wholly inside
+    pub bounds: Rectangle,
+    pub no_overlaps: bool,
+    pub spacing: f32,
+    pub weights: Vec<(ShapeKind, f32)>,
+    pub size: Range<f32>,
+    // Positions tried for each shape before giving up on it
+    pub attempts: usize,
+}
+
+impl Default for Constraints {
+    fn default() -> Constraints {
+        let shapes = GeneratorConfig::default();
+        Constraints {
+            bounds: Rectangle {
+                origin: Coord::default(),
+                side_a: 100.0,
+                side_b: 100.0,
+            },
+            no_overlaps: true,
+            spacing: 0.0,
+            weights: shapes.weights,
+            size: shapes.size,
+            attempts: 100,
+        }
+    }
+}
+
+// Adds up to n random shapes where the constraints allow, by trying random
+// positions and keeping the first that fits, checked against nearby shapes
+// only through the canvas's spatial index when it has one. Shapes already on
+// the canvas are kept clear of but needn't meet the constraints themselves.
+// On a symmetric canvas the copies have to fit too. Fewer than n come back
+// when the space runs out.
+pub fn place_random(
+    canvas: &mut Canvas,
+    n: usize,
+    constraints: &Constraints,
+    seed: u64,
+) -> Result<Vec<ShapeId>, ShapeError> {
+    let bounds = &constraints.bounds;
+    coordinate("bounds origin", bounds.origin)?;
+    dimension("bounds side", Some(bounds.side_a.min(bounds.side_b)))?;
+    if !(constraints.spacing >= 0.0 && constraints.spacing.is_finite()) {
+        return Err(ShapeError::InvalidDimension {
+            name: "spacing",
+            value: constraints.spacing,
+        });
+    }
+    dimension("size", Some(constraints.size.start))?;
+    dimension("size", Some(constraints.size.end))?;
+
+    let mut rng = Rng::new(seed);
+    let mut placed = Vec::new();
+    for _ in 0..n {
+        for _ in 0..constraints.attempts {
+            let Some(kind) = pick_kind(&mut rng, &constraints.weights) else {
+                return Ok(placed);
+            };
+            let origin = rng.point_in(bounds);
+            let shape = random_shape(&mut rng, kind, origin, constraints.size.clone());
+            if !fits(canvas, &shape.outline(), None, constraints) {
+                continue;
+            }
+            let id = canvas.add(shape.into_object());
+            let copies = canvas.copies_of(id);
+            let copies_fit = copies.len() == 1
+                || copies.iter().all(|&copy| {
+                    canvas
+                        .outline(copy)
+                        .is_some_and(|outline| fits(canvas, &outline, Some(copy), constraints))
+                });
+            if copies_fit {
+                placed.push(id);
+                break;
+            }
+            canvas.remove(id);
+        }
+    }
+    Ok(placed)
+}
+
+fn fits(
+    canvas: &Canvas,
+    outline: &Outline,
+    id: Option<ShapeId>,
+    constraints: &Constraints,
+) -> bool {
+    let (bounds, spacing) = (&constraints.bounds, constraints.spacing);
+    let extent = outline.bounding_box();
+    let (min, max) = (extent.min(), extent.max());
+    if min.x < bounds.min().x
+        || min.y < bounds.min().y
+        || max.x > bounds.max().x
+        || max.y > bounds.max().y
+    {
+        return false;
+    }
+    if !constraints.no_overlaps && spacing == 0.0 {
+        return true;
+    }
+    let reach = Rectangle {
+        side_a: extent.side_a + 2.0 * spacing,
+        side_b: extent.side_b + 2.0 * spacing,
+        ..extent
+    };
+    canvas
+        .candidates(&reach)
+        .into_iter()
+        .filter(|&other| Some(other) != id)
+        // Nothing goes near a shape whose lock is poisoned
+        .all(|other| {
+            canvas.outline(other).is_some_and(|other| {
+                let gap = other.distance(outline);
+                gap > 0.0 && gap >= spacing
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origins(canvas: &Canvas) -> Vec<Coord> {
+        canvas
+            .shapes
+            .values()
+            .iter()
+            .map(|shape| shape.lock().unwrap().origin())
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_canvas() {
+        let config = GeneratorConfig {
+            placement: Placement::Clustered {
+                bounds: Rectangle {
+                    origin: Coord::default(),
+                    side_a: 50.0,
+                    side_b: 50.0,
+                },
+                clusters: 3,
+                spread: 2.0,
+            },
+            ..Default::default()
+        };
+        let a = random_canvas(7, &config);
+        let b = random_canvas(7, &config);
+        let c = random_canvas(8, &config);
+
+        assert_eq!(a.len(), 100);
+        assert_eq!(origins(&a), origins(&b));
+        assert_ne!(origins(&a), origins(&c));
+    }
+
+    #[test]
+    fn respects_config() {
+        let config = GeneratorConfig {
+            count: 50,
+            weights: vec![(ShapeKind::Circle, 1.0), (ShapeKind::Triangle, 0.0)],
+            size: 2.0..3.0,
+            placement: Placement::Radial {
+                center: Coord::new(10.0, 10.0),
+                radius: 5.0,
+            },
+        };
+        let canvas = random_canvas(1, &config);
+
+        for shape in canvas.shapes.values() {
+            let shape = shape.lock().unwrap();
+            assert!(shape.origin().distance(Coord::new(10.0, 10.0)) <= 5.0);
+            // Only circles, with radius in range
+            let area = shape.get_area();
+            assert!((4.0 * PI..9.0 * PI).contains(&area));
+        }
+    }
+
+    #[test]
+    fn placement_keeps_constraints() {
+        let constraints = Constraints {
+            bounds: Rectangle {
+                origin: Coord::default(),
+                side_a: 60.0,
+                side_b: 60.0,
+            },
+            spacing: 1.0,
+            ..Default::default()
+        };
+        let mut canvas = Canvas::default();
+        let middle = canvas.add(
+            ShapeEnum::Circle(Circle {
+                origin: Coord::default(),
+                radius: 10.0,
+            })
+            .into_object(),
+        );
+        let placed = place_random(&mut canvas, 60, &constraints, 3).unwrap();
+        assert_eq!(placed.len(), 60);
+        let outlines = canvas.outlines(canvas.ids());
+        for (i, outline) in outlines.iter().enumerate() {
+            let extent = outline.bounding_box();
+            assert!(extent.min().x >= -30.0 && extent.max().y <= 30.0);
+            for other in &outlines[i + 1..] {
+                assert!(outline.distance(other) >= 1.0);
+            }
+        }
+        assert!(placed.iter().all(|&id| id != middle));
+
+        // The spatial index finds the same neighbours, so the same places
+        #[cfg(feature = "spatial")]
+        {
+            let mut indexed = Canvas::default();
+            indexed.set_spatial_index(Some(crate::spatial::IndexKind::SpatialHash {
+                cell_size: 8.0,
+            }));
+            indexed.add(canvas.get(middle).unwrap().clone());
+            place_random(&mut indexed, 60, &constraints, 3).unwrap();
+            assert_eq!(origins(&indexed), origins(&canvas));
+        }
+
+        // Copies on a symmetric canvas fit as well
+        let mut symmetric = Canvas::default();
+        symmetric
+            .set_symmetry(Some(crate::symmetry::Symmetry {
+                group: crate::symmetry::SymmetryGroup::Cyclic(4),
+                center: Coord::default(),
+            }))
+            .unwrap();
+        place_random(&mut symmetric, 10, &constraints, 5).unwrap();
+        let outlines = symmetric.outlines(symmetric.ids());
+        assert_eq!(outlines.len(), 40);
+        for (i, outline) in outlines.iter().enumerate() {
+            assert!(outlines[i + 1..]
+                .iter()
+                .all(|other| outline.distance(other) >= 1.0));
+        }
+
+        // Running out of room places fewer
+        let cramped = Constraints {
+            size: 20.0..25.0,
+            attempts: 20,
+            ..constraints.clone()
+        };
+        let few = place_random(&mut Canvas::default(), 10, &cramped, 1).unwrap();
+        assert!(few.len() < 10);
+        let bad = Constraints {
+            spacing: -1.0,
+            ..constraints
+        };
+        assert!(place_random(&mut canvas, 1, &bad, 1).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+// Conversions to and from the geo crate's types, in f32 like the rest of
+// the crate. Shapes go to geo as polygons through their outlines, circles
+// and ellipses as the many sided polygons drawn for them. A geo polygon
+// comes back with the origin at 0 0 and its points where geo has them,
+// unclosed, and without any holes.
+use geo::{Coord as GeoCoord, LineString, Point, Polygon as GeoPolygon, Rect};
+
+use crate::{
+    geometry::outline_points, storage::ShapeEnum, Circle, Coord, Ellipse, Polygon, Rectangle,
+    Shape, Square, Triangle,
+};
+
+impl From<Coord> for GeoCoord<f32> {
+    fn from(Coord { x, y }: Coord) -> GeoCoord<f32> {
+        GeoCoord { x, y }
+    }
+}
+impl From<GeoCoord<f32>> for Coord {
+    fn from(GeoCoord { x, y }: GeoCoord<f32>) -> Coord {
+        Coord::new(x, y)
+    }
+}
+impl From<Coord> for Point<f32> {
+    fn from(coord: Coord) -> Point<f32> {
+        Point(coord.into())
+    }
+}
+impl From<Point<f32>> for Coord {
+    fn from(point: Point<f32>) -> Coord {
+        point.0.into()
+    }
+}
+
+impl From<Rectangle> for Rect<f32> {
+    fn from(rectangle: Rectangle) -> Rect<f32> {
+        Rect::new(rectangle.min(), rectangle.max())
+    }
+}
+impl From<Rect<f32>> for Rectangle {
+    fn from(rect: Rect<f32>) -> Rectangle {
+        Rectangle::from_corners(rect.min().into(), rect.max().into())
+    }
+}
+
+fn ring(shape: &dyn Shape) -> GeoPolygon<f32> {
+    let points = outline_points(shape.outline());
+    GeoPolygon::new(points.into_iter().map(GeoCoord::from).collect(), Vec::new())
+}
+
+impl From<Circle> for GeoPolygon<f32> {
+    fn from(circle: Circle) -> GeoPolygon<f32> {
+        ring(&circle)
+    }
+}
+impl From<Rectangle> for GeoPolygon<f32> {
+    fn from(rectangle: Rectangle) -> GeoPolygon<f32> {
+        ring(&rectangle)
+    }
+}
+impl From<Triangle> for GeoPolygon<f32> {
+    fn from(triangle: Triangle) -> GeoPolygon<f32> {
+        ring(&triangle)
+    }
+}
+impl From<Square> for GeoPolygon<f32> {
+    fn from(square: Square) -> GeoPolygon<f32> {
+        ring(&square)
+    }
+}
+impl From<Ellipse> for GeoPolygon<f32> {
+    fn from(ellipse: Ellipse) -> GeoPolygon<f32> {
+        ring(&ellipse)
+    }
+}
+impl From<Polygon> for GeoPolygon<f32> {
+    fn from(polygon: Polygon) -> GeoPolygon<f32> {
+        ring(&polygon)
+    }
+}
+impl From<ShapeEnum> for GeoPolygon<f32> {
+    fn from(shape: ShapeEnum) -> GeoPolygon<f32> {
+        ring(&shape)
+    }
+}
+
+impl From<GeoPolygon<f32>> for Polygon {
+    fn from(polygon: GeoPolygon<f32>) -> Polygon {
+        let (exterior, _) = polygon.into_inner();
+        let mut points: Vec<Coord> = exterior.0.into_iter().map(Coord::from).collect();
+        if points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        Polygon {
+            origin: Coord::default(),
+            points,
+        }
+    }
+}
+impl From<LineString<f32>> for Polygon {
+    fn from(ring: LineString<f32>) -> Polygon {
+        GeoPolygon::new(ring, Vec::new()).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geo::{Area, BooleanOps, Contains};
+
+    use super::*;
+    use crate::assert_approx_eq;
+
+    #[test]
+    fn round_trips_and_geo_algorithms() {
+        let point = Coord::new(1.5, -2.0);
+        assert_eq!(Coord::from(Point::from(point)), point);
+        let rectangle = Rectangle::from_corners(Coord::new(1.0, 0.0), Coord::new(4.0, 2.0));
+        assert_eq!(Rectangle::from(Rect::from(rectangle.clone())), rectangle);
+
+        let square = GeoPolygon::from(Square {
+            origin: Coord::new(1.0, 1.0),
+            side: 2.0,
+        });
+        assert_approx_eq!(square.unsigned_area(), 4.0);
+        assert!(square.contains(&Point::new(1.5, 1.5)));
+        let polygon = Polygon::from(square.clone());
+        assert_eq!(polygon.points.len(), 4);
+        assert_approx_eq!(polygon.get_area(), 4.0);
+
+        // geo's boolean ops, on shapes from here, and the result back
+        let overlap = square.intersection(&GeoPolygon::from(rectangle));
+        let [part] = &overlap.0[..] else {
+            panic!("one piece expected: {overlap:?}");
+        };
+        assert_approx_eq!(Polygon::from(part.clone()).get_area(), 2.0);
+        let circle = GeoPolygon::from(Circle {
+            origin: Coord::default(),
+            radius: 1.0,
+        });
+        assert_approx_eq!(circle.unsigned_area(), std::f32::consts::PI, 0.05);
+    }
+}
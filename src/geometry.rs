@@ -0,0 +1,864 @@
+use alloc::{vec, vec::Vec};
+use core::f32::consts::PI;
+
+#[cfg(not(any(feature = "std", test)))]
+use crate::math::Float;
+use crate::{Coord, Polygon, Rectangle, SecondMoments, Shape};
+
+// Angular resolution used when curves are approximated by straight segments
+pub const ARC_STEP: f32 = PI / 32.0;
+
+// The exact boundary of a shape, used for distance and intersection queries
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outline {
+    Circle { center: Coord, radius: f32 },
+    // Vertices in counter-clockwise order
+    Polygon(Vec<Coord>),
+}
+
+impl Outline {
+    pub fn bounding_box(&self) -> Rectangle {
+        match self {
+            Outline::Circle { center, radius } => Rectangle {
+                origin: *center,
+                side_a: 2.0 * radius,
+                side_b: 2.0 * radius,
+            },
+            Outline::Polygon(points) => {
+                let mut min = Coord::new(f32::INFINITY, f32::INFINITY);
+                let mut max = Coord::new(f32::NEG_INFINITY, f32::NEG_INFINITY);
+                for point in points {
+                    min = Coord::new(min.x.min(point.x), min.y.min(point.y));
+                    max = Coord::new(max.x.max(point.x), max.y.max(point.y));
+                }
+                Rectangle::from_corners(min, max)
+            }
+        }
+    }
+
+    // Of the area, by the shoelace formula for polygons
+    pub fn centroid(&self) -> Coord {
+        let Outline::Polygon(points) = self else {
+            return self.bounding_box().origin;
+        };
+        let (mut sum, mut twice_area) = (Coord::default(), 0.0);
+        for (a, b) in edges(points) {
+            let cross = a.cross(b);
+            sum = sum + (a + b) * cross;
+            twice_area += cross;
+        }
+        if twice_area == 0.0 {
+            return self.bounding_box().origin;
+        }
+        sum * (1.0 / (3.0 * twice_area))
+    }
+
+    // About axes through the centroid, summed edge by edge like the
+    // shoelace formula, from the centroid so far off shapes keep precision
+    pub fn second_moments(&self) -> SecondMoments {
+        let points = match self {
+            Outline::Circle { radius, .. } => {
+                let i = PI * radius * radius * radius * radius / 4.0;
+                return SecondMoments {
+                    ix: i,
+                    iy: i,
+                    ixy: 0.0,
+                };
+            }
+            Outline::Polygon(points) => points,
+        };
+        let centroid = self.centroid();
+        let points: Vec<Coord> = points.iter().map(|&p| p - centroid).collect();
+        let mut moments = SecondMoments::default();
+        for (a, b) in edges(&points) {
+            let cross = a.cross(b);
+            moments.ix += cross * (a.y * a.y + a.y * b.y + b.y * b.y);
+            moments.iy += cross * (a.x * a.x + a.x * b.x + b.x * b.x);
+            moments.ixy += cross * (a.x * b.y + 2.0 * (a.x * a.y + b.x * b.y) + b.x * a.y);
+        }
+        // Clockwise outlines sum to negatives
+        let sign = if signed_area(&points) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+        SecondMoments {
+            ix: sign * moments.ix / 12.0,
+            iy: sign * moments.iy / 12.0,
+            ixy: sign * moments.ixy / 24.0,
+        }
+    }
+
+    pub fn perimeter(&self) -> f32 {
+        match self {
+            Outline::Circle { radius, .. } => 2.0 * PI * radius,
+            Outline::Polygon(points) => edges(points).map(|(a, b)| a.distance(b)).sum(),
+        }
+    }
+
+    pub fn contains(&self, point: Coord) -> bool {
+        match self {
+            Outline::Circle { center, radius } => center.distance(point) <= *radius,
+            Outline::Polygon(points) => point_in_polygon(point, points),
+        }
+    }
+
+    // Zero when the point is inside
+    pub fn distance_to(&self, point: Coord) -> f32 {
+        match self {
+            Outline::Circle { center, radius } => (center.distance(point) - radius).max(0.0),
+            Outline::Polygon(points) => {
+                if point_in_polygon(point, points) {
+                    return 0.0;
+                }
+                edges(points)
+                    .map(|(a, b)| point_segment_distance(point, a, b))
+                    .fold(f32::INFINITY, f32::min)
+            }
+        }
+    }
+
+    // Distance to the boundary itself, from inside or out
+    pub fn boundary_distance(&self, point: Coord) -> f32 {
+        match self {
+            Outline::Circle { center, radius } => (center.distance(point) - radius).abs(),
+            Outline::Polygon(points) => edges(points)
+                .map(|(a, b)| point_segment_distance(point, a, b))
+                .fold(f32::INFINITY, f32::min),
+        }
+    }
+
+    // Zero when the outlines overlap
+    pub fn distance(&self, other: &Outline) -> f32 {
+        match (self, other) {
+            (
+                Outline::Circle {
+                    center: a,
+                    radius: ra,
+                },
+                Outline::Circle {
+                    center: b,
+                    radius: rb,
+                },
+            ) => (a.distance(*b) - ra - rb).max(0.0),
+            (Outline::Circle { center, radius }, polygon)
+            | (polygon, Outline::Circle { center, radius }) => {
+                (polygon.distance_to(*center) - radius).max(0.0)
+            }
+            (Outline::Polygon(a), Outline::Polygon(b)) => polygon_distance(a, b),
+        }
+    }
+
+    // Sorted parameters t in [0, max_t] at which start + t * direction crosses
+    // the boundary
+    pub fn crossings(&self, start: Coord, direction: Coord, max_t: f32) -> Vec<f32> {
+        let mut ts = match self {
+            Outline::Circle { center, radius } => {
+                let offset = start - *center;
+                let a = direction.dot(direction);
+                let b = 2.0 * direction.dot(offset);
+                let c = offset.dot(offset) - radius * radius;
+                let discriminant = b * b - 4.0 * a * c;
+                if a == 0.0 || discriminant < 0.0 {
+                    Vec::new()
+                } else if discriminant == 0.0 {
+                    vec![-b / (2.0 * a)]
+                } else {
+                    let root = discriminant.sqrt();
+                    vec![(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+                }
+            }
+            Outline::Polygon(points) => edges(points)
+                .filter_map(|(e0, e1)| {
+                    let edge = e1 - e0;
+                    let denominator = direction.cross(edge);
+                    if denominator == 0.0 {
+                        return None;
+                    }
+                    let t = (e0 - start).cross(edge) / denominator;
+                    let u = (e0 - start).cross(direction) / denominator;
+                    // Half open so a crossing through a vertex is counted once
+                    (0.0..1.0).contains(&u).then_some(t)
+                })
+                .collect(),
+        };
+        ts.retain(|t| (0.0..=max_t).contains(t));
+        ts.sort_by(f32::total_cmp);
+        ts
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Triangle3 {
+    pub a: Coord,
+    pub b: Coord,
+    pub c: Coord,
+}
+
+impl Triangle3 {
+    pub fn area(&self) -> f32 {
+        0.5 * (self.b - self.a).cross(self.c - self.a).abs()
+    }
+
+    // Inclusive of the edges
+    pub fn contains(&self, point: Coord) -> bool {
+        let d1 = (self.b - self.a).cross(point - self.a);
+        let d2 = (self.c - self.b).cross(point - self.b);
+        let d3 = (self.a - self.c).cross(point - self.c);
+        let has_negative = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+        let has_positive = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+        !(has_negative && has_positive)
+    }
+}
+
+// Ear clipping triangulation of a simple (possibly concave) polygon
+pub fn triangulate(polygon: &Polygon) -> Vec<Triangle3> {
+    triangulate_points(&polygon.vertices())
+}
+
+// Expects counter-clockwise vertices
+pub fn triangulate_points(points: &[Coord]) -> Vec<Triangle3> {
+    let mut remaining = points.to_vec();
+    let mut triangles = Vec::with_capacity(points.len().saturating_sub(2));
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let corner = |i: usize| Triangle3 {
+            a: remaining[(i + n - 1) % n],
+            b: remaining[i],
+            c: remaining[(i + 1) % n],
+        };
+
+        // Collinear vertices add nothing and would never form an ear
+        if let Some(i) = (0..n).find(|&i| {
+            let t = corner(i);
+            (t.b - t.a).cross(t.c - t.b) == 0.0
+        }) {
+            remaining.remove(i);
+            continue;
+        }
+
+        let ear = (0..n).find(|&i| {
+            let t = corner(i);
+            (t.b - t.a).cross(t.c - t.b) > 0.0
+                && !remaining
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i && *j != (i + n - 1) % n && *j != (i + 1) % n)
+                    .any(|(_, p)| t.contains(*p))
+        });
+        // Only self-intersecting input runs out of ears
+        let Some(i) = ear else {
+            break;
+        };
+        triangles.push(corner(i));
+        remaining.remove(i);
+    }
+    if remaining.len() == 3 {
+        triangles.push(Triangle3 {
+            a: remaining[0],
+            b: remaining[1],
+            c: remaining[2],
+        });
+    }
+    triangles
+}
+
+// Absolute, counter-clockwise vertices of the offset outline. Empty when an
+// inset consumes the whole shape.
+pub fn offset(outline: &Outline, distance: f32) -> Vec<Coord> {
+    let points = match outline {
+        Outline::Circle { center, radius } => {
+            let radius = radius + distance;
+            if radius <= 0.0 {
+                return Vec::new();
+            }
+            return circle_points(*center, radius);
+        }
+        Outline::Polygon(points) => points,
+    };
+
+    let n = points.len();
+    let normal = |i: usize| {
+        let edge = points[(i + 1) % n] - points[i];
+        Coord::new(edge.y, -edge.x) * (1.0 / edge.length())
+    };
+    let mut result = Vec::new();
+    for (i, &vertex) in points.iter().enumerate() {
+        let (before, after) = (normal((i + n - 1) % n), normal(i));
+        let turn = before.cross(after);
+        if turn * distance > 0.0 {
+            // The corner opens up away from the shape, so round it off
+            let sweep = turn.atan2(before.dot(after));
+            result.extend(arc(vertex, before, sweep, distance));
+        } else if 1.0 + before.dot(after) > f32::EPSILON {
+            // Otherwise the offset edges meet at a mitre point
+            result.push(vertex + (before + after) * (distance / (1.0 + before.dot(after))));
+        } else {
+            result.push(vertex + before * distance);
+            result.push(vertex + after * distance);
+        }
+    }
+    if distance < 0.0 {
+        // Mitre points from parts of the shape thinner than the inset end up
+        // closer to the boundary than the inset distance, or outside it
+        result.retain(|point| {
+            point_in_polygon(*point, points)
+                && edges(points)
+                    .map(|(a, b)| point_segment_distance(*point, a, b))
+                    .fold(f32::INFINITY, f32::min)
+                    >= -distance * 0.999
+        });
+    }
+    if result.len() < 3 || signed_area(&result) <= 0.0 {
+        return Vec::new();
+    }
+    result
+}
+
+// Points on an arc around center, starting in the unit direction `from` and
+// turning through `sweep` radians, both ends included. A negative radius
+// mirrors the arc through the center.
+fn arc(center: Coord, from: Coord, sweep: f32, radius: f32) -> Vec<Coord> {
+    let steps = ((sweep.abs() / ARC_STEP).ceil() as usize).max(1);
+    (0..=steps)
+        .map(|i| center + from.rotate(sweep * i as f32 / steps as f32) * radius)
+        .collect()
+}
+
+// Counter-clockwise points on a circle, without repeating the first
+pub fn circle_points(center: Coord, radius: f32) -> Vec<Coord> {
+    let mut points = arc(center, Coord::new(1.0, 0.0), 2.0 * PI, radius);
+    points.pop();
+    points
+}
+
+// Boundary points of an outline, with circles sampled
+pub fn outline_points(outline: Outline) -> Vec<Coord> {
+    match outline {
+        Outline::Circle { center, radius } => circle_points(center, radius),
+        Outline::Polygon(points) => points,
+    }
+}
+
+// Part way from one outline to another, t from 0 to 1. Both outlines are
+// resampled at the union of their vertices' positions round the perimeter,
+// so the corners of each survive. The second is started from the point on
+// it, relative to its origin, nearest the first's start relative to its own.
+pub fn morph(a: &dyn Shape, b: &dyn Shape, t: f32) -> Polygon {
+    let t = t.clamp(0.0, 1.0);
+    let from = outline_points(a.outline());
+    let mut to = outline_points(b.outline());
+    let target = from[0] - a.origin() + b.origin();
+    let nearest = edges(&to)
+        .enumerate()
+        .map(|(i, (p, q))| {
+            let edge = q - p;
+            let along = ((target - p).dot(edge) / edge.dot(edge)).clamp(0.0, 1.0);
+            (i, p + edge * along)
+        })
+        .min_by(|(_, p), (_, q)| p.distance(target).total_cmp(&q.distance(target)));
+    if let Some((i, start)) = nearest {
+        to.rotate_left(i + 1);
+        if to.last() == Some(&start) {
+            to.rotate_right(1);
+        } else if to.first() != Some(&start) {
+            to.insert(0, start);
+        }
+    }
+    let (from_at, to_at) = (perimeter_positions(&from), perimeter_positions(&to));
+    let mut positions: Vec<f32> = from_at.iter().chain(&to_at).copied().collect();
+    positions.sort_by(f32::total_cmp);
+    positions.dedup_by(|a, b| (*a - *b).abs() < 1e-6);
+
+    let origin = a.origin() + (b.origin() - a.origin()) * t;
+    let points = positions
+        .into_iter()
+        .map(|u| {
+            let (p, q) = (along(&from, &from_at, u), along(&to, &to_at, u));
+            p + (q - p) * t - origin
+        })
+        .collect();
+    Polygon { origin, points }
+}
+
+// How far round the closed chain each vertex is, as a share of the whole
+fn perimeter_positions(points: &[Coord]) -> Vec<f32> {
+    let mut total = 0.0;
+    let mut positions = vec![0.0];
+    for (a, b) in edges(points) {
+        total += a.distance(b);
+        positions.push(total);
+    }
+    positions.pop();
+    if total > 0.0 {
+        positions.iter_mut().for_each(|p| *p /= total);
+    }
+    positions
+}
+
+// The point a share u of the way round the closed chain
+fn along(points: &[Coord], positions: &[f32], u: f32) -> Coord {
+    let i = positions.partition_point(|&p| p <= u).max(1) - 1;
+    let (start, end) = (positions[i], positions.get(i + 1).copied().unwrap_or(1.0));
+    let (a, b) = (points[i], points[(i + 1) % points.len()]);
+    if end > start {
+        a + (b - a) * ((u - start) / (end - start))
+    } else {
+        a
+    }
+}
+
+// Douglas-Peucker over an open chain, keeping both end points
+pub fn simplify_chain(points: &[Coord], tolerance: f32) -> Vec<Coord> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let (index, deviation) = points[1..points.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (i + 1, point_segment_distance(*p, first, last)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap();
+    if deviation <= tolerance {
+        return vec![first, last];
+    }
+    let mut simplified = simplify_chain(&points[..=index], tolerance);
+    simplified.pop();
+    simplified.extend(simplify_chain(&points[index..], tolerance));
+    simplified
+}
+
+// Sutherland-Hodgman clipping against an axis aligned box
+pub fn clip_polygon(points: &[Coord], min: Coord, max: Coord) -> Vec<Coord> {
+    // Each boundary as a signed distance, positive on the inside
+    let boundaries: [&dyn Fn(Coord) -> f32; 4] =
+        [&|p| p.x - min.x, &|p| max.x - p.x, &|p| p.y - min.y, &|p| {
+            max.y - p.y
+        }];
+    let mut clipped = points.to_vec();
+    for inside in boundaries {
+        let input = core::mem::take(&mut clipped);
+        for (a, b) in edges(&input) {
+            let (da, db) = (inside(a), inside(b));
+            if da >= 0.0 {
+                clipped.push(a);
+            }
+            if (da >= 0.0) != (db >= 0.0) {
+                clipped.push(a + (b - a) * (da / (da - db)));
+            }
+        }
+    }
+    clipped
+}
+
+// Andrew's monotone chain, counter-clockwise without collinear points
+pub fn convex_hull(points: &[Coord]) -> Vec<Coord> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    let mut hull: Vec<Coord> = Vec::with_capacity(2 * sorted.len());
+    for pass in [sorted.clone(), sorted.into_iter().rev().collect()] {
+        let start = hull.len();
+        for point in pass {
+            while hull.len() >= start + 2
+                && (hull[hull.len() - 1] - hull[hull.len() - 2]).cross(point - hull[hull.len() - 1])
+                    <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        // The last point of each chain starts the other
+        hull.pop();
+    }
+    hull
+}
+
+// Smallest circle containing every point, as (center, radius). Welzl's
+// algorithm in its iterative form.
+pub fn enclosing_circle(points: &[Coord]) -> Option<(Coord, f32)> {
+    let contains = |(center, radius): (Coord, f32), point: Coord| {
+        center.distance(point) <= radius * (1.0 + 1e-5)
+    };
+    let mut circle = (*points.first()?, 0.0);
+    for i in 0..points.len() {
+        if contains(circle, points[i]) {
+            continue;
+        }
+        circle = (points[i], 0.0);
+        for j in 0..i {
+            if contains(circle, points[j]) {
+                continue;
+            }
+            let center = (points[i] + points[j]) * 0.5;
+            circle = (center, center.distance(points[i]));
+            for k in 0..j {
+                if !contains(circle, points[k]) {
+                    circle = circumcircle(points[i], points[j], points[k]);
+                }
+            }
+        }
+    }
+    Some(circle)
+}
+
+fn circumcircle(a: Coord, b: Coord, c: Coord) -> (Coord, f32) {
+    let (ab, ac) = (b - a, c - a);
+    let denominator = 2.0 * ab.cross(ac);
+    if denominator == 0.0 {
+        // Collinear, so the two furthest apart points span the circle
+        let (p, q) = [(a, b), (a, c), (b, c)]
+            .into_iter()
+            .max_by(|x, y| x.0.distance(x.1).total_cmp(&y.0.distance(y.1)))
+            .unwrap();
+        let center = (p + q) * 0.5;
+        return (center, center.distance(p));
+    }
+    let offset = Coord::new(
+        ac.y * ab.dot(ab) - ab.y * ac.dot(ac),
+        ab.x * ac.dot(ac) - ac.x * ab.dot(ab),
+    ) * (1.0 / denominator);
+    (a + offset, offset.length())
+}
+
+// Corners (counter-clockwise) of the smallest area rectangle, at any
+// rotation, containing every point. Rotating calipers over the convex hull.
+pub fn minimum_area_rectangle(points: &[Coord]) -> Option<[Coord; 4]> {
+    let hull = convex_hull(points);
+    let m = hull.len();
+    if m < 3 {
+        let min = *hull.first()?;
+        let max = *hull.last()?;
+        return Some([min, max, max, min]);
+    }
+    let next = |i: usize| (i + 1) % m;
+    let (mut right, mut top, mut left) = (0, 0, 0);
+    let mut best: Option<(f32, [Coord; 4])> = None;
+    for i in 0..m {
+        let base = hull[i];
+        let edge = hull[next(i)] - base;
+        let u = edge * (1.0 / edge.length());
+        let v = Coord::new(-u.y, u.x);
+
+        // Each caliper only ever moves forward around the hull
+        while (hull[next(right)] - hull[right]).dot(u) > 0.0 {
+            right = next(right);
+        }
+        if i == 0 {
+            top = right;
+        }
+        while (hull[next(top)] - hull[top]).dot(v) > 0.0 {
+            top = next(top);
+        }
+        if i == 0 {
+            left = top;
+        }
+        while (hull[next(left)] - hull[left]).dot(u) < 0.0 {
+            left = next(left);
+        }
+
+        let max_u = (hull[right] - base).dot(u);
+        let min_u = (hull[left] - base).dot(u);
+        let height = (hull[top] - base).dot(v);
+        let area = (max_u - min_u) * height;
+        if best.is_none_or(|(best_area, _)| area < best_area) {
+            best = Some((
+                area,
+                [
+                    base + u * min_u,
+                    base + u * max_u,
+                    base + u * max_u + v * height,
+                    base + u * min_u + v * height,
+                ],
+            ));
+        }
+    }
+    best.map(|(_, corners)| corners)
+}
+
+// Positive for counter-clockwise vertices
+pub fn signed_area(points: &[Coord]) -> f32 {
+    0.5 * edges(points).map(|(a, b)| a.cross(b)).sum::<f32>()
+}
+
+pub fn distance(a: &dyn Shape, b: &dyn Shape) -> f32 {
+    a.outline().distance(&b.outline())
+}
+
+fn polygon_distance(a: &[Coord], b: &[Coord]) -> f32 {
+    // One polygon entirely inside the other has no crossing edges
+    if a.first().is_some_and(|p| point_in_polygon(*p, b))
+        || b.first().is_some_and(|p| point_in_polygon(*p, a))
+    {
+        return 0.0;
+    }
+    let mut best = f32::INFINITY;
+    for (a0, a1) in edges(a) {
+        for (b0, b1) in edges(b) {
+            if segments_intersect(a0, a1, b0, b1) {
+                return 0.0;
+            }
+            best = best.min(segment_distance(a0, a1, b0, b1));
+        }
+    }
+    best
+}
+
+pub fn edges(points: &[Coord]) -> impl Iterator<Item = (Coord, Coord)> + '_ {
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| (*a, *b))
+}
+
+// Even-odd rule, so it also holds for concave polygons
+pub fn point_in_polygon(point: Coord, points: &[Coord]) -> bool {
+    let mut inside = false;
+    for (a, b) in edges(points) {
+        if (a.y > point.y) != (b.y > point.y) {
+            let x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+            if point.x < x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+pub fn point_segment_distance(point: Coord, a: Coord, b: Coord) -> f32 {
+    let ab = b - a;
+    let length_squared = ab.dot(ab);
+    if length_squared == 0.0 {
+        return point.distance(a);
+    }
+    let t = ((point - a).dot(ab) / length_squared).clamp(0.0, 1.0);
+    point.distance(a + ab * t)
+}
+
+pub fn segments_intersect(a0: Coord, a1: Coord, b0: Coord, b1: Coord) -> bool {
+    let d1 = (a1 - a0).cross(b0 - a0);
+    let d2 = (a1 - a0).cross(b1 - a0);
+    let d3 = (b1 - b0).cross(a0 - b0);
+    let d4 = (b1 - b0).cross(a1 - b0);
+    if ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0))
+        && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+    {
+        return true;
+    }
+    // Touching or collinear cases
+    (d1 == 0.0 && point_segment_distance(b0, a0, a1) == 0.0)
+        || (d2 == 0.0 && point_segment_distance(b1, a0, a1) == 0.0)
+        || (d3 == 0.0 && point_segment_distance(a0, b0, b1) == 0.0)
+        || (d4 == 0.0 && point_segment_distance(a1, b0, b1) == 0.0)
+}
+
+pub fn segment_distance(a0: Coord, a1: Coord, b0: Coord, b1: Coord) -> f32 {
+    if segments_intersect(a0, a1, b0, b1) {
+        return 0.0;
+    }
+    point_segment_distance(a0, b0, b1)
+        .min(point_segment_distance(a1, b0, b1))
+        .min(point_segment_distance(b0, a0, a1))
+        .min(point_segment_distance(b1, a0, a1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_approx_eq, Circle, Triangle};
+
+    #[test]
+    fn morph_circle_to_square() {
+        let circle = Circle {
+            origin: Coord::default(),
+            radius: 1.0,
+        };
+        let square = Rectangle {
+            origin: Coord::new(4.0, 0.0),
+            side_a: 2.0,
+            side_b: 2.0,
+        };
+        let start = morph(&circle, &square, 0.0);
+        let end = morph(&circle, &square, 1.0);
+        assert_eq!(start.points.len(), end.points.len());
+        let circle_area = signed_area(&circle_points(Coord::default(), 1.0));
+        assert_approx_eq!(start.get_area(), circle_area);
+        assert_approx_eq!(end.get_area(), 4.0);
+        assert_eq!(end.origin, Coord::new(4.0, 0.0));
+        // Every corner of the square is kept
+        for corner in outline_points(square.outline()) {
+            assert!(end.vertices().iter().any(|v| v.distance(corner) < 1e-5));
+        }
+        let halfway = morph(&circle, &square, 0.5);
+        assert_eq!(halfway.origin, Coord::new(2.0, 0.0));
+        let area = halfway.get_area();
+        assert!(area > circle_area && area < 4.0, "{area}");
+    }
+
+    #[test]
+    fn distance_between_shapes() {
+        let circle = Circle {
+            origin: Coord::default(),
+            radius: 1.0,
+        };
+        let far_circle = Circle {
+            origin: Coord::new(5.0, 0.0),
+            radius: 2.0,
+        };
+        assert_eq!(distance(&circle, &far_circle), 2.0);
+
+        let rectangle = Rectangle {
+            origin: Coord::new(4.0, 0.0),
+            side_a: 2.0,
+            side_b: 2.0,
+        };
+        assert_eq!(distance(&circle, &rectangle), 2.0);
+        assert_eq!(distance(&far_circle, &rectangle), 0.0);
+
+        let triangle = Triangle {
+            origin: Coord::new(4.0, 5.0),
+            base: 2.0,
+            height: 2.0,
+        };
+        assert_eq!(distance(&rectangle, &triangle), 3.0);
+    }
+
+    #[test]
+    fn nested_shapes_overlap() {
+        let outer = Rectangle {
+            origin: Coord::default(),
+            side_a: 10.0,
+            side_b: 10.0,
+        };
+        let inner = Triangle {
+            origin: Coord::default(),
+            base: 1.0,
+            height: 1.0,
+        };
+        assert_eq!(distance(&outer, &inner), 0.0);
+        assert_eq!(distance(&inner, &outer), 0.0);
+    }
+
+    #[test]
+    fn line_intersections() {
+        let circle = Circle {
+            origin: Coord::default(),
+            radius: 1.0,
+        };
+        assert_eq!(
+            circle.intersect_line(Coord::new(2.0, 0.0), Coord::new(-2.0, 0.0)),
+            vec![Coord::new(1.0, 0.0), Coord::new(-1.0, 0.0)]
+        );
+        assert!(circle
+            .intersect_line(Coord::new(-2.0, 2.0), Coord::new(2.0, 2.0))
+            .is_empty());
+
+        // Starting inside only exits
+        let rectangle = Rectangle {
+            origin: Coord::default(),
+            side_a: 2.0,
+            side_b: 2.0,
+        };
+        assert_eq!(
+            rectangle.intersect_line(Coord::default(), Coord::new(0.0, 5.0)),
+            vec![Coord::new(0.0, 1.0)]
+        );
+
+        // Through the apex vertex
+        let triangle = Triangle {
+            origin: Coord::default(),
+            base: 2.0,
+            height: 2.0,
+        };
+        assert_eq!(
+            triangle.intersect_line(Coord::new(0.0, -5.0), Coord::new(0.0, 5.0)),
+            vec![Coord::new(0.0, -1.0), Coord::new(0.0, 1.0)]
+        );
+    }
+
+    #[test]
+    fn offset_shapes() {
+        let square = Rectangle {
+            origin: Coord::new(3.0, 3.0),
+            side_a: 2.0,
+            side_b: 2.0,
+        };
+        // Square plus four edge strips plus a circle's worth of rounded corners
+        let grown = square.offset(1.0).get_area();
+        assert_approx_eq!(grown, 4.0 + 8.0 + PI, 3e-3);
+        assert_approx_eq!(square.offset(-0.5).get_area(), 1.0);
+        assert!(square.offset(-1.5).points.is_empty());
+
+        let circle = Circle {
+            origin: Coord::default(),
+            radius: 1.0,
+        };
+        let grown = circle.offset(1.0).get_area();
+        assert_approx_eq!(grown, 4.0 * PI, 4e-3);
+    }
+
+    #[test]
+    fn smallest_enclosing_circle() {
+        let points = [
+            Coord::new(0.0, 0.0),
+            Coord::new(4.0, 0.0),
+            Coord::new(2.0, 1.0),
+            Coord::new(2.0, -1.0),
+        ];
+        let (center, radius) = enclosing_circle(&points).unwrap();
+        assert_eq!(center, Coord::new(2.0, 0.0));
+        assert_eq!(radius, 2.0);
+
+        let (center, radius) = enclosing_circle(&[
+            Coord::new(0.0, 0.0),
+            Coord::new(2.0, 0.0),
+            Coord::new(1.0, 3.0f32.sqrt()),
+        ])
+        .unwrap();
+        assert!((center.distance(Coord::new(1.0, 3.0f32.sqrt() / 3.0))) < 1e-5);
+        assert_approx_eq!(radius, 2.0 / 3.0f32.sqrt());
+        assert_eq!(enclosing_circle(&[]), None);
+    }
+
+    #[test]
+    fn minimum_rectangle_of_rotated_square() {
+        // A unit square rotated by 45 degrees, plus an interior point
+        let h = 0.5f32.sqrt();
+        let points = [
+            Coord::new(0.0, -h),
+            Coord::new(h, 0.0),
+            Coord::new(0.0, h),
+            Coord::new(-h, 0.0),
+            Coord::new(0.1, 0.1),
+        ];
+        let corners = minimum_area_rectangle(&points).unwrap();
+        assert_approx_eq!(signed_area(&corners), 1.0);
+        assert_eq!(convex_hull(&points).len(), 4);
+    }
+
+    #[test]
+    fn triangulate_concave_polygon() {
+        // L shape, given clockwise
+        let polygon = Polygon {
+            origin: Coord::new(1.0, 1.0),
+            points: vec![
+                Coord::new(0.0, 0.0),
+                Coord::new(0.0, 2.0),
+                Coord::new(1.0, 2.0),
+                Coord::new(1.0, 1.0),
+                Coord::new(2.0, 1.0),
+                Coord::new(2.0, 0.0),
+            ],
+        };
+        let triangles = triangulate(&polygon);
+
+        assert_eq!(triangles.len(), 4);
+        assert_eq!(polygon.get_area(), 3.0);
+        // No triangle covers the notch of the L
+        assert!(!triangles.iter().any(|t| t.contains(Coord::new(2.5, 2.5))));
+    }
+}
@@ -0,0 +1,39 @@
+// Conversions to and from glam's vectors, so positions from a game engine
+// go straight into anything taking impl Into<Coord>
+use glam::Vec2;
+
+use crate::Coord;
+
+impl From<Vec2> for Coord {
+    fn from(Vec2 { x, y }: Vec2) -> Coord {
+        Coord::new(x, y)
+    }
+}
+impl From<Coord> for Vec2 {
+    fn from(Coord { x, y }: Coord) -> Vec2 {
+        Vec2::new(x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{Canvas, Circle, ShapeCell};
+
+    #[test]
+    fn vectors_round_trip() {
+        let point = Vec2::new(1.5, -2.0);
+        assert_eq!(Vec2::from(Coord::from(point)), point);
+
+        let mut canvas = Canvas::default();
+        let id = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::default(),
+            radius: 1.0,
+        })));
+        canvas.set_origin(id, Vec2::new(3.0, 4.0)).unwrap();
+        let origin: Vec2 = canvas.get(id).unwrap().lock().unwrap().origin().into();
+        assert_eq!(origin.length(), 5.0);
+    }
+}
@@ -0,0 +1,174 @@
+// Rendering regression tests against the pictures in goldens/, drawn as
+// text a character a pixel, darker characters for darker pixels. After a
+// change to rendering that's meant, look over the new pictures with
+// UPDATE_GOLDENS=1 cargo test golden
+// and commit them with the change.
+use std::{env, fs, path::Path};
+
+use crate::{
+    camera::{Axes, Camera, YAxis},
+    command::Session,
+    render::{render, Image, RenderConfig},
+    style::{Color, Dash, Paint, Style},
+    Coord, Rectangle,
+};
+
+// Light to dark
+const RAMP: &[u8] = b" .:-=+*#%@";
+
+pub fn to_text(image: &Image) -> String {
+    let mut text = String::new();
+    for row in image.pixels.chunks(image.width as usize) {
+        for &[r, g, b, _] in row {
+            let luma = (299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000;
+            text.push(RAMP[(255 - luma as usize) * RAMP.len() / 256] as char);
+        }
+        text.truncate(text.trim_end().len());
+        text.push('\n');
+    }
+    text
+}
+
+// Pixels twice as tall as they're wide, as characters are, over the square
+// from -24 to 24
+fn config() -> RenderConfig {
+    RenderConfig {
+        width: 48,
+        height: 24,
+        view: Rectangle {
+            origin: Coord::default(),
+            side_a: 48.0,
+            side_b: 48.0,
+        },
+        threads: 1,
+        ..RenderConfig::default()
+    }
+}
+
+fn scene(script: &str) -> Session {
+    let mut session = Session::new();
+    session.run(script).unwrap();
+    session
+}
+
+pub fn check(name: &str, image: &Image) {
+    let actual = to_text(image);
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("goldens")
+        .join(format!("{name}.txt"));
+    if env::var_os("UPDATE_GOLDENS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "no golden at {}, make it with UPDATE_GOLDENS=1\n{actual}",
+            path.display()
+        )
+    });
+    if let Some((line, (want, got))) = expected
+        .lines()
+        .zip(actual.lines())
+        .enumerate()
+        .find(|(_, (want, got))| want != got)
+    {
+        panic!(
+            "{name} differs from its golden on line {}\nwant |{want}\ngot  |{got}\n{actual}",
+            line + 1
+        );
+    }
+    assert_eq!(
+        expected.lines().count(),
+        actual.lines().count(),
+        "{name} differs from its golden in height\n{actual}"
+    );
+}
+
+#[test]
+fn golden_shapes() {
+    let session = scene(
+        "add circle 6 at -14 10
+add rectangle 14 8 at 12 12
+add triangle 12 10 at -14 -12
+add polygon 0 0 10 4 4 10 -6 6 at 8 -16",
+    );
+    check("shapes", &render(&session.canvas, &config()).unwrap());
+}
+
+#[test]
+fn golden_symmetry() {
+    let session = scene(
+        "symmetry dihedral 6
+add triangle 4 10 at 12 4
+symmetry cyclic 3
+add circle 3 at 0 6",
+    );
+    check("symmetry", &render(&session.canvas, &config()).unwrap());
+}
+
+#[test]
+fn golden_styles() {
+    let mut session = scene(
+        "add circle 8 at -12 8
+add rectangle 16 12 at 12 8
+add triangle 20 12 at 0 -12
+add rectangle 40 4 at 0 -20",
+    );
+    let canvas = &mut session.canvas;
+    let ids = canvas.ids().to_vec();
+    let outline = Style {
+        fill: None,
+        stroke: Some(Color::BLACK),
+        stroke_width: 1.5,
+        ..Style::default()
+    };
+    canvas.set_style(ids[0], outline).unwrap();
+    let dashed = Style {
+        dash: Some(Dash {
+            on: 3.0,
+            off: 2.0,
+            offset: 0.0,
+        }),
+        ..outline
+    };
+    canvas.set_style(ids[1], dashed).unwrap();
+    let faded = Style {
+        opacity: 0.5,
+        ..Style::default()
+    };
+    canvas.set_style(ids[2], faded).unwrap();
+    let shaded = Style {
+        fill: Some(Paint::Linear {
+            start: Coord::new(-20.0, 0.0),
+            end: Coord::new(20.0, 0.0),
+            colors: [Color::WHITE, Color::BLACK],
+        }),
+        ..Style::default()
+    };
+    canvas.set_style(ids[3], shaded).unwrap();
+    check("styles", &render(canvas, &config()).unwrap());
+}
+
+#[test]
+fn golden_views() {
+    let mut session = scene(
+        "add rectangle 20 4 at 0 8
+add triangle 8 8 at -10 -4",
+    );
+    let camera = Camera {
+        center: Coord::new(0.0, 2.0),
+        zoom: 1.0,
+        rotation: std::f32::consts::FRAC_PI_6,
+    };
+    let turned = RenderConfig {
+        camera: Some(camera),
+        ..config()
+    };
+    check("camera", &render(&session.canvas, &turned).unwrap());
+    session.canvas.set_axes(Axes {
+        y: YAxis::Down,
+        ..Axes::default()
+    });
+    check("y_down", &render(&session.canvas, &config()).unwrap());
+}
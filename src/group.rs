@@ -0,0 +1,239 @@
+use crate::{arena::ShapeId, error::CanvasError, transform::Transform, Canvas, ShapeObject};
+
+// A node in a tree of transforms. Member shapes are kept in the group's own
+// coordinates, while the canvas holds their images through the group's
+// transform and those of all its ancestors, so every canvas query sees world
+// space. Moving a member on the canvas directly lasts only until the group
+// is next refreshed; move the group, or the member's local shape, instead.
+#[derive(Clone)]
+pub struct Group {
+    transform: Transform,
+    parent: Option<usize>,
+    members: Vec<(ShapeId, ShapeObject)>,
+    hidden: bool,
+}
+
+impl Canvas {
+    // Groups are numbered in the order they were added
+    pub fn add_group(
+        &mut self,
+        parent: Option<usize>,
+        transform: Transform,
+    ) -> Result<usize, CanvasError> {
+        if let Some(parent) = parent {
+            self.group(parent)?;
+        }
+        transform.validate()?;
+        self.groups.push(Group {
+            transform,
+            parent,
+            members: Vec::new(),
+            hidden: false,
+        });
+        Ok(self.groups.len() - 1)
+    }
+    // The shape is given in the group's coordinates. Members aren't given
+    // symmetric copies or reflections.
+    pub fn add_to_group(
+        &mut self,
+        group: usize,
+        shape: ShapeObject,
+    ) -> Result<ShapeId, CanvasError> {
+        let world = self.world_transform(group)?;
+        let image = world.image(&*shape.lock()?);
+        let id = self.shapes.insert(image);
+        self.groups[group].members.push((id, shape));
+        self.debug_check(self.shapes.get(id));
+        Ok(id)
+    }
+    // Relative to the parent group, or to the canvas for groups without one
+    pub fn group_transform(&self, group: usize) -> Result<Transform, CanvasError> {
+        Ok(self.group(group)?.transform)
+    }
+    pub fn set_group_transform(
+        &mut self,
+        group: usize,
+        transform: Transform,
+    ) -> Result<(), CanvasError> {
+        self.group(group)?;
+        transform.validate()?;
+        // Members of the group and its descendants all move with it
+        for descendant in 0..self.groups.len() {
+            if self.descends_from(descendant, group) {
+                for (id, _) in &self.groups[descendant].members {
+                    self.unlocked(*id)?;
+                }
+            }
+        }
+        self.groups[group].transform = transform;
+        self.refresh_group(group)
+    }
+    // Composed with every ancestor's transform
+    pub fn world_transform(&self, group: usize) -> Result<Transform, CanvasError> {
+        let mut world = self.group(group)?.transform;
+        let mut parent = self.groups[group].parent;
+        while let Some(ancestor) = parent {
+            world = self.groups[ancestor].transform.after(&world);
+            parent = self.groups[ancestor].parent;
+        }
+        Ok(world)
+    }
+    // Ids of the group's own members, not those of its descendants
+    pub fn group_members(&self, group: usize) -> Result<Vec<ShapeId>, CanvasError> {
+        Ok(self
+            .group(group)?
+            .members
+            .iter()
+            .map(|(id, _)| *id)
+            .collect())
+    }
+    // A member's shape in its group's coordinates
+    pub fn local_shape(&self, id: ShapeId) -> Option<&ShapeObject> {
+        self.groups
+            .iter()
+            .flat_map(|group| &group.members)
+            .find(|(member, _)| *member == id)
+            .map(|(_, shape)| shape)
+    }
+    // Recomputes the world shapes of the group and all its descendants, for
+    // after their local shapes have been changed
+    pub fn refresh_group(&mut self, group: usize) -> Result<(), CanvasError> {
+        self.group(group)?;
+        for descendant in 0..self.groups.len() {
+            if !self.descends_from(descendant, group) {
+                continue;
+            }
+            let world = self.world_transform(descendant)?;
+            for (id, local) in &self.groups[descendant].members {
+                let image = world.image(&*local.lock()?);
+                if let Some(shape) = self.shapes.get_mut(*id) {
+                    *shape = image;
+                }
+            }
+        }
+        self.debug_check(self.shapes.values());
+        Ok(())
+    }
+
+    // Hiding a group hides its members and those of its descendants, as
+    // set_visible hides a shape
+    pub fn set_group_visible(&mut self, group: usize, visible: bool) -> Result<(), CanvasError> {
+        self.group(group)?;
+        self.groups[group].hidden = !visible;
+        Ok(())
+    }
+    // The group's own setting, whether or not an ancestor is hidden
+    pub fn group_visible(&self, group: usize) -> Result<bool, CanvasError> {
+        Ok(!self.group(group)?.hidden)
+    }
+    pub(crate) fn hidden_by_group(&self, id: ShapeId) -> bool {
+        if !self.groups.iter().any(|group| group.hidden) {
+            return false;
+        }
+        let Some(mut current) = self
+            .groups
+            .iter()
+            .position(|group| group.members.iter().any(|(member, _)| *member == id))
+        else {
+            return false;
+        };
+        loop {
+            if self.groups[current].hidden {
+                return true;
+            }
+            match self.groups[current].parent {
+                Some(parent) => current = parent,
+                None => return false,
+            }
+        }
+    }
+
+    pub(crate) fn is_member(&self, id: ShapeId) -> bool {
+        self.groups
+            .iter()
+            .any(|group| group.members.iter().any(|(member, _)| *member == id))
+    }
+    pub(crate) fn forget_member(&mut self, id: ShapeId) {
+        for group in &mut self.groups {
+            group.members.retain(|(member, _)| *member != id);
+        }
+    }
+    fn group(&self, group: usize) -> Result<&Group, CanvasError> {
+        self.groups
+            .get(group)
+            .ok_or(CanvasError::UnknownIndex(group))
+    }
+    // Including the group itself
+    fn descends_from(&self, group: usize, ancestor: usize) -> bool {
+        let mut current = Some(group);
+        while let Some(g) = current {
+            if g == ancestor {
+                return true;
+            }
+            current = self.groups[g].parent;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f32::consts::FRAC_PI_2, sync::Arc};
+
+    use super::*;
+    use crate::{assert_approx_eq, Coord, Rectangle, ShapeCell, ShapeError};
+
+    fn close(a: Coord, b: Coord) -> bool {
+        a.distance(b) < 1e-4
+    }
+
+    #[test]
+    fn nested_groups_compose() {
+        let mut canvas = Canvas::default();
+        // A wheel about the zero origin, with a smaller wheel on its rim
+        let wheel = canvas.add_group(None, Transform::IDENTITY).unwrap();
+        let rim = Transform::translation(Coord::new(10.0, 0.0));
+        let small = canvas.add_group(Some(wheel), rim).unwrap();
+        let square = canvas
+            .add_to_group(
+                small,
+                Arc::new(ShapeCell::new(Rectangle {
+                    origin: Coord::new(1.0, 0.0),
+                    side_a: 1.0,
+                    side_b: 1.0,
+                })),
+            )
+            .unwrap();
+        let origin = |canvas: &Canvas| canvas.get(square).unwrap().lock().unwrap().origin();
+        assert!(close(origin(&canvas), Coord::new(11.0, 0.0)));
+
+        canvas
+            .set_group_transform(wheel, Transform::rotation(FRAC_PI_2))
+            .unwrap();
+        assert!(close(origin(&canvas), Coord::new(0.0, 11.0)));
+        canvas
+            .set_group_transform(small, rim.after(&Transform::rotation(FRAC_PI_2)))
+            .unwrap();
+        assert!(close(origin(&canvas), Coord::new(-1.0, 10.0)));
+        assert_eq!(canvas.hit_test(Coord::new(-1.2, 10.2)), vec![square]);
+        assert_approx_eq!(canvas.get_area(square).unwrap(), 1.0);
+
+        // Local edits show once refreshed, and the local shape is unmoved
+        let local = canvas.local_shape(square).unwrap().clone();
+        local.lock().unwrap().set_origin(Coord::new(2.0, 0.0));
+        canvas.refresh_group(wheel).unwrap();
+        assert!(close(origin(&canvas), Coord::new(-2.0, 10.0)));
+        assert_eq!(local.lock().unwrap().origin(), Coord::new(2.0, 0.0));
+
+        assert!(matches!(
+            canvas.set_group_transform(small, Transform::scale(0.0, 1.0)),
+            Err(CanvasError::Shape(ShapeError::SingularTransform))
+        ));
+        assert!(matches!(
+            canvas.add_group(Some(5), Transform::IDENTITY),
+            Err(CanvasError::UnknownIndex(5))
+        ));
+        canvas.remove(square);
+        assert!(canvas.group_members(small).unwrap().is_empty());
+    }
+}
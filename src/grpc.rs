@@ -0,0 +1,390 @@
+// Canvases over gRPC, the service in proto/kaleidoscope.proto, for callers
+// that would rather have a typed protocol than the HTTP server's plain text.
+// Each rpc is one of that server's routes and runs the same session command,
+// on a restricted session, so the two agree on what's allowed and what
+// comes back. WatchStats streams a canvas's shape count, area and metrics,
+// a message to start with and another whenever they change.
+//
+// The messages and service traits in grpc/proto.rs are checked in, so
+// building needs no protoc. After changing the .proto they're made again
+// with tonic-build 0.12, from a build script or a throwaway binary running
+//     tonic_build::configure()
+//         .out_dir("src/grpc")
+//         .compile_protos(&["proto/kaleidoscope.proto"], &["proto"])
+// with protoc on the PATH, and the output renamed to proto.rs.
+//
+// Status is big, but it's what every rpc has to return.
+#![allow(clippy::result_large_err)]
+
+use std::{
+    fmt::Write,
+    pin::Pin,
+    sync::{Mutex, MutexGuard},
+};
+
+use tokio::{net::TcpListener, sync::watch};
+use tokio_stream::{
+    wrappers::{TcpListenerStream, WatchStream},
+    Stream, StreamExt,
+};
+use tonic::{transport, Request, Response, Status};
+
+pub mod proto;
+
+use crate::{command::Session, error::CommandError};
+use proto::{
+    add_shape_request::Shape,
+    canvases_server::{Canvases, CanvasesServer},
+    AddShapeRequest, AreaReply, AreaRequest, CanvasRef, Empty, HitTestRequest, Hits,
+    MoveShapeRequest, Output, Point, Script, ShapeRef, Stats,
+};
+
+#[derive(Default)]
+pub struct Service {
+    canvases: Mutex<Vec<Option<Entry>>>,
+}
+
+struct Entry {
+    session: Session,
+    // Dropped with the canvas, which ends its watchers' streams
+    stats: watch::Sender<Stats>,
+}
+
+impl Entry {
+    fn new() -> Entry {
+        let mut session = Session::new();
+        session.restrict();
+        let stats = watch::Sender::new(stats(&session));
+        Entry { session, stats }
+    }
+
+    fn execute(&mut self, line: &str) -> Result<String, Status> {
+        self.publish(|session| session.execute(line))
+    }
+    fn run(&mut self, script: &str) -> Result<String, Status> {
+        self.publish(|session| session.run(script))
+    }
+
+    // Tells watchers if whatever was done to the session changed anything
+    fn publish(
+        &mut self,
+        f: impl FnOnce(&mut Session) -> Result<String, CommandError>,
+    ) -> Result<String, Status> {
+        let output = f(&mut self.session);
+        let now = stats(&self.session);
+        self.stats.send_if_modified(|stats| {
+            let changed = *stats != now;
+            *stats = now;
+            changed
+        });
+        output.map_err(status)
+    }
+}
+
+fn stats(session: &Session) -> Stats {
+    let metrics = session.canvas.metrics();
+    Stats {
+        shapes: session.canvas.len() as u64,
+        area: session.canvas.total_area(),
+        adds: metrics.adds,
+        removes: metrics.removes,
+        moves: metrics.moves,
+        hit_tests: metrics.hit_tests,
+    }
+}
+
+// The gRPC code for what the HTTP server would answer with
+fn status(error: CommandError) -> Status {
+    let mut cause = &error;
+    while let CommandError::Line(_, inner) = cause {
+        cause = inner;
+    }
+    let message = error.to_string();
+    match cause {
+        CommandError::UnknownNumber(_) => Status::not_found(message),
+        CommandError::Restricted(_) => Status::permission_denied(message),
+        CommandError::Unsupported(_) => Status::unimplemented(message),
+        _ => Status::invalid_argument(message),
+    }
+}
+
+fn point(point: Option<Point>, name: &str) -> Result<Point, Status> {
+    point.ok_or_else(|| Status::invalid_argument(format!("{name} is missing")))
+}
+
+// What add takes after its kind, up to the origin
+fn shape_words(shape: Shape) -> String {
+    match shape {
+        Shape::Circle(circle) => format!("circle {}", circle.radius),
+        Shape::Rectangle(rectangle) => {
+            format!("rectangle {} {}", rectangle.width, rectangle.height)
+        }
+        Shape::Triangle(triangle) => format!("triangle {} {}", triangle.base, triangle.height),
+        Shape::Polygon(polygon) => {
+            let mut words = String::from("polygon");
+            for Point { x, y } in polygon.points {
+                let _ = write!(words, " {x} {y}");
+            }
+            words
+        }
+    }
+}
+
+// The numbers in the replies of add and hit
+fn numbers(output: &str) -> Vec<u64> {
+    output
+        .split_whitespace()
+        .filter_map(|word| word.parse().ok())
+        .collect()
+}
+
+impl Service {
+    fn lock(&self) -> Result<MutexGuard<'_, Vec<Option<Entry>>>, Status> {
+        self.canvases
+            .lock()
+            .map_err(|_| Status::internal("poisoned"))
+    }
+
+    fn with<T>(
+        &self,
+        canvas: u64,
+        f: impl FnOnce(&mut Entry) -> Result<T, Status>,
+    ) -> Result<Response<T>, Status> {
+        let mut canvases = self.lock()?;
+        let index = (canvas as usize).checked_sub(1);
+        let entry = index.and_then(|index| canvases.get_mut(index)?.as_mut());
+        let entry =
+            entry.ok_or_else(|| Status::not_found(format!("no canvas numbered {canvas}")))?;
+        f(entry).map(Response::new)
+    }
+}
+
+type StatsStream = Pin<Box<dyn Stream<Item = Result<Stats, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Canvases for Service {
+    async fn create_canvas(&self, _: Request<Empty>) -> Result<Response<CanvasRef>, Status> {
+        let mut canvases = self.lock()?;
+        canvases.push(Some(Entry::new()));
+        Ok(Response::new(CanvasRef {
+            canvas: canvases.len() as u64,
+        }))
+    }
+
+    async fn delete_canvas(&self, request: Request<CanvasRef>) -> Result<Response<Empty>, Status> {
+        let canvas = request.into_inner().canvas;
+        let mut canvases = self.lock()?;
+        let index = (canvas as usize).checked_sub(1);
+        match index.and_then(|index| canvases.get_mut(index)?.take()) {
+            Some(_) => Ok(Response::new(Empty {})),
+            None => Err(Status::not_found(format!("no canvas numbered {canvas}"))),
+        }
+    }
+
+    async fn run(&self, request: Request<Script>) -> Result<Response<Output>, Status> {
+        let Script { canvas, script } = request.into_inner();
+        self.with(canvas, |entry| {
+            let output = entry.run(&script)?;
+            Ok(Output { output })
+        })
+    }
+
+    async fn add_shape(
+        &self,
+        request: Request<AddShapeRequest>,
+    ) -> Result<Response<ShapeRef>, Status> {
+        let AddShapeRequest {
+            canvas,
+            origin,
+            shape,
+        } = request.into_inner();
+        let Point { x, y } = point(origin, "origin")?;
+        let shape = shape.ok_or_else(|| Status::invalid_argument("shape is missing"))?;
+        let line = format!("add {} at {x} {y}", shape_words(shape));
+        self.with(canvas, |entry| {
+            let output = entry.execute(&line)?;
+            let shape = numbers(&output).pop().unwrap_or_default();
+            Ok(ShapeRef { canvas, shape })
+        })
+    }
+
+    async fn move_shape(
+        &self,
+        request: Request<MoveShapeRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let MoveShapeRequest { canvas, shape, to } = request.into_inner();
+        let Point { x, y } = point(to, "to")?;
+        self.with(canvas, |entry| {
+            entry.execute(&format!("move {shape} to {x} {y}"))?;
+            Ok(Empty {})
+        })
+    }
+
+    async fn remove_shape(&self, request: Request<ShapeRef>) -> Result<Response<Empty>, Status> {
+        let ShapeRef { canvas, shape } = request.into_inner();
+        self.with(canvas, |entry| {
+            entry.execute(&format!("remove {shape}"))?;
+            Ok(Empty {})
+        })
+    }
+
+    async fn list_shapes(&self, request: Request<CanvasRef>) -> Result<Response<Output>, Status> {
+        self.with(request.into_inner().canvas, |entry| {
+            let output = entry.execute("list")?;
+            Ok(Output { output })
+        })
+    }
+
+    async fn area(&self, request: Request<AreaRequest>) -> Result<Response<AreaReply>, Status> {
+        let AreaRequest { canvas, shape } = request.into_inner();
+        let line = match shape {
+            Some(shape) => format!("area {shape}"),
+            None => String::from("area"),
+        };
+        self.with(canvas, |entry| {
+            let area = entry.execute(&line)?.parse().unwrap_or_default();
+            Ok(AreaReply { area })
+        })
+    }
+
+    async fn hit_test(&self, request: Request<HitTestRequest>) -> Result<Response<Hits>, Status> {
+        let HitTestRequest { canvas, at } = request.into_inner();
+        let Point { x, y } = point(at, "at")?;
+        self.with(canvas, |entry| {
+            let shapes = numbers(&entry.execute(&format!("hit {x} {y}"))?);
+            Ok(Hits { shapes })
+        })
+    }
+
+    type WatchStatsStream = StatsStream;
+
+    async fn watch_stats(
+        &self,
+        request: Request<CanvasRef>,
+    ) -> Result<Response<StatsStream>, Status> {
+        self.with(request.into_inner().canvas, |entry| {
+            let stats = WatchStream::new(entry.stats.subscribe()).map(Ok);
+            Ok(Box::pin(stats) as StatsStream)
+        })
+    }
+}
+
+// Serves a fresh set of canvases on the listener until it fails
+pub async fn serve(listener: TcpListener) -> Result<(), transport::Error> {
+    transport::Server::builder()
+        .add_service(CanvasesServer::new(Service::default()))
+        .serve_with_incoming(TcpListenerStream::new(listener))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::runtime;
+    use tonic::Code;
+
+    use super::{proto::canvases_client::CanvasesClient, *};
+    use crate::assert_approx_eq;
+    use proto::{Circle, Polygon, Rectangle};
+
+    #[test]
+    fn drive_canvases_over_grpc() {
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap();
+            tokio::spawn(serve(listener));
+            let mut client = CanvasesClient::connect(format!("http://{address}"))
+                .await
+                .unwrap();
+
+            let canvas = client.create_canvas(Empty {}).await.unwrap();
+            let CanvasRef { canvas } = canvas.into_inner();
+            let mut stats = client
+                .watch_stats(CanvasRef { canvas })
+                .await
+                .unwrap()
+                .into_inner();
+            let start = stats.next().await.unwrap().unwrap();
+            assert_eq!((start.shapes, start.area), (0, 0.0));
+
+            let add = |origin: (f32, f32), shape| AddShapeRequest {
+                canvas,
+                origin: Some(Point {
+                    x: origin.0,
+                    y: origin.1,
+                }),
+                shape: Some(shape),
+            };
+            let square = Shape::Rectangle(Rectangle {
+                width: 2.0,
+                height: 2.0,
+            });
+            let square = client.add_shape(add((5.0, 0.0), square)).await.unwrap();
+            assert_eq!(square.get_ref().shape, 1);
+            let triangle = Shape::Polygon(Polygon {
+                points: vec![
+                    Point { x: 0.0, y: 0.0 },
+                    Point { x: 4.0, y: 0.0 },
+                    Point { x: 0.0, y: 3.0 },
+                ],
+            });
+            client.add_shape(add((0.0, 0.0), triangle)).await.unwrap();
+            let added = stats.next().await.unwrap().unwrap();
+            assert!(added.adds >= 1, "{added:?}");
+
+            let circle = add((0.0, 0.0), Shape::Circle(Circle { radius: 1.0 }));
+            client.add_shape(circle).await.unwrap();
+            client
+                .move_shape(MoveShapeRequest {
+                    canvas,
+                    shape: 3,
+                    to: Some(Point { x: 5.0, y: 0.0 }),
+                })
+                .await
+                .unwrap();
+            let hits = client
+                .hit_test(HitTestRequest {
+                    canvas,
+                    at: Some(Point { x: 5.0, y: 0.0 }),
+                })
+                .await
+                .unwrap();
+            assert_eq!(hits.into_inner().shapes, [3, 1]);
+            let triangle = AreaRequest {
+                canvas,
+                shape: Some(2),
+            };
+            let area = client.area(triangle).await.unwrap().into_inner().area;
+            assert_approx_eq!(area, 6.0);
+
+            // Errors get the codes the HTTP server's statuses stand for
+            let removed = ShapeRef { canvas, shape: 1 };
+            client.remove_shape(removed).await.unwrap();
+            let again = client.remove_shape(removed).await.unwrap_err();
+            assert_eq!(again.code(), Code::NotFound);
+            let render = Script {
+                canvas,
+                script: String::from("area\nrender out.png"),
+            };
+            let render = client.run(render).await.unwrap_err();
+            assert_eq!(render.code(), Code::PermissionDenied);
+            let negative = add((0.0, 0.0), Shape::Circle(Circle { radius: -1.0 }));
+            let negative = client.add_shape(negative).await.unwrap_err();
+            assert_eq!(negative.code(), Code::InvalidArgument);
+
+            // The stream has caught up, and ends with the canvas
+            let mut last = added;
+            client.delete_canvas(CanvasRef { canvas }).await.unwrap();
+            while let Some(next) = stats.next().await {
+                last = next.unwrap();
+            }
+            assert_eq!(last.shapes, 2);
+            assert_approx_eq!(last.area, 6.0 + std::f32::consts::PI);
+            let gone = client.list_shapes(CanvasRef { canvas }).await.unwrap_err();
+            assert_eq!(gone.code(), Code::NotFound);
+        });
+    }
+}
@@ -0,0 +1,915 @@
+// Generated by tonic-build 0.12 from proto/kaleidoscope.proto, as the top of
+// src/grpc.rs says. Edit the .proto and generate it again rather than this.
+// This file is @generated by prost-build.
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Empty {}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Point {
+    #[prost(float, tag = "1")]
+    pub x: f32,
+    #[prost(float, tag = "2")]
+    pub y: f32,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct CanvasRef {
+    #[prost(uint64, tag = "1")]
+    pub canvas: u64,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct ShapeRef {
+    #[prost(uint64, tag = "1")]
+    pub canvas: u64,
+    #[prost(uint64, tag = "2")]
+    pub shape: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Script {
+    #[prost(uint64, tag = "1")]
+    pub canvas: u64,
+    #[prost(string, tag = "2")]
+    pub script: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Output {
+    #[prost(string, tag = "1")]
+    pub output: ::prost::alloc::string::String,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Circle {
+    #[prost(float, tag = "1")]
+    pub radius: f32,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Rectangle {
+    #[prost(float, tag = "1")]
+    pub width: f32,
+    #[prost(float, tag = "2")]
+    pub height: f32,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Triangle {
+    #[prost(float, tag = "1")]
+    pub base: f32,
+    #[prost(float, tag = "2")]
+    pub height: f32,
+}
+/// Points relative to the origin
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Polygon {
+    #[prost(message, repeated, tag = "1")]
+    pub points: ::prost::alloc::vec::Vec<Point>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AddShapeRequest {
+    #[prost(uint64, tag = "1")]
+    pub canvas: u64,
+    #[prost(message, optional, tag = "2")]
+    pub origin: ::core::option::Option<Point>,
+    #[prost(oneof = "add_shape_request::Shape", tags = "3, 4, 5, 6")]
+    pub shape: ::core::option::Option<add_shape_request::Shape>,
+}
+/// Nested message and enum types in `AddShapeRequest`.
+pub mod add_shape_request {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Shape {
+        #[prost(message, tag = "3")]
+        Circle(super::Circle),
+        #[prost(message, tag = "4")]
+        Rectangle(super::Rectangle),
+        #[prost(message, tag = "5")]
+        Triangle(super::Triangle),
+        #[prost(message, tag = "6")]
+        Polygon(super::Polygon),
+    }
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct MoveShapeRequest {
+    #[prost(uint64, tag = "1")]
+    pub canvas: u64,
+    #[prost(uint64, tag = "2")]
+    pub shape: u64,
+    #[prost(message, optional, tag = "3")]
+    pub to: ::core::option::Option<Point>,
+}
+/// Of one shape, or of the whole canvas without one
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct AreaRequest {
+    #[prost(uint64, tag = "1")]
+    pub canvas: u64,
+    #[prost(uint64, optional, tag = "2")]
+    pub shape: ::core::option::Option<u64>,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct AreaReply {
+    #[prost(float, tag = "1")]
+    pub area: f32,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct HitTestRequest {
+    #[prost(uint64, tag = "1")]
+    pub canvas: u64,
+    #[prost(message, optional, tag = "2")]
+    pub at: ::core::option::Option<Point>,
+}
+/// Topmost first
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Hits {
+    #[prost(uint64, repeated, tag = "1")]
+    pub shapes: ::prost::alloc::vec::Vec<u64>,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Stats {
+    #[prost(uint64, tag = "1")]
+    pub shapes: u64,
+    #[prost(float, tag = "2")]
+    pub area: f32,
+    #[prost(uint64, tag = "3")]
+    pub adds: u64,
+    #[prost(uint64, tag = "4")]
+    pub removes: u64,
+    #[prost(uint64, tag = "5")]
+    pub moves: u64,
+    #[prost(uint64, tag = "6")]
+    pub hit_tests: u64,
+}
+/// Generated client implementations.
+pub mod canvases_client {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value
+    )]
+    use tonic::codegen::http::Uri;
+    use tonic::codegen::*;
+    #[derive(Debug, Clone)]
+    pub struct CanvasesClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl CanvasesClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> CanvasesClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + std::marker::Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + std::marker::Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> CanvasesClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<http::Request<tonic::body::BoxBody>>>::Error:
+                Into<StdError> + std::marker::Send + std::marker::Sync,
+        {
+            CanvasesClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_decoding_message_size(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_encoding_message_size(limit);
+            self
+        }
+        pub async fn create_canvas(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Empty>,
+        ) -> std::result::Result<tonic::Response<super::CanvasRef>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/kaleidoscope.Canvases/CreateCanvas");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("kaleidoscope.Canvases", "CreateCanvas"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn delete_canvas(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CanvasRef>,
+        ) -> std::result::Result<tonic::Response<super::Empty>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/kaleidoscope.Canvases/DeleteCanvas");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("kaleidoscope.Canvases", "DeleteCanvas"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Session commands, one per line, as a scene script
+        pub async fn run(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Script>,
+        ) -> std::result::Result<tonic::Response<super::Output>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/kaleidoscope.Canvases/Run");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("kaleidoscope.Canvases", "Run"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn add_shape(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AddShapeRequest>,
+        ) -> std::result::Result<tonic::Response<super::ShapeRef>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/kaleidoscope.Canvases/AddShape");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("kaleidoscope.Canvases", "AddShape"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn move_shape(
+            &mut self,
+            request: impl tonic::IntoRequest<super::MoveShapeRequest>,
+        ) -> std::result::Result<tonic::Response<super::Empty>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/kaleidoscope.Canvases/MoveShape");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("kaleidoscope.Canvases", "MoveShape"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn remove_shape(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ShapeRef>,
+        ) -> std::result::Result<tonic::Response<super::Empty>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/kaleidoscope.Canvases/RemoveShape");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("kaleidoscope.Canvases", "RemoveShape"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn list_shapes(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CanvasRef>,
+        ) -> std::result::Result<tonic::Response<super::Output>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/kaleidoscope.Canvases/ListShapes");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("kaleidoscope.Canvases", "ListShapes"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn area(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AreaRequest>,
+        ) -> std::result::Result<tonic::Response<super::AreaReply>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/kaleidoscope.Canvases/Area");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("kaleidoscope.Canvases", "Area"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn hit_test(
+            &mut self,
+            request: impl tonic::IntoRequest<super::HitTestRequest>,
+        ) -> std::result::Result<tonic::Response<super::Hits>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/kaleidoscope.Canvases/HitTest");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("kaleidoscope.Canvases", "HitTest"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// The canvas's stats now and again after every change, until it's deleted
+        pub async fn watch_stats(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CanvasRef>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::Stats>>,
+            tonic::Status,
+        > {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/kaleidoscope.Canvases/WatchStats");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("kaleidoscope.Canvases", "WatchStats"));
+            self.inner.server_streaming(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod canvases_server {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value
+    )]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with CanvasesServer.
+    #[async_trait]
+    pub trait Canvases: std::marker::Send + std::marker::Sync + 'static {
+        async fn create_canvas(
+            &self,
+            request: tonic::Request<super::Empty>,
+        ) -> std::result::Result<tonic::Response<super::CanvasRef>, tonic::Status>;
+        async fn delete_canvas(
+            &self,
+            request: tonic::Request<super::CanvasRef>,
+        ) -> std::result::Result<tonic::Response<super::Empty>, tonic::Status>;
+        /// Session commands, one per line, as a scene script
+        async fn run(
+            &self,
+            request: tonic::Request<super::Script>,
+        ) -> std::result::Result<tonic::Response<super::Output>, tonic::Status>;
+        async fn add_shape(
+            &self,
+            request: tonic::Request<super::AddShapeRequest>,
+        ) -> std::result::Result<tonic::Response<super::ShapeRef>, tonic::Status>;
+        async fn move_shape(
+            &self,
+            request: tonic::Request<super::MoveShapeRequest>,
+        ) -> std::result::Result<tonic::Response<super::Empty>, tonic::Status>;
+        async fn remove_shape(
+            &self,
+            request: tonic::Request<super::ShapeRef>,
+        ) -> std::result::Result<tonic::Response<super::Empty>, tonic::Status>;
+        async fn list_shapes(
+            &self,
+            request: tonic::Request<super::CanvasRef>,
+        ) -> std::result::Result<tonic::Response<super::Output>, tonic::Status>;
+        async fn area(
+            &self,
+            request: tonic::Request<super::AreaRequest>,
+        ) -> std::result::Result<tonic::Response<super::AreaReply>, tonic::Status>;
+        async fn hit_test(
+            &self,
+            request: tonic::Request<super::HitTestRequest>,
+        ) -> std::result::Result<tonic::Response<super::Hits>, tonic::Status>;
+        /// Server streaming response type for the WatchStats method.
+        type WatchStatsStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::Stats, tonic::Status>,
+            > + std::marker::Send
+            + 'static;
+        /// The canvas's stats now and again after every change, until it's deleted
+        async fn watch_stats(
+            &self,
+            request: tonic::Request<super::CanvasRef>,
+        ) -> std::result::Result<tonic::Response<Self::WatchStatsStream>, tonic::Status>;
+    }
+    #[derive(Debug)]
+    pub struct CanvasesServer<T> {
+        inner: Arc<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    impl<T> CanvasesServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(inner: T, interceptor: F) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for CanvasesServer<T>
+    where
+        T: Canvases,
+        B: Body + std::marker::Send + 'static,
+        B::Error: Into<StdError> + std::marker::Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            match req.uri().path() {
+                "/kaleidoscope.Canvases/CreateCanvas" => {
+                    #[allow(non_camel_case_types)]
+                    struct CreateCanvasSvc<T: Canvases>(pub Arc<T>);
+                    impl<T: Canvases> tonic::server::UnaryService<super::Empty> for CreateCanvasSvc<T> {
+                        type Response = super::CanvasRef;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Empty>) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Canvases>::create_canvas(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = CreateCanvasSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/kaleidoscope.Canvases/DeleteCanvas" => {
+                    #[allow(non_camel_case_types)]
+                    struct DeleteCanvasSvc<T: Canvases>(pub Arc<T>);
+                    impl<T: Canvases> tonic::server::UnaryService<super::CanvasRef> for DeleteCanvasSvc<T> {
+                        type Response = super::Empty;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CanvasRef>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Canvases>::delete_canvas(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = DeleteCanvasSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/kaleidoscope.Canvases/Run" => {
+                    #[allow(non_camel_case_types)]
+                    struct RunSvc<T: Canvases>(pub Arc<T>);
+                    impl<T: Canvases> tonic::server::UnaryService<super::Script> for RunSvc<T> {
+                        type Response = super::Output;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Script>) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move { <T as Canvases>::run(&inner, request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = RunSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/kaleidoscope.Canvases/AddShape" => {
+                    #[allow(non_camel_case_types)]
+                    struct AddShapeSvc<T: Canvases>(pub Arc<T>);
+                    impl<T: Canvases> tonic::server::UnaryService<super::AddShapeRequest> for AddShapeSvc<T> {
+                        type Response = super::ShapeRef;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AddShapeRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut =
+                                async move { <T as Canvases>::add_shape(&inner, request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = AddShapeSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/kaleidoscope.Canvases/MoveShape" => {
+                    #[allow(non_camel_case_types)]
+                    struct MoveShapeSvc<T: Canvases>(pub Arc<T>);
+                    impl<T: Canvases> tonic::server::UnaryService<super::MoveShapeRequest> for MoveShapeSvc<T> {
+                        type Response = super::Empty;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::MoveShapeRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut =
+                                async move { <T as Canvases>::move_shape(&inner, request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = MoveShapeSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/kaleidoscope.Canvases/RemoveShape" => {
+                    #[allow(non_camel_case_types)]
+                    struct RemoveShapeSvc<T: Canvases>(pub Arc<T>);
+                    impl<T: Canvases> tonic::server::UnaryService<super::ShapeRef> for RemoveShapeSvc<T> {
+                        type Response = super::Empty;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ShapeRef>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut =
+                                async move { <T as Canvases>::remove_shape(&inner, request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = RemoveShapeSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/kaleidoscope.Canvases/ListShapes" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListShapesSvc<T: Canvases>(pub Arc<T>);
+                    impl<T: Canvases> tonic::server::UnaryService<super::CanvasRef> for ListShapesSvc<T> {
+                        type Response = super::Output;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CanvasRef>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut =
+                                async move { <T as Canvases>::list_shapes(&inner, request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ListShapesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/kaleidoscope.Canvases/Area" => {
+                    #[allow(non_camel_case_types)]
+                    struct AreaSvc<T: Canvases>(pub Arc<T>);
+                    impl<T: Canvases> tonic::server::UnaryService<super::AreaRequest> for AreaSvc<T> {
+                        type Response = super::AreaReply;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AreaRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move { <T as Canvases>::area(&inner, request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = AreaSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/kaleidoscope.Canvases/HitTest" => {
+                    #[allow(non_camel_case_types)]
+                    struct HitTestSvc<T: Canvases>(pub Arc<T>);
+                    impl<T: Canvases> tonic::server::UnaryService<super::HitTestRequest> for HitTestSvc<T> {
+                        type Response = super::Hits;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::HitTestRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut =
+                                async move { <T as Canvases>::hit_test(&inner, request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = HitTestSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/kaleidoscope.Canvases/WatchStats" => {
+                    #[allow(non_camel_case_types)]
+                    struct WatchStatsSvc<T: Canvases>(pub Arc<T>);
+                    impl<T: Canvases> tonic::server::ServerStreamingService<super::CanvasRef> for WatchStatsSvc<T> {
+                        type Response = super::Stats;
+                        type ResponseStream = T::WatchStatsStream;
+                        type Future =
+                            BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CanvasRef>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut =
+                                async move { <T as Canvases>::watch_stats(&inner, request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = WatchStatsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => Box::pin(async move {
+                    let mut response = http::Response::new(empty_body());
+                    let headers = response.headers_mut();
+                    headers.insert(
+                        tonic::Status::GRPC_STATUS,
+                        (tonic::Code::Unimplemented as i32).into(),
+                    );
+                    headers.insert(
+                        http::header::CONTENT_TYPE,
+                        tonic::metadata::GRPC_CONTENT_TYPE,
+                    );
+                    Ok(response)
+                }),
+            }
+        }
+    }
+    impl<T> Clone for CanvasesServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    /// Generated gRPC service name
+    pub const SERVICE_NAME: &str = "kaleidoscope.Canvases";
+    impl<T> tonic::server::NamedService for CanvasesServer<T> {
+        const NAME: &'static str = SERVICE_NAME;
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{
+    builder::{coordinate, dimension},
+    error::CanvasError,
+    Canvas, Coord,
+};
+
+// Lines and points to line shapes up against. They're never drawn or
+// exported, and only matter to snapping and the guide queries.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Guide {
+    // The line at this x
+    Vertical(f32),
+    // The line at this y
+    Horizontal(f32),
+    Point(Coord),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GuideHit {
+    // Into guides()
+    pub index: usize,
+    // The nearest point on the guide
+    pub point: Coord,
+    pub distance: f32,
+}
+
+impl Guide {
+    pub fn nearest_point(&self, point: Coord) -> Coord {
+        match *self {
+            Guide::Vertical(x) => Coord::new(x, point.y),
+            Guide::Horizontal(y) => Coord::new(point.x, y),
+            Guide::Point(at) => at,
+        }
+    }
+}
+
+impl Canvas {
+    // Guides are numbered in the order they were added, and renumbered when
+    // one is removed
+    pub fn add_guide(&mut self, guide: Guide) -> Result<usize, CanvasError> {
+        coordinate("guide", guide.nearest_point(Coord::default()))?;
+        self.guides.push(guide);
+        Ok(self.guides.len() - 1)
+    }
+    pub fn guides(&self) -> &[Guide] {
+        &self.guides
+    }
+    pub fn remove_guide(&mut self, index: usize) -> Result<Guide, CanvasError> {
+        if index >= self.guides.len() {
+            return Err(CanvasError::UnknownIndex(index));
+        }
+        Ok(self.guides.remove(index))
+    }
+    pub fn clear_guides(&mut self) {
+        self.guides.clear();
+    }
+
+    // Shapes moved by set_origin or translate to within the distance of a
+    // guide are pulled onto it. None, the default, turns snapping off.
+    pub fn set_snap_distance(&mut self, distance: Option<f32>) -> Result<(), CanvasError> {
+        if let Some(distance) = distance {
+            dimension("snap distance", Some(distance))?;
+        }
+        self.snap_distance = distance;
+        Ok(())
+    }
+    pub fn snap_distance(&self) -> Option<f32> {
+        self.snap_distance
+    }
+
+    // The earliest added of the closest, if there are any guides
+    pub fn nearest_guide(&self, point: impl Into<Coord>) -> Option<GuideHit> {
+        let point = point.into();
+        self.guides
+            .iter()
+            .enumerate()
+            .map(|(index, guide)| {
+                let on = guide.nearest_point(point);
+                GuideHit {
+                    index,
+                    point: on,
+                    distance: on.distance(point),
+                }
+            })
+            .fold(None, |best: Option<GuideHit>, hit| match best {
+                Some(best) if best.distance <= hit.distance => Some(best),
+                _ => Some(hit),
+            })
+    }
+
+    // Where a point lands with snapping on: on the nearest guide point in
+    // reach, else with x and y each taken from the nearest line in reach
+    // that fixes them, so a point near two crossing lines lands where they
+    // cross
+    pub fn snap(&self, point: impl Into<Coord>) -> Coord {
+        let point = point.into();
+        let Some(reach) = self.snap_distance else {
+            return point;
+        };
+        let nearest = |keep: fn(&Guide) -> bool| {
+            self.guides
+                .iter()
+                .filter(|guide| keep(guide))
+                .map(|guide| guide.nearest_point(point))
+                .filter(|on| on.distance(point) <= reach)
+                .min_by(|a, b| a.distance(point).total_cmp(&b.distance(point)))
+        };
+        if let Some(on) = nearest(|guide| matches!(guide, Guide::Point(_))) {
+            return on;
+        }
+        Coord::new(
+            nearest(|guide| matches!(guide, Guide::Vertical(_))).map_or(point.x, |on| on.x),
+            nearest(|guide| matches!(guide, Guide::Horizontal(_))).map_or(point.y, |on| on.y),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{Circle, ShapeCell};
+
+    #[test]
+    fn shapes_snap_to_guides() {
+        let mut canvas = Canvas::default();
+        let id = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::default(),
+            radius: 1.0,
+        })));
+        assert_eq!(canvas.nearest_guide((1.0, 1.0)), None);
+        canvas.add_guide(Guide::Vertical(10.0)).unwrap();
+        canvas.add_guide(Guide::Horizontal(5.0)).unwrap();
+        let point = canvas
+            .add_guide(Guide::Point(Coord::new(20.0, 20.0)))
+            .unwrap();
+        assert!(canvas.add_guide(Guide::Horizontal(f32::NAN)).is_err());
+        assert_eq!(
+            canvas.nearest_guide((9.0, 0.0)),
+            Some(GuideHit {
+                index: 0,
+                point: Coord::new(10.0, 0.0),
+                distance: 1.0,
+            })
+        );
+
+        // Off until given a distance
+        canvas.set_origin(id, (9.5, 4.8)).unwrap();
+        assert_eq!(
+            canvas.get(id).unwrap().lock().unwrap().origin(),
+            Coord::new(9.5, 4.8)
+        );
+        canvas.set_snap_distance(Some(1.0)).unwrap();
+        assert!(canvas.set_snap_distance(Some(-1.0)).is_err());
+        canvas.set_origin(id, (9.5, 4.8)).unwrap();
+        assert_eq!(
+            canvas.get(id).unwrap().lock().unwrap().origin(),
+            Coord::new(10.0, 5.0)
+        );
+        // Only the line in reach
+        canvas.translate(id, (0.0, 3.0)).unwrap();
+        assert_eq!(
+            canvas.get(id).unwrap().lock().unwrap().origin(),
+            Coord::new(10.0, 8.0)
+        );
+        assert_eq!(canvas.snap((19.6, 20.5)), Coord::new(20.0, 20.0));
+        assert_eq!(canvas.snap((30.0, 30.0)), Coord::new(30.0, 30.0));
+
+        assert_eq!(
+            canvas.remove_guide(point).unwrap(),
+            Guide::Point(Coord::new(20.0, 20.0))
+        );
+        assert!(canvas.remove_guide(point).is_err());
+        canvas.clear_guides();
+        assert!(canvas.guides().is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{command::Session, error::CommandError};
+
+// Crash recovery for a session: a snapshot script at the given path, and
+// beside it a journal of every change made since, appended as each one
+// succeeds. Every so many changes the snapshot is rewritten and the journal
+// emptied. Both start with a generation number, and a journal whose number
+// doesn't match the snapshot's has already been folded into it, which is
+// what a crash between writing one and emptying the other leaves.
+pub struct Journal {
+    path: PathBuf,
+    log: File,
+    generation: u64,
+    snapshot_every: usize,
+    since_snapshot: usize,
+}
+
+impl Journal {
+    fn start(session: &Session, path: &Path, snapshot_every: usize) -> io::Result<Journal> {
+        let generation = read_generation(path, "snapshot").unwrap_or(0);
+        let mut journal = Journal {
+            path: path.to_path_buf(),
+            log: File::create(log_path(path))?,
+            generation,
+            snapshot_every: snapshot_every.max(1),
+            since_snapshot: 0,
+        };
+        journal.snapshot(session)?;
+        Ok(journal)
+    }
+
+    pub(crate) fn record(&mut self, session: &Session, line: &str) -> io::Result<()> {
+        event!(TRACE, line = line.trim(), "journaling");
+        // Unbuffered, so the line is with the OS before the command returns
+        writeln!(self.log, "{}", line.trim())?;
+        self.since_snapshot += 1;
+        if self.since_snapshot >= self.snapshot_every {
+            self.snapshot(session)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn snapshot(&mut self, session: &Session) -> io::Result<()> {
+        span!(DEBUG, "snapshot", generation = self.generation + 1);
+        let script = session
+            .snapshot()
+            .map_err(|error| io::Error::other(error.to_string()))?;
+        self.generation += 1;
+        // Written aside and renamed over, so there's always a whole one
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+        let mut file = File::create(&temporary)?;
+        write!(file, "# snapshot {}\n{script}", self.generation)?;
+        file.sync_all()?;
+        fs::rename(&temporary, &self.path)?;
+        self.log = File::create(log_path(&self.path))?;
+        writeln!(self.log, "# journal {}", self.generation)?;
+        self.log.sync_all()?;
+        self.since_snapshot = 0;
+        Ok(())
+    }
+}
+
+impl Session {
+    // Starts journaling to the path, replacing whatever is there with a
+    // snapshot of the session as it is
+    pub fn journal_to(
+        &mut self,
+        path: impl AsRef<Path>,
+        snapshot_every: usize,
+    ) -> Result<(), CommandError> {
+        self.journal = Some(Journal::start(self, path.as_ref(), snapshot_every)?);
+        Ok(())
+    }
+
+    // Rebuilds a journaled session from its snapshot and journal, then
+    // carries on journaling to them. A last journal line cut short by the
+    // crash is dropped.
+    pub fn recover(path: impl AsRef<Path>, snapshot_every: usize) -> Result<Session, CommandError> {
+        let path = path.as_ref();
+        let mut session = Session::new();
+        session.run(&fs::read_to_string(path)?)?;
+        let log = fs::read_to_string(log_path(path)).unwrap_or_default();
+        if read_generation(path, "snapshot") == generation(&log, "journal") {
+            let whole = match log.rfind('\n') {
+                Some(end) => &log[..end],
+                None => "",
+            };
+            session.run(whole)?;
+        }
+        session.journal_to(path, snapshot_every)?;
+        Ok(session)
+    }
+}
+
+fn log_path(path: &Path) -> PathBuf {
+    let mut log = OsString::from(path);
+    log.push(".journal");
+    PathBuf::from(log)
+}
+
+fn read_generation(path: &Path, kind: &str) -> Option<u64> {
+    generation(&fs::read_to_string(path).ok()?, kind)
+}
+fn generation(text: &str, kind: &str) -> Option<u64> {
+    let header = text.lines().next()?.strip_prefix("# ")?;
+    header.strip_prefix(kind)?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs::OpenOptions};
+
+    use super::*;
+
+    #[test]
+    fn recover_after_crash() {
+        let directory =
+            env::temp_dir().join(format!("kaleidoscope-journal-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("session.kscript");
+        let list = |session: &mut Session| session.execute("list").unwrap();
+
+        let mut session = Session::new();
+        session.execute("add circle 1 at 0 0").unwrap();
+        session.journal_to(&path, 3).unwrap();
+        for line in [
+            "add circle 2 at 5 5",
+            "remove 1",
+            "area",
+            "symmetry cyclic 2",
+            "add triangle 1 1 at 3 0",
+            "add rectangle 0 1 at 9 9",
+            "add rectangle 1 1 at 9 9",
+        ] {
+            let _ = session.execute(line);
+        }
+        // Snapshotted after the third change, with the two that worked since
+        let log = fs::read_to_string(log_path(&path)).unwrap();
+        assert_eq!(
+            log,
+            "# journal 2\nadd triangle 1 1 at 3 0\nadd rectangle 1 1 at 9 9\n"
+        );
+        let before = list(&mut session);
+        drop(session);
+
+        let mut recovered = Session::recover(&path, 3).unwrap();
+        assert_eq!(list(&mut recovered), before);
+        // The circle left, and the last two shapes with their copies
+        assert_eq!(recovered.canvas.len(), 5);
+        // Numbers are kept, so later commands mean the same after recovery
+        recovered.execute("move 2 to 0 0").unwrap();
+        assert!(recovered.shape(1).is_none());
+
+        // A torn last line, and a stale journal left by a crash mid snapshot
+        let mut log = OpenOptions::new()
+            .append(true)
+            .open(log_path(&path))
+            .unwrap();
+        write!(log, "move 2 to 7").unwrap();
+        let mut recovered = Session::recover(&path, 3).unwrap();
+        assert!(list(&mut recovered).starts_with("2 circle at 0 0,"));
+        fs::write(log_path(&path), "# journal 1\nremove 2\n").unwrap();
+        let recovered = Session::recover(&path, 3).unwrap();
+        assert!(recovered.shape(2).is_some());
+
+        // Tags, hiding and locks since the snapshot are journaled too
+        let mut session = Session::recover(&path, 100).unwrap();
+        for line in [
+            "group at 0 0",
+            "tag 2 motif",
+            "hide 2",
+            "hide group 1",
+            "lock 2",
+        ] {
+            session.execute(line).unwrap();
+        }
+        drop(session);
+        let recovered = Session::recover(&path, 100).unwrap();
+        let id = recovered.shape(2).unwrap();
+        assert_eq!(recovered.canvas.find_by_tag("motif"), [id]);
+        assert!(!recovered.canvas.is_visible(id));
+        assert!(!recovered.canvas.group_visible(0).unwrap());
+        assert!(recovered.canvas.is_locked(id));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,407 @@
+use alloc::{vec, vec::Vec};
+
+#[cfg(not(any(feature = "std", test)))]
+use crate::math::Float;
+use crate::{Circle, Coord, Rectangle};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PackStrategy {
+    // Rows of items sorted by height, fast but leaves gaps above short items
+    Shelf,
+    // Best short side fit over the maximal free rectangles, tighter but slower
+    MaxRects,
+}
+
+// What circles are packed into
+#[derive(Clone, Debug, PartialEq)]
+pub enum Container {
+    Circle(Circle),
+    Rectangle(Rectangle),
+}
+
+impl Container {
+    // Whether a circle of the given radius fits with its centre there
+    fn holds(&self, center: Coord, radius: f32) -> bool {
+        const SLACK: f32 = 1e-4;
+        match self {
+            Container::Circle(circle) => {
+                center.distance(circle.origin) + radius <= circle.radius + SLACK
+            }
+            Container::Rectangle(rectangle) => {
+                let (min, max) = (rectangle.min(), rectangle.max());
+                center.x - radius >= min.x - SLACK
+                    && center.y - radius >= min.y - SLACK
+                    && center.x + radius <= max.x + SLACK
+                    && center.y + radius <= max.y + SLACK
+            }
+        }
+    }
+    fn center(&self) -> Coord {
+        match self {
+            Container::Circle(circle) => circle.origin,
+            Container::Rectangle(rectangle) => rectangle.origin,
+        }
+    }
+}
+
+// Free space for the max rects packer, by min corner and size
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Space {
+    min: Coord,
+    size: Coord,
+}
+
+impl Space {
+    fn max(&self) -> Coord {
+        self.min + self.size
+    }
+    fn contains(&self, other: &Space) -> bool {
+        other.min.x >= self.min.x
+            && other.min.y >= self.min.y
+            && other.max().x <= self.max().x
+            && other.max().y <= self.max().y
+    }
+    fn overlaps(&self, other: &Space) -> bool {
+        self.min.x < other.max().x
+            && other.min.x < self.max().x
+            && self.min.y < other.max().y
+            && other.min.y < self.max().y
+    }
+}
+
+// Min corner, relative to the frame's min corner, for each item of the given
+// size, or None where it doesn't fit
+pub fn pack(sizes: &[Coord], frame: Coord, strategy: PackStrategy) -> Vec<Option<Coord>> {
+    match strategy {
+        PackStrategy::Shelf => shelf(sizes, frame),
+        PackStrategy::MaxRects => max_rects(sizes, frame),
+    }
+}
+
+// Where each item's anchor point goes in a grid with the given number of
+// columns, filled row by row along increasing y, relative to the grid's min
+// corner. Items are given by how far they reach below and above their
+// anchor on both axes. The anchors sit on a regular lattice, with every cell
+// big enough for any item and the spacing between cells.
+pub fn grid(reaches: &[(Coord, Coord)], columns: usize, spacing: f32) -> Vec<Coord> {
+    let columns = columns.max(1);
+    let (mut below, mut above) = (Coord::default(), Coord::default());
+    for (low, high) in reaches {
+        below = Coord::new(below.x.max(low.x), below.y.max(low.y));
+        above = Coord::new(above.x.max(high.x), above.y.max(high.y));
+    }
+    let pitch = below + above + Coord::new(spacing, spacing);
+    (0..reaches.len())
+        .map(|i| {
+            let (row, column) = (i / columns, i % columns);
+            below + Coord::new(column as f32 * pitch.x, row as f32 * pitch.y)
+        })
+        .collect()
+}
+
+// Centres for circles of the given radii packed without overlapping inside
+// the container, or None for those that don't fit. Largest first, each goes
+// in the position nearest the container's centre out of those touching two
+// circles already placed, or a circle and the container's edge, or two
+// edges of a rectangle. That keeps the packing tight and roughly round about the centre.
+pub fn pack_circles(radii: &[f32], container: &Container) -> Vec<Option<Coord>> {
+    let mut order: Vec<usize> = (0..radii.len()).collect();
+    order.sort_by(|&a, &b| radii[b].total_cmp(&radii[a]));
+
+    let center = container.center();
+    let mut placed: Vec<(Coord, f32)> = Vec::new();
+    let mut centers = vec![None; radii.len()];
+    for index in order {
+        let radius = radii[index];
+        if !(radius >= 0.0 && radius.is_finite()) {
+            continue;
+        }
+        let mut candidates = vec![center];
+        candidates.extend(edge_positions(container, radius));
+        for (i, &(a, ra)) in placed.iter().enumerate() {
+            // Beside it, for when it's alone in the middle of a round
+            // container and touches the edge everywhere or nowhere
+            candidates.push(a + Coord::new(ra + radius, 0.0));
+            candidates.extend(edge_touching(container, a, ra + radius, radius));
+            for &(b, rb) in &placed[i + 1..] {
+                candidates.extend(circle_intersections(a, ra + radius, b, rb + radius));
+            }
+        }
+        let free = |point: &Coord| {
+            container.holds(*point, radius)
+                && placed
+                    .iter()
+                    .all(|&(other, r)| point.distance(other) >= r + radius - 1e-4)
+        };
+        let best = candidates
+            .into_iter()
+            .filter(free)
+            .min_by(|a, b| a.distance(center).total_cmp(&b.distance(center)));
+        if let Some(best) = best {
+            placed.push((best, radius));
+            centers[index] = Some(best);
+        }
+    }
+    centers
+}
+
+// Where a circle of the given radius touches two edges of a rectangle, in
+// its corners
+fn edge_positions(container: &Container, radius: f32) -> Vec<Coord> {
+    let Container::Rectangle(rectangle) = container else {
+        return Vec::new();
+    };
+    let (min, max) = (rectangle.min(), rectangle.max());
+    let (low, high) = (
+        min + Coord::new(radius, radius),
+        max - Coord::new(radius, radius),
+    );
+    vec![
+        low,
+        Coord::new(high.x, low.y),
+        high,
+        Coord::new(low.x, high.y),
+    ]
+}
+
+// Where a circle of the given radius touches the container's edge with its
+// centre the given distance from a point
+fn edge_touching(container: &Container, point: Coord, distance: f32, radius: f32) -> Vec<Coord> {
+    match container {
+        Container::Circle(circle) => {
+            circle_intersections(circle.origin, circle.radius - radius, point, distance)
+        }
+        Container::Rectangle(rectangle) => {
+            let (min, max) = (rectangle.min(), rectangle.max());
+            let mut points = Vec::new();
+            for x in [min.x + radius, max.x - radius] {
+                let dx = x - point.x;
+                let dy = (distance * distance - dx * dx).sqrt();
+                if dy.is_finite() {
+                    points.extend([Coord::new(x, point.y - dy), Coord::new(x, point.y + dy)]);
+                }
+            }
+            for y in [min.y + radius, max.y - radius] {
+                let dy = y - point.y;
+                let dx = (distance * distance - dy * dy).sqrt();
+                if dx.is_finite() {
+                    points.extend([Coord::new(point.x - dx, y), Coord::new(point.x + dx, y)]);
+                }
+            }
+            points
+        }
+    }
+}
+
+// Points at the given distances from both centres
+fn circle_intersections(a: Coord, ra: f32, b: Coord, rb: f32) -> Vec<Coord> {
+    let d = a.distance(b);
+    if d == 0.0 || d > ra + rb || d < (ra - rb).abs() {
+        return Vec::new();
+    }
+    let along = (d * d + ra * ra - rb * rb) / (2.0 * d);
+    let across = (ra * ra - along * along).max(0.0).sqrt();
+    let unit = (b - a) * (1.0 / d);
+    let middle = a + unit * along;
+    let normal = Coord::new(-unit.y, unit.x) * across;
+    vec![middle + normal, middle - normal]
+}
+
+fn shelf(sizes: &[Coord], frame: Coord) -> Vec<Option<Coord>> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by(|&a, &b| sizes[b].y.total_cmp(&sizes[a].y));
+
+    let mut placed = vec![None; sizes.len()];
+    let (mut x, mut y, mut shelf_height) = (0.0, 0.0, 0.0);
+    for index in order {
+        let size = sizes[index];
+        if x + size.x > frame.x {
+            // Start a new shelf above the tallest item on this one
+            y += shelf_height;
+            x = 0.0;
+            shelf_height = 0.0;
+        }
+        if x + size.x > frame.x || y + size.y > frame.y {
+            continue;
+        }
+        placed[index] = Some(Coord::new(x, y));
+        x += size.x;
+        shelf_height = f32::max(shelf_height, size.y);
+    }
+    placed
+}
+
+fn max_rects(sizes: &[Coord], frame: Coord) -> Vec<Option<Coord>> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by(|&a, &b| (sizes[b].x * sizes[b].y).total_cmp(&(sizes[a].x * sizes[a].y)));
+
+    let mut free = vec![Space {
+        min: Coord::default(),
+        size: frame,
+    }];
+    let mut placed = vec![None; sizes.len()];
+    for index in order {
+        let size = sizes[index];
+        let best = free
+            .iter()
+            .filter(|space| size.x <= space.size.x && size.y <= space.size.y)
+            .min_by(|a, b| {
+                let leftover = |s: &Space| (s.size.x - size.x).min(s.size.y - size.y);
+                leftover(a).total_cmp(&leftover(b))
+            });
+        let Some(best) = best.copied() else {
+            continue;
+        };
+        let used = Space {
+            min: best.min,
+            size,
+        };
+        placed[index] = Some(used.min);
+
+        // Carve the used space out of every free space it overlaps, keeping
+        // the maximal remainders on each side
+        let mut remaining = Vec::with_capacity(free.len() + 4);
+        for space in free {
+            if !space.overlaps(&used) {
+                remaining.push(space);
+                continue;
+            }
+            let (min, max) = (space.min, space.max());
+            let (used_min, used_max) = (used.min, used.max());
+            let sides = [
+                (min, Coord::new(used_min.x - min.x, space.size.y)),
+                (
+                    Coord::new(used_max.x, min.y),
+                    Coord::new(max.x - used_max.x, space.size.y),
+                ),
+                (min, Coord::new(space.size.x, used_min.y - min.y)),
+                (
+                    Coord::new(min.x, used_max.y),
+                    Coord::new(space.size.x, max.y - used_max.y),
+                ),
+            ];
+            remaining.extend(
+                sides
+                    .into_iter()
+                    .filter(|(_, size)| size.x > 0.0 && size.y > 0.0)
+                    .map(|(min, size)| Space { min, size }),
+            );
+        }
+        free = remaining
+            .iter()
+            .enumerate()
+            .filter(|(i, space)| {
+                !remaining.iter().enumerate().any(|(j, other)| {
+                    *i != j && other.contains(space) && (other != *space || j < *i)
+                })
+            })
+            .map(|(_, space)| *space)
+            .collect();
+    }
+    placed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_no_overlaps(sizes: &[Coord], placed: &[Option<Coord>], frame: Coord) {
+        let spaces: Vec<Space> = placed
+            .iter()
+            .zip(sizes)
+            .filter_map(|(min, size)| min.map(|min| Space { min, size: *size }))
+            .collect();
+        let frame = Space {
+            min: Coord::default(),
+            size: frame,
+        };
+        for (i, a) in spaces.iter().enumerate() {
+            assert!(frame.contains(a));
+            assert!(spaces[i + 1..].iter().all(|b| !a.overlaps(b)));
+        }
+    }
+
+    #[test]
+    fn pack_into_frame() {
+        let sizes = [
+            Coord::new(2.0, 2.0),
+            Coord::new(2.0, 1.0),
+            Coord::new(1.0, 1.0),
+            Coord::new(1.0, 1.0),
+            Coord::new(4.0, 1.0),
+            Coord::new(5.0, 5.0),
+        ];
+        let frame = Coord::new(4.0, 4.0);
+
+        for strategy in [PackStrategy::Shelf, PackStrategy::MaxRects] {
+            let placed = pack(&sizes, frame, strategy);
+            assert_no_overlaps(&sizes, &placed, frame);
+            // Everything but the item bigger than the frame fits
+            assert_eq!(placed.iter().filter(|p| p.is_some()).count(), 5);
+            assert_eq!(placed[5], None);
+        }
+    }
+    #[test]
+    fn grid_cells() {
+        let reaches = [
+            (Coord::new(1.0, 1.0), Coord::new(1.0, 1.0)),
+            (Coord::new(3.0, 0.5), Coord::new(1.0, 2.0)),
+            (Coord::new(0.5, 0.5), Coord::new(0.5, 0.5)),
+        ];
+        // Cells 4 wide and 3 tall, with 1 between
+        assert_eq!(
+            grid(&reaches, 2, 1.0),
+            [
+                Coord::new(3.0, 1.0),
+                Coord::new(8.0, 1.0),
+                Coord::new(3.0, 5.0)
+            ]
+        );
+        assert_eq!(grid(&reaches[..1], 0, 0.0), [Coord::new(1.0, 1.0)]);
+    }
+
+    #[test]
+    fn circles_packed_without_overlaps() {
+        let check = |radii: &[f32], container: &Container, placed: &[Option<Coord>]| {
+            let circles: Vec<(Coord, f32)> = placed
+                .iter()
+                .zip(radii)
+                .filter_map(|(center, &radius)| Some(((*center)?, radius)))
+                .collect();
+            for (i, &(a, ra)) in circles.iter().enumerate() {
+                assert!(container.holds(a, ra));
+                for &(b, rb) in &circles[i + 1..] {
+                    assert!(a.distance(b) >= ra + rb - 1e-3);
+                }
+            }
+            circles
+        };
+
+        // Seven equal circles fill a circle three times as wide exactly, in
+        // a hexagon around the middle one
+        let round = Container::Circle(Circle {
+            origin: Coord::new(5.0, 5.0),
+            radius: 3.0,
+        });
+        let placed = pack_circles(&[1.0; 8], &round);
+        check(&[1.0; 8], &round, &placed);
+        assert_eq!(placed.iter().filter(|p| p.is_some()).count(), 7);
+        assert_eq!(placed[0], Some(Coord::new(5.0, 5.0)));
+
+        let radii: Vec<f32> = (0..40).map(|i| 0.5 + (i * 7 % 11) as f32 / 5.0).collect();
+        let frame = Container::Rectangle(Rectangle {
+            origin: Coord::default(),
+            side_a: 30.0,
+            side_b: 20.0,
+        });
+        let placed = pack_circles(&radii, &frame);
+        let circles = check(&radii, &frame, &placed);
+        // Tightly, so most of them fit
+        let covered: f32 = circles
+            .iter()
+            .map(|(_, r)| core::f32::consts::PI * r * r)
+            .sum();
+        assert!(covered > 0.5 * 600.0, "{covered}");
+        assert!(pack_circles(&[11.0], &frame)[0].is_none());
+    }
+}
@@ -0,0 +1,141 @@
+// Shapes, a canvas to arrange them on, and the geometry and rendering
+// built on top. The binary in main.rs is a small demo of the canvas API.
+//
+// Without the std feature only the geometry core is built: Coord, the shape
+// types, builders and transforms, and the pure geometry, layout and mesh
+// functions, needing just an allocator.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+// First, so the macros in it are there for every module after. Only std
+// code is traced.
+#[cfg(feature = "std")]
+#[macro_use]
+mod trace;
+
+#[cfg(feature = "std")]
+pub mod animation;
+pub mod approx;
+pub mod arena;
+#[cfg(feature = "bevy")]
+pub mod bevy_plugin;
+pub mod builder;
+#[cfg(feature = "std")]
+mod cache;
+pub mod camera;
+#[cfg(feature = "std")]
+pub mod canvas;
+#[cfg(feature = "std")]
+pub mod cell;
+#[cfg(feature = "std")]
+pub mod chunked;
+#[cfg(feature = "std")]
+pub mod clipboard;
+#[cfg(feature = "std")]
+pub mod command;
+#[cfg(feature = "std")]
+pub mod constraint;
+#[cfg(feature = "std")]
+pub mod document;
+pub mod error;
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod fragment;
+#[cfg(any(all(test, feature = "std"), feature = "testutil"))]
+pub mod fuzz;
+#[cfg(feature = "std")]
+pub mod generator;
+#[cfg(feature = "geo")]
+mod geo_interop;
+pub mod geometry;
+#[cfg(feature = "glam")]
+mod glam_interop;
+#[cfg(all(test, feature = "render"))]
+mod golden;
+#[cfg(feature = "std")]
+pub mod group;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "std")]
+pub mod guide;
+#[cfg(feature = "std")]
+pub mod journal;
+pub mod layout;
+#[cfg(feature = "lyon")]
+mod lyon_interop;
+#[cfg(feature = "std")]
+pub mod macros;
+// Used by builds without std, and tested against std in builds with it
+#[cfg(any(not(any(feature = "std", test)), all(feature = "std", test)))]
+mod math;
+#[cfg(feature = "std")]
+pub mod memory;
+pub mod mesh;
+#[cfg(feature = "std")]
+pub mod metrics;
+#[cfg(feature = "nalgebra")]
+mod nalgebra_interop;
+#[cfg(feature = "std")]
+pub mod ops;
+pub mod palette;
+#[cfg(feature = "std")]
+pub mod param;
+#[cfg(feature = "std")]
+pub mod physics;
+#[cfg(feature = "std")]
+pub mod pivot;
+#[cfg(feature = "std")]
+pub mod prelude;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "std")]
+pub mod registry;
+#[cfg(feature = "render")]
+pub mod render;
+#[cfg(feature = "std")]
+pub mod replica;
+#[cfg(feature = "std")]
+pub mod report;
+#[cfg(feature = "rhai")]
+mod scripting;
+#[cfg(feature = "std")]
+pub mod server;
+pub mod shapes;
+#[cfg(feature = "simd")]
+mod simd;
+#[cfg(feature = "std")]
+pub mod snapshots;
+#[cfg(feature = "spatial")]
+pub mod spatial;
+#[cfg(feature = "std")]
+pub mod storage;
+pub mod stroke;
+pub mod style;
+#[cfg(feature = "std")]
+pub mod symmetry;
+#[cfg(any(all(test, feature = "std"), feature = "testutil"))]
+pub mod testutil;
+#[cfg(feature = "std")]
+pub mod tiling;
+pub mod transform;
+pub mod units;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use arena::ShapeId;
+#[cfg(feature = "std")]
+pub use canvas::{Canvas, DrawCommand, RayHit};
+#[cfg(feature = "std")]
+pub use cell::{History, ShapeCell, ShapeGuard, ShapeObject};
+#[cfg(feature = "std")]
+pub use error::CanvasError;
+pub use error::ShapeError;
+pub use shapes::{
+    Circle, Coord, Ellipse, Polygon, Rectangle, SecondMoments, Shape, Square, Triangle,
+};
+pub use style::{Color, Dash, LineCap, LineJoin, Paint, Style};
+pub use transform::{Transform, Transformed};
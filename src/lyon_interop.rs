@@ -0,0 +1,146 @@
+// Meshes from lyon's tessellators. Circles go to lyon as circles, so how
+// finely their edges are cut follows the tolerance, the furthest the mesh
+// may stray from the true outline, rather than the fixed sampling of
+// Mesh::fill and Mesh::stroke. Triangles come out counter-clockwise as
+// theirs do.
+use lyon_tessellation::{
+    geom::point,
+    path::{Path, Polygon, Winding},
+    BuffersBuilder, FillOptions, FillTessellator, FillVertex, LineJoin, StrokeOptions,
+    StrokeTessellator, StrokeVertex, VertexBuffers,
+};
+
+use crate::{
+    builder::dimension,
+    error::CanvasError,
+    geometry::Outline,
+    mesh::{Mesh, MITER_LIMIT},
+    Canvas, Coord,
+};
+
+fn path(outline: &Outline) -> Path {
+    let mut builder = Path::builder();
+    match outline {
+        Outline::Circle { center, radius } => {
+            builder.add_circle(point(center.x, center.y), *radius, Winding::Positive)
+        }
+        Outline::Polygon(points) => {
+            let points: Vec<_> = points.iter().map(|p| point(p.x, p.y)).collect();
+            builder.add_polygon(Polygon {
+                points: &points,
+                closed: true,
+            });
+        }
+    }
+    builder.build()
+}
+
+// lyon doesn't promise an orientation, so any clockwise triangle is flipped
+fn counter_clockwise(buffers: VertexBuffers<[f32; 2], u32>) -> Mesh {
+    let mut mesh = Mesh {
+        vertices: buffers.vertices,
+        indices: buffers.indices,
+    };
+    for triangle in mesh.indices.chunks_mut(3) {
+        let [a, b, c] = [0, 1, 2].map(|k| Coord::from(mesh.vertices[triangle[k] as usize]));
+        if (b - a).cross(c - a) < 0.0 {
+            triangle.swap(1, 2);
+        }
+    }
+    mesh
+}
+
+impl Mesh {
+    pub fn tessellate_fill(outline: &Outline, tolerance: f32) -> Result<Mesh, CanvasError> {
+        let tolerance = dimension("tolerance", Some(tolerance))?;
+        let mut buffers = VertexBuffers::new();
+        FillTessellator::new().tessellate_path(
+            &path(outline),
+            &FillOptions::tolerance(tolerance),
+            &mut BuffersBuilder::new(&mut buffers, |vertex: FillVertex| {
+                vertex.position().to_array()
+            }),
+        )?;
+        Ok(counter_clockwise(buffers))
+    }
+    // Centred on the outline with mitred corners, as Mesh::stroke is
+    pub fn tessellate_stroke(
+        outline: &Outline,
+        width: f32,
+        tolerance: f32,
+    ) -> Result<Mesh, CanvasError> {
+        let width = dimension("stroke width", Some(width))?;
+        let tolerance = dimension("tolerance", Some(tolerance))?;
+        let options = StrokeOptions::tolerance(tolerance)
+            .with_line_width(width)
+            .with_line_join(LineJoin::MiterClip)
+            .with_miter_limit(MITER_LIMIT);
+        let mut buffers = VertexBuffers::new();
+        StrokeTessellator::new().tessellate_path(
+            &path(outline),
+            &options,
+            &mut BuffersBuilder::new(&mut buffers, |vertex: StrokeVertex| {
+                vertex.position().to_array()
+            }),
+        )?;
+        Ok(counter_clockwise(buffers))
+    }
+}
+
+impl Canvas {
+    // fill_mesh and stroke_mesh through lyon, skipping the same shapes
+    pub fn tessellate_fill(&self, tolerance: f32) -> Result<Mesh, CanvasError> {
+        self.tessellate(|outline| Mesh::tessellate_fill(outline, tolerance))
+    }
+    pub fn tessellate_stroke(&self, width: f32, tolerance: f32) -> Result<Mesh, CanvasError> {
+        self.tessellate(|outline| Mesh::tessellate_stroke(outline, width, tolerance))
+    }
+    fn tessellate(
+        &self,
+        build: impl Fn(&Outline) -> Result<Mesh, CanvasError>,
+    ) -> Result<Mesh, CanvasError> {
+        let mut mesh = Mesh::default();
+        for command in self.draw_commands() {
+            mesh.extend(&build(&command.outline)?);
+        }
+        Ok(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f32::consts::PI, sync::Arc};
+
+    use super::*;
+    use crate::{assert_approx_eq, Circle, Rectangle, Shape, ShapeCell};
+
+    #[test]
+    fn fill_and_stroke_through_lyon() {
+        let square = Rectangle {
+            origin: Coord::default(),
+            side_a: 4.0,
+            side_b: 4.0,
+        };
+        let fill = Mesh::tessellate_fill(&square.outline(), 0.01).unwrap();
+        assert_approx_eq!(fill.area(), 16.0);
+        let stroke = Mesh::tessellate_stroke(&square.outline(), 2.0, 0.01).unwrap();
+        assert_approx_eq!(stroke.area(), 32.0);
+
+        // Finer with a smaller tolerance, and within it of the true area
+        let circle = Circle {
+            origin: Coord::new(9.0, 0.0),
+            radius: 1.0,
+        };
+        let coarse = Mesh::tessellate_fill(&circle.outline(), 0.1).unwrap();
+        let fine = Mesh::tessellate_fill(&circle.outline(), 0.001).unwrap();
+        assert!(coarse.vertices.len() < fine.vertices.len());
+        assert!((fine.area() - PI).abs() < 2.0 * PI * 0.001);
+
+        let mut canvas = Canvas::default();
+        canvas.add(Arc::new(ShapeCell::new(square)));
+        canvas.add(Arc::new(ShapeCell::new(circle)));
+        let both = canvas.tessellate_fill(0.001).unwrap();
+        assert!((both.area() - 16.0 - PI).abs() < 0.01);
+        assert!(canvas.tessellate_stroke(1.0, 0.0).is_err());
+    }
+}
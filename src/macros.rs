@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use crate::{command::Session, error::CommandError};
+
+// Command lines recorded from a session, to be run again later. Shapes the
+// macro added are renumbered on replay to those it adds then, and shapes it
+// only used are its parameters, which a replay can swap for others. Group
+// numbers are replayed as they were.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Macro {
+    pub lines: Vec<String>,
+    // Numbers of shapes from before the recording, in the order first used
+    pub params: Vec<usize>,
+    // The number the first shape added while recording got
+    first: usize,
+}
+
+// Commands whose second word is a shape number
+const NUMBERED: [&str; 10] = [
+    "move", "remove", "meta", "tag", "hide", "show", "lock", "unlock", "area", "prop",
+];
+
+// The position of the shape number in the line's words, if it has one
+fn number_at(words: &[&str]) -> Option<usize> {
+    match words {
+        [command, n, ..] if NUMBERED.contains(command) && n.parse::<usize>().is_ok() => Some(1),
+        _ => None,
+    }
+}
+
+impl Macro {
+    fn push(&mut self, line: &str) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if let Some(at) = number_at(&words) {
+            let number = words[at].parse().unwrap();
+            if number < self.first && !self.params.contains(&number) {
+                self.params.push(number);
+            }
+        }
+        self.lines.push(line.trim().into());
+    }
+
+    // The lines for a replay whose first added shape will get the number
+    // given, with the parameters swapped for args or, with none, left be
+    fn lines_for(&self, first: usize, args: &[usize]) -> Vec<String> {
+        self.lines
+            .iter()
+            .map(|line| {
+                let mut words: Vec<String> = line.split_whitespace().map(String::from).collect();
+                let borrowed: Vec<&str> = words.iter().map(String::as_str).collect();
+                if let Some(at) = number_at(&borrowed) {
+                    let number: usize = words[at].parse().unwrap();
+                    let number = match self.params.iter().position(|&p| p == number) {
+                        Some(i) if !args.is_empty() => args[i],
+                        Some(_) => number,
+                        None => number - self.first + first,
+                    };
+                    words[at] = number.to_string();
+                }
+                words.join(" ")
+            })
+            .collect()
+    }
+}
+
+impl Session {
+    // Starts recording the commands run from now on under the name, dropping
+    // any recording still going. Queries are recorded along with changes;
+    // comments and the commands below aren't.
+    pub fn start_recording(&mut self, name: impl Into<String>) {
+        let first = self.next_number();
+        self.recording = Some((
+            name.into(),
+            Macro {
+                first,
+                ..Macro::default()
+            },
+        ));
+    }
+    // Keeps the macro recorded, replacing any of the same name, and returns
+    // its name
+    pub fn stop_recording(&mut self) -> Option<String> {
+        let (name, recorded) = self.recording.take()?;
+        self.macros.insert(name.clone(), recorded);
+        Some(name)
+    }
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+    pub(crate) fn record_line(&mut self, line: &str) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if let (Some((_, recording)), [first, ..]) = (&mut self.recording, &words[..]) {
+            if !first.starts_with('#') && !matches!(*first, "record" | "stop" | "replay") {
+                recording.push(line);
+            }
+        }
+    }
+
+    pub fn macros(&self) -> &BTreeMap<String, Macro> {
+        &self.macros
+    }
+    // Runs the macro again, with args, if given, standing in for its params
+    // one for one. Outputs are joined as run joins them, and errors give the
+    // line of the macro they came from.
+    pub fn replay(&mut self, name: &str, args: &[usize]) -> Result<String, CommandError> {
+        let recorded = self
+            .macros
+            .get(name)
+            .ok_or_else(|| CommandError::UnknownCommand(format!("replay {name}")))?;
+        if !args.is_empty() && args.len() != recorded.params.len() {
+            return Err(CommandError::Usage(
+                "replay <name> [<n>...], one n per parameter",
+            ));
+        }
+        let lines = recorded.lines_for(self.next_number(), args);
+        self.run(&lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_and_replay() {
+        let mut session = Session::new();
+        session.execute("add circle 1 at 0 0").unwrap();
+        session.execute("add circle 1 at 10 0").unwrap();
+
+        session.execute("record petal").unwrap();
+        session.execute("add triangle 2 1 at 0 0").unwrap();
+        session.execute("move 3 to 0 5").unwrap();
+        session.execute("tag 1 centre").unwrap();
+        session.execute("# not kept").unwrap();
+        assert_eq!(session.execute("stop").unwrap(), "recorded petal");
+        assert_eq!(session.macros()["petal"].params, [1]);
+
+        // The triangle it adds is the one moved, and shape 2 stands in for 1
+        assert_eq!(session.execute("replay petal 2").unwrap(), "added shape 4");
+        assert_eq!(session.execute("list").unwrap().lines().count(), 4);
+        let origin = |session: &Session, n| {
+            let id = session.shape(n).unwrap();
+            session.canvas.get(id).unwrap().lock().unwrap().origin()
+        };
+        assert_eq!(origin(&session, 4).y, 5.0);
+        assert!(session.canvas.has_tag(session.shape(2).unwrap(), "centre"));
+
+        // Without args the same shapes are used
+        session.replay("petal", &[]).unwrap();
+        assert_eq!(origin(&session, 5).y, 5.0);
+
+        assert!(matches!(
+            session.execute("replay petal 1 2"),
+            Err(CommandError::Usage(_))
+        ));
+        assert!(matches!(
+            session.execute("replay leaf"),
+            Err(CommandError::UnknownCommand(_))
+        ));
+        assert!(matches!(
+            session.execute("stop"),
+            Err(CommandError::Usage(_))
+        ));
+    }
+}
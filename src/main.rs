@@ -1,195 +1,314 @@
 use std::{
-    f32::consts::PI,
-    sync::{Arc, Mutex},
+    error::Error,
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
     thread,
 };
 
-#[derive(Clone, Copy, Default, Debug, PartialEq)]
-struct Coord {
-    x: f32,
-    y: f32,
-}
+use clap::{Parser, Subcommand};
+use kaleidoscope::{
+    command::Session,
+    prelude::*,
+    server::{LineServer, Server},
+    symmetry::{Symmetry, SymmetryGroup},
+};
 
-trait Shape {
-    fn origin(&self) -> Coord;
-    fn set_origin(&mut self, origin: Coord);
-    fn get_area(&self) -> f32;
+#[derive(Parser)]
+#[command(
+    name = "kaleido",
+    about = "Shapes on a symmetric canvas; with no command, a short demo",
+    after_help = "Scripts and canvas files hold commands as typed at the repl, one per line.\n\
+                  Commands that change a canvas file write it back."
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-struct Circle {
-    origin: Coord,
-    radius: f32,
-}
-struct Rectangle {
-    origin: Coord,
-    side_a: f32,
-    side_b: f32,
-}
-struct Triangle {
-    origin: Coord,
-    base: f32,
-    height: f32,
+#[derive(Subcommand)]
+enum Command {
+    #[command(about = "Reads commands from stdin, journaled to a file if given")]
+    Repl {
+        #[arg(long)]
+        journal: Option<PathBuf>,
+    },
+    #[command(about = "Runs a script, printing what it outputs")]
+    Run { script: PathBuf },
+    #[command(about = "Serves canvases over HTTP on loopback")]
+    Serve {
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+    },
+    #[command(about = "Takes repl commands a line at a time over TCP on loopback")]
+    Listen {
+        #[arg(long, default_value_t = 7878)]
+        port: u16,
+    },
+    #[cfg(feature = "grpc")]
+    #[command(about = "Serves canvases over gRPC on loopback")]
+    Grpc {
+        #[arg(long, default_value_t = 50051)]
+        port: u16,
+    },
+    #[command(about = "Creates an empty canvas file")]
+    New { canvas: PathBuf },
+    #[command(about = "Adds a shape, given as to the repl: circle 2 at 0 0, say")]
+    Add {
+        canvas: PathBuf,
+        #[arg(required = true, allow_hyphen_values = true)]
+        shape: Vec<String>,
+    },
+    #[command(about = "Moves shape n to x y")]
+    Move {
+        canvas: PathBuf,
+        n: usize,
+        #[arg(allow_negative_numbers = true)]
+        x: f32,
+        #[arg(allow_negative_numbers = true)]
+        y: f32,
+    },
+    #[command(about = "Removes shape n")]
+    Remove { canvas: PathBuf, n: usize },
+    #[command(about = "Lists the shapes")]
+    List { canvas: PathBuf },
+    #[command(about = "Prints the area of shape n, or of them all")]
+    Area { canvas: PathBuf, n: Option<usize> },
+    #[command(about = "Renders the canvas to a PNG, 512 by 512 unless given")]
+    Render {
+        canvas: PathBuf,
+        path: String,
+        #[arg(requires = "height")]
+        width: Option<u32>,
+        height: Option<u32>,
+    },
+    #[command(about = "Runs a script against the canvas")]
+    Import { canvas: PathBuf, script: PathBuf },
+    #[command(about = "Writes the canvas out as a script")]
+    Export { canvas: PathBuf, script: PathBuf },
 }
 
-impl Shape for Circle {
-    fn origin(&self) -> Coord {
-        self.origin
+fn main() -> ExitCode {
+    let result = match Cli::parse().command {
+        None => demo().map_err(Into::into),
+        Some(command) => run(command),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
     }
+}
 
-    fn set_origin(&mut self, origin: Coord) {
-        self.origin = origin;
+fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Repl { journal: None } => Ok(repl(Session::new())?),
+        Command::Repl {
+            journal: Some(path),
+        } => Ok(repl(journaled(&path)?)?),
+        Command::Run { script } => {
+            let output = Session::new().run(&fs::read_to_string(script)?)?;
+            println!("{output}");
+            Ok(())
+        }
+        Command::Serve { port } => serve(port),
+        Command::Listen { port } => listen(port),
+        #[cfg(feature = "grpc")]
+        Command::Grpc { port } => grpc(port),
+        Command::New { canvas } => Ok(fs::write(canvas, "")?),
+        Command::Import { canvas, script } => edit(&canvas, |session| {
+            session.run(&fs::read_to_string(script)?)?;
+            Ok(())
+        }),
+        Command::Export { canvas, script } => Ok(fs::write(script, open(&canvas)?.script()?)?),
+        Command::Add { canvas, shape } => change(&canvas, format!("add {}", shape.join(" "))),
+        Command::Move { canvas, n, x, y } => change(&canvas, format!("move {n} to {x} {y}")),
+        Command::Remove { canvas, n } => change(&canvas, format!("remove {n}")),
+        Command::List { canvas } => show(&canvas, String::from("list")),
+        Command::Area { canvas, n: None } => show(&canvas, String::from("area")),
+        Command::Area { canvas, n: Some(n) } => show(&canvas, format!("area {n}")),
+        Command::Render {
+            canvas,
+            path,
+            width,
+            height,
+        } => {
+            let size = width.zip(height);
+            let size = size.map_or(String::new(), |(w, h)| format!(" {w} {h}"));
+            show(&canvas, format!("render {path}{size}"))
+        }
     }
+}
 
-    fn get_area(&self) -> f32 {
-        PI * self.radius.powi(2)
-    }
+// Runs one command against the canvas file, writing it back
+fn change(canvas: &Path, line: String) -> Result<(), Box<dyn Error>> {
+    edit(canvas, |session| {
+        session.execute(&line)?;
+        Ok(())
+    })
 }
-impl Shape for Rectangle {
-    fn origin(&self) -> Coord {
-        self.origin
-    }
 
-    fn set_origin(&mut self, origin: Coord) {
-        self.origin = origin;
+// Runs one command against the canvas file, printing what it outputs
+fn show(canvas: &Path, line: String) -> Result<(), Box<dyn Error>> {
+    let output = open(canvas)?.execute(&line)?;
+    if !output.is_empty() {
+        println!("{output}");
     }
+    Ok(())
+}
 
-    fn get_area(&self) -> f32 {
-        self.side_a * self.side_b
-    }
+fn open(canvas: &Path) -> Result<Session, Box<dyn Error>> {
+    let mut session = Session::new();
+    session.run(&fs::read_to_string(canvas)?)?;
+    Ok(session)
 }
-impl Shape for Triangle {
-    fn origin(&self) -> Coord {
-        self.origin
-    }
 
-    fn set_origin(&mut self, origin: Coord) {
-        self.origin = origin;
-    }
+// Saves the canvas file only if the change goes through
+fn edit(
+    canvas: &Path,
+    change: impl FnOnce(&mut Session) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut session = open(canvas)?;
+    change(&mut session)?;
+    fs::write(canvas, session.script()?)?;
+    Ok(())
+}
 
-    fn get_area(&self) -> f32 {
-        0.5 * self.base * self.height
-    }
+// On the loopback interface only, since anyone who can connect can edit
+fn serve(port: u16) -> Result<(), Box<dyn Error>> {
+    let server = Server::bind(("127.0.0.1", port))?;
+    println!("serving on http://{}", server.local_addr()?);
+    Ok(server.run()?)
 }
 
-type ShapeObject = Arc<Mutex<dyn Shape + Send + Sync>>;
+// Takes repl commands a line at a time over TCP, also on loopback only
+fn listen(port: u16) -> Result<(), Box<dyn Error>> {
+    let server = LineServer::bind(("127.0.0.1", port))?;
+    println!("listening on {}", server.local_addr()?);
+    Ok(server.run()?)
+}
 
-struct Canvas {
-    shapes: Vec<ShapeObject>,
+// The gRPC service, on loopback for the same reason
+#[cfg(feature = "grpc")]
+fn grpc(port: u16) -> Result<(), Box<dyn Error>> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await?;
+        println!("serving gRPC on {}", listener.local_addr()?);
+        Ok(kaleidoscope::grpc::serve(listener).await?)
+    })
 }
 
-impl Canvas {
-    fn add(&mut self, shape: ShapeObject) {
-        self.shapes.push(shape);
-    }
-    fn get(&self, index: usize) -> Option<&ShapeObject> {
-        self.shapes.get(index)
+// Picks up where a session journaled to the path left off, or starts one
+fn journaled(path: &Path) -> Result<Session, Box<dyn Error>> {
+    const SNAPSHOT_EVERY: usize = 100;
+    if fs::metadata(path).is_ok() {
+        let session = Session::recover(path, SNAPSHOT_EVERY)?;
+        println!("recovered {}", path.display());
+        return Ok(session);
     }
-    fn remove(&mut self, index: usize) -> ShapeObject {
-        self.shapes.remove(index)
-    }
-    fn get_area(&self, index: usize) -> Option<f32> {
-        self.get(index).map(|s| s.lock().unwrap().get_area())
-    }
-    fn set_origin(&self, index: usize, origin: Coord) {
-        if let Some(shape) = self.shapes.get(index) {
-            shape.lock().unwrap().set_origin(origin);
+    let mut session = Session::new();
+    session.journal_to(path, SNAPSHOT_EVERY)?;
+    Ok(session)
+}
+
+// Reads commands from stdin until it ends or says quit
+fn repl(mut session: Session) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    println!("kaleido repl, type help for commands");
+    loop {
+        print!("> ");
+        stdout.flush()?;
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        match line.trim() {
+            "quit" | "exit" => return Ok(()),
+            line => match session.execute(line) {
+                Ok(output) if output.is_empty() => {}
+                Ok(output) => println!("{output}"),
+                Err(error) => eprintln!("error: {error}"),
+            },
         }
     }
 }
 
-fn main() {
-    let circle = Arc::new(Mutex::new(Circle {
+// Adds one of each shape to a symmetric canvas and moves one from several
+// threads at once
+fn demo() -> Result<(), CanvasError> {
+    let circle = Arc::new(ShapeCell::new(Circle {
         origin: Coord::default(),
         radius: 5.0,
     })) as ShapeObject;
 
-    let rectangle = Arc::new(Mutex::new(Rectangle {
+    let rectangle = Arc::new(ShapeCell::new(Rectangle {
         origin: Coord::default(),
         side_a: 2.0,
         side_b: 4.0,
     })) as ShapeObject;
 
-    let triangle = Arc::new(Mutex::new(Triangle {
+    let triangle = Arc::new(ShapeCell::new(Triangle {
         origin: Coord::default(),
         base: 2.0,
         height: 4.0,
     })) as ShapeObject;
 
-    let mut canvas = Canvas { shapes: Vec::new() };
+    let mut canvas = Canvas::default();
+    // Every shape gets mirrored and rotated copies about the centre
+    canvas.set_symmetry(Some(Symmetry {
+        group: SymmetryGroup::Dihedral(6),
+        center: Coord::default(),
+    }))?;
     canvas.add(circle);
-    canvas.add(rectangle);
+    let rectangle = canvas.add(rectangle);
     canvas.add(triangle);
 
     println!(
         "rectangle origin: {:?}",
-        canvas.get(1).unwrap().lock().unwrap().origin()
+        canvas.get(rectangle).unwrap().lock().unwrap().origin()
     );
 
     // Update origin of rectangle
-    canvas.set_origin(1, Coord { x: 5.0, y: 5.0 });
+    canvas.set_origin(rectangle, Coord { x: 5.0, y: 5.0 })?;
 
     println!(
         "rectangle origin: {:?}",
-        canvas.get(1).unwrap().lock().unwrap().origin()
+        canvas.get(rectangle).unwrap().lock().unwrap().origin()
     );
 
-    // Increment origin of rectangle in multiple threads
+    // Increment origin of rectangle in multiple threads, each retrying until
+    // no other thread has moved it between its read and its write
     let canvas = Arc::new(canvas);
     thread::scope(|scope| {
         for _ in 0..10 {
             let canvas = canvas.clone();
-            scope.spawn(move || {
-                let Coord { x, y } = canvas.get(1).unwrap().lock().unwrap().origin();
-                canvas.set_origin(1, Coord { x: x + 1.0, y });
+            scope.spawn(move || loop {
+                let origin = canvas.get(rectangle).unwrap().lock().unwrap().origin();
+                let moved = Coord {
+                    x: origin.x + 1.0,
+                    ..origin
+                };
+                if canvas
+                    .compare_and_set_origin(rectangle, origin, moved)
+                    .unwrap()
+                {
+                    break;
+                }
             });
         }
     });
 
     println!(
         "rectangle origin: {:?}",
-        canvas.get(1).unwrap().lock().unwrap().origin()
+        canvas.get(rectangle).unwrap().lock().unwrap().origin()
     );
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    // Test the area computations
-    #[test]
-    fn calculate_shape_areas() {
-        let rectangle = Rectangle {
-            origin: Coord::default(),
-            side_a: 2.0,
-            side_b: 4.0,
-        };
-        assert_eq!(rectangle.get_area(), 8.0);
-
-        // Repeat for different values and shapes
-    }
-
-    // Test the canvas api: set_origin, remove, etc
-    #[test]
-    fn update_origin() {
-        let rectangle = Rectangle {
-            origin: Coord::default(),
-            side_a: 2.0,
-            side_b: 4.0,
-        };
-        let canvas = Canvas {
-            shapes: vec![Arc::new(Mutex::new(rectangle))],
-        };
-
-        assert_eq!(
-            canvas.get(0).unwrap().lock().unwrap().origin(),
-            Coord::default()
-        );
-        canvas.set_origin(0, Coord { x: 2.0, y: 2.0 });
-        assert_eq!(
-            canvas.get(0).unwrap().lock().unwrap().origin(),
-            Coord { x: 2.0, y: 2.0 }
-        );
-    }
-
-    // Multithreaded tests - eg what is in main
+    println!("shapes on canvas: {}", canvas.len());
+    Ok(())
 }
@@ -0,0 +1,62 @@
+// Float functions that live in std rather than core, for builds without
+// std, from libm. With std on, the inherent methods are used instead.
+pub trait Float {
+    fn floor(self) -> Self;
+    fn ceil(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn hypot(self, other: Self) -> Self;
+    fn powi(self, n: i32) -> Self;
+    fn sin_cos(self) -> (Self, Self)
+    where
+        Self: Sized;
+    fn atan2(self, other: Self) -> Self;
+}
+
+impl Float for f32 {
+    fn floor(self) -> f32 {
+        libm::floorf(self)
+    }
+    fn ceil(self) -> f32 {
+        libm::ceilf(self)
+    }
+    fn sqrt(self) -> f32 {
+        libm::sqrtf(self)
+    }
+    fn hypot(self, other: f32) -> f32 {
+        libm::hypotf(self, other)
+    }
+    fn powi(self, n: i32) -> f32 {
+        libm::powf(self, n as f32)
+    }
+    fn sin_cos(self) -> (f32, f32) {
+        libm::sincosf(self)
+    }
+    fn atan2(self, other: f32) -> f32 {
+        libm::atan2f(self, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Float;
+
+    #[test]
+    fn matches_std() {
+        let close = |a: f32, b: f32| (a - b).abs() <= 1e-6 * b.abs().max(1.0);
+        for x in [-7.3, -1.5, -0.25, 0.0, 0.4, 1.0, 2.75, 100.1] {
+            assert_eq!(Float::floor(x), x.floor());
+            assert_eq!(Float::ceil(x), x.ceil());
+            assert!(close(Float::sqrt(x.abs()), x.abs().sqrt()), "{x}");
+            assert!(close(Float::hypot(x, 2.5), x.hypot(2.5)), "{x}");
+            assert!(close(Float::powi(x, 3), x.powi(3)), "{x}");
+            if x != 0.0 {
+                assert!(close(Float::powi(x, -2), x.powi(-2)), "{x}");
+            }
+            let (sin, cos) = Float::sin_cos(x);
+            assert!(close(sin, x.sin()) && close(cos, x.cos()), "{x}");
+            assert!(close(Float::atan2(x, 1.5), x.atan2(1.5)), "{x}");
+            assert!(close(Float::atan2(-1.5, x), (-1.5f32).atan2(x)), "{x}");
+        }
+        assert!(Float::sqrt(-1.0f32).is_nan());
+    }
+}
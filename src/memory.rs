@@ -0,0 +1,141 @@
+use std::{
+    mem::{size_of, size_of_val},
+    sync::Arc,
+};
+
+use crate::{arena::ShapeId, Canvas};
+
+// Roughly what a canvas has allocated, in bytes, worked out from the sizes
+// and capacities of what it holds. Allocator overhead isn't counted, and
+// nor is anything shapes defined outside the crate allocate unless they say
+// so through Shape::heap_bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    pub shapes: usize,
+    // The shape cells and the arena holding them
+    pub storage_bytes: usize,
+    // The spatial index, when there is one
+    pub index_bytes: usize,
+    // Memoized bounds, areas, triangles and hulls
+    pub cache_bytes: usize,
+    // Styles, metadata, tags and the other tables kept by id slot
+    pub side_table_bytes: usize,
+    // Strong references to the shapes, the canvas's own included. Symmetric
+    // copies, reflections, groups and callers holding a ShapeObject make
+    // the rest.
+    pub shape_references: usize,
+    // Shapes referenced from anywhere besides the canvas
+    pub shared_shapes: usize,
+}
+
+impl MemoryStats {
+    pub fn total_bytes(&self) -> usize {
+        self.storage_bytes + self.index_bytes + self.cache_bytes + self.side_table_bytes
+    }
+}
+
+// A table of values by id slot with room for so many, the table given only
+// for its type
+fn slots<T>(_: &[Option<(ShapeId, T)>], capacity: usize) -> usize {
+    capacity * size_of::<Option<(ShapeId, T)>>()
+}
+
+impl Canvas {
+    pub fn memory_stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats {
+            shapes: self.shapes.len(),
+            storage_bytes: self.shapes.heap_bytes(),
+            cache_bytes: self.derived.heap_bytes(),
+            ..MemoryStats::default()
+        };
+        for shape in self.shapes.values() {
+            // The cell, with the reference counts ahead of it
+            stats.storage_bytes += size_of_val(&**shape) + 2 * size_of::<usize>();
+            stats.storage_bytes += shape.lock().map_or(0, |shape| shape.heap_bytes());
+            let references = Arc::strong_count(shape);
+            stats.shape_references += references;
+            if references > 1 {
+                stats.shared_shapes += 1;
+            }
+        }
+        #[cfg(feature = "spatial")]
+        {
+            stats.index_bytes = self.index_bytes();
+        }
+
+        let strings: usize = self
+            .metadata
+            .iter()
+            .flatten()
+            .flat_map(|(_, metadata)| metadata)
+            .map(|(key, value)| 2 * size_of::<String>() + key.capacity() + value.capacity())
+            .chain(self.tags.iter().flatten().map(|(_, tags)| {
+                let text: usize = tags.iter().map(String::capacity).sum();
+                tags.capacity() * size_of::<String>() + text
+            }))
+            .sum();
+        stats.side_table_bytes = strings
+            + slots(&self.styles, self.styles.capacity())
+            + slots(&self.metadata, self.metadata.capacity())
+            + slots(&self.tags, self.tags.capacity())
+            + slots(&self.hidden, self.hidden.capacity())
+            + slots(&self.locked, self.locked.capacity())
+            + slots(&self.motions, self.motions.capacity());
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Circle, Coord, Polygon, ShapeCell, Style};
+
+    #[test]
+    fn stats_grow_with_the_canvas() {
+        let mut canvas = Canvas::default();
+        assert_eq!(canvas.memory_stats(), MemoryStats::default());
+
+        let circle = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::default(),
+            radius: 1.0,
+        })));
+        let empty = canvas.memory_stats();
+        assert_eq!(empty.shapes, 1);
+        assert_eq!(empty.shape_references, 1);
+        assert_eq!(empty.cache_bytes, 0);
+        assert!(empty.storage_bytes >= size_of::<ShapeCell<Circle>>());
+
+        // Polygon points are counted, and so is a handle held outside
+        let points: Vec<Coord> = (0..1000)
+            .map(|i| Coord::new(i as f32, (i * i % 7) as f32))
+            .collect();
+        let polygon = Arc::new(ShapeCell::new(Polygon {
+            origin: Coord::default(),
+            points,
+        }));
+        canvas.add(polygon.clone());
+        let stats = canvas.memory_stats();
+        assert!(stats.storage_bytes >= empty.storage_bytes + 1000 * size_of::<Coord>());
+        assert_eq!((stats.shape_references, stats.shared_shapes), (3, 1));
+
+        canvas.triangles(circle).unwrap();
+        canvas.set_style(circle, Style::default()).unwrap();
+        canvas.tag(circle, "round").unwrap();
+        let stats = canvas.memory_stats();
+        assert!(stats.cache_bytes > 0);
+        assert!(stats.side_table_bytes > 0);
+        assert_eq!(
+            stats.total_bytes(),
+            stats.storage_bytes + stats.index_bytes + stats.cache_bytes + stats.side_table_bytes
+        );
+
+        #[cfg(feature = "spatial")]
+        {
+            canvas.set_spatial_index(Some(crate::spatial::IndexKind::SpatialHash {
+                cell_size: 10.0,
+            }));
+            canvas.hit_test((0.0, 0.0));
+            assert!(canvas.memory_stats().index_bytes > 0);
+        }
+    }
+}
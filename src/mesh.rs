@@ -0,0 +1,116 @@
+use alloc::vec::Vec;
+
+use crate::{
+    geometry::{outline_points, triangulate_points, Outline},
+    Coord,
+};
+
+// How far a stroke's corners may reach past the outline, in half widths,
+// before they are pulled back in. The same default as SVG's.
+pub const MITER_LIMIT: f32 = 4.0;
+
+// Indexed triangles ready for a vertex and index buffer. Triangles are
+// counter-clockwise, with indices into vertices.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<[f32; 2]>,
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    // Circles are sampled as in the rest of the geometry
+    pub fn fill(outline: &Outline) -> Mesh {
+        let points = outline_points(outline.clone());
+        let index = |corner: Coord| points.iter().position(|p| *p == corner).unwrap() as u32;
+        let indices = triangulate_points(&points)
+            .iter()
+            .flat_map(|t| [index(t.a), index(t.b), index(t.c)])
+            .collect();
+        Mesh {
+            vertices: points.into_iter().map(Into::into).collect(),
+            indices,
+        }
+    }
+
+    // A band of the given width centred on the outline, with mitred corners
+    pub fn stroke(outline: &Outline, width: f32) -> Mesh {
+        let points = outline_points(outline.clone());
+        let n = points.len();
+        let normal = |i: usize| {
+            let edge = points[(i + 1) % n] - points[i];
+            Coord::new(edge.y, -edge.x) * (1.0 / edge.length())
+        };
+        let mut mesh = Mesh::default();
+        for (i, &vertex) in points.iter().enumerate() {
+            let (before, after) = (normal((i + n - 1) % n), normal(i));
+            let miter = if 1.0 + before.dot(after) > f32::EPSILON {
+                (before + after) * (1.0 / (1.0 + before.dot(after)))
+            } else {
+                before
+            };
+            let miter = miter * (MITER_LIMIT / miter.length()).min(1.0) * (width / 2.0);
+            mesh.vertices.push((vertex + miter).into());
+            mesh.vertices.push((vertex - miter).into());
+        }
+        for i in 0..n as u32 {
+            let j = (i + 1) % n as u32;
+            let (outer, inner) = (2 * i, 2 * i + 1);
+            let (next_outer, next_inner) = (2 * j, 2 * j + 1);
+            mesh.indices
+                .extend([inner, outer, next_outer, inner, next_outer, next_inner]);
+        }
+        mesh
+    }
+
+    // Appends another mesh, drawn after this one
+    pub fn extend(&mut self, other: &Mesh) {
+        let offset = self.vertices.len() as u32;
+        self.vertices.extend(&other.vertices);
+        self.indices
+            .extend(other.indices.iter().map(|i| i + offset));
+    }
+
+    pub fn area(&self) -> f32 {
+        self.indices
+            .chunks(3)
+            .map(|t| {
+                let [a, b, c] = [0, 1, 2].map(|k| Coord::from(self.vertices[t[k] as usize]));
+                0.5 * (b - a).cross(c - a)
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_approx_eq, Circle, Rectangle, Shape};
+
+    #[test]
+    fn fill_and_stroke() {
+        let square = Rectangle {
+            origin: Coord::default(),
+            side_a: 4.0,
+            side_b: 4.0,
+        }
+        .outline();
+        let fill = Mesh::fill(&square);
+        assert_eq!((fill.vertices.len(), fill.indices.len()), (4, 6));
+        assert_eq!(fill.area(), 16.0);
+
+        // From a 6 by 6 square out to a 2 by 2 one in
+        let stroke = Mesh::stroke(&square, 2.0);
+        assert_eq!((stroke.vertices.len(), stroke.indices.len()), (8, 24));
+        assert_approx_eq!(stroke.area(), 32.0);
+
+        let circle = Circle {
+            origin: Coord::new(9.0, 0.0),
+            radius: 1.0,
+        }
+        .outline();
+        let mut both = fill.clone();
+        both.extend(&Mesh::fill(&circle));
+        assert!((both.area() - 16.0 - std::f32::consts::PI).abs() < 0.01);
+        assert!(both.indices[6..].iter().all(|&i| i >= 4));
+    }
+}
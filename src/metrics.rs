@@ -0,0 +1,184 @@
+use std::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use crate::Canvas;
+
+// Upper bounds of the lock wait buckets, in seconds, before the last one
+// that takes everything longer
+pub const LOCK_WAIT_BOUNDS: [f64; 7] = [1e-6, 1e-5, 1e-4, 1e-3, 1e-2, 1e-1, 1.0];
+
+// Counts of operations on one canvas, as they happen
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    pub adds: AtomicU64,
+    pub removes: AtomicU64,
+    pub moves: AtomicU64,
+    pub hit_tests: AtomicU64,
+}
+
+impl Counters {
+    pub(crate) fn count(counter: &AtomicU64, n: u64) {
+        counter.fetch_add(n, Ordering::Relaxed);
+    }
+}
+
+// Waits for shape locks held elsewhere, across every canvas, since shapes
+// can be shared between them
+static LOCK_WAITS: [AtomicU64; LOCK_WAIT_BOUNDS.len() + 1] =
+    [const { AtomicU64::new(0) }; LOCK_WAIT_BOUNDS.len() + 1];
+static LOCK_WAIT_NANOS: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn record_lock_wait(waited: Duration) {
+    let seconds = waited.as_secs_f64();
+    let bucket = LOCK_WAIT_BOUNDS
+        .iter()
+        .position(|&bound| seconds <= bound)
+        .unwrap_or(LOCK_WAIT_BOUNDS.len());
+    LOCK_WAITS[bucket].fetch_add(1, Ordering::Relaxed);
+    LOCK_WAIT_NANOS.fetch_add(waited.as_nanos() as u64, Ordering::Relaxed);
+}
+
+// Operation counts since the canvas was made. Moves count each shape moved,
+// however it was moved.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metrics {
+    pub adds: u64,
+    pub removes: u64,
+    pub moves: u64,
+    pub hit_tests: u64,
+    pub lock_waits: Histogram,
+}
+
+// How many waits fell in each of the LOCK_WAIT_BOUNDS buckets and the one
+// after, so not cumulative, and their total
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Histogram {
+    pub counts: Vec<u64>,
+    pub total: Duration,
+}
+
+impl Histogram {
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+// Waits for shape locks so far, process wide
+pub fn lock_waits() -> Histogram {
+    Histogram {
+        counts: LOCK_WAITS
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect(),
+        total: Duration::from_nanos(LOCK_WAIT_NANOS.load(Ordering::Relaxed)),
+    }
+}
+
+impl Canvas {
+    pub fn metrics(&self) -> Metrics {
+        let counters = &self.counters;
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        Metrics {
+            adds: load(&counters.adds),
+            removes: load(&counters.removes),
+            moves: load(&counters.moves),
+            hit_tests: load(&counters.hit_tests),
+            lock_waits: lock_waits(),
+        }
+    }
+}
+
+// The name, help text and value of each counter exported
+type Counter = (&'static str, &'static str, fn(&Metrics) -> u64);
+const COUNTERS: [Counter; 4] = [
+    ("adds", "Shapes added", |m| m.adds),
+    ("removes", "Shapes removed", |m| m.removes),
+    ("moves", "Shapes moved", |m| m.moves),
+    ("hit_tests", "Hit tests run", |m| m.hit_tests),
+];
+
+// The Prometheus text format for canvases, each labelled with its name,
+// followed by the process wide lock waits
+pub fn prometheus<'a>(canvases: impl IntoIterator<Item = (&'a str, &'a Metrics)>) -> String {
+    let canvases: Vec<(&str, &Metrics)> = canvases.into_iter().collect();
+    let mut out = String::new();
+    for (name, help, value) in COUNTERS {
+        let _ = writeln!(out, "# HELP kaleidoscope_{name}_total {help}");
+        let _ = writeln!(out, "# TYPE kaleidoscope_{name}_total counter");
+        for (canvas, metrics) in &canvases {
+            let canvas = canvas.replace('\\', "\\\\").replace('"', "\\\"");
+            let _ = writeln!(
+                out,
+                "kaleidoscope_{name}_total{{canvas=\"{canvas}\"}} {}",
+                value(metrics)
+            );
+        }
+    }
+    let waits = lock_waits();
+    let name = "kaleidoscope_lock_wait_seconds";
+    let _ = writeln!(out, "# HELP {name} Waits for shape locks held elsewhere");
+    let _ = writeln!(out, "# TYPE {name} histogram");
+    let mut cumulative = 0;
+    for (i, count) in waits.counts.iter().enumerate() {
+        cumulative += count;
+        let bound = LOCK_WAIT_BOUNDS
+            .get(i)
+            .map_or_else(|| String::from("+Inf"), f64::to_string);
+        let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {cumulative}");
+    }
+    let _ = writeln!(out, "{name}_sum {}", waits.total.as_secs_f64());
+    let _ = writeln!(out, "{name}_count {cumulative}");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{Circle, Coord, ShapeCell};
+
+    #[test]
+    fn operations_are_counted() {
+        let mut canvas = Canvas::default();
+        let shape = |x| {
+            Arc::new(ShapeCell::new(Circle {
+                origin: Coord::new(x, 0.0),
+                radius: 1.0,
+            }))
+        };
+        let a = canvas.add(shape(0.0));
+        let b = canvas.add(shape(5.0));
+        canvas.set_origin(a, (1.0, 1.0)).unwrap();
+        canvas.translate_all((1.0, 0.0)).unwrap();
+        canvas.hit_test((2.0, 1.0));
+        canvas.remove(b);
+        canvas.remove(b);
+        let metrics = canvas.metrics();
+        assert_eq!(
+            (
+                metrics.adds,
+                metrics.removes,
+                metrics.moves,
+                metrics.hit_tests
+            ),
+            (2, 1, 3, 1)
+        );
+
+        record_lock_wait(Duration::from_micros(50));
+        let text = prometheus([("main", &metrics)]);
+        assert!(text.contains("kaleidoscope_moves_total{canvas=\"main\"} 3\n"));
+        assert!(text.contains("# TYPE kaleidoscope_lock_wait_seconds histogram\n"));
+        // Cumulative, and at least the wait just recorded from 100µs on
+        let bucket = |le: &str| -> u64 {
+            let prefix = format!("kaleidoscope_lock_wait_seconds_bucket{{le=\"{le}\"}} ");
+            let line = text.lines().find(|l| l.starts_with(&prefix)).unwrap();
+            line[prefix.len()..].parse().unwrap()
+        };
+        assert!(bucket("0.0001") >= 1);
+        assert!(bucket("+Inf") >= bucket("0.0001"));
+    }
+}
@@ -0,0 +1,53 @@
+// Conversions to and from nalgebra's points and vectors, points for
+// positions and vectors for offsets, though either converts to a Coord
+use nalgebra::{Point2, Vector2};
+
+use crate::Coord;
+
+impl From<Point2<f32>> for Coord {
+    fn from(point: Point2<f32>) -> Coord {
+        Coord::new(point.x, point.y)
+    }
+}
+impl From<Coord> for Point2<f32> {
+    fn from(Coord { x, y }: Coord) -> Point2<f32> {
+        Point2::new(x, y)
+    }
+}
+impl From<Vector2<f32>> for Coord {
+    fn from(vector: Vector2<f32>) -> Coord {
+        Coord::new(vector.x, vector.y)
+    }
+}
+impl From<Coord> for Vector2<f32> {
+    fn from(Coord { x, y }: Coord) -> Vector2<f32> {
+        Vector2::new(x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{Canvas, Rectangle, ShapeCell};
+
+    #[test]
+    fn points_and_vectors_round_trip() {
+        let point = Point2::new(1.5, -2.0);
+        assert_eq!(Point2::from(Coord::from(point)), point);
+        let vector = Vector2::new(0.5, 4.0);
+        assert_eq!(Vector2::from(Coord::from(vector)), vector);
+
+        let mut canvas = Canvas::default();
+        let id = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::default(),
+            side_a: 2.0,
+            side_b: 1.0,
+        })));
+        canvas.set_origin(id, Point2::new(1.0, 1.0)).unwrap();
+        canvas.translate_all(Vector2::new(2.0, 0.0)).unwrap();
+        let origin: Point2<f32> = canvas.get(id).unwrap().lock().unwrap().origin().into();
+        assert_eq!(origin, Point2::new(3.0, 1.0));
+    }
+}
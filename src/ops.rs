@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use crate::{
+    error::CanvasError,
+    symmetry::{MirrorLine, Symmetry},
+    Canvas, Coord, Shape, ShapeCell, ShapeId, ShapeObject,
+};
+
+// Chains canvas edits, so a scene can be built in one expression:
+//
+//     canvas.ops().add(circle).add(square).translate_all(offset)?;
+//
+// Keeps the ids of the shapes it added for use afterwards.
+pub struct CanvasOps<'a> {
+    canvas: &'a mut Canvas,
+    added: Vec<ShapeId>,
+}
+
+impl Canvas {
+    pub fn ops(&mut self) -> CanvasOps<'_> {
+        CanvasOps {
+            canvas: self,
+            added: Vec::new(),
+        }
+    }
+}
+
+impl CanvasOps<'_> {
+    pub fn add(&mut self, shape: impl Shape + Send + Sync + 'static) -> &mut Self {
+        self.add_object(Arc::new(ShapeCell::new(shape)))
+    }
+    pub fn add_object(&mut self, shape: ShapeObject) -> &mut Self {
+        let id = self.canvas.add(shape);
+        self.added.push(id);
+        self
+    }
+    pub fn translate_all(&mut self, offset: impl Into<Coord>) -> Result<&mut Self, CanvasError> {
+        self.canvas.translate_all(offset)?;
+        Ok(self)
+    }
+    pub fn rotate_all(
+        &mut self,
+        angle: f32,
+        pivot: impl Into<Coord>,
+    ) -> Result<&mut Self, CanvasError> {
+        self.canvas.rotate_all(angle, pivot)?;
+        Ok(self)
+    }
+    pub fn set_symmetry(&mut self, symmetry: Option<Symmetry>) -> Result<&mut Self, CanvasError> {
+        self.canvas.set_symmetry(symmetry)?;
+        Ok(self)
+    }
+    pub fn add_mirror_line(&mut self, line: MirrorLine) -> &mut Self {
+        self.canvas.add_mirror_line(line);
+        self
+    }
+    pub fn set_live_mirroring(&mut self, live: bool) -> &mut Self {
+        self.canvas.set_live_mirroring(live);
+        self
+    }
+
+    // Ids of the shapes added through this chain, not counting symmetric
+    // copies or reflections, in the order they were added
+    pub fn added(&self) -> &[ShapeId] {
+        &self.added
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+    use crate::{symmetry::SymmetryGroup, Circle, Rectangle};
+
+    #[test]
+    fn chained_scene() {
+        let mut canvas = Canvas::default();
+        let mut ops = canvas.ops();
+        ops.add(Circle {
+            origin: Coord::new(1.0, 0.0),
+            radius: 1.0,
+        })
+        .set_symmetry(Some(Symmetry {
+            group: SymmetryGroup::Cyclic(4),
+            center: Coord::default(),
+        }))
+        .unwrap()
+        .add(Rectangle {
+            origin: Coord::new(3.0, 0.0),
+            side_a: 1.0,
+            side_b: 1.0,
+        })
+        .rotate_all(PI, Coord::default())
+        .unwrap()
+        .translate_all(Coord::new(0.0, 10.0))
+        .unwrap();
+        let added = ops.added().to_vec();
+
+        assert_eq!(added.len(), 2);
+        assert_eq!(canvas.len(), 5);
+        let origin = canvas.get(added[0]).unwrap().lock().unwrap().origin();
+        assert!(origin.distance(Coord::new(-1.0, 10.0)) < 1e-5);
+    }
+}
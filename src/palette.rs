@@ -0,0 +1,235 @@
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use crate::{arena::ShapeId, style::Paint, Canvas};
+use crate::{style::Color, Style};
+
+// A set of colours meant to be used together, handed out in turn and
+// wrapping round when there are more shapes than colours
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+    colors: Vec<Color>,
+}
+
+// The built in schemes, as #rrggbb
+const SCHEMES: [(&str, [u32; 5]); 5] = [
+    ("mono", [0x1a1a1a, 0x4d4d4d, 0x808080, 0xb3b3b3, 0xe6e6e6]),
+    ("pastel", [0xffb3ba, 0xffdfba, 0xffffba, 0xbaffc9, 0xbae1ff]),
+    ("vivid", [0xe63946, 0xf4a261, 0x2a9d8f, 0x457b9d, 0x8338ec]),
+    ("earth", [0x603813, 0x8c5e34, 0xb08d57, 0x6b8e23, 0x556b2f]),
+    ("ocean", [0x03045e, 0x0077b6, 0x00b4d8, 0x90e0ef, 0xcaf0f8]),
+];
+
+impl Palette {
+    pub const NAMES: [&'static str; 5] = ["mono", "pastel", "vivid", "earth", "ocean"];
+
+    // None when there are no colours to hand out
+    pub fn new(colors: Vec<Color>) -> Option<Palette> {
+        (!colors.is_empty()).then_some(Palette { colors })
+    }
+    pub fn named(name: &str) -> Option<Palette> {
+        let (_, scheme) = SCHEMES.iter().find(|(scheme, _)| *scheme == name)?;
+        Palette::new(
+            scheme
+                .iter()
+                .map(|rgb| {
+                    let [_, r, g, b] = rgb.to_be_bytes();
+                    Color::rgb(r, g, b)
+                })
+                .collect(),
+        )
+    }
+    // Hues evenly spaced round the colour wheel from the starting one, in
+    // degrees, all at the same saturation and lightness
+    pub fn hues(count: usize, start: f32, saturation: f32, lightness: f32) -> Option<Palette> {
+        Palette::new(
+            (0..count)
+                .map(|i| {
+                    Color::hsl(
+                        start + 360.0 * i as f32 / count as f32,
+                        saturation,
+                        lightness,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+    pub fn color(&self, index: usize) -> Color {
+        self.colors[index % self.colors.len()]
+    }
+    // The default style filled with a palette colour
+    pub fn style(&self, index: usize) -> Style {
+        Style {
+            fill: Some(self.color(index).into()),
+            ..Style::default()
+        }
+    }
+}
+
+// What decides which palette colour a shape gets
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Assignment {
+    // Painting order
+    ByIndex,
+    // Shape::name, in the order each kind first appears
+    ByKind,
+    // The group the shape belongs to, by group number
+    ByGroup,
+}
+
+#[cfg(feature = "std")]
+impl Canvas {
+    // Fills shapes with palette colours, keeping the rest of their styles.
+    // Symmetric copies count once and take their shape's colour. Shapes in
+    // no group are left as they are when assigning by group, and shapes
+    // whose locks are poisoned when assigning by kind.
+    pub fn apply_palette(&mut self, palette: &Palette, assignment: Assignment) {
+        let group_of = |canvas: &Canvas, id: ShapeId| {
+            (0..canvas.groups.len()).find(|&group| {
+                canvas
+                    .group_members(group)
+                    .is_ok_and(|members| members.contains(&id))
+            })
+        };
+        let mut kinds: Vec<&'static str> = Vec::new();
+        let mut done: Vec<ShapeId> = Vec::new();
+        let mut next = 0;
+        for id in self.ids().to_vec() {
+            if done.contains(&id) {
+                continue;
+            }
+            let index = match assignment {
+                Assignment::ByIndex => next,
+                Assignment::ByKind => {
+                    let Ok(shape) = self.get(id).unwrap().lock() else {
+                        continue;
+                    };
+                    let name = shape.name();
+                    match kinds.iter().position(|&kind| kind == name) {
+                        Some(index) => index,
+                        None => {
+                            kinds.push(name);
+                            kinds.len() - 1
+                        }
+                    }
+                }
+                Assignment::ByGroup => match group_of(self, id) {
+                    Some(group) => group,
+                    None => continue,
+                },
+            };
+            next += 1;
+            let style = Style {
+                fill: Some(Paint::Solid(palette.color(index))),
+                ..self.style(id).unwrap_or_default()
+            };
+            self.store_style(id, style);
+            done.extend(self.copies_of(id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    use std::sync::Arc;
+
+    use super::*;
+    #[cfg(feature = "std")]
+    use crate::{
+        symmetry::{Symmetry, SymmetryGroup},
+        transform::Transform,
+        Circle, Coord, Rectangle, ShapeCell, ShapeObject,
+    };
+
+    #[test]
+    fn schemes() {
+        for name in Palette::NAMES {
+            assert_eq!(Palette::named(name).unwrap().colors().len(), 5);
+        }
+        assert!(Palette::named("plaid").is_none());
+        assert!(Palette::new(Vec::new()).is_none());
+
+        let wheel = Palette::hues(3, 0.0, 1.0, 0.5).unwrap();
+        assert_eq!(
+            wheel.colors(),
+            [
+                Color::rgb(255, 0, 0),
+                Color::rgb(0, 255, 0),
+                Color::rgb(0, 0, 255)
+            ]
+        );
+        assert_eq!(wheel.color(4), Color::rgb(0, 255, 0));
+        assert_eq!(wheel.style(2).fill_color(), Some(Color::rgb(0, 0, 255)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn assign_to_shapes() {
+        let fill = |canvas: &Canvas, id| canvas.style(id).unwrap().fill_color().unwrap();
+        let circle = |x: f32| -> ShapeObject {
+            Arc::new(ShapeCell::new(Circle {
+                origin: Coord::new(x, 0.0),
+                radius: 1.0,
+            }))
+        };
+        let square: ShapeObject = Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::new(5.0, 5.0),
+            side_a: 1.0,
+            side_b: 1.0,
+        }));
+        let palette = Palette::hues(4, 0.0, 1.0, 0.5).unwrap();
+
+        let mut canvas = Canvas::default();
+        let ids = [
+            canvas.add(circle(0.0)),
+            canvas.add(square),
+            canvas.add(circle(3.0)),
+        ];
+        canvas.apply_palette(&palette, Assignment::ByKind);
+        let fills = ids.map(|id| fill(&canvas, id));
+        assert_eq!(
+            fills,
+            [palette.color(0), palette.color(1), palette.color(0)]
+        );
+
+        // Only the fill changes
+        let outlined = Style {
+            stroke: Some(Color::WHITE),
+            ..canvas.style(ids[1]).unwrap()
+        };
+        canvas.set_style(ids[1], outlined).unwrap();
+        canvas.apply_palette(&palette, Assignment::ByIndex);
+        let fills = ids.map(|id| fill(&canvas, id));
+        assert_eq!(
+            fills,
+            [palette.color(0), palette.color(1), palette.color(2)]
+        );
+        assert_eq!(canvas.style(ids[1]).unwrap().stroke, Some(Color::WHITE));
+
+        let group = canvas.add_group(None, Transform::IDENTITY).unwrap();
+        let grouped = canvas.add_to_group(group, circle(9.0)).unwrap();
+        canvas.apply_palette(&Palette::named("mono").unwrap(), Assignment::ByGroup);
+        assert_eq!(fill(&canvas, grouped), Color::rgb(0x1a, 0x1a, 0x1a));
+        assert_eq!(fill(&canvas, ids[0]), palette.color(0));
+
+        // Copies share their shape's colour and don't use up colours
+        let mut canvas = Canvas::default();
+        canvas
+            .set_symmetry(Some(Symmetry {
+                group: SymmetryGroup::Cyclic(3),
+                center: Coord::default(),
+            }))
+            .unwrap();
+        canvas.add(circle(4.0));
+        let last = canvas.add(circle(8.0));
+        canvas.apply_palette(&palette, Assignment::ByIndex);
+        assert_eq!(canvas.len(), 6);
+        assert_eq!(fill(&canvas, canvas.ids()[2]), palette.color(0));
+        assert_eq!(fill(&canvas, last), palette.color(1));
+    }
+}
@@ -0,0 +1,392 @@
+use std::{str::FromStr, sync::Arc};
+
+use crate::{
+    arena::ShapeId,
+    builder::{coordinate, dimension},
+    error::{CanvasError, ParseExprError, ShapeError},
+    Canvas, Circle, Coord, Ellipse, Rectangle, ShapeCell, ShapeObject, Square, Triangle,
+};
+
+// Arithmetic on numbers and named canvas parameters, parsed from text like
+// "base_size * 0.5 + 1". Names start with a letter or underscore.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Number(f32),
+    Param(String),
+    Neg(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+}
+
+// The size of a parametric shape, each dimension worked out from the
+// canvas parameters whenever one of them is set
+#[derive(Clone, Debug, PartialEq)]
+pub enum Dimensions {
+    Circle { radius: Expr },
+    Rectangle { side_a: Expr, side_b: Expr },
+    Square { side: Expr },
+    Triangle { base: Expr, height: Expr },
+    Ellipse { radius_x: Expr, radius_y: Expr },
+}
+
+impl Expr {
+    // Parameters missing from the canvas give UnknownName
+    pub fn eval(&self, canvas: &Canvas) -> Result<f32, CanvasError> {
+        Ok(match self {
+            Expr::Number(value) => *value,
+            Expr::Param(name) => canvas
+                .param(name)
+                .ok_or_else(|| CanvasError::UnknownName(name.clone()))?,
+            Expr::Neg(a) => -a.eval(canvas)?,
+            Expr::Add(a, b) => a.eval(canvas)? + b.eval(canvas)?,
+            Expr::Sub(a, b) => a.eval(canvas)? - b.eval(canvas)?,
+            Expr::Mul(a, b) => a.eval(canvas)? * b.eval(canvas)?,
+            Expr::Div(a, b) => a.eval(canvas)? / b.eval(canvas)?,
+        })
+    }
+    pub fn uses(&self, name: &str) -> bool {
+        match self {
+            Expr::Number(_) => false,
+            Expr::Param(param) => param == name,
+            Expr::Neg(a) => a.uses(name),
+            Expr::Add(a, b) | Expr::Sub(a, b) | Expr::Mul(a, b) | Expr::Div(a, b) => {
+                a.uses(name) || b.uses(name)
+            }
+        }
+    }
+}
+
+impl FromStr for Expr {
+    type Err = ParseExprError;
+    fn from_str(text: &str) -> Result<Expr, ParseExprError> {
+        let mut parser = Parser {
+            text,
+            chars: text.chars().collect(),
+            position: 0,
+        };
+        let expr = parser.sum()?;
+        parser.skip_space();
+        if parser.position < parser.chars.len() {
+            return Err(parser.error("an operator"));
+        }
+        Ok(expr)
+    }
+}
+
+// Recursive descent, with * and / binding tighter than + and -
+struct Parser<'a> {
+    text: &'a str,
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, expected: &'static str) -> ParseExprError {
+        ParseExprError {
+            input: self.text.into(),
+            position: self.position,
+            expected,
+        }
+    }
+    fn skip_space(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+    // The next character if it's one of those given
+    fn operator(&mut self, operators: &[char]) -> Option<char> {
+        self.skip_space();
+        let next = self.peek().filter(|c| operators.contains(c))?;
+        self.position += 1;
+        Some(next)
+    }
+
+    fn sum(&mut self) -> Result<Expr, ParseExprError> {
+        let mut expr = self.product()?;
+        while let Some(operator) = self.operator(&['+', '-']) {
+            let (a, b) = (Box::new(expr), Box::new(self.product()?));
+            expr = if operator == '+' {
+                Expr::Add(a, b)
+            } else {
+                Expr::Sub(a, b)
+            };
+        }
+        Ok(expr)
+    }
+    fn product(&mut self) -> Result<Expr, ParseExprError> {
+        let mut expr = self.factor()?;
+        while let Some(operator) = self.operator(&['*', '/']) {
+            let (a, b) = (Box::new(expr), Box::new(self.factor()?));
+            expr = if operator == '*' {
+                Expr::Mul(a, b)
+            } else {
+                Expr::Div(a, b)
+            };
+        }
+        Ok(expr)
+    }
+    fn factor(&mut self) -> Result<Expr, ParseExprError> {
+        if self.operator(&['-']).is_some() {
+            return Ok(Expr::Neg(Box::new(self.factor()?)));
+        }
+        if self.operator(&['(']).is_some() {
+            let expr = self.sum()?;
+            if self.operator(&[')']).is_none() {
+                return Err(self.error("')'"));
+            }
+            return Ok(expr);
+        }
+        let start = self.position;
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                self.take_while(|c| c.is_ascii_digit() || c == '.');
+                let number: String = self.chars[start..self.position].iter().collect();
+                number.parse().map(Expr::Number).map_err(|_| {
+                    self.position = start;
+                    self.error("a number")
+                })
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                self.take_while(|c| c.is_alphanumeric() || c == '_');
+                Ok(Expr::Param(
+                    self.chars[start..self.position].iter().collect(),
+                ))
+            }
+            _ => Err(self.error("a number, name or '('")),
+        }
+    }
+    fn take_while(&mut self, keep: impl Fn(char) -> bool) {
+        while self.peek().is_some_and(&keep) {
+            self.position += 1;
+        }
+    }
+}
+
+impl Dimensions {
+    pub fn uses(&self, name: &str) -> bool {
+        self.exprs().iter().any(|expr| expr.uses(name))
+    }
+    fn exprs(&self) -> Vec<&Expr> {
+        match self {
+            Dimensions::Circle { radius } => vec![radius],
+            Dimensions::Rectangle { side_a, side_b } => vec![side_a, side_b],
+            Dimensions::Square { side } => vec![side],
+            Dimensions::Triangle { base, height } => vec![base, height],
+            Dimensions::Ellipse { radius_x, radius_y } => vec![radius_x, radius_y],
+        }
+    }
+    // The shape at the origin, failing when a dimension comes out zero,
+    // negative or not finite
+    pub fn build(&self, canvas: &Canvas, origin: Coord) -> Result<ShapeObject, CanvasError> {
+        let value = |name, expr: &Expr| -> Result<f32, CanvasError> {
+            Ok(dimension(name, Some(expr.eval(canvas)?))?)
+        };
+        Ok(match self {
+            Dimensions::Circle { radius } => Arc::new(ShapeCell::new(Circle {
+                origin,
+                radius: value("radius", radius)?,
+            })),
+            Dimensions::Rectangle { side_a, side_b } => Arc::new(ShapeCell::new(Rectangle {
+                origin,
+                side_a: value("side_a", side_a)?,
+                side_b: value("side_b", side_b)?,
+            })),
+            Dimensions::Square { side } => Arc::new(ShapeCell::new(Square {
+                origin,
+                side: value("side", side)?,
+            })),
+            Dimensions::Triangle { base, height } => Arc::new(ShapeCell::new(Triangle {
+                origin,
+                base: value("base", base)?,
+                height: value("height", height)?,
+            })),
+            Dimensions::Ellipse { radius_x, radius_y } => Arc::new(ShapeCell::new(Ellipse {
+                origin,
+                radius_x: value("radius_x", radius_x)?,
+                radius_y: value("radius_y", radius_y)?,
+            })),
+        })
+    }
+}
+
+impl Canvas {
+    pub fn param(&self, name: &str) -> Option<f32> {
+        self.params.get(name).copied()
+    }
+    pub fn params(&self) -> impl Iterator<Item = (&str, f32)> {
+        self.params
+            .iter()
+            .map(|(name, value)| (name.as_str(), *value))
+    }
+    // Rebuilds every shape whose dimensions use the parameter, keeping each
+    // where it is, and returns their ids. Nothing changes if any of them
+    // would come out invalid. Locked shapes, symmetric copies and group
+    // members keep their old size.
+    pub fn set_param(
+        &mut self,
+        name: impl Into<String>,
+        value: f32,
+    ) -> Result<Vec<ShapeId>, CanvasError> {
+        let name = name.into();
+        if !value.is_finite() {
+            return Err(ShapeError::InvalidCoordinate { name: "parameter" }.into());
+        }
+        let old = self.params.insert(name.clone(), value);
+        let rebuilt: Result<Vec<(ShapeId, ShapeObject)>, CanvasError> = self
+            .parametric
+            .iter()
+            .filter(|(id, dimensions)| {
+                dimensions.uses(&name)
+                    && !self.is_locked(*id)
+                    && self.copies_of(*id).len() <= 1
+                    && !self.is_member(*id)
+            })
+            .map(|(id, dimensions)| {
+                let origin = self.shapes.get(*id).unwrap().lock()?.origin();
+                Ok((*id, dimensions.build(self, origin)?))
+            })
+            .collect();
+        let rebuilt = match rebuilt {
+            Ok(rebuilt) => rebuilt,
+            Err(error) => {
+                match old {
+                    Some(old) => self.params.insert(name, old),
+                    None => self.params.remove(&name),
+                };
+                return Err(error);
+            }
+        };
+        let mut ids = Vec::with_capacity(rebuilt.len());
+        for (id, shape) in rebuilt {
+            let old = self.shapes.get_mut(id).unwrap();
+            shape.set_history(old.history());
+            shape.touch();
+            *old = shape;
+            self.debug_check(self.shapes.get(id));
+            ids.push(id);
+        }
+        Ok(ids)
+    }
+
+    // Adds the shape built from the current parameters, which has to work
+    pub fn add_parametric(
+        &mut self,
+        origin: impl Into<Coord>,
+        dimensions: Dimensions,
+    ) -> Result<ShapeId, CanvasError> {
+        let origin = coordinate("origin", origin.into())?;
+        let id = self.add(dimensions.build(self, origin)?);
+        self.parametric.push((id, dimensions));
+        Ok(id)
+    }
+    pub fn dimensions(&self, id: ShapeId) -> Option<&Dimensions> {
+        self.parametric
+            .iter()
+            .find(|(shape, _)| *shape == id)
+            .map(|(_, dimensions)| dimensions)
+    }
+    // Leaves the shape at its current size, no longer following parameters
+    pub fn release_dimensions(&mut self, id: ShapeId) -> Option<Dimensions> {
+        let index = self.parametric.iter().position(|(shape, _)| *shape == id)?;
+        Some(self.parametric.remove(index).1)
+    }
+    pub(crate) fn forget_dimensions(&mut self, id: ShapeId) {
+        self.parametric.retain(|(shape, _)| *shape != id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    fn expr(text: &str) -> Expr {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn expressions_parse_with_precedence() {
+        let mut canvas = Canvas::default();
+        canvas.set_param("base_size", 10.0).unwrap();
+        let eval = |text| expr(text).eval(&canvas).unwrap();
+        assert_approx_eq!(eval("base_size * 0.5"), 5.0);
+        assert_approx_eq!(eval("1 + 2 * 3"), 7.0);
+        assert_approx_eq!(eval(" (1 + 2) * -base_size / 4 "), -7.5);
+        assert_approx_eq!(eval("10 - 4 - 3"), 3.0);
+
+        let error = "2 * (base_size + ".parse::<Expr>().unwrap_err();
+        assert_eq!(error.position, 17);
+        assert_eq!("2 3".parse::<Expr>().unwrap_err().position, 2);
+        assert_eq!("(1".parse::<Expr>().unwrap_err().expected, "')'");
+        assert_eq!("1..2".parse::<Expr>().unwrap_err().position, 0);
+        assert!(matches!(
+            expr("size").eval(&canvas),
+            Err(CanvasError::UnknownName(name)) if name == "size"
+        ));
+    }
+
+    #[test]
+    fn setting_a_param_resizes_dependent_shapes() {
+        let mut canvas = Canvas::default();
+        canvas.set_param("base_size", 10.0).unwrap();
+        let circle = canvas
+            .add_parametric(
+                (0.0, 0.0),
+                Dimensions::Circle {
+                    radius: expr("base_size * 0.5"),
+                },
+            )
+            .unwrap();
+        let rect = canvas
+            .add_parametric(
+                (20.0, 0.0),
+                Dimensions::Rectangle {
+                    side_a: expr("base_size"),
+                    side_b: expr("2"),
+                },
+            )
+            .unwrap();
+        let fixed = canvas
+            .add_parametric((40.0, 0.0), Dimensions::Square { side: expr("3") })
+            .unwrap();
+        assert_approx_eq!(canvas.bounding_box(circle).unwrap().side_a, 10.0);
+
+        canvas.set_origin(rect, (25.0, 5.0)).unwrap();
+        assert_eq!(
+            canvas.set_param("base_size", 4.0).unwrap(),
+            vec![circle, rect]
+        );
+        assert_approx_eq!(canvas.bounding_box(circle).unwrap().side_a, 4.0);
+        let bounds = canvas.bounding_box(rect).unwrap();
+        assert_eq!(bounds.origin, Coord::new(25.0, 5.0));
+        assert_approx_eq!(bounds.side_a, 4.0);
+        assert_approx_eq!(bounds.side_b, 2.0);
+        assert_approx_eq!(canvas.bounding_box(fixed).unwrap().side_a, 3.0);
+
+        // A radius of zero is turned down and the old value kept
+        assert!(matches!(
+            canvas.set_param("base_size", 0.0),
+            Err(CanvasError::Shape(ShapeError::InvalidDimension {
+                name: "radius",
+                ..
+            }))
+        ));
+        assert_eq!(canvas.param("base_size"), Some(4.0));
+        assert!(canvas.set_param("base_size", f32::NAN).is_err());
+
+        canvas.release_dimensions(circle).unwrap();
+        assert_eq!(canvas.set_param("base_size", 8.0).unwrap(), vec![rect]);
+        assert_approx_eq!(canvas.bounding_box(circle).unwrap().side_a, 4.0);
+        canvas.remove(rect);
+        assert_eq!(canvas.dimensions(rect), None);
+        assert!(canvas
+            .add_parametric((0.0, 0.0), Dimensions::Square { side: expr("size") })
+            .is_err());
+        assert_eq!(canvas.len(), 2);
+    }
+}
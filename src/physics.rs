@@ -0,0 +1,476 @@
+use std::thread;
+
+use crate::{
+    arena::ShapeId,
+    builder::coordinate,
+    error::{CanvasError, ShapeError},
+    geometry::Outline,
+    Canvas, Coord, Rectangle, Shape,
+};
+
+// How a shape moves, in canvas units per second and per second squared
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Motion {
+    pub velocity: Coord,
+    pub acceleration: Coord,
+}
+
+// What a moving shape does on reaching the edge of the bounds
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Edge {
+    // Pushed back inside with the velocity across the edge reversed
+    #[default]
+    Bounce,
+    // Comes back in from the opposite edge once its origin goes out
+    Wrap,
+    // Held at the edge with the velocity across it dropped
+    Stop,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Boundary {
+    pub bounds: Rectangle,
+    pub edge: Edge,
+}
+
+// Makes overlapping shapes push apart and bounce off each other during
+// step_physics. Shapes with a motion are moved, weighted by area, and those
+// without one are fixed in place. Circles meet as circles and everything
+// else as its bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CollisionResponse {
+    // Share of the closing speed kept after a hit: 1 for perfectly elastic,
+    // 0 for shapes that stop dead against each other
+    pub restitution: f32,
+}
+
+impl Canvas {
+    // Only shapes with a motion are kept in bounds, and only as they move
+    pub fn set_boundary(&mut self, boundary: Option<Boundary>) -> Result<(), CanvasError> {
+        if let Some(boundary) = &boundary {
+            boundary.bounds.validate()?;
+        }
+        self.boundary = boundary;
+        Ok(())
+    }
+    pub fn boundary(&self) -> Option<&Boundary> {
+        self.boundary.as_ref()
+    }
+    pub fn set_collision_response(
+        &mut self,
+        response: Option<CollisionResponse>,
+    ) -> Result<(), CanvasError> {
+        if let Some(CollisionResponse { restitution }) = response {
+            if !(0.0..=1.0).contains(&restitution) {
+                return Err(ShapeError::InvalidDimension {
+                    name: "restitution",
+                    value: restitution,
+                }
+                .into());
+            }
+        }
+        self.collision_response = response;
+        Ok(())
+    }
+
+    // Shapes without a motion of their own stand still
+    pub fn motion(&self, id: ShapeId) -> Option<Motion> {
+        self.get(id)?;
+        match self.motions.get(id.slot()) {
+            Some(Some((owner, motion))) if *owner == id => Some(*motion),
+            _ => Some(Motion::default()),
+        }
+    }
+    pub fn set_motion(&mut self, id: ShapeId, motion: Motion) -> Result<(), CanvasError> {
+        self.get(id).ok_or(CanvasError::UnknownShape(id))?;
+        coordinate("velocity", motion.velocity)?;
+        coordinate("acceleration", motion.acceleration)?;
+        if self.motions.len() <= id.slot() {
+            self.motions.resize(id.slot() + 1, None);
+        }
+        self.motions[id.slot()] = Some((id, motion));
+        Ok(())
+    }
+
+    // Moves every shape with a motion on by dt seconds, velocity first and
+    // then position from the new velocity. Moving a shape with symmetric
+    // copies moves them too, as set_origin does.
+    pub fn step_physics(&mut self, dt: f32) -> Result<(), CanvasError> {
+        let moving = self.accelerate(dt)?;
+        for &(id, velocity) in &moving {
+            self.advance_origin(id, velocity, dt)?;
+        }
+        self.finish_step(&moving)
+    }
+
+    // The same, with the shapes shared out between threads
+    pub fn step_physics_parallel(&mut self, dt: f32, threads: usize) -> Result<(), CanvasError> {
+        let moving = self.accelerate(dt)?;
+        let chunk = moving.len().div_ceil(threads.max(1)).max(1);
+        let canvas = &*self;
+        thread::scope(|scope| -> Result<(), CanvasError> {
+            let workers: Vec<_> = moving
+                .chunks(chunk)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .try_for_each(|&(id, velocity)| canvas.advance_origin(id, velocity, dt))
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .try_for_each(|worker| worker.join().unwrap_or(Err(CanvasError::Poisoned)))
+        })?;
+        self.finish_step(&moving)
+    }
+
+    // Updates the velocities and returns those of the shapes that move
+    fn accelerate(&mut self, dt: f32) -> Result<Vec<(ShapeId, Coord)>, CanvasError> {
+        if !dt.is_finite() {
+            return Err(ShapeError::InvalidDimension {
+                name: "dt",
+                value: dt,
+            }
+            .into());
+        }
+        let mut moving = Vec::new();
+        for entry in &mut self.motions {
+            let Some((id, motion)) = entry else {
+                continue;
+            };
+            if !self.shapes.contains(*id) {
+                *entry = None;
+                continue;
+            }
+            motion.velocity = motion.velocity + motion.acceleration * dt;
+            if motion.velocity != Coord::default() {
+                moving.push((*id, motion.velocity));
+            }
+        }
+        // Locked shapes keep their velocity, but stay where they are
+        moving.retain(|&(id, _)| !self.is_locked(id));
+        Ok(moving)
+    }
+    // Collisions first, then the boundary, so nothing is left pushed out
+    fn finish_step(&mut self, moving: &[(ShapeId, Coord)]) -> Result<(), CanvasError> {
+        if let Some(response) = self.collision_response {
+            self.resolve_collisions(response)?;
+        }
+        self.keep_in_bounds(moving)
+    }
+    fn keep_in_bounds(&mut self, moving: &[(ShapeId, Coord)]) -> Result<(), CanvasError> {
+        let Some(Boundary { bounds, edge }) = self.boundary.clone() else {
+            return Ok(());
+        };
+        let (min, max) = (bounds.min(), bounds.max());
+        for &(id, _) in moving {
+            let velocity = self.motion(id).unwrap_or_default().velocity;
+            let shape = self.get(id).ok_or(CanvasError::UnknownShape(id))?;
+            let (origin, reach) = {
+                let shape = shape.lock()?;
+                (shape.origin(), shape.bounding_box())
+            };
+            let mut velocity = [velocity.x, velocity.y];
+            let mut shift = [0.0; 2];
+            let axes = [
+                (min.x, max.x, reach.min().x, reach.max().x, origin.x),
+                (min.y, max.y, reach.min().y, reach.max().y, origin.y),
+            ];
+            for (axis, (low, high, from, to, at)) in axes.into_iter().enumerate() {
+                match edge {
+                    Edge::Wrap if at < low => shift[axis] = high - low,
+                    Edge::Wrap if at > high => shift[axis] = low - high,
+                    Edge::Wrap => {}
+                    // Folded back in by as far as it overshot
+                    Edge::Bounce if from < low => {
+                        shift[axis] = 2.0 * (low - from);
+                        velocity[axis] = velocity[axis].abs();
+                    }
+                    Edge::Bounce if to > high => {
+                        shift[axis] = 2.0 * (high - to);
+                        velocity[axis] = -velocity[axis].abs();
+                    }
+                    Edge::Stop if from < low || to > high => {
+                        shift[axis] = if from < low { low - from } else { high - to };
+                        velocity[axis] = 0.0;
+                    }
+                    Edge::Bounce | Edge::Stop => {}
+                }
+            }
+            if shift != [0.0; 2] {
+                self.place(id, origin + Coord::new(shift[0], shift[1]))?;
+            }
+            if let Some(Some((_, motion))) = self.motions.get_mut(id.slot()) {
+                motion.velocity = Coord::new(velocity[0], velocity[1]);
+            }
+        }
+        Ok(())
+    }
+    fn resolve_collisions(&mut self, response: CollisionResponse) -> Result<(), CanvasError> {
+        for (a, b) in self.collisions() {
+            // Inverse masses, zero for fixed and locked shapes
+            let weight = |canvas: &Canvas, id: ShapeId| match canvas.motions.get(id.slot()) {
+                Some(Some((owner, _))) if *owner == id && !canvas.is_locked(id) => {
+                    1.0 / canvas.get_area(id).unwrap_or(1.0).max(f32::EPSILON)
+                }
+                _ => 0.0,
+            };
+            let (wa, wb) = (weight(self, a), weight(self, b));
+            if wa + wb == 0.0 {
+                continue;
+            }
+            let outline = |id: ShapeId| -> Result<Outline, CanvasError> {
+                Ok(self
+                    .get(id)
+                    .ok_or(CanvasError::UnknownShape(id))?
+                    .lock()?
+                    .outline())
+            };
+            let Some((normal, depth)) = contact(&outline(a)?, &outline(b)?) else {
+                continue;
+            };
+
+            // Apart along the normal, the lighter shape moving further
+            for (id, weight, sign) in [(a, wa, -1.0), (b, wb, 1.0)] {
+                if weight > 0.0 && depth > 0.0 {
+                    let origin = self.get(id).unwrap().lock()?.origin();
+                    let shift = normal * (sign * depth * weight / (wa + wb));
+                    self.place(id, origin + shift)?;
+                }
+            }
+            let (va, vb) = (
+                self.motion(a).unwrap_or_default().velocity,
+                self.motion(b).unwrap_or_default().velocity,
+            );
+            let closing = (vb - va).dot(normal);
+            if closing >= 0.0 {
+                continue;
+            }
+            let impulse = -(1.0 + response.restitution) * closing / (wa + wb);
+            for (id, weight, sign) in [(a, wa, -1.0), (b, wb, 1.0)] {
+                if let Some(Some((_, motion))) = self.motions.get_mut(id.slot()) {
+                    if weight > 0.0 {
+                        motion.velocity = motion.velocity + normal * (sign * impulse * weight);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    fn advance_origin(&self, id: ShapeId, velocity: Coord, dt: f32) -> Result<(), CanvasError> {
+        let origin = self
+            .get(id)
+            .ok_or(CanvasError::UnknownShape(id))?
+            .lock()?
+            .origin();
+        self.place(id, origin + velocity * dt)
+    }
+}
+
+// The direction from the first outline to the second and how far they
+// overlap along it
+fn contact(a: &Outline, b: &Outline) -> Option<(Coord, f32)> {
+    if let (
+        Outline::Circle {
+            center: ca,
+            radius: ra,
+        },
+        Outline::Circle {
+            center: cb,
+            radius: rb,
+        },
+    ) = (a, b)
+    {
+        let between = *cb - *ca;
+        let distance = between.length();
+        let normal = match distance > 0.0 {
+            true => between * (1.0 / distance),
+            false => Coord::new(1.0, 0.0),
+        };
+        return Some((normal, (ra + rb - distance).max(0.0)));
+    }
+    let (a, b) = (a.bounding_box(), b.bounding_box());
+    let overlap =
+        |a_min: f32, a_max: f32, b_min: f32, b_max: f32| a_max.min(b_max) - a_min.max(b_min);
+    let x = overlap(a.min().x, a.max().x, b.min().x, b.max().x);
+    let y = overlap(a.min().y, a.max().y, b.min().y, b.max().y);
+    if x < 0.0 || y < 0.0 {
+        return None;
+    }
+    let sign = |delta: f32| if delta < 0.0 { -1.0 } else { 1.0 };
+    Some(if x <= y {
+        (Coord::new(sign(b.origin.x - a.origin.x), 0.0), x)
+    } else {
+        (Coord::new(0.0, sign(b.origin.y - a.origin.y)), y)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{Circle, ShapeCell};
+
+    #[test]
+    fn integrate_motion() {
+        let mut canvas = Canvas::default();
+        let ids: Vec<ShapeId> = (0..10)
+            .map(|i| {
+                canvas.add(Arc::new(ShapeCell::new(Circle {
+                    origin: Coord::new(i as f32, 0.0),
+                    radius: 0.5,
+                })))
+            })
+            .collect();
+        let falling = Motion {
+            velocity: Coord::new(1.0, 0.0),
+            acceleration: Coord::new(0.0, -2.0),
+        };
+        for &id in &ids[..5] {
+            canvas.set_motion(id, falling).unwrap();
+        }
+        assert_eq!(canvas.motion(ids[7]), Some(Motion::default()));
+        for _ in 0..4 {
+            canvas.step_physics(0.25).unwrap();
+        }
+        // Semi-implicit Euler, a quarter second at a time
+        let origin = |canvas: &Canvas, id| canvas.get(id).unwrap().lock().unwrap().origin();
+        let expected = Coord::new(1.0, -0.25 * 0.25 * 2.0 * (1.0 + 2.0 + 3.0 + 4.0));
+        assert!(origin(&canvas, ids[0]).distance(expected) < 1e-5);
+        assert_eq!(
+            canvas.motion(ids[0]).unwrap().velocity,
+            Coord::new(1.0, -2.0)
+        );
+        assert_eq!(origin(&canvas, ids[7]), Coord::new(7.0, 0.0));
+
+        let mut parallel = Canvas::default();
+        for i in 0..10 {
+            let id = parallel.add(Arc::new(ShapeCell::new(Circle {
+                origin: Coord::new(i as f32, 0.0),
+                radius: 0.5,
+            })));
+            if i < 5 {
+                parallel.set_motion(id, falling).unwrap();
+            }
+        }
+        for _ in 0..4 {
+            parallel.step_physics_parallel(0.25, 3).unwrap();
+        }
+        for (a, b) in canvas.ids().iter().zip(parallel.ids()) {
+            assert_eq!(origin(&canvas, *a), origin(&parallel, *b));
+        }
+
+        assert!(canvas.step_physics(f32::NAN).is_err());
+        let bad = Motion {
+            velocity: Coord::new(f32::INFINITY, 0.0),
+            ..falling
+        };
+        assert!(canvas.set_motion(ids[1], bad).is_err());
+        canvas.remove(ids[0]);
+        canvas.step_physics(0.25).unwrap();
+    }
+
+    #[test]
+    fn edges() {
+        let run = |edge| {
+            let mut canvas = Canvas::default();
+            let id = canvas.add(Arc::new(ShapeCell::new(Circle {
+                origin: Coord::new(8.0, 5.0),
+                radius: 1.0,
+            })));
+            let bounds = Rectangle::from_corners(Coord::default(), Coord::new(10.0, 10.0));
+            canvas
+                .set_boundary(Some(Boundary { bounds, edge }))
+                .unwrap();
+            let motion = Motion {
+                velocity: Coord::new(2.0, 0.0),
+                ..Motion::default()
+            };
+            canvas.set_motion(id, motion).unwrap();
+            canvas.step_physics(1.5).unwrap();
+            let origin = canvas.get(id).unwrap().lock().unwrap().origin();
+            (origin, canvas.motion(id).unwrap().velocity)
+        };
+        // Out to 11, the right side 2 past the edge at 10
+        assert_eq!(
+            run(Edge::Bounce),
+            (Coord::new(7.0, 5.0), Coord::new(-2.0, 0.0))
+        );
+        assert_eq!(run(Edge::Stop), (Coord::new(9.0, 5.0), Coord::default()));
+        assert_eq!(
+            run(Edge::Wrap),
+            (Coord::new(1.0, 5.0), Coord::new(2.0, 0.0))
+        );
+
+        let mut canvas = Canvas::default();
+        let flat = Boundary {
+            bounds: Rectangle {
+                origin: Coord::default(),
+                side_a: 0.0,
+                side_b: 1.0,
+            },
+            edge: Edge::Wrap,
+        };
+        assert!(canvas.set_boundary(Some(flat)).is_err());
+    }
+
+    #[test]
+    fn elastic_collisions() {
+        let mut canvas = Canvas::default();
+        let ball = |x: f32| {
+            Arc::new(ShapeCell::new(Circle {
+                origin: Coord::new(x, 0.0),
+                radius: 1.0,
+            }))
+        };
+        let (a, b) = (canvas.add(ball(0.0)), canvas.add(ball(2.5)));
+        let towards = |x: f32| Motion {
+            velocity: Coord::new(x, 0.0),
+            ..Motion::default()
+        };
+        canvas.set_motion(a, towards(1.0)).unwrap();
+        canvas.set_motion(b, towards(-1.0)).unwrap();
+        canvas
+            .set_collision_response(Some(CollisionResponse { restitution: 1.0 }))
+            .unwrap();
+        // Overlapping by a half after the step, pushed apart evenly and with
+        // their velocities swapped, as equal masses do
+        canvas.step_physics(0.5).unwrap();
+        let origin = |id| canvas.get(id).unwrap().lock().unwrap().origin();
+        assert!(origin(a).distance(Coord::new(0.25, 0.0)) < 1e-5);
+        assert!(origin(b).distance(Coord::new(2.25, 0.0)) < 1e-5);
+        assert_eq!(canvas.motion(a).unwrap().velocity, Coord::new(-1.0, 0.0));
+        assert_eq!(canvas.motion(b).unwrap().velocity, Coord::new(1.0, 0.0));
+
+        // A fixed wall takes the whole push and sends the box straight back
+        let mut canvas = Canvas::default();
+        let wall = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::new(5.0, 0.0),
+            side_a: 2.0,
+            side_b: 10.0,
+        })));
+        let block = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::new(2.5, 0.0),
+            side_a: 2.0,
+            side_b: 2.0,
+        })));
+        canvas.set_motion(block, towards(2.0)).unwrap();
+        canvas
+            .set_collision_response(Some(CollisionResponse { restitution: 0.5 }))
+            .unwrap();
+        canvas.step_physics(0.5).unwrap();
+        let origin = |id| canvas.get(id).unwrap().lock().unwrap().origin();
+        assert_eq!(origin(block), Coord::new(3.0, 0.0));
+        assert_eq!(origin(wall), Coord::new(5.0, 0.0));
+        assert_eq!(
+            canvas.motion(block).unwrap().velocity,
+            Coord::new(-1.0, 0.0)
+        );
+        assert!(canvas
+            .set_collision_response(Some(CollisionResponse { restitution: 2.0 }))
+            .is_err());
+    }
+}
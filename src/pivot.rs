@@ -0,0 +1,186 @@
+use crate::{
+    arena::ShapeId,
+    builder::{coordinate, dimension},
+    error::{CanvasError, ShapeError},
+    transform::Transform,
+    Canvas, Coord, Rectangle,
+};
+
+impl Canvas {
+    // The given shapes that can be reshaped on their own: locked shapes are
+    // left where they are, and symmetric copies and group members, which
+    // follow other shapes, are left as they are. Ids that aren't on the
+    // canvas are errors, before anything changes.
+    fn reshapeable(&self, ids: &[ShapeId]) -> Result<Vec<ShapeId>, CanvasError> {
+        let mut kept = Vec::new();
+        for &id in ids {
+            self.shapes.get(id).ok_or(CanvasError::UnknownShape(id))?;
+            if !kept.contains(&id)
+                && !self.is_locked(id)
+                && self.copies_of(id).len() == 1
+                && !self.is_member(id)
+            {
+                kept.push(id);
+            }
+        }
+        Ok(kept)
+    }
+
+    // Scales the shapes uniformly about the pivot, their positions along
+    // with their sizes, as reshapeable allows. Shapes with named dimensions
+    // keep their kind, and the rest are redrawn as circles or polygons the
+    // way Transform::image does. Stroke widths stay as they are.
+    pub fn scale_about(
+        &mut self,
+        ids: &[ShapeId],
+        pivot: impl Into<Coord>,
+        factor: f32,
+    ) -> Result<(), CanvasError> {
+        let pivot = coordinate("pivot", pivot.into())?;
+        dimension("factor", Some(factor))?;
+        for id in self.reshapeable(ids)? {
+            self.scale_shape(id, pivot, factor)?;
+        }
+        Ok(())
+    }
+
+    fn scale_shape(&mut self, id: ShapeId, pivot: Coord, factor: f32) -> Result<(), CanvasError> {
+        let shape = self.shapes.get(id).unwrap();
+        let mut locked = shape.lock()?;
+        let properties = locked.properties();
+        if properties.is_empty() {
+            let scaled = Transform::scale(factor, factor)
+                .about(pivot)
+                .image(&*locked);
+            drop(locked);
+            scaled.set_history(shape.history());
+            scaled.touch();
+            *self.shapes.get_mut(id).unwrap() = scaled;
+        } else {
+            // Checked first so a dimension out of range leaves the shape be
+            let mut scaled = Vec::with_capacity(properties.len());
+            for &name in properties {
+                let value = locked.get_property(name).unwrap_or_default() * factor;
+                scaled.push((name, dimension(name, Some(value))?));
+            }
+            for (name, value) in scaled {
+                locked.set_property(name, value)?;
+            }
+            let origin = locked.origin();
+            locked.set_origin(coordinate("origin", pivot + (origin - pivot) * factor)?);
+            drop(locked);
+        }
+        self.debug_check(self.shapes.get(id));
+        Ok(())
+    }
+
+    // Turns the shapes' positions counter-clockwise about the pivot, as
+    // reshapeable allows. Shapes have no orientation of their own yet, so
+    // each keeps its kind and faces the way it did.
+    pub fn rotate_about(
+        &mut self,
+        ids: &[ShapeId],
+        pivot: impl Into<Coord>,
+        angle: f32,
+    ) -> Result<(), CanvasError> {
+        let pivot = coordinate("pivot", pivot.into())?;
+        if !angle.is_finite() {
+            return Err(ShapeError::InvalidCoordinate { name: "angle" }.into());
+        }
+        let rotation = Transform::rotation(angle).about(pivot);
+        for id in self.reshapeable(ids)? {
+            let origin = self.shapes.get(id).unwrap().lock()?.origin();
+            self.place(id, rotation.map(origin))?;
+        }
+        Ok(())
+    }
+
+    // Scales the shapes uniformly, as scale_about does, until their bounding
+    // box is as big as fits the target, centred in it, and returns the
+    // factor used
+    pub fn resize_to_fit(
+        &mut self,
+        ids: &[ShapeId],
+        target: &Rectangle,
+    ) -> Result<f32, CanvasError> {
+        coordinate("target", target.origin)?;
+        dimension("target width", Some(target.side_a))?;
+        dimension("target height", Some(target.side_b))?;
+        let ids = self.reshapeable(ids)?;
+        let Some(bounds) = self.bounding_box_of(&ids) else {
+            return Ok(1.0);
+        };
+        let factor = (target.side_a / bounds.side_a).min(target.side_b / bounds.side_b);
+        self.scale_about(&ids, bounds.origin, factor)?;
+        let offset = target.origin - bounds.origin;
+        for id in ids {
+            let origin = self.shapes.get(id).unwrap().lock()?.origin();
+            self.place(id, origin + offset)?;
+        }
+        Ok(factor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f32::consts::PI, sync::Arc};
+
+    use super::*;
+    use crate::{assert_approx_eq, Circle, Polygon, ShapeCell};
+
+    #[test]
+    fn scale_rotate_and_fit() {
+        let mut canvas = Canvas::default();
+        let circle = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(1.0, 0.0),
+            radius: 1.0,
+        })));
+        let triangle = canvas.add(Arc::new(ShapeCell::new(Polygon {
+            origin: Coord::new(4.0, 0.0),
+            points: vec![
+                Coord::new(0.0, 0.0),
+                Coord::new(2.0, 0.0),
+                Coord::new(0.0, 2.0),
+            ],
+        })));
+        let created = canvas.history(triangle).unwrap().created;
+
+        canvas
+            .scale_about(&[circle, triangle], (0.0, 0.0), 2.0)
+            .unwrap();
+        let shape = canvas.get(circle).unwrap().lock().unwrap();
+        assert_eq!(shape.name(), "circle");
+        assert_eq!(shape.origin(), Coord::new(2.0, 0.0));
+        assert_eq!(shape.get_property("radius"), Some(2.0));
+        drop(shape);
+        assert_approx_eq!(canvas.get_area(triangle).unwrap(), 8.0);
+        assert_eq!(canvas.history(triangle).unwrap().created, created);
+
+        // From 0..12 by -2..4, fitted into a 6 by 6 square about (10, 10)
+        let target = Rectangle::from_corners(Coord::new(7.0, 7.0), Coord::new(13.0, 13.0));
+        let factor = canvas.resize_to_fit(&[circle, triangle], &target).unwrap();
+        assert_approx_eq!(factor, 0.5);
+        let fitted = canvas.bounding_box_of(&[circle, triangle]).unwrap();
+        assert!(fitted.origin.distance(target.origin) < 1e-5);
+        assert_approx_eq!(fitted.side_a, 6.0);
+        assert_approx_eq!(fitted.side_b, 3.0);
+
+        assert!(canvas.scale_about(&[circle], (0.0, 0.0), 0.0).is_err());
+        // A quarter turn about the target's centre, where the circle was
+        // fitted 2 left and half below
+        canvas
+            .rotate_about(&[circle, triangle], (10.0, 10.0), PI / 2.0)
+            .unwrap();
+        let shape = canvas.get(circle).unwrap().lock().unwrap();
+        assert!(shape.origin().distance(Coord::new(10.5, 8.0)) < 1e-5);
+        assert_eq!(shape.get_property("radius"), Some(1.0));
+        drop(shape);
+        assert_eq!(
+            canvas.get(triangle).unwrap().lock().unwrap().name(),
+            "polygon"
+        );
+        canvas.lock_shape(circle).unwrap();
+        canvas.scale_about(&[circle], (0.0, 0.0), 3.0).unwrap();
+        assert_approx_eq!(canvas.get_area(circle).unwrap(), PI);
+    }
+}
@@ -0,0 +1,5 @@
+// The types most code using the crate needs, for a single glob import
+pub use crate::{
+    Canvas, CanvasError, Circle, Color, Coord, Ellipse, Paint, Polygon, Rectangle, Shape,
+    ShapeCell, ShapeError, ShapeId, ShapeObject, Square, Style, Transform, Transformed, Triangle,
+};
@@ -0,0 +1,281 @@
+// A Python module, built with maturin build, pyproject.toml turning on the
+// python feature, and imported as kaleidoscope. Shapes are made by the
+// module's functions, circle(x, y, radius) and so on, and added to a
+// Canvas, which hands back an int id for each. Invalid sizes raise
+// ValueError and unknown or locked ids KeyError.
+//
+//     import kaleidoscope as k
+//     canvas = k.Canvas()
+//     id = canvas.add(k.circle(0, 0, 2))
+//     canvas.area(), canvas.hit_test(1, 1), canvas.svg(-5, -5, 5, 5)
+use pyo3::{
+    exceptions::{PyKeyError, PyValueError},
+    prelude::*,
+};
+
+#[cfg(feature = "render")]
+use crate::render::{render, RenderConfig};
+use crate::{
+    error::CanvasError, export, storage::ShapeEnum, Canvas, Circle, Coord, Ellipse, Polygon,
+    Rectangle, Shape, ShapeError, ShapeId, Square, Triangle,
+};
+
+fn shape_error(error: ShapeError) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+fn canvas_error(error: CanvasError) -> PyErr {
+    match error {
+        CanvasError::Shape(error) => shape_error(error),
+        CanvasError::UnknownShape(_) | CanvasError::Locked(_) => {
+            PyKeyError::new_err(error.to_string())
+        }
+        error => PyValueError::new_err(error.to_string()),
+    }
+}
+
+// One shape, not yet on a canvas
+#[pyclass(name = "Shape", frozen)]
+#[derive(Clone)]
+pub struct PyShape(ShapeEnum);
+
+fn checked(shape: impl Into<ShapeEnum>) -> PyResult<PyShape> {
+    let shape = shape.into();
+    shape.validate().map_err(shape_error)?;
+    Ok(PyShape(shape))
+}
+
+#[pyfunction]
+fn circle(x: f32, y: f32, radius: f32) -> PyResult<PyShape> {
+    checked(Circle {
+        origin: Coord::new(x, y),
+        radius,
+    })
+}
+#[pyfunction]
+fn rectangle(x: f32, y: f32, width: f32, height: f32) -> PyResult<PyShape> {
+    checked(Rectangle {
+        origin: Coord::new(x, y),
+        side_a: width,
+        side_b: height,
+    })
+}
+#[pyfunction]
+fn triangle(x: f32, y: f32, base: f32, height: f32) -> PyResult<PyShape> {
+    checked(Triangle {
+        origin: Coord::new(x, y),
+        base,
+        height,
+    })
+}
+#[pyfunction]
+fn square(x: f32, y: f32, side: f32) -> PyResult<PyShape> {
+    checked(Square {
+        origin: Coord::new(x, y),
+        side,
+    })
+}
+#[pyfunction]
+fn ellipse(x: f32, y: f32, radius_x: f32, radius_y: f32) -> PyResult<PyShape> {
+    checked(Ellipse {
+        origin: Coord::new(x, y),
+        radius_x,
+        radius_y,
+    })
+}
+// The points are relative to the origin, as (x, y) pairs
+#[pyfunction]
+#[pyo3(signature = (points, x = 0.0, y = 0.0))]
+fn polygon(points: Vec<(f32, f32)>, x: f32, y: f32) -> PyResult<PyShape> {
+    checked(Polygon {
+        origin: Coord::new(x, y),
+        points: points.into_iter().map(Coord::from).collect(),
+    })
+}
+
+#[pymethods]
+impl PyShape {
+    #[getter]
+    fn kind(&self) -> String {
+        format!("{:?}", self.0.kind()).to_lowercase()
+    }
+    #[getter]
+    fn origin(&self) -> (f32, f32) {
+        let Coord { x, y } = self.0.origin();
+        (x, y)
+    }
+    fn area(&self) -> f32 {
+        self.0.get_area()
+    }
+    fn contains(&self, x: f32, y: f32) -> bool {
+        self.0.outline().contains(Coord::new(x, y))
+    }
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+#[pyclass(name = "Canvas")]
+#[derive(Default)]
+pub struct PyCanvas(Canvas);
+
+fn id(id: u64) -> ShapeId {
+    ShapeId::from_bits(id)
+}
+fn ids(ids: Vec<ShapeId>) -> Vec<u64> {
+    ids.into_iter().map(ShapeId::to_bits).collect()
+}
+
+#[pymethods]
+impl PyCanvas {
+    #[new]
+    fn new() -> PyCanvas {
+        PyCanvas::default()
+    }
+    fn __len__(&self) -> usize {
+        self.0.len()
+    }
+
+    fn add(&mut self, shape: &PyShape) -> u64 {
+        self.0.add(shape.0.clone().into_object()).to_bits()
+    }
+    fn remove(&mut self, shape: u64) -> bool {
+        self.0.remove(id(shape)).is_some()
+    }
+    fn move_to(&self, shape: u64, x: f32, y: f32) -> PyResult<()> {
+        self.0
+            .set_origin(id(shape), Coord::new(x, y))
+            .map_err(canvas_error)
+    }
+    fn translate_all(&mut self, dx: f32, dy: f32) -> PyResult<()> {
+        self.0
+            .translate_all(Coord::new(dx, dy))
+            .map_err(canvas_error)
+    }
+    fn ids(&self) -> Vec<u64> {
+        ids(self.0.ids().to_vec())
+    }
+
+    // Of one shape, or of them all
+    #[pyo3(signature = (shape = None))]
+    fn area(&self, shape: Option<u64>) -> PyResult<f32> {
+        match shape {
+            None => Ok(self.0.total_area()),
+            Some(shape) => self
+                .0
+                .get_area(id(shape))
+                .ok_or_else(|| canvas_error(CanvasError::UnknownShape(id(shape)))),
+        }
+    }
+    // As (min x, min y, max x, max y)
+    fn bounding_box(&self, shape: u64) -> PyResult<(f32, f32, f32, f32)> {
+        let bounds = self.0.bounding_box(id(shape));
+        let bounds = bounds.ok_or_else(|| canvas_error(CanvasError::UnknownShape(id(shape))))?;
+        let (min, max) = (bounds.min(), bounds.max());
+        Ok((min.x, min.y, max.x, max.y))
+    }
+    fn hit_test(&self, x: f32, y: f32) -> Vec<u64> {
+        ids(self.0.hit_test(Coord::new(x, y)))
+    }
+    fn query_region(&self, min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Vec<u64> {
+        let region = Rectangle::from_corners(Coord::new(min_x, min_y), Coord::new(max_x, max_y));
+        ids(self.0.query_region(&region))
+    }
+    fn nearest(&self, x: f32, y: f32, k: usize) -> PyResult<Vec<u64>> {
+        let nearest = self.0.nearest(Coord::new(x, y), k).map_err(canvas_error)?;
+        Ok(ids(nearest))
+    }
+
+    // Exports of the part of the canvas between the corners
+    fn svg(&self, min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> String {
+        let frame = Rectangle::from_corners(Coord::new(min_x, min_y), Coord::new(max_x, max_y));
+        export::svg(&self.0, &frame)
+    }
+    fn pdf(&self, min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Vec<u8> {
+        let frame = Rectangle::from_corners(Coord::new(min_x, min_y), Coord::new(max_x, max_y));
+        export::pdf(&[(&self.0, frame)])
+    }
+    #[cfg(feature = "render")]
+    #[pyo3(signature = (min_x, min_y, max_x, max_y, width = 512, height = 512))]
+    fn png(
+        &self,
+        min_x: f32,
+        min_y: f32,
+        max_x: f32,
+        max_y: f32,
+        width: u32,
+        height: u32,
+    ) -> PyResult<Vec<u8>> {
+        let config = RenderConfig {
+            width,
+            height,
+            view: Rectangle::from_corners(Coord::new(min_x, min_y), Coord::new(max_x, max_y)),
+            ..RenderConfig::default()
+        };
+        let image = render(&self.0, &config).map_err(canvas_error)?;
+        image.to_png().map_err(canvas_error)
+    }
+}
+
+#[pymodule]
+fn kaleidoscope(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyCanvas>()?;
+    module.add_class::<PyShape>()?;
+    for function in [
+        wrap_pyfunction!(circle, module)?,
+        wrap_pyfunction!(rectangle, module)?,
+        wrap_pyfunction!(triangle, module)?,
+        wrap_pyfunction!(square, module)?,
+        wrap_pyfunction!(ellipse, module)?,
+        wrap_pyfunction!(polygon, module)?,
+    ] {
+        module.add_function(function)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::{ffi::c_str, types::PyDict};
+
+    use super::*;
+
+    #[test]
+    fn drive_canvas_from_python() {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "kaleidoscope").unwrap();
+            kaleidoscope(&module).unwrap();
+            let globals = PyDict::new(py);
+            globals.set_item("k", module).unwrap();
+            py.run(
+                c_str!(
+                    "canvas = k.Canvas()
+circle = canvas.add(k.circle(0, 0, 1))
+square = canvas.add(k.square(5, 0, 2))
+assert len(canvas) == 2
+assert canvas.hit_test(5.5, 0.5) == [square]
+canvas.move_to(circle, 5, 0)
+assert canvas.hit_test(5, 0) == [square, circle]
+assert abs(canvas.area() - 7.14159) < 1e-4
+assert canvas.bounding_box(square) == (4, -1, 6, 1)
+assert canvas.svg(0, -5, 10, 5).startswith('<svg')
+assert canvas.remove(square) and not canvas.remove(square)
+try:
+    k.circle(0, 0, -1)
+    raise AssertionError('negative radius accepted')
+except ValueError:
+    pass
+try:
+    canvas.area(square)
+    raise AssertionError('removed shape has an area')
+except KeyError:
+    pass
+assert k.polygon([(0, 0), (4, 0), (0, 3)]).area() == 6"
+                ),
+                Some(&globals),
+                None,
+            )
+            .unwrap_or_else(|error| panic!("{error}"));
+        });
+    }
+}
@@ -0,0 +1,207 @@
+use std::sync::{Arc, RwLock};
+
+use crate::{
+    error::{CanvasError, ShapeError},
+    Coord, Shape, ShapeCell, ShapeObject,
+};
+
+// Shape types defined outside the crate, by kind name, with how to write
+// them as numbers and read them back. Scripts, clipboards and chunk files
+// all go through the global registry, writing a registered shape as
+// "<kind> <number>... at <x> <y>". Shapes of other types are written as
+// polygons through their outlines, and all shapes render by their outlines
+// whether registered or not.
+#[derive(Default)]
+pub struct ShapeRegistry {
+    kinds: Vec<Kind>,
+}
+
+// None for shapes of other types that happen to share the name
+type Encode = Box<dyn Fn(&dyn Shape) -> Option<Vec<f32>> + Send + Sync>;
+type Decode = Box<dyn Fn(&[f32], Coord) -> Result<ShapeObject, ShapeError> + Send + Sync>;
+
+struct Kind {
+    name: &'static str,
+    encode: Encode,
+    decode: Decode,
+}
+
+// The kinds scripts already know
+const BUILT_IN: [&str; 6] = [
+    "circle",
+    "rectangle",
+    "triangle",
+    "polygon",
+    "square",
+    "ellipse",
+];
+
+static GLOBAL: RwLock<ShapeRegistry> = RwLock::new(ShapeRegistry::new());
+
+impl ShapeRegistry {
+    pub const fn new() -> ShapeRegistry {
+        ShapeRegistry { kinds: Vec::new() }
+    }
+    pub fn global() -> &'static RwLock<ShapeRegistry> {
+        &GLOBAL
+    }
+
+    // Shapes of type S are recognised by their name() being the kind and
+    // their as_any() returning themselves. encode gives the numbers after
+    // the kind, leaving out the origin, which decode is handed separately.
+    // Kinds are single words, and can't be taken twice or be one of the
+    // built in shapes.
+    pub fn register<S: Shape + Send + Sync + 'static>(
+        &mut self,
+        kind: &'static str,
+        encode: impl Fn(&S) -> Vec<f32> + Send + Sync + 'static,
+        decode: impl Fn(&[f32], Coord) -> Result<S, ShapeError> + Send + Sync + 'static,
+    ) -> Result<(), CanvasError> {
+        assert!(
+            !kind.is_empty() && !kind.contains(char::is_whitespace),
+            "shape kinds are single words, not {kind:?}"
+        );
+        if BUILT_IN.contains(&kind) || self.contains(kind) {
+            return Err(CanvasError::NameTaken(kind.into()));
+        }
+        self.kinds.push(Kind {
+            name: kind,
+            encode: Box::new(move |shape| {
+                let shape = shape.as_any()?.downcast_ref::<S>()?;
+                Some(encode(shape))
+            }),
+            decode: Box::new(move |numbers, origin| {
+                let shape = decode(numbers, origin)?;
+                shape.validate()?;
+                Ok(Arc::new(ShapeCell::new(shape)))
+            }),
+        });
+        Ok(())
+    }
+    pub fn contains(&self, kind: &str) -> bool {
+        self.kinds.iter().any(|k| k.name == kind)
+    }
+    // In the order registered
+    pub fn kinds(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.kinds.iter().map(|kind| kind.name)
+    }
+
+    // The kind and numbers the shape is written as, if its type is
+    // registered
+    pub fn encode(&self, shape: &dyn Shape) -> Option<(&'static str, Vec<f32>)> {
+        let kind = self.kinds.iter().find(|kind| kind.name == shape.name())?;
+        Some((kind.name, (kind.encode)(shape)?))
+    }
+    // None for kinds not registered
+    pub fn decode(
+        &self,
+        kind: &str,
+        numbers: &[f32],
+        origin: Coord,
+    ) -> Option<Result<ShapeObject, ShapeError>> {
+        let kind = self.kinds.iter().find(|k| k.name == kind)?;
+        Some((kind.decode)(numbers, origin))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+
+    use super::*;
+    use crate::{
+        builder::{coordinate, dimension},
+        clipboard::Clipboard,
+        command::Session,
+        geometry::Outline,
+        Canvas,
+    };
+
+    // A diamond, its corners on the axes through the origin
+    #[derive(Clone, Debug, PartialEq)]
+    struct Rhombus {
+        origin: Coord,
+        width: f32,
+        height: f32,
+    }
+
+    impl Shape for Rhombus {
+        fn name(&self) -> &'static str {
+            "rhombus"
+        }
+        fn as_any(&self) -> Option<&dyn Any> {
+            Some(self)
+        }
+        fn origin(&self) -> Coord {
+            self.origin
+        }
+        fn set_origin(&mut self, origin: Coord) {
+            self.origin = origin;
+        }
+        fn get_area(&self) -> f32 {
+            self.width * self.height / 2.0
+        }
+        fn outline(&self) -> Outline {
+            let (x, y) = (self.width / 2.0, self.height / 2.0);
+            Outline::Polygon(
+                [(x, 0.0), (0.0, y), (-x, 0.0), (0.0, -y)]
+                    .map(|offset| self.origin + offset.into())
+                    .to_vec(),
+            )
+        }
+        fn duplicate(&self) -> ShapeObject {
+            Arc::new(ShapeCell::new(self.clone()))
+        }
+        fn validate(&self) -> Result<(), ShapeError> {
+            coordinate("origin", self.origin)?;
+            dimension("width", Some(self.width))?;
+            dimension("height", Some(self.height))?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn registered_shapes_survive_scripts_and_clipboards() {
+        ShapeRegistry::global()
+            .write()
+            .unwrap()
+            .register(
+                "rhombus",
+                |rhombus: &Rhombus| vec![rhombus.width, rhombus.height],
+                |numbers, origin| match *numbers {
+                    [width, height] => Ok(Rhombus {
+                        origin,
+                        width,
+                        height,
+                    }),
+                    _ => Err(ShapeError::MissingDimension { name: "height" }),
+                },
+            )
+            .unwrap();
+        assert!(matches!(
+            ShapeRegistry::new().register("circle", |_: &Rhombus| vec![], |_, _| unreachable!()),
+            Err(CanvasError::NameTaken(_))
+        ));
+
+        let mut session = Session::new();
+        session.execute("add rhombus 4 2 at 1 1").unwrap();
+        assert_eq!(session.execute("area 1").unwrap(), "4");
+        assert!(session.execute("add rhombus 4 at 1 1").is_err());
+        assert!(session.execute("add rhombus 4 -2 at 1 1").is_err());
+        assert_eq!(session.script().unwrap(), "add rhombus 4 2 at 1 1\n");
+
+        let mut canvas = Canvas::default();
+        let id = canvas.add(Arc::new(ShapeCell::new(Rhombus {
+            origin: Coord::new(0.0, 0.0),
+            width: 2.0,
+            height: 6.0,
+        })));
+        let text = canvas.copy(&[id]).to_string();
+        assert!(text.starts_with("shape rhombus 2 6 at 0 0\nhistory "));
+        let pasted = text.parse::<Clipboard>().unwrap();
+        let id = canvas.paste(&pasted, Coord::new(5.0, 0.0)).unwrap()[0];
+        let shape = canvas.get(id).unwrap().lock().unwrap();
+        let rhombus = shape.as_any().unwrap().downcast_ref::<Rhombus>().unwrap();
+        assert_eq!(rhombus.width, 2.0);
+    }
+}
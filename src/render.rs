@@ -0,0 +1,687 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use crate::{
+    camera::{Camera, YAxis},
+    error::CanvasError,
+    stroke::StrokeArea,
+    style::Color,
+    transform::Transform,
+    Canvas, Coord, Rectangle,
+};
+
+pub type Rgba = [u8; 4];
+
+pub const BACKGROUND: Rgba = [255, 255, 255, 255];
+// The default style's fill
+pub const FILL: Rgba = [0, 0, 0, 255];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    // Row by row from the top
+    pub pixels: Vec<Rgba>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderConfig {
+    pub width: u32,
+    pub height: u32,
+    // The part of the canvas stretched over the image, with the canvas's
+    // y axis deciding which side is the top
+    pub view: Rectangle,
+    // Used instead of the view when set, for panned, zoomed or turned views
+    pub camera: Option<Camera>,
+    // Side of the square tiles handed to each thread, in pixels
+    pub tile_size: u32,
+    pub threads: usize,
+    // Samples per pixel along each axis, for anti-aliasing
+    pub samples: u32,
+}
+
+impl Default for RenderConfig {
+    fn default() -> RenderConfig {
+        RenderConfig {
+            width: 512,
+            height: 512,
+            view: Rectangle {
+                origin: Coord::new(50.0, 50.0),
+                side_a: 100.0,
+                side_b: 100.0,
+            },
+            camera: None,
+            tile_size: 64,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            samples: 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Tile {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+// Shapes are painted in the order they were added. Tiles are shared out
+// between the threads as each finishes its last, and every tile only looks
+// at the shapes whose bounds reach into it, using the canvas's spatial index
+// when it has one.
+pub fn render(canvas: &Canvas, config: &RenderConfig) -> Result<Image, CanvasError> {
+    span!(
+        DEBUG,
+        "render",
+        width = config.width,
+        height = config.height,
+        threads = config.threads,
+        shapes = canvas.len()
+    );
+    let view = &config.view;
+    let invalid = |name| Err(CanvasError::InvalidRenderConfig { name });
+    if config.width == 0 || config.height == 0 {
+        return invalid("image size");
+    }
+    if !(view.side_a > 0.0
+        && view.side_b > 0.0
+        && view.side_a.is_finite()
+        && view.side_b.is_finite())
+    {
+        return invalid("view size");
+    }
+    if !(view.origin.x.is_finite() && view.origin.y.is_finite()) {
+        return invalid("view origin");
+    }
+    if let Some(camera) = &config.camera {
+        let finite = camera.center.x.is_finite()
+            && camera.center.y.is_finite()
+            && camera.rotation.is_finite();
+        if !(finite && camera.zoom > 0.0 && camera.zoom.is_finite()) {
+            return invalid("camera");
+        }
+    }
+
+    let (width, height) = (config.width, config.height);
+    let tile_size = config.tile_size.max(1);
+    let tiles: Vec<Tile> = (0..height.div_ceil(tile_size))
+        .flat_map(|row| {
+            (0..width.div_ceil(tile_size)).map(move |column| {
+                let (x, y) = (column * tile_size, row * tile_size);
+                Tile {
+                    x,
+                    y,
+                    width: tile_size.min(width - x),
+                    height: tile_size.min(height - y),
+                }
+            })
+        })
+        .collect();
+
+    // Strokes reach past the shapes' bounds, by half their width or more at
+    // mitred corners
+    let reach = canvas
+        .visible_ids()
+        .into_iter()
+        .filter_map(|id| canvas.style(id))
+        .map(|style| style.stroke_reach())
+        .fold(0.0, f32::max);
+    let image = Mutex::new(Image {
+        width,
+        height,
+        pixels: vec![BACKGROUND; width as usize * height as usize],
+    });
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..config.threads.clamp(1, tiles.len().max(1)) {
+            scope.spawn(|| {
+                while let Some(tile) = tiles.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let pixels = render_tile(canvas, config, tile, reach);
+                    // Tiles are disjoint, so a panicked thread can't have left
+                    // another tile's pixels half written
+                    let mut image = image.lock().unwrap_or_else(|e| e.into_inner());
+                    for (row, line) in pixels.chunks(tile.width as usize).enumerate() {
+                        let start = (tile.y as usize + row) * width as usize + tile.x as usize;
+                        image.pixels[start..start + line.len()].copy_from_slice(line);
+                    }
+                }
+            });
+        }
+    });
+    Ok(image.into_inner()?)
+}
+
+impl RenderConfig {
+    // Canvas position of a point given in pixels from the image's top left
+    pub fn to_world(&self, y_axis: YAxis) -> Transform {
+        if let Some(camera) = &self.camera {
+            if let Some(to_world) = camera.to_world(self.width, self.height, y_axis) {
+                return to_world;
+            }
+        }
+        let view = &self.view;
+        let (top, flip) = match y_axis {
+            YAxis::Up => (view.max().y, -1.0),
+            YAxis::Down => (view.min().y, 1.0),
+        };
+        Transform::translation(Coord::new(view.min().x, top)).after(&Transform::scale(
+            view.side_a / self.width as f32,
+            flip * view.side_b / self.height as f32,
+        ))
+    }
+}
+
+// Bounds of the box's corners after the transform
+pub(crate) fn map_box(transform: &Transform, area: &Rectangle) -> Rectangle {
+    let (min, max) = (area.min(), area.max());
+    let corners = [min, Coord::new(max.x, min.y), max, Coord::new(min.x, max.y)]
+        .map(|corner| transform.map(corner));
+    let fold = |pick: fn(f32, f32) -> f32| {
+        corners[1..].iter().fold(corners[0], |bound, c| {
+            Coord::new(pick(bound.x, c.x), pick(bound.y, c.y))
+        })
+    };
+    Rectangle::from_corners(fold(f32::min), fold(f32::max))
+}
+
+fn grow(area: &Rectangle, by: f32) -> Rectangle {
+    Rectangle {
+        side_a: area.side_a + 2.0 * by,
+        side_b: area.side_b + 2.0 * by,
+        ..area.clone()
+    }
+}
+
+fn render_tile(canvas: &Canvas, config: &RenderConfig, tile: &Tile, reach: f32) -> Vec<Rgba> {
+    let to_world = config.to_world(canvas.axes().y);
+    let to_screen = to_world.inverse().unwrap_or_default();
+    let to_canvas = |x: f32, y: f32| to_world.map(Coord::new(x, y));
+    let region = map_box(
+        &to_world,
+        &Rectangle::from_corners(
+            Coord::new(tile.x as f32, tile.y as f32),
+            Coord::new((tile.x + tile.width) as f32, (tile.y + tile.height) as f32),
+        ),
+    );
+
+    let samples = config.samples.max(1);
+    let offsets: Vec<(f32, f32)> = (0..samples)
+        .flat_map(|i| {
+            (0..samples).map(move |j| {
+                (
+                    (i as f32 + 0.5) / samples as f32,
+                    (j as f32 + 0.5) / samples as f32,
+                )
+            })
+        })
+        .collect();
+
+    let mut pixels = vec![BACKGROUND; tile.width as usize * tile.height as usize];
+    for id in canvas.candidates(&grow(&region, reach)) {
+        if !canvas.is_visible(id) {
+            continue;
+        }
+        // Shapes whose locks are poisoned are left out
+        let Some(outline) = canvas.outline(id) else {
+            continue;
+        };
+        let style = canvas.style(id).unwrap_or_default();
+        let stroke = style
+            .stroke
+            .is_some()
+            .then(|| StrokeArea::new(&outline, &style));
+        // The shape's bounds in the tile's pixels, clamped to the tile
+        let bounds = map_box(
+            &to_screen,
+            &grow(&outline.bounding_box(), style.stroke_reach()),
+        );
+        let pixel = |value: f32, start: u32| (value - start as f32).max(0.0);
+        let (x0, x1) = (
+            pixel(bounds.min().x, tile.x).floor() as u32,
+            (pixel(bounds.max().x, tile.x).ceil() as u32).min(tile.width),
+        );
+        let (y0, y1) = (
+            pixel(bounds.min().y, tile.y).floor() as u32,
+            (pixel(bounds.max().y, tile.y).ceil() as u32).min(tile.height),
+        );
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let (px, py) = ((tile.x + x) as f32, (tile.y + y) as f32);
+                let coverage = |inside: &dyn Fn(Coord) -> bool| {
+                    let covered = offsets
+                        .iter()
+                        .filter(|(dx, dy)| inside(to_canvas(px + dx, py + dy)))
+                        .count();
+                    covered as f32 / offsets.len() as f32
+                };
+                let pixel = &mut pixels[(y * tile.width + x) as usize];
+                // The stroke goes over the fill
+                if let Some(fill) = style.fill_paint() {
+                    let covered = coverage(&|point| outline.contains(point));
+                    if covered > 0.0 {
+                        let color = fill.at(to_canvas(px + 0.5, py + 0.5));
+                        *pixel = blend(*pixel, color, covered);
+                    }
+                }
+                if let (Some(color), Some(stroke)) = (style.stroke_color(), &stroke) {
+                    let covered = coverage(&|point| stroke.contains(point));
+                    if covered > 0.0 {
+                        *pixel = blend(*pixel, color, covered);
+                    }
+                }
+            }
+        }
+    }
+    pixels
+}
+
+// Paints colour over the pixel with the given coverage
+fn blend(under: Rgba, over: Color, coverage: f32) -> Rgba {
+    over.with_opacity(coverage).over(under.into()).into()
+}
+
+impl Image {
+    pub fn pixel(&self, x: u32, y: u32) -> Rgba {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    // An uncompressed PNG: zlib allows stored blocks, which keeps the encoder
+    // tiny at the cost of file size
+    pub fn to_png(&self) -> Result<Vec<u8>, CanvasError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(CanvasError::EmptyImage);
+        }
+        let mut raw = Vec::with_capacity(self.pixels.len() * 4 + self.height as usize);
+        for row in self.pixels.chunks(self.width as usize) {
+            // No filter
+            raw.push(0);
+            raw.extend(row.iter().flatten());
+        }
+
+        let mut zlib = vec![0x78, 0x01];
+        let mut blocks = raw.chunks(0xffff).peekable();
+        while let Some(block) = blocks.next() {
+            zlib.push(blocks.peek().is_none() as u8);
+            let length = block.len() as u16;
+            zlib.extend(length.to_le_bytes());
+            zlib.extend((!length).to_le_bytes());
+            zlib.extend(block);
+        }
+        zlib.extend(adler32(&raw).to_be_bytes());
+
+        let mut header = Vec::with_capacity(13);
+        header.extend(self.width.to_be_bytes());
+        header.extend(self.height.to_be_bytes());
+        // 8 bit RGBA, default compression and filtering, not interlaced
+        header.extend([8, 6, 0, 0, 0]);
+
+        let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+        for (kind, data) in [(b"IHDR", &header), (b"IDAT", &zlib), (b"IEND", &Vec::new())] {
+            png.extend((data.len() as u32).to_be_bytes());
+            let start = png.len();
+            png.extend(kind);
+            png.extend(data);
+            let crc = crc32(&png[start..]);
+            png.extend(crc.to_be_bytes());
+        }
+        Ok(png)
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), CanvasError> {
+        span!(DEBUG, "save_png", path = %path.as_ref().display());
+        Ok(fs::write(path, self.to_png()?)?)
+    }
+}
+
+// Renders a numbered frame per tick as the canvas changes, for assembling
+// into video with something like ffmpeg -i frames/%04d.png
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recorder {
+    pub config: RenderConfig,
+    // Number of the first frame written
+    pub first_frame: u32,
+}
+
+impl Recorder {
+    pub fn new(config: RenderConfig) -> Recorder {
+        Recorder {
+            config,
+            first_frame: 0,
+        }
+    }
+
+    // Writes the canvas as it is and then again after each of the ticks, so
+    // ticks + 1 frames in all, frame n showing the canvas after n ticks. The
+    // pattern numbers frames the way ffmpeg's do, %d or %0Nd for a zero
+    // padded width, and its directory is made if need be.
+    pub fn capture_frames(
+        &self,
+        canvas: &mut Canvas,
+        ticks: u32,
+        path_pattern: &str,
+        mut tick: impl FnMut(&mut Canvas, u32),
+    ) -> Result<Vec<PathBuf>, CanvasError> {
+        let (prefix, width, suffix) =
+            frame_pattern(path_pattern).ok_or(CanvasError::InvalidRenderConfig {
+                name: "path pattern",
+            })?;
+        let mut paths = Vec::with_capacity(ticks as usize + 1);
+        for n in 0..=ticks {
+            if n > 0 {
+                tick(canvas, n - 1);
+            }
+            let frame = self.first_frame + n;
+            let path = PathBuf::from(format!("{prefix}{frame:0width$}{suffix}"));
+            if let Some(directory) = path.parent() {
+                fs::create_dir_all(directory)?;
+            }
+            render(canvas, &self.config)?.save_png(&path)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+// The text either side of the one %d or %0Nd, and the width it pads to
+fn frame_pattern(pattern: &str) -> Option<(&str, usize, &str)> {
+    let start = pattern.find('%')?;
+    let rest = &pattern[start + 1..];
+    let end = rest.find('d')?;
+    let width = match &rest[..end] {
+        "" => 0,
+        digits if digits.starts_with('0') => digits.parse().ok()?,
+        _ => return None,
+    };
+    let suffix = &rest[end + 1..];
+    if suffix.contains('%') {
+        return None;
+    }
+    Some((&pattern[..start], width, suffix))
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        assert_approx_eq,
+        camera::{Axes, Corner},
+        generator::random_canvas,
+        style::{Color, Paint, Style},
+        Circle, ShapeCell,
+    };
+
+    #[test]
+    fn tiles_match_single_thread() {
+        let canvas = random_canvas(5, &Default::default());
+        let config = RenderConfig {
+            width: 100,
+            height: 70,
+            tile_size: 16,
+            threads: 4,
+            ..Default::default()
+        };
+        let parallel = render(&canvas, &config).unwrap();
+        let serial = render(
+            &canvas,
+            &RenderConfig {
+                tile_size: 1000,
+                threads: 1,
+                ..config
+            },
+        )
+        .unwrap();
+        assert_eq!(parallel, serial);
+    }
+
+    #[test]
+    fn strokes_over_fills() {
+        let mut canvas = Canvas::default();
+        let ring = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(5.0, 5.0),
+            radius: 3.0,
+        })));
+        let red = Color::rgb(255, 0, 0);
+        let style = Style {
+            fill: None,
+            stroke: Some(red),
+            ..Default::default()
+        };
+        canvas.set_style(ring, style).unwrap();
+        let config = RenderConfig {
+            width: 10,
+            height: 10,
+            view: Rectangle::from_corners(Coord::default(), Coord::new(10.0, 10.0)),
+            samples: 1,
+            ..Default::default()
+        };
+        // Pixel centres half a unit in, inside the stroke and inside the ring
+        let image = render(&canvas, &config).unwrap();
+        assert_eq!(image.pixel(7, 4), <[u8; 4]>::from(red));
+        assert_eq!(image.pixel(4, 4), BACKGROUND);
+
+        canvas
+            .set_style(
+                ring,
+                Style {
+                    fill: Some(Color::BLACK.into()),
+                    ..style
+                },
+            )
+            .unwrap();
+        let image = render(&canvas, &config).unwrap();
+        assert_eq!(image.pixel(7, 4), <[u8; 4]>::from(red));
+        assert_eq!(image.pixel(4, 4), FILL);
+
+        // Half transparent black over the white background
+        canvas
+            .set_style(
+                ring,
+                Style {
+                    fill: Some(Color::BLACK.into()),
+                    opacity: 0.5,
+                    ..style
+                },
+            )
+            .unwrap();
+        let image = render(&canvas, &config).unwrap();
+        assert_eq!(image.pixel(4, 4), [127, 127, 127, 255]);
+
+        // Patterns are looked up at each pixel's centre in canvas units
+        let blue = Color::rgb(0, 0, 255);
+        canvas
+            .set_style(
+                ring,
+                Style {
+                    fill: Some(Paint::Checks {
+                        size: 1.0,
+                        colors: [Color::BLACK, blue],
+                    }),
+                    ..style
+                },
+            )
+            .unwrap();
+        let image = render(&canvas, &config).unwrap();
+        assert_eq!(image.pixel(4, 4), <[u8; 4]>::from(blue));
+        assert_eq!(image.pixel(5, 4), FILL);
+        let bad = Style {
+            fill: Some(Paint::Checks {
+                size: 0.0,
+                colors: [Color::BLACK, blue],
+            }),
+            ..style
+        };
+        assert!(canvas.set_style(ring, bad).is_err());
+    }
+
+    #[test]
+    fn renders_circle() {
+        let mut canvas = Canvas::default();
+        let circle = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(5.0, 7.0),
+            radius: 2.0,
+        })));
+        let config = RenderConfig {
+            width: 40,
+            height: 40,
+            view: Rectangle::from_corners(Coord::default(), Coord::new(10.0, 10.0)),
+            ..Default::default()
+        };
+        let image = render(&canvas, &config).unwrap();
+
+        // Above centre, since y points up on the canvas and down the image
+        assert_eq!(image.pixel(20, 12), FILL);
+        assert_eq!(image.pixel(20, 28), BACKGROUND);
+        let ink: f32 = image.pixels.iter().map(|p| 1.0 - p[0] as f32 / 255.0).sum();
+        // Each pixel is a quarter unit across
+        let area = ink / 16.0;
+        assert_approx_eq!(area, 4.0 * std::f32::consts::PI, 0.015);
+
+        // Hidden shapes aren't drawn
+        canvas.set_visible(circle, false).unwrap();
+        let hidden = render(&canvas, &config).unwrap();
+        assert!(hidden.pixels.iter().all(|&pixel| pixel == BACKGROUND));
+        canvas.set_visible(circle, true).unwrap();
+
+        // The same view through a camera, and turned a half circle about
+        // the centre
+        let mut camera = Camera {
+            center: Coord::new(5.0, 5.0),
+            zoom: 4.0,
+            rotation: 0.0,
+        };
+        let through = |camera| {
+            let config = RenderConfig {
+                camera: Some(camera),
+                ..config.clone()
+            };
+            render(&canvas, &config).unwrap()
+        };
+        assert_eq!(through(camera), image);
+        camera.rotation = std::f32::consts::PI;
+        let turned = through(camera);
+        assert_eq!(turned.pixel(20, 28), FILL);
+        assert_eq!(turned.pixel(20, 12), BACKGROUND);
+        assert_eq!(
+            canvas.hit_test_screen(&camera, (40, 40), Coord::new(20.0, 28.0)),
+            canvas.ids()
+        );
+
+        // With y down the circle is below centre, in the same place as when
+        // the view is turned
+        canvas.set_axes(Axes {
+            y: YAxis::Down,
+            origin: Corner::TopLeft,
+        });
+        let config = RenderConfig {
+            view: canvas.axes().page(10.0, 10.0),
+            ..config
+        };
+        assert_eq!(render(&canvas, &config).unwrap(), turned);
+
+        let png = image.to_png().unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        // The empty IEND chunk, with its well known checksum
+        assert_eq!(
+            png[png.len() - 8..],
+            [b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+        );
+    }
+
+    #[test]
+    fn record_frames() {
+        assert_eq!(frame_pattern("out/%04d.png"), Some(("out/", 4, ".png")));
+        assert_eq!(frame_pattern("%d"), Some(("", 0, "")));
+        for bad in ["frame.png", "%4d.png", "%d-%d.png", "%x.png"] {
+            assert_eq!(frame_pattern(bad), None, "{bad}");
+        }
+
+        let mut canvas = Canvas::default();
+        let id = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(2.0, 5.0),
+            radius: 1.0,
+        })));
+        let recorder = Recorder {
+            first_frame: 1,
+            ..Recorder::new(RenderConfig {
+                width: 10,
+                height: 10,
+                view: Rectangle::from_corners(Coord::default(), Coord::new(10.0, 10.0)),
+                samples: 1,
+                ..Default::default()
+            })
+        };
+        let directory =
+            std::env::temp_dir().join(format!("kaleidoscope-frames-{}", std::process::id()));
+        let pattern = format!("{}/frame-%03d.png", directory.display());
+        let mut ticks = Vec::new();
+        let paths = recorder
+            .capture_frames(&mut canvas, 3, &pattern, |canvas, tick| {
+                ticks.push(tick);
+                canvas.translate_all((2.0, 0.0)).unwrap();
+            })
+            .unwrap();
+        assert_eq!(ticks, [0, 1, 2]);
+        let names: Vec<_> = paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "frame-001.png",
+                "frame-002.png",
+                "frame-003.png",
+                "frame-004.png"
+            ]
+        );
+        // The last frame shows the circle after all three moves
+        let last = fs::read(&paths[3]).unwrap();
+        assert_eq!(
+            last,
+            render(&canvas, &recorder.config).unwrap().to_png().unwrap()
+        );
+        assert_eq!(
+            canvas.get(id).unwrap().lock().unwrap().origin(),
+            Coord::new(8.0, 5.0)
+        );
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert!(recorder
+            .capture_frames(&mut canvas, 1, "frame.png", |_, _| {})
+            .is_err());
+    }
+}
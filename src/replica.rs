@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+
+use crate::{builder::coordinate, error::ShapeError, storage::ShapeEnum, Canvas, Coord, Shape};
+
+// A Lamport clock reading with ties broken by replica, so stamps from
+// different replicas are totally ordered the same way everywhere. A shape is
+// known by the stamp of the edit that added it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Stamp {
+    pub clock: u64,
+    pub replica: u32,
+}
+
+// An edit, made on one replica and applied on the others. Applying the same
+// edits in any order, any number of times, leaves every replica the same.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Op {
+    Add {
+        id: Stamp,
+        shape: ShapeEnum,
+    },
+    // The latest stamp wins when moves race
+    Move {
+        id: Stamp,
+        origin: Coord,
+        stamp: Stamp,
+    },
+    // Wins over any move, before or after
+    Remove {
+        id: Stamp,
+    },
+}
+
+impl Op {
+    // The shape it edits
+    pub fn id(&self) -> Stamp {
+        match self {
+            Op::Add { id, .. } | Op::Move { id, .. } | Op::Remove { id } => *id,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Element {
+    // None until the add arrives, since moves and removes may overtake it
+    shape: Option<ShapeEnum>,
+    origin: Option<(Stamp, Coord)>,
+    removed: bool,
+}
+
+// One copy of a canvas that edits can be made to without talking to the
+// others, as a conflict free replicated data type. Send the ops each edit
+// returns to the other replicas and apply theirs, or merge whole replicas;
+// either way they converge. Shapes are painted in the order of their ids.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Replica {
+    replica: u32,
+    clock: u64,
+    elements: BTreeMap<Stamp, Element>,
+}
+
+impl Replica {
+    // Every replica editing the same canvas needs its own number
+    pub fn new(replica: u32) -> Replica {
+        Replica {
+            replica,
+            ..Replica::default()
+        }
+    }
+
+    pub fn add(&mut self, shape: impl Into<ShapeEnum>) -> Result<Op, ShapeError> {
+        let shape = shape.into();
+        shape.validate()?;
+        let op = Op::Add {
+            id: self.tick(),
+            shape,
+        };
+        self.apply(&op);
+        Ok(op)
+    }
+    // Ids this replica hasn't seen yet are fine, the move waiting for the add
+    pub fn move_to(&mut self, id: Stamp, origin: impl Into<Coord>) -> Result<Op, ShapeError> {
+        let origin = coordinate("origin", origin.into())?;
+        let op = Op::Move {
+            id,
+            origin,
+            stamp: self.tick(),
+        };
+        self.apply(&op);
+        Ok(op)
+    }
+    pub fn remove(&mut self, id: Stamp) -> Op {
+        let op = Op::Remove { id };
+        self.apply(&op);
+        op
+    }
+
+    pub fn apply(&mut self, op: &Op) {
+        match op {
+            Op::Add { id, shape } => {
+                self.witness(*id);
+                let element = self.elements.entry(*id).or_default();
+                element.shape = Some(shape.clone());
+                set_origin(element, *id, shape.origin());
+            }
+            Op::Move { id, origin, stamp } => {
+                self.witness(*stamp);
+                set_origin(self.elements.entry(*id).or_default(), *stamp, *origin);
+            }
+            Op::Remove { id } => self.elements.entry(*id).or_default().removed = true,
+        }
+    }
+    // Takes in every edit the other replica has seen
+    pub fn merge(&mut self, other: &Replica) {
+        self.clock = self.clock.max(other.clock);
+        for (id, theirs) in &other.elements {
+            let ours = self.elements.entry(*id).or_default();
+            if ours.shape.is_none() {
+                ours.shape.clone_from(&theirs.shape);
+            }
+            if let Some((stamp, origin)) = theirs.origin {
+                set_origin(ours, stamp, origin);
+            }
+            ours.removed |= theirs.removed;
+        }
+    }
+
+    // Live shapes with their ids, in painting order
+    pub fn shapes(&self) -> impl Iterator<Item = (Stamp, ShapeEnum)> + '_ {
+        self.elements.iter().filter_map(|(id, element)| {
+            let mut shape = element.shape.clone().filter(|_| !element.removed)?;
+            if let Some((_, origin)) = element.origin {
+                shape.set_origin(origin);
+            }
+            Some((*id, shape))
+        })
+    }
+    pub fn to_canvas(&self) -> Canvas {
+        self.shapes()
+            .map(|(_, shape)| shape.into_object())
+            .collect()
+    }
+
+    fn tick(&mut self) -> Stamp {
+        self.clock += 1;
+        Stamp {
+            clock: self.clock,
+            replica: self.replica,
+        }
+    }
+    fn witness(&mut self, stamp: Stamp) {
+        self.clock = self.clock.max(stamp.clock);
+    }
+}
+
+fn set_origin(element: &mut Element, stamp: Stamp, origin: Coord) {
+    if element.origin.is_none_or(|(current, _)| stamp > current) {
+        element.origin = Some((stamp, origin));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Circle, Rectangle};
+
+    fn origins(replica: &Replica) -> Vec<Coord> {
+        replica.shapes().map(|(_, shape)| shape.origin()).collect()
+    }
+
+    #[test]
+    fn replicas_converge() {
+        let (mut a, mut b) = (Replica::new(1), Replica::new(2));
+        let circle = a
+            .add(Circle {
+                origin: Coord::default(),
+                radius: 1.0,
+            })
+            .unwrap();
+        let shared = circle.id();
+        b.apply(&circle);
+
+        // Both move the circle at once and b adds a shape
+        let from_a = a.move_to(shared, (5.0, 0.0)).unwrap();
+        let from_b = [
+            b.move_to(shared, (0.0, 5.0)).unwrap(),
+            b.add(Rectangle {
+                origin: Coord::new(9.0, 9.0),
+                side_a: 1.0,
+                side_b: 1.0,
+            })
+            .unwrap(),
+        ];
+        for op in &from_b {
+            a.apply(op);
+        }
+        // Out of order and twice over on b
+        b.apply(&from_a);
+        b.apply(&circle);
+        b.apply(&from_a);
+        assert_eq!(origins(&a), origins(&b));
+        // Equal clocks, so the higher replica's move wins
+        assert_eq!(origins(&a)[0], Coord::new(0.0, 5.0));
+
+        // A move overtaking a removal doesn't bring the shape back
+        let removal = a.remove(shared);
+        let late = b.move_to(shared, (1.0, 1.0)).unwrap();
+        a.apply(&late);
+        b.apply(&removal);
+        assert_eq!(origins(&a), [Coord::new(9.0, 9.0)]);
+        assert_eq!(origins(&a), origins(&b));
+
+        // A third replica catching up by merging starts out the same
+        let mut c = Replica::new(3);
+        c.merge(&a);
+        c.merge(&b);
+        assert_eq!(origins(&c), origins(&a));
+        assert_eq!(c.to_canvas().len(), 1);
+        // And its edits sort after everything it has seen
+        let added = c
+            .add(Circle {
+                origin: Coord::default(),
+                radius: 2.0,
+            })
+            .unwrap();
+        assert!(added.id() > from_b[1].id());
+
+        assert!(a
+            .add(Circle {
+                origin: Coord::default(),
+                radius: -1.0
+            })
+            .is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt::Write;
+
+use crate::{arena::ShapeId, Canvas, Coord, Rectangle};
+
+// One row a shape, in painting order, for exporting to spreadsheets and
+// the like. Everything is in canvas units.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    pub rows: Vec<ReportRow>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReportRow {
+    pub id: ShapeId,
+    pub kind: &'static str,
+    pub origin: Coord,
+    pub area: f32,
+    pub perimeter: f32,
+    pub bounds: Rectangle,
+}
+
+const COLUMNS: [&str; 10] = [
+    "id",
+    "kind",
+    "origin_x",
+    "origin_y",
+    "area",
+    "perimeter",
+    "min_x",
+    "min_y",
+    "max_x",
+    "max_y",
+];
+
+impl ReportRow {
+    // In the order of COLUMNS, ids as their packed bits
+    fn values(&self) -> [String; 10] {
+        let (min, max) = (self.bounds.min(), self.bounds.max());
+        [
+            self.id.to_bits().to_string(),
+            self.kind.to_string(),
+            self.origin.x.to_string(),
+            self.origin.y.to_string(),
+            self.area.to_string(),
+            self.perimeter.to_string(),
+            min.x.to_string(),
+            min.y.to_string(),
+            max.x.to_string(),
+            max.y.to_string(),
+        ]
+    }
+}
+
+impl Report {
+    // With a header line
+    pub fn to_csv(&self) -> String {
+        let mut out = COLUMNS.join(",");
+        out.push('\n');
+        for row in &self.rows {
+            out.push_str(&row.values().join(","));
+            out.push('\n');
+        }
+        out
+    }
+    // An array of objects keyed by the CSV column names
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("[");
+        for (i, row) in self.rows.iter().enumerate() {
+            out.push_str(if i == 0 { "\n  {" } else { ",\n  {" });
+            for (j, (column, value)) in COLUMNS.iter().zip(row.values()).enumerate() {
+                let separator = if j == 0 { "" } else { ", " };
+                // The kind is the only string, and names need no escaping
+                let _ = match *column {
+                    "kind" => write!(out, "{separator}\"{column}\": \"{value}\""),
+                    _ => write!(out, "{separator}\"{column}\": {value}"),
+                };
+            }
+            out.push('}');
+        }
+        out.push_str(if self.rows.is_empty() { "]\n" } else { "\n]\n" });
+        out
+    }
+}
+
+impl Canvas {
+    // Leaves out shapes whose locks are poisoned
+    pub fn report(&self) -> Report {
+        let rows = self
+            .shapes
+            .iter()
+            .filter_map(|(id, shape)| {
+                let shape = shape.lock().ok()?;
+                Some(ReportRow {
+                    id,
+                    kind: shape.name(),
+                    origin: shape.origin(),
+                    area: shape.get_area(),
+                    perimeter: shape.perimeter(),
+                    bounds: shape.bounding_box(),
+                })
+            })
+            .collect();
+        Report { rows }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{Circle, ShapeCell};
+
+    #[test]
+    fn report_as_csv_and_json() {
+        let mut canvas = Canvas::default();
+        assert_eq!(canvas.report().to_json(), "[]\n");
+        let square = canvas.add(Arc::new(ShapeCell::new(Rectangle {
+            origin: Coord::new(1.0, 2.0),
+            side_a: 2.0,
+            side_b: 2.0,
+        })));
+        let circle = canvas.add(Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(-3.0, 0.0),
+            radius: 0.5,
+        })));
+        let report = canvas.report();
+        assert_eq!(report.rows.len(), 2);
+        assert_eq!(report.rows[0].id, square);
+        assert_eq!(report.rows[1].kind, "circle");
+        assert_eq!(report.rows[1].bounds, canvas.bounding_box(circle).unwrap());
+
+        let csv = report.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "id,kind,origin_x,origin_y,area,perimeter,min_x,min_y,max_x,max_y"
+        );
+        assert_eq!(
+            lines[1],
+            format!("{},rectangle,1,2,4,8,0,1,2,3", square.to_bits())
+        );
+        assert!(lines[2].starts_with(&format!("{},circle,-3,0,0.785", circle.to_bits())));
+        assert_eq!(lines.len(), 3);
+
+        let json = report.to_json();
+        assert!(json.starts_with(&format!(
+            "[\n  {{\"id\": {}, \"kind\": \"rectangle\", \"origin_x\": 1, \"origin_y\": 2, \
+             \"area\": 4, \"perimeter\": 8, \"min_x\": 0, \"min_y\": 1, \"max_x\": 2, \
+             \"max_y\": 3}},\n  {{\"id\": {}, \"kind\": \"circle\"",
+            square.to_bits(),
+            circle.to_bits()
+        )));
+        assert!(json.ends_with("}\n]\n"));
+    }
+}
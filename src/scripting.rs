@@ -0,0 +1,201 @@
+// Shapes generated by rhai scripts, for patterns that want loops and
+// conditionals. Scripts make shapes with circle(x, y, radius),
+// rectangle(x, y, width, height), triangle(x, y, base, height),
+// square(x, y, side), ellipse(x, y, radius_x, radius_y) and
+// polygon([[x, y], ...], x, y), and work on the canvas with
+//     add(shape) -> id, remove(id), move_to(id, x, y), translate_all(dx, dy),
+//     area(), area(id), len(), hit_test(x, y) -> [id, ...]
+// where shapes also have an area() of their own. Numbers can be written
+// with or without a decimal point; ids are ints. rhai's maths, sin(x) and
+// PI() say, is there too.
+//
+//     for i in 0..12 {
+//         let angle = i * PI() / 6.0;
+//         add(circle(10.0 * cos(angle), 10.0 * sin(angle), 1 + i % 3));
+//     }
+use std::{cell::RefCell, mem, rc::Rc};
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, INT};
+
+use crate::{
+    error::ScriptError, storage::ShapeEnum, Canvas, Circle, Coord, Ellipse, Polygon, Rectangle,
+    Shape, ShapeId, Square, Triangle,
+};
+
+type Fallible<T> = Result<T, Box<EvalAltResult>>;
+
+impl Canvas {
+    // Runs the script against the canvas. Whatever it did before an error
+    // stays done.
+    pub fn run_script(&mut self, script: &str) -> Result<(), ScriptError> {
+        let canvas = Rc::new(RefCell::new(mem::take(self)));
+        let result = engine(&canvas).run(script);
+        *self = mem::take(&mut *canvas.borrow_mut());
+        result.map_err(|error| ScriptError {
+            line: error.position().line(),
+            message: error.to_string(),
+        })
+    }
+}
+
+fn number(value: Dynamic) -> Fallible<f32> {
+    match value.as_int() {
+        Ok(int) => Ok(int as f32),
+        Err(_) => value
+            .as_float()
+            .map_err(|kind| format!("expected a number, got {kind}").into()),
+    }
+}
+fn point(x: Dynamic, y: Dynamic) -> Fallible<Coord> {
+    Ok(Coord::new(number(x)?, number(y)?))
+}
+fn checked(shape: impl Into<ShapeEnum>) -> Fallible<ShapeEnum> {
+    let shape = shape.into();
+    shape.validate().map_err(|error| error.to_string())?;
+    Ok(shape)
+}
+fn id(id: INT) -> ShapeId {
+    ShapeId::from_bits(id as u64)
+}
+
+fn engine(canvas: &Rc<RefCell<Canvas>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.register_type_with_name::<ShapeEnum>("Shape");
+    engine.register_fn("area", |shape: &mut ShapeEnum| shape.get_area());
+
+    engine.register_fn("circle", |x, y, radius| {
+        checked(Circle {
+            origin: point(x, y)?,
+            radius: number(radius)?,
+        })
+    });
+    engine.register_fn("rectangle", |x, y, width, height| {
+        checked(Rectangle {
+            origin: point(x, y)?,
+            side_a: number(width)?,
+            side_b: number(height)?,
+        })
+    });
+    engine.register_fn("triangle", |x, y, base, height| {
+        checked(Triangle {
+            origin: point(x, y)?,
+            base: number(base)?,
+            height: number(height)?,
+        })
+    });
+    engine.register_fn("square", |x, y, side| {
+        checked(Square {
+            origin: point(x, y)?,
+            side: number(side)?,
+        })
+    });
+    engine.register_fn("ellipse", |x, y, radius_x, radius_y| {
+        checked(Ellipse {
+            origin: point(x, y)?,
+            radius_x: number(radius_x)?,
+            radius_y: number(radius_y)?,
+        })
+    });
+    engine.register_fn("polygon", |points: Array, x, y| {
+        let points =
+            points
+                .into_iter()
+                .map(|pair| match pair.into_typed_array::<Dynamic>().as_deref() {
+                    Ok([x, y]) => point(x.clone(), y.clone()),
+                    _ => Err("polygon points are [x, y] pairs".into()),
+                });
+        checked(Polygon {
+            origin: point(x, y)?,
+            points: points.collect::<Fallible<_>>()?,
+        })
+    });
+
+    let shared = Rc::clone(canvas);
+    engine.register_fn("add", move |shape: ShapeEnum| {
+        shared.borrow_mut().add(shape.into_object()).to_bits() as INT
+    });
+    let shared = Rc::clone(canvas);
+    engine.register_fn("remove", move |shape: INT| {
+        shared.borrow_mut().remove(id(shape)).is_some()
+    });
+    let shared = Rc::clone(canvas);
+    engine.register_fn("move_to", move |shape: INT, x, y| -> Fallible<()> {
+        let origin = point(x, y)?;
+        Ok(shared
+            .borrow()
+            .set_origin(id(shape), origin)
+            .map_err(|error| error.to_string())?)
+    });
+    let shared = Rc::clone(canvas);
+    engine.register_fn("translate_all", move |dx, dy| -> Fallible<()> {
+        let offset = point(dx, dy)?;
+        Ok(shared
+            .borrow_mut()
+            .translate_all(offset)
+            .map_err(|error| error.to_string())?)
+    });
+    let shared = Rc::clone(canvas);
+    engine.register_fn("area", move || shared.borrow().total_area());
+    let shared = Rc::clone(canvas);
+    engine.register_fn("area", move |shape: INT| -> Fallible<f32> {
+        let area = shared.borrow().get_area(id(shape));
+        Ok(area.ok_or("no such shape")?)
+    });
+    let shared = Rc::clone(canvas);
+    engine.register_fn("len", move || shared.borrow().len() as INT);
+    let shared = Rc::clone(canvas);
+    engine.register_fn("hit_test", move |x, y| -> Fallible<Array> {
+        let hits = shared.borrow().hit_test(point(x, y)?);
+        Ok(hits
+            .into_iter()
+            .map(|id| Dynamic::from_int(id.to_bits() as INT))
+            .collect())
+    });
+    engine
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+    use crate::assert_approx_eq;
+
+    #[test]
+    fn generate_shapes_from_scripts() {
+        let mut canvas = Canvas::default();
+        canvas
+            .run_script(
+                "
+                // A ring of circles, every other one replaced by a square
+                for i in 0..12 {
+                    let angle = i * PI() / 6.0;
+                    let x = 10.0 * cos(angle);
+                    let y = 10.0 * sin(angle);
+                    let id = add(circle(x, y, 1));
+                    if i % 2 == 1 {
+                        remove(id);
+                        add(square(x, y, 2));
+                    }
+                }
+                let corner = add(polygon([[0, 0], [4, 0], [0, 3]], 0, 0));
+                move_to(corner, -1, -1);
+                if area(corner) != 6.0 || hit_test(-0.5, -0.5) != [corner] {
+                    throw \"polygon misplaced\";
+                }
+                ",
+            )
+            .unwrap();
+        assert_eq!(canvas.len(), 13);
+        assert_approx_eq!(canvas.total_area(), 6.0 * PI + 6.0 * 4.0 + 6.0, 1e-4);
+
+        // Errors say where, and leave what was done before them
+        let error = canvas
+            .run_script("add(circle(0, 0, 1));\nadd(circle(0, 0, -1));")
+            .unwrap_err();
+        assert_eq!(error.line, Some(2));
+        assert!(error.message.contains("radius"), "{error}");
+        assert_eq!(canvas.len(), 14);
+        assert!(canvas.run_script("let x = ;").is_err());
+    }
+}
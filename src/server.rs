@@ -0,0 +1,680 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex, MutexGuard},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    command::{Event, Session},
+    error::{CanvasError, CommandError},
+    metrics::{self, Metrics},
+};
+
+// Requests with bigger bodies are turned away unread
+const MAX_BODY: usize = 1 << 20;
+// How long an event socket gets to take a message before it's dropped
+const WRITE_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Sessions served over HTTP/1.1, one request per connection and one thread
+// per request. Bodies are plain text, mostly the output of the session
+// command a route stands for:
+//
+//   POST   /canvases                       a new canvas
+//   DELETE /canvases/<c>
+//   POST   /canvases/<c>/commands          the body run as a script
+//   GET    /canvases/<c>/shapes            list
+//   POST   /canvases/<c>/shapes            the body is add's arguments
+//   PUT    /canvases/<c>/shapes/<n>        the body is <x> <y> to move to
+//   DELETE /canvases/<c>/shapes/<n>
+//   GET    /canvases/<c>/area
+//   GET    /canvases/<c>/shapes/<n>/area
+//   GET    /canvases/<c>/hit?x=<x>&y=<y>
+//   GET    /canvases/<c>/render.png?width=<w>&height=<h>
+//   GET    /canvases/<c>/events                a WebSocket of changes
+//   GET    /metrics                        every canvas's, for Prometheus
+//
+// The event socket gets a text message per change, as Event displays it,
+// and a last one saying deleted if the canvas goes. Messages from the
+// client are never read; a closed socket is noticed at the next write.
+// Sessions are restricted, so no command a client sends touches the
+// server's files.
+pub struct Server {
+    listener: TcpListener,
+    state: Arc<State>,
+}
+
+#[derive(Default)]
+pub struct State {
+    canvases: Mutex<Vec<Option<Session>>>,
+    // Event sockets, with the index of the canvas each is watching
+    watchers: Mutex<Vec<(usize, TcpStream)>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn text(status: u16, body: impl Into<String>) -> Response {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: body.into().into_bytes(),
+        }
+    }
+    fn output(output: Result<String, CommandError>) -> Response {
+        match output {
+            Ok(output) if output.is_empty() => Response::text(204, ""),
+            Ok(output) => Response::text(200, output),
+            Err(error) => {
+                // A script's status is that of the line it stopped at
+                let mut cause = &error;
+                while let CommandError::Line(_, inner) = cause {
+                    cause = inner;
+                }
+                let status = match cause {
+                    CommandError::UnknownNumber(_) => 404,
+                    CommandError::Restricted(_) => 403,
+                    CommandError::Unsupported(_) => 501,
+                    _ => 400,
+                };
+                Response::text(status, error.to_string())
+            }
+        }
+    }
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            201 => "Created",
+            204 => "No Content",
+            400 => "Bad Request",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Payload Too Large",
+            501 => "Not Implemented",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+impl Server {
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Server> {
+        Ok(Server {
+            listener: TcpListener::bind(address)?,
+            state: Arc::default(),
+        })
+    }
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // Serves until accepting a connection fails
+    pub fn run(&self) -> io::Result<()> {
+        loop {
+            let (stream, _) = self.listener.accept()?;
+            let state = Arc::clone(&self.state);
+            thread::spawn(move || {
+                // A client going away mid request is its own problem
+                let _ = serve(stream, &state);
+            });
+        }
+    }
+}
+
+fn serve(stream: TcpStream, state: &State) -> io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut words = line.split_whitespace();
+    let (method, target) = (words.next().unwrap_or(""), words.next().unwrap_or(""));
+    let (mut length, mut key) = (0, None);
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(0);
+            } else if name.eq_ignore_ascii_case("sec-websocket-key") {
+                key = Some(String::from(value.trim()));
+            }
+        }
+    }
+    if let (Some(key), "GET", Some(canvas)) = (&key, method, watched(target)) {
+        return watch(stream, state, canvas, key);
+    }
+    let response = if length > MAX_BODY {
+        Response::text(413, "body too large")
+    } else {
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        handle(state, method, target, &String::from_utf8_lossy(&body))
+    };
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.reason(),
+        response.content_type,
+        response.body.len()
+    )?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
+
+// One session shared by every connection, driven by the REPL's commands a
+// line at a time. Each reply is any lines of output followed by a line of
+// ok, or else a single line starting error:, so a client reads to one of
+// those. quit closes the connection. The session is restricted, as the
+// HTTP server's are.
+pub struct LineServer {
+    listener: TcpListener,
+    session: Arc<Mutex<Session>>,
+}
+
+impl LineServer {
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<LineServer> {
+        let mut session = Session::new();
+        session.restrict();
+        Ok(LineServer {
+            listener: TcpListener::bind(address)?,
+            session: Arc::new(Mutex::new(session)),
+        })
+    }
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn run(&self) -> io::Result<()> {
+        loop {
+            let (stream, _) = self.listener.accept()?;
+            let session = Arc::clone(&self.session);
+            thread::spawn(move || {
+                let _ = converse(stream, &session);
+            });
+        }
+    }
+}
+
+fn converse(stream: TcpStream, session: &Mutex<Session>) -> io::Result<()> {
+    span!(DEBUG, "converse", peer = ?stream.peer_addr().ok());
+    let mut writer = &stream;
+    for line in BufReader::new(&stream).lines() {
+        let line = line?;
+        if line.trim() == "quit" {
+            break;
+        }
+        let output = match session.lock() {
+            Ok(mut session) => session.execute(&line),
+            Err(_) => Err(CommandError::Canvas(CanvasError::Poisoned)),
+        };
+        match output {
+            Ok(output) if output.is_empty() => writeln!(writer, "ok")?,
+            Ok(output) => writeln!(writer, "{output}\nok")?,
+            // Kept to one line whatever the error says
+            Err(error) => writeln!(writer, "error: {}", error.to_string().replace('\n', " "))?,
+        }
+    }
+    Ok(())
+}
+
+// The canvas number in an event socket's path
+fn watched(target: &str) -> Option<usize> {
+    let number = target.strip_prefix("/canvases/")?.strip_suffix("/events")?;
+    number.parse().ok()
+}
+
+// Completes the WebSocket handshake and leaves the socket for broadcast.
+// The watchers stay locked from the check that the canvas is there until
+// the socket joins them, so no change or delete in between goes unheard,
+// though the canvases are let go before anything is written.
+fn watch(mut stream: TcpStream, state: &State, canvas: usize, key: &str) -> io::Result<()> {
+    let canvases = state.canvases.lock().map_err(|_| io::ErrorKind::Other)?;
+    let index = canvas.checked_sub(1);
+    let found = index.is_some_and(|index| canvases.get(index).is_some_and(Option::is_some));
+    let mut watchers = state.watchers.lock().map_err(|_| io::ErrorKind::Other)?;
+    drop(canvases);
+    // Writes that can't finish in time drop the watcher, and with the
+    // watchers locked the handshake can't hold them for longer either
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    if !found {
+        let body = format!("no canvas numbered {canvas}");
+        return write!(
+            stream,
+            "HTTP/1.1 404 Not Found\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+    }
+    let mut digest = String::from(key);
+    digest.push_str("258EAFA5-E914-47DA-95CA-C5AB0DC85B11");
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        base64(&sha1(digest.as_bytes()))
+    )?;
+    watchers.push((canvas - 1, stream));
+    Ok(())
+}
+
+// Sends text messages to everyone watching the canvas, dropping those
+// that have gone or stopped reading, and with last, every watcher of it.
+// Callers take the watchers before letting go of the canvases, so messages
+// go out in the order their changes were made, but nobody waits on a
+// watcher's socket with the canvases locked.
+fn broadcast(
+    mut watchers: MutexGuard<'_, Vec<(usize, TcpStream)>>,
+    canvas: usize,
+    messages: &[String],
+    last: bool,
+) {
+    watchers.retain_mut(|(watched, stream)| {
+        *watched != canvas
+            || messages
+                .iter()
+                .all(|message| stream.write_all(&frame(message)).is_ok())
+                && !last
+    });
+}
+
+// An unmasked, unfragmented text frame
+fn frame(message: &str) -> Vec<u8> {
+    let length = message.len();
+    let mut frame = vec![0x81];
+    match length {
+        0..=125 => frame.push(length as u8),
+        126..=0xffff => {
+            frame.push(126);
+            frame.extend((length as u16).to_be_bytes());
+        }
+        _ => {
+            frame.push(127);
+            frame.extend((length as u64).to_be_bytes());
+        }
+    }
+    frame.extend(message.as_bytes());
+    frame
+}
+
+// Answers one request, apart from the wire format and event sockets
+pub fn handle(state: &State, method: &str, target: &str, body: &str) -> Response {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let parameter = |name: &str| {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+    };
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let Ok(mut canvases) = state.canvases.lock() else {
+        return Response::text(500, "poisoned");
+    };
+    if let (["canvases"], "POST") = (segments.as_slice(), method) {
+        let mut session = Session::new();
+        session.restrict();
+        session.record_events();
+        canvases.push(Some(session));
+        return Response::text(201, format!("added canvas {}", canvases.len()));
+    }
+    if let (["metrics"], "GET") = (segments.as_slice(), method) {
+        let metrics: Vec<(String, Metrics)> = canvases
+            .iter()
+            .enumerate()
+            .filter_map(|(i, session)| {
+                Some(((i + 1).to_string(), session.as_ref()?.canvas.metrics()))
+            })
+            .collect();
+        let body = metrics::prometheus(metrics.iter().map(|(n, m)| (n.as_str(), m)));
+        return Response {
+            status: 200,
+            content_type: "text/plain; version=0.0.4",
+            body: body.into_bytes(),
+        };
+    }
+    let ["canvases", number, rest @ ..] = segments.as_slice() else {
+        return Response::text(404, "no such route");
+    };
+    let index = number.parse::<usize>().ok().and_then(|n| n.checked_sub(1));
+    let Some((index, slot)) = index.and_then(|index| Some((index, canvases.get_mut(index)?)))
+    else {
+        return Response::text(404, format!("no canvas numbered {number}"));
+    };
+    if let ([], "DELETE") = (rest, method) {
+        return match slot.take() {
+            Some(_) => {
+                if let Ok(watchers) = state.watchers.lock() {
+                    drop(canvases);
+                    broadcast(watchers, index, &[String::from("deleted")], true);
+                }
+                Response::text(204, "")
+            }
+            None => Response::text(404, format!("no canvas numbered {number}")),
+        };
+    }
+    let Some(session) = slot else {
+        return Response::text(404, format!("no canvas numbered {number}"));
+    };
+    let response = route(session, rest, method, body, &parameter);
+    let events: Vec<String> = session.take_events().iter().map(Event::to_string).collect();
+    if !events.is_empty() {
+        if let Ok(watchers) = state.watchers.lock() {
+            drop(canvases);
+            broadcast(watchers, index, &events, false);
+        }
+    }
+    response
+}
+
+fn route<'a>(
+    session: &mut Session,
+    rest: &[&str],
+    method: &str,
+    body: &str,
+    parameter: &impl Fn(&str) -> Option<&'a str>,
+) -> Response {
+    let output = match (rest, method) {
+        (["commands"], "POST") => session.run(body),
+        (["shapes"], "GET") => session.execute("list"),
+        (["shapes"], "POST") => {
+            let output = session.execute(&format!("add {body}"));
+            return match output {
+                Ok(output) => Response::text(201, output),
+                error => Response::output(error),
+            };
+        }
+        (["shapes", n], "PUT") => session.execute(&format!("move {n} to {body}")),
+        (["shapes", n], "DELETE") => session.execute(&format!("remove {n}")),
+        (["area"], "GET") => session.execute("area"),
+        (["shapes", n, "area"], "GET") => session.execute(&format!("area {n}")),
+        (["hit"], "GET") => match (parameter("x"), parameter("y")) {
+            (Some(x), Some(y)) => session.execute(&format!("hit {x} {y}")),
+            _ => Err(CommandError::Usage("hit?x=<x>&y=<y>")),
+        },
+        (["render.png"], "GET") => return render(session, parameter("width"), parameter("height")),
+        (
+            ["commands" | "shapes" | "area" | "hit" | "render.png"]
+            | ["shapes", _]
+            | ["shapes", _, "area"],
+            _,
+        ) => return Response::text(405, format!("{method} not allowed here")),
+        _ => return Response::text(404, "no such route"),
+    };
+    Response::output(output)
+}
+
+#[cfg(feature = "render")]
+fn render(session: &Session, width: Option<&str>, height: Option<&str>) -> Response {
+    let size = |value: Option<&str>| value.map_or(Ok(512), str::parse);
+    // Bigger images are refused rather than allocated
+    const MAX_SIDE: u32 = 8192;
+    let usage = "render.png?width=<width>&height=<height>, at most 8192 each";
+    let (Ok(width), Ok(height)) = (size(width), size(height)) else {
+        return Response::output(Err(CommandError::Usage(usage)));
+    };
+    if width > MAX_SIDE || height > MAX_SIDE {
+        return Response::output(Err(CommandError::Usage(usage)));
+    }
+    match session
+        .image(width, height)
+        .and_then(|image| Ok(image.to_png()?))
+    {
+        Ok(png) => Response {
+            status: 200,
+            content_type: "image/png",
+            body: png,
+        },
+        Err(error) => Response::output(Err(error)),
+    }
+}
+#[cfg(not(feature = "render"))]
+fn render(_: &Session, _: Option<&str>, _: Option<&str>) -> Response {
+    Response::output(Err(CommandError::Unsupported("render")))
+}
+
+// Only for the WebSocket handshake, which needs nothing stronger
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((data.len() as u64 * 8).to_be_bytes());
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for i in 0..80 {
+            w[i] = match i {
+                0..=15 => u32::from_be_bytes(block[i * 4..i * 4 + 4].try_into().unwrap()),
+                _ => (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1),
+            };
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let next = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            (a, b, c, d, e) = (next, a, b.rotate_left(30), c, d);
+        }
+        for (h, x) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(x);
+        }
+    }
+    let mut digest = [0; 20];
+    for (bytes, word) in digest.chunks_mut(4).zip(h) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| {
+            bits | (byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            out.push(match i <= chunk.len() {
+                true => ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char,
+                false => '=',
+            });
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(response: Response) -> (u16, String) {
+        (response.status, String::from_utf8(response.body).unwrap())
+    }
+
+    #[test]
+    fn routes() {
+        let state = State::default();
+        let request = |method, target, body| text(handle(&state, method, target, body));
+        assert_eq!(
+            request("POST", "/canvases", ""),
+            (201, String::from("added canvas 1"))
+        );
+        assert_eq!(
+            request("POST", "/canvases/1/shapes", "circle 1 at 0 0"),
+            (201, String::from("added shape 1"))
+        );
+        request("POST", "/canvases/1/shapes", "rectangle 2 2 at 10 10");
+        assert_eq!(request("PUT", "/canvases/1/shapes/2", "5 5").0, 204);
+        assert_eq!(
+            request("GET", "/canvases/1/hit?x=5.5&y=4.5", ""),
+            (200, String::from("2"))
+        );
+        assert_eq!(
+            request("GET", "/canvases/1/shapes/2/area", ""),
+            (200, String::from("4"))
+        );
+        assert_eq!(request("DELETE", "/canvases/1/shapes/1", "").0, 204);
+        assert_eq!(
+            request("GET", "/canvases/1/shapes", ""),
+            (200, String::from("2 rectangle at 5 5, area 4"))
+        );
+        assert_eq!(
+            request(
+                "POST",
+                "/canvases/1/commands",
+                "add circle 1 at 0 0\narea 3"
+            ),
+            (200, String::from("added shape 3\n3.1415927"))
+        );
+
+        assert_eq!(request("GET", "/canvases/1/shapes/1/area", "").0, 404);
+        assert_eq!(request("POST", "/canvases/1/shapes", "blob").0, 400);
+        assert_eq!(request("PATCH", "/canvases/1/shapes", "").0, 405);
+        // Nothing a client sends writes files, and images have a size limit
+        assert_eq!(
+            request("POST", "/canvases/1/commands", "render /tmp/out.png").0,
+            403
+        );
+        #[cfg(feature = "render")]
+        assert_eq!(
+            request("GET", "/canvases/1/render.png?width=100000&height=8", "").0,
+            400
+        );
+        assert_eq!(request("GET", "/canvases/2/shapes", "").0, 404);
+        assert_eq!(request("GET", "/elsewhere", "").0, 404);
+        let (status, metrics) = request("GET", "/metrics", "");
+        assert_eq!(status, 200);
+        assert!(metrics.contains("kaleidoscope_adds_total{canvas=\"1\"} 3\n"));
+        assert!(metrics.contains("kaleidoscope_hit_tests_total{canvas=\"1\"} 1\n"));
+        assert_eq!(request("DELETE", "/canvases/1", "").0, 204);
+        assert_eq!(request("GET", "/canvases/1/area", "").0, 404);
+    }
+
+    #[test]
+    fn over_tcp() {
+        let server = Server::bind("127.0.0.1:0").unwrap();
+        let address = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+        let request = |request: &str| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).unwrap();
+            String::from_utf8_lossy(&response).into_owned()
+        };
+        let created = request("POST /canvases HTTP/1.1\r\nHost: test\r\n\r\n");
+        assert!(created.starts_with("HTTP/1.1 201 Created\r\n"), "{created}");
+        assert!(created.ends_with("\r\n\r\nadded canvas 1"));
+        let added = request(
+            "POST /canvases/1/shapes HTTP/1.1\r\nContent-Length: 15\r\n\r\ncircle 2 at 0 0",
+        );
+        assert!(added.ends_with("added shape 1"), "{added}");
+        #[cfg(feature = "render")]
+        {
+            let png = request("GET /canvases/1/render.png?width=8&height=8 HTTP/1.1\r\n\r\n");
+            assert!(png.contains("Content-Type: image/png\r\n"));
+        }
+
+        let mut events = TcpStream::connect(address).unwrap();
+        events
+            .write_all(
+                b"GET /canvases/1/events HTTP/1.1\r\nUpgrade: websocket\r\n\
+                  Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                  Sec-WebSocket-Version: 13\r\n\r\n",
+            )
+            .unwrap();
+        let mut reader = BufReader::new(&events);
+        let mut handshake = String::new();
+        while !handshake.ends_with("\r\n\r\n") {
+            reader.read_line(&mut handshake).unwrap();
+        }
+        assert!(handshake.starts_with("HTTP/1.1 101 "), "{handshake}");
+        // The accept key given for this client key in RFC 6455
+        assert!(handshake.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+
+        request("PUT /canvases/1/shapes/1 HTTP/1.1\r\nContent-Length: 3\r\n\r\n4 5");
+        request("DELETE /canvases/1 HTTP/1.1\r\n\r\n");
+        let mut messages = Vec::new();
+        reader.read_to_end(&mut messages).unwrap();
+        assert_eq!(
+            messages,
+            [frame("moved 1 to 4 5"), frame("deleted")].concat()
+        );
+    }
+
+    #[test]
+    fn stalled_watchers_are_dropped() {
+        let state = State::default();
+        handle(&state, "POST", "/canvases", "");
+        handle(&state, "POST", "/canvases/1/shapes", "circle 1 at 0 0");
+        // A watcher that never reads, as watch leaves one
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stalled, _) = listener.accept().unwrap();
+        stalled.set_write_timeout(Some(WRITE_TIMEOUT)).unwrap();
+        state.watchers.lock().unwrap().push((0, stalled));
+
+        let script: String = (0..20_000).map(|x| format!("move 1 to {x} 0\n")).collect();
+        for _ in 0..100 {
+            if state.watchers.lock().unwrap().is_empty() {
+                break;
+            }
+            let response = handle(&state, "POST", "/canvases/1/commands", &script);
+            assert_eq!(response.status, 204);
+        }
+        assert!(state.watchers.lock().unwrap().is_empty());
+        assert_eq!(handle(&state, "GET", "/canvases/1/area", "").status, 200);
+    }
+
+    #[test]
+    fn line_protocol() {
+        let server = LineServer::bind("127.0.0.1:0").unwrap();
+        let address = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+        let converse = |lines: &str| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(lines.as_bytes()).unwrap();
+            let mut replies = String::new();
+            stream.read_to_string(&mut replies).unwrap();
+            replies
+        };
+        assert_eq!(
+            converse("add circle 1 at 0 0\nmove 1 to 2 2\nmove 1\nquit\narea\n"),
+            "added shape 1\nok\nok\nerror: usage: move <n> to <x> <y>\n"
+        );
+        // Later connections see the same canvas
+        assert_eq!(
+            converse("add rectangle 1 1 at 9 9\nlist\nquit\n"),
+            "added shape 2\nok\n\
+             1 circle at 2 2, area 3.1415927\n\
+             2 rectangle at 9 9, area 1\nok\n"
+        );
+        assert_eq!(
+            converse("render out.png\nquit\n"),
+            "error: render is not allowed in this session\n"
+        );
+    }
+
+    #[test]
+    fn handshake_encoding() {
+        assert_eq!(base64(&sha1(b"abc")), "qZk+NkcGgWq6PiVxeFDCbJzQ2J0=");
+        assert_eq!(base64(&sha1(b"")), "2jmj7l5rSw0yVb/vlWAYkK/YBwk=");
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(frame(&"x".repeat(200))[..4], [0x81, 126, 0, 200]);
+    }
+}
@@ -0,0 +1,982 @@
+use alloc::{vec, vec::Vec};
+use core::{
+    any::Any,
+    f32::consts::PI,
+    ops::{Add, Mul, Neg, Sub},
+};
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+#[cfg(feature = "std")]
+use crate::cell::{ShapeCell, ShapeObject};
+#[cfg(not(any(feature = "std", test)))]
+use crate::math::Float;
+use crate::{
+    builder::{coordinate, dimension},
+    error::ShapeError,
+    geometry::{self, triangulate, Outline, Triangle3},
+    transform::{Transform, Transformed},
+};
+
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Coord {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Coord {
+    pub fn new(x: f32, y: f32) -> Coord {
+        Coord { x, y }
+    }
+    pub fn dot(self, other: Coord) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+    pub fn cross(self, other: Coord) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+    pub fn length(self) -> f32 {
+        self.x.hypot(self.y)
+    }
+    pub fn distance(self, other: Coord) -> f32 {
+        (self - other).length()
+    }
+    // Counter-clockwise about the zero origin
+    pub fn rotate(self, angle: f32) -> Coord {
+        let (sin, cos) = angle.sin_cos();
+        Coord::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+}
+
+// For vectors from other libraries, most of which convert to and from
+// arrays or tuples of two floats
+impl From<(f32, f32)> for Coord {
+    fn from((x, y): (f32, f32)) -> Coord {
+        Coord::new(x, y)
+    }
+}
+impl From<[f32; 2]> for Coord {
+    fn from([x, y]: [f32; 2]) -> Coord {
+        Coord::new(x, y)
+    }
+}
+impl From<Coord> for (f32, f32) {
+    fn from(coord: Coord) -> (f32, f32) {
+        (coord.x, coord.y)
+    }
+}
+impl From<Coord> for [f32; 2] {
+    fn from(coord: Coord) -> [f32; 2] {
+        [coord.x, coord.y]
+    }
+}
+
+impl Add for Coord {
+    type Output = Coord;
+    fn add(self, other: Coord) -> Coord {
+        Coord::new(self.x + other.x, self.y + other.y)
+    }
+}
+impl Sub for Coord {
+    type Output = Coord;
+    fn sub(self, other: Coord) -> Coord {
+        Coord::new(self.x - other.x, self.y - other.y)
+    }
+}
+impl Mul<f32> for Coord {
+    type Output = Coord;
+    fn mul(self, factor: f32) -> Coord {
+        Coord::new(self.x * factor, self.y * factor)
+    }
+}
+impl Neg for Coord {
+    type Output = Coord;
+    fn neg(self) -> Coord {
+        Coord::new(-self.x, -self.y)
+    }
+}
+
+pub trait Shape {
+    fn origin(&self) -> Coord;
+    fn set_origin(&mut self, origin: Coord);
+    fn get_area(&self) -> f32;
+    fn outline(&self) -> Outline;
+    // About axes through the centroid, measured from the outline unless the
+    // shape knows better
+    fn second_moment_of_area(&self) -> SecondMoments {
+        self.outline().second_moments()
+    }
+    // About the axis through the centroid at right angles to the plane
+    fn polar_moment_of_area(&self) -> f32 {
+        let moments = self.second_moment_of_area();
+        moments.ix + moments.iy
+    }
+    #[cfg(feature = "std")]
+    fn duplicate(&self) -> ShapeObject;
+    // Coordinates must be finite and dimensions positive and finite, as the
+    // builders enforce
+    fn validate(&self) -> Result<(), ShapeError>;
+
+    // What kind of shape it is, in lower case. Shapes defined outside the
+    // crate get their type's name unless they say otherwise.
+    fn name(&self) -> &'static str {
+        core::any::type_name::<Self>()
+    }
+    // The shape as its concrete type, for code that knows it, such as the
+    // hooks of a ShapeRegistry. Shapes defined outside the crate return
+    // Some(self) to be told apart.
+    fn as_any(&self) -> Option<&dyn Any> {
+        None
+    }
+    // Dimensions by name, for scripts and editors that don't know the
+    // concrete type. Names are those of the fields, and values set are
+    // checked as the builders check them.
+    fn properties(&self) -> &'static [&'static str] {
+        &[]
+    }
+    fn get_property(&self, _name: &str) -> Option<f32> {
+        None
+    }
+    fn set_property(&mut self, name: &str, _value: f32) -> Result<(), ShapeError> {
+        Err(ShapeError::UnknownProperty(name.into()))
+    }
+    // Bytes allocated beyond the shape itself, for memory reports
+    fn heap_bytes(&self) -> usize {
+        0
+    }
+    fn bounding_box(&self) -> Rectangle {
+        self.outline().bounding_box()
+    }
+    fn perimeter(&self) -> f32 {
+        self.outline().perimeter()
+    }
+    // Zero when the point is inside the shape
+    fn distance_to(&self, point: Coord) -> f32 {
+        self.outline().distance_to(point)
+    }
+    // Where the segment p0 -> p1 crosses the boundary, ordered from p0
+    fn intersect_line(&self, p0: Coord, p1: Coord) -> Vec<Coord> {
+        let direction = p1 - p0;
+        self.outline()
+            .crossings(p0, direction, 1.0)
+            .into_iter()
+            .map(|t| p0 + direction * t)
+            .collect()
+    }
+    // Wrapped so that it is drawn and measured through the transform
+    fn transformed(self, transform: Transform) -> Transformed<Self>
+    where
+        Self: Sized,
+    {
+        Transformed {
+            shape: self,
+            transform,
+        }
+    }
+    // Grown outwards (or shrunk for negative distances) with round joins
+    fn offset(&self, distance: f32) -> Polygon {
+        let origin = self.origin();
+        let points = geometry::offset(&self.outline(), distance);
+        Polygon {
+            origin,
+            points: points.into_iter().map(|p| p - origin).collect(),
+        }
+    }
+}
+
+// Second moments of area about axes through the centroid: ix about the one
+// parallel to the x axis, so the integral of y squared, iy about the one
+// parallel to the y axis, and ixy the product of the two
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SecondMoments {
+    pub ix: f32,
+    pub iy: f32,
+    pub ixy: f32,
+}
+
+// Shapes are positioned by the centre of their bounding box
+#[derive(Clone, Debug, PartialEq)]
+pub struct Circle {
+    pub origin: Coord,
+    pub radius: f32,
+}
+// side_a runs along the x axis, side_b along the y axis
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rectangle {
+    pub origin: Coord,
+    pub side_a: f32,
+    pub side_b: f32,
+}
+// Isosceles, with the base parallel to the x axis and the apex above it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Triangle {
+    pub origin: Coord,
+    pub base: f32,
+    pub height: f32,
+}
+// Points are offsets from the origin and may be given in either winding order
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polygon {
+    pub origin: Coord,
+    pub points: Vec<Coord>,
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct Square {
+    pub origin: Coord,
+    pub side: f32,
+}
+// Axis aligned, radius_x along the x axis and radius_y along the y axis
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ellipse {
+    pub origin: Coord,
+    pub radius_x: f32,
+    pub radius_y: f32,
+}
+
+impl Rectangle {
+    pub fn from_corners(min: Coord, max: Coord) -> Rectangle {
+        Rectangle {
+            origin: (min + max) * 0.5,
+            side_a: max.x - min.x,
+            side_b: max.y - min.y,
+        }
+    }
+    pub fn min(&self) -> Coord {
+        self.origin - Coord::new(self.side_a, self.side_b) * 0.5
+    }
+    pub fn max(&self) -> Coord {
+        self.origin + Coord::new(self.side_a, self.side_b) * 0.5
+    }
+    // Gap between two axis aligned boxes, zero if they overlap
+    pub fn distance_to_box(&self, other: &Rectangle) -> f32 {
+        let dx = (other.min().x - self.max().x).max(self.min().x - other.max().x);
+        let dy = (other.min().y - self.max().y).max(self.min().y - other.max().y);
+        dx.max(0.0).hypot(dy.max(0.0))
+    }
+}
+
+impl Shape for Circle {
+    fn name(&self) -> &'static str {
+        "circle"
+    }
+
+    fn properties(&self) -> &'static [&'static str] {
+        &["radius"]
+    }
+    fn get_property(&self, name: &str) -> Option<f32> {
+        match name {
+            "radius" => Some(self.radius),
+            _ => None,
+        }
+    }
+    fn set_property(&mut self, name: &str, value: f32) -> Result<(), ShapeError> {
+        match name {
+            "radius" => self.radius = dimension("radius", Some(value))?,
+            _ => return Err(ShapeError::UnknownProperty(name.into())),
+        }
+        Ok(())
+    }
+
+    fn origin(&self) -> Coord {
+        self.origin
+    }
+
+    fn set_origin(&mut self, origin: Coord) {
+        self.origin = origin;
+    }
+
+    fn get_area(&self) -> f32 {
+        PI * self.radius.powi(2)
+    }
+    fn second_moment_of_area(&self) -> SecondMoments {
+        let i = PI * self.radius.powi(4) / 4.0;
+        SecondMoments {
+            ix: i,
+            iy: i,
+            ixy: 0.0,
+        }
+    }
+
+    fn outline(&self) -> Outline {
+        Outline::Circle {
+            center: self.origin,
+            radius: self.radius,
+        }
+    }
+
+    fn validate(&self) -> Result<(), ShapeError> {
+        coordinate("origin", self.origin)?;
+        dimension("radius", Some(self.radius))?;
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    fn duplicate(&self) -> ShapeObject {
+        Arc::new(ShapeCell::new(self.clone()))
+    }
+}
+impl Shape for Rectangle {
+    fn name(&self) -> &'static str {
+        "rectangle"
+    }
+
+    fn properties(&self) -> &'static [&'static str] {
+        &["side_a", "side_b"]
+    }
+    fn get_property(&self, name: &str) -> Option<f32> {
+        match name {
+            "side_a" => Some(self.side_a),
+            "side_b" => Some(self.side_b),
+            _ => None,
+        }
+    }
+    fn set_property(&mut self, name: &str, value: f32) -> Result<(), ShapeError> {
+        match name {
+            "side_a" => self.side_a = dimension("side_a", Some(value))?,
+            "side_b" => self.side_b = dimension("side_b", Some(value))?,
+            _ => return Err(ShapeError::UnknownProperty(name.into())),
+        }
+        Ok(())
+    }
+
+    fn origin(&self) -> Coord {
+        self.origin
+    }
+
+    fn set_origin(&mut self, origin: Coord) {
+        self.origin = origin;
+    }
+
+    fn get_area(&self) -> f32 {
+        self.side_a * self.side_b
+    }
+    fn second_moment_of_area(&self) -> SecondMoments {
+        SecondMoments {
+            ix: self.side_a * self.side_b.powi(3) / 12.0,
+            iy: self.side_b * self.side_a.powi(3) / 12.0,
+            ixy: 0.0,
+        }
+    }
+
+    fn outline(&self) -> Outline {
+        let (min, max) = (self.min(), self.max());
+        Outline::Polygon(vec![
+            min,
+            Coord::new(max.x, min.y),
+            max,
+            Coord::new(min.x, max.y),
+        ])
+    }
+
+    fn validate(&self) -> Result<(), ShapeError> {
+        coordinate("origin", self.origin)?;
+        dimension("side_a", Some(self.side_a))?;
+        dimension("side_b", Some(self.side_b))?;
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    fn duplicate(&self) -> ShapeObject {
+        Arc::new(ShapeCell::new(self.clone()))
+    }
+}
+impl Shape for Triangle {
+    fn name(&self) -> &'static str {
+        "triangle"
+    }
+
+    fn properties(&self) -> &'static [&'static str] {
+        &["base", "height"]
+    }
+    fn get_property(&self, name: &str) -> Option<f32> {
+        match name {
+            "base" => Some(self.base),
+            "height" => Some(self.height),
+            _ => None,
+        }
+    }
+    fn set_property(&mut self, name: &str, value: f32) -> Result<(), ShapeError> {
+        match name {
+            "base" => self.base = dimension("base", Some(value))?,
+            "height" => self.height = dimension("height", Some(value))?,
+            _ => return Err(ShapeError::UnknownProperty(name.into())),
+        }
+        Ok(())
+    }
+
+    fn origin(&self) -> Coord {
+        self.origin
+    }
+
+    fn set_origin(&mut self, origin: Coord) {
+        self.origin = origin;
+    }
+
+    fn get_area(&self) -> f32 {
+        0.5 * self.base * self.height
+    }
+    // The centroid is a third of the way up from the base
+    fn second_moment_of_area(&self) -> SecondMoments {
+        SecondMoments {
+            ix: self.base * self.height.powi(3) / 36.0,
+            iy: self.height * self.base.powi(3) / 48.0,
+            ixy: 0.0,
+        }
+    }
+
+    fn outline(&self) -> Outline {
+        let Coord { x, y } = self.origin;
+        let (half_base, half_height) = (self.base / 2.0, self.height / 2.0);
+        Outline::Polygon(vec![
+            Coord::new(x - half_base, y - half_height),
+            Coord::new(x + half_base, y - half_height),
+            Coord::new(x, y + half_height),
+        ])
+    }
+
+    fn validate(&self) -> Result<(), ShapeError> {
+        coordinate("origin", self.origin)?;
+        dimension("base", Some(self.base))?;
+        dimension("height", Some(self.height))?;
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    fn duplicate(&self) -> ShapeObject {
+        Arc::new(ShapeCell::new(self.clone()))
+    }
+}
+
+impl Polygon {
+    // Absolute vertex positions in counter-clockwise order
+    pub fn vertices(&self) -> Vec<Coord> {
+        let mut vertices: Vec<Coord> = self.points.iter().map(|p| self.origin + *p).collect();
+        if geometry::signed_area(&vertices) < 0.0 {
+            vertices.reverse();
+        }
+        vertices
+    }
+
+    // Douglas-Peucker: drops vertices that deviate from the simplified
+    // outline by no more than the tolerance
+    pub fn simplify(&self, tolerance: f32) -> Polygon {
+        let points = &self.points;
+        if points.len() <= 3 {
+            return self.clone();
+        }
+        // Split the closed ring into two chains at the vertex furthest from
+        // the first one, since both ends of a chain are always kept
+        let far = (1..points.len())
+            .max_by(|&a, &b| {
+                let (da, db) = (points[0].distance(points[a]), points[0].distance(points[b]));
+                da.total_cmp(&db)
+            })
+            .unwrap();
+        let mut simplified = geometry::simplify_chain(&points[..=far], tolerance);
+        let mut closing = points[far..].to_vec();
+        closing.push(points[0]);
+        simplified.pop();
+        simplified.extend(geometry::simplify_chain(&closing, tolerance));
+        simplified.pop();
+        if simplified.len() < 3 {
+            return self.clone();
+        }
+        Polygon {
+            origin: self.origin,
+            points: simplified,
+        }
+    }
+}
+
+impl Shape for Polygon {
+    fn name(&self) -> &'static str {
+        "polygon"
+    }
+
+    fn heap_bytes(&self) -> usize {
+        self.points.capacity() * core::mem::size_of::<Coord>()
+    }
+
+    fn origin(&self) -> Coord {
+        self.origin
+    }
+
+    fn set_origin(&mut self, origin: Coord) {
+        self.origin = origin;
+    }
+
+    // Summed over the triangulation so concave polygons are handled too
+    fn get_area(&self) -> f32 {
+        triangulate(self).iter().map(Triangle3::area).sum()
+    }
+
+    fn outline(&self) -> Outline {
+        Outline::Polygon(self.vertices())
+    }
+
+    fn validate(&self) -> Result<(), ShapeError> {
+        coordinate("origin", self.origin)?;
+        for point in &self.points {
+            coordinate("points", *point)?;
+        }
+        if self.points.len() < 3 || geometry::signed_area(&self.points) == 0.0 {
+            return Err(ShapeError::DegeneratePolygon);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    fn duplicate(&self) -> ShapeObject {
+        Arc::new(ShapeCell::new(self.clone()))
+    }
+}
+
+impl Shape for Square {
+    fn name(&self) -> &'static str {
+        "square"
+    }
+
+    fn properties(&self) -> &'static [&'static str] {
+        &["side"]
+    }
+    fn get_property(&self, name: &str) -> Option<f32> {
+        match name {
+            "side" => Some(self.side),
+            _ => None,
+        }
+    }
+    fn set_property(&mut self, name: &str, value: f32) -> Result<(), ShapeError> {
+        match name {
+            "side" => self.side = dimension("side", Some(value))?,
+            _ => return Err(ShapeError::UnknownProperty(name.into())),
+        }
+        Ok(())
+    }
+
+    fn origin(&self) -> Coord {
+        self.origin
+    }
+
+    fn set_origin(&mut self, origin: Coord) {
+        self.origin = origin;
+    }
+
+    fn get_area(&self) -> f32 {
+        self.side * self.side
+    }
+    fn second_moment_of_area(&self) -> SecondMoments {
+        Rectangle::from(self.clone()).second_moment_of_area()
+    }
+
+    fn outline(&self) -> Outline {
+        Rectangle::from(self.clone()).outline()
+    }
+
+    #[cfg(feature = "std")]
+    fn duplicate(&self) -> ShapeObject {
+        Arc::new(ShapeCell::new(self.clone()))
+    }
+
+    fn validate(&self) -> Result<(), ShapeError> {
+        coordinate("origin", self.origin)?;
+        dimension("side", Some(self.side))?;
+        Ok(())
+    }
+}
+
+impl Shape for Ellipse {
+    fn name(&self) -> &'static str {
+        "ellipse"
+    }
+
+    fn properties(&self) -> &'static [&'static str] {
+        &["radius_x", "radius_y"]
+    }
+    fn get_property(&self, name: &str) -> Option<f32> {
+        match name {
+            "radius_x" => Some(self.radius_x),
+            "radius_y" => Some(self.radius_y),
+            _ => None,
+        }
+    }
+    fn set_property(&mut self, name: &str, value: f32) -> Result<(), ShapeError> {
+        match name {
+            "radius_x" => self.radius_x = dimension("radius_x", Some(value))?,
+            "radius_y" => self.radius_y = dimension("radius_y", Some(value))?,
+            _ => return Err(ShapeError::UnknownProperty(name.into())),
+        }
+        Ok(())
+    }
+
+    fn origin(&self) -> Coord {
+        self.origin
+    }
+
+    fn set_origin(&mut self, origin: Coord) {
+        self.origin = origin;
+    }
+
+    fn get_area(&self) -> f32 {
+        PI * self.radius_x * self.radius_y
+    }
+    // Exact, where the outline is sampled
+    fn second_moment_of_area(&self) -> SecondMoments {
+        SecondMoments {
+            ix: PI * self.radius_x * self.radius_y.powi(3) / 4.0,
+            iy: PI * self.radius_y * self.radius_x.powi(3) / 4.0,
+            ixy: 0.0,
+        }
+    }
+
+    // Sampled like circles are elsewhere, at the same angular resolution
+    fn outline(&self) -> Outline {
+        let points = geometry::circle_points(Coord::default(), 1.0)
+            .into_iter()
+            .map(|p| self.origin + Coord::new(p.x * self.radius_x, p.y * self.radius_y))
+            .collect();
+        Outline::Polygon(points)
+    }
+
+    #[cfg(feature = "std")]
+    fn duplicate(&self) -> ShapeObject {
+        Arc::new(ShapeCell::new(self.clone()))
+    }
+
+    fn validate(&self) -> Result<(), ShapeError> {
+        coordinate("origin", self.origin)?;
+        dimension("radius_x", Some(self.radius_x))?;
+        dimension("radius_y", Some(self.radius_y))?;
+        Ok(())
+    }
+}
+
+impl From<Square> for Rectangle {
+    fn from(square: Square) -> Rectangle {
+        Rectangle {
+            origin: square.origin,
+            side_a: square.side,
+            side_b: square.side,
+        }
+    }
+}
+impl From<Circle> for Ellipse {
+    fn from(circle: Circle) -> Ellipse {
+        Ellipse {
+            origin: circle.origin,
+            radius_x: circle.radius,
+            radius_y: circle.radius,
+        }
+    }
+}
+impl From<Rectangle> for Polygon {
+    fn from(rectangle: Rectangle) -> Polygon {
+        polygon_of(&rectangle)
+    }
+}
+impl From<Triangle> for Polygon {
+    fn from(triangle: Triangle) -> Polygon {
+        polygon_of(&triangle)
+    }
+}
+
+// For shapes whose outline is already a polygon
+fn polygon_of(shape: &dyn Shape) -> Polygon {
+    let origin = shape.origin();
+    Polygon {
+        origin,
+        points: geometry::outline_points(shape.outline())
+            .into_iter()
+            .map(|point| point - origin)
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    use proptest::{collection::vec, prelude::*};
+
+    use super::*;
+    #[cfg(feature = "std")]
+    use crate::{approx, storage::ShapeEnum};
+    use crate::{assert_approx_eq, assert_area_eq};
+
+    // Test the area computations
+    #[test]
+    fn calculate_shape_areas() {
+        let rectangle = Rectangle {
+            origin: Coord::default(),
+            side_a: 2.0,
+            side_b: 4.0,
+        };
+        assert_area_eq!(rectangle, 8.0);
+
+        // Conversions keep the origin and the area
+        let triangle = Triangle {
+            origin: Coord::new(1.0, 1.0),
+            base: 2.0,
+            height: 3.0,
+        };
+        let polygon = Polygon::from(triangle.clone());
+        assert_eq!(polygon.origin, triangle.origin);
+        assert_area_eq!(polygon, triangle.get_area());
+        assert_area_eq!(Polygon::from(rectangle.clone()), 8.0);
+        let square = Square {
+            origin: Coord::new(2.0, 0.0),
+            side: 3.0,
+        };
+        assert_area_eq!(Rectangle::from(square.clone()), square.get_area());
+        let circle = Circle {
+            origin: Coord::default(),
+            radius: 2.0,
+        };
+        assert_area_eq!(Ellipse::from(circle.clone()), circle.get_area());
+    }
+
+    // Property tests, over shapes of every kind anywhere from -50 to 50 and
+    // from tiny to large. proptest shrinks a failing shape to a small one.
+    #[cfg(feature = "std")]
+    fn coords(range: f32) -> impl Strategy<Value = Coord> {
+        (-range..range, -range..range).prop_map(|(x, y)| Coord::new(x, y))
+    }
+    #[cfg(feature = "std")]
+    fn shapes() -> impl Strategy<Value = ShapeEnum> {
+        let size = || 0.1f32..20.0;
+        let origin = || coords(50.0);
+        prop_oneof![
+            (origin(), size()).prop_map(|(origin, radius)| Circle { origin, radius }.into()),
+            (origin(), size(), size()).prop_map(|(origin, side_a, side_b)| {
+                Rectangle {
+                    origin,
+                    side_a,
+                    side_b,
+                }
+                .into()
+            }),
+            (origin(), size(), size()).prop_map(|(origin, base, height)| {
+                Triangle {
+                    origin,
+                    base,
+                    height,
+                }
+                .into()
+            }),
+            (origin(), size()).prop_map(|(origin, side)| Square { origin, side }.into()),
+            (origin(), size(), size()).prop_map(|(origin, radius_x, radius_y)| {
+                Ellipse {
+                    origin,
+                    radius_x,
+                    radius_y,
+                }
+                .into()
+            }),
+            // Star shaped, so always simple: vertices in angle order, each
+            // at its own distance from the origin
+            (origin(), vec((size(), 0.0f32..0.8), 3..9)).prop_map(|(origin, radii)| {
+                let sides = radii.len() as f32;
+                let points = radii
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &(radius, turn))| {
+                        Coord::new(radius, 0.0).rotate(2.0 * PI * (i as f32 + turn) / sides)
+                    })
+                    .collect();
+                Polygon { origin, points }.into()
+            }),
+        ]
+    }
+    #[cfg(feature = "std")]
+    fn close(a: f32, b: f32) -> bool {
+        approx::within(a, b, 1e-4)
+    }
+    #[cfg(feature = "std")]
+    fn scaled(shape: &ShapeEnum, k: f32) -> ShapeEnum {
+        let mut shape = shape.clone();
+        match &mut shape {
+            ShapeEnum::Circle(circle) => circle.radius *= k,
+            ShapeEnum::Rectangle(rectangle) => {
+                rectangle.side_a *= k;
+                rectangle.side_b *= k;
+            }
+            ShapeEnum::Triangle(triangle) => {
+                triangle.base *= k;
+                triangle.height *= k;
+            }
+            ShapeEnum::Square(square) => square.side *= k,
+            ShapeEnum::Ellipse(ellipse) => {
+                ellipse.radius_x *= k;
+                ellipse.radius_y *= k;
+            }
+            ShapeEnum::Polygon(polygon) => {
+                for point in &mut polygon.points {
+                    *point = *point * k;
+                }
+            }
+        }
+        shape
+    }
+
+    #[cfg(feature = "std")]
+    proptest! {
+        #[test]
+        fn area_properties(shape in shapes(), k in 0.1f32..10.0, origin in coords(1e3)) {
+            let area = shape.get_area();
+            prop_assert!(area >= 0.0);
+            // Against the bounding box, as slivers lose most of their area
+            // to rounding
+            let size = shape.outline().bounding_box().get_area() * k * k;
+            prop_assert!((scaled(&shape, k).get_area() - area * k * k).abs() <= 1e-4 * size);
+            let mut moved = shape.clone();
+            moved.set_origin(origin);
+            prop_assert!(close(moved.get_area(), area));
+        }
+    }
+
+    #[test]
+    fn second_moments_of_area() {
+        let rectangle = Rectangle {
+            origin: Coord::new(100.0, -50.0),
+            side_a: 4.0,
+            side_b: 2.0,
+        };
+        let moments = rectangle.second_moment_of_area();
+        assert_approx_eq!(moments.ix, 8.0 / 3.0);
+        assert_approx_eq!(moments.iy, 32.0 / 3.0);
+        assert_approx_eq!(rectangle.polar_moment_of_area(), 40.0 / 3.0);
+
+        // The exact formulas agree with the outline's, however it winds
+        let triangle = Triangle {
+            origin: Coord::new(3.0, 1.0),
+            base: 3.0,
+            height: 5.0,
+        };
+        for shape in [&rectangle as &dyn Shape, &triangle] {
+            let exact = shape.second_moment_of_area();
+            let mut outline = shape.outline();
+            for _ in 0..2 {
+                let measured = outline.second_moments();
+                assert_approx_eq!(measured.ix, exact.ix, 1e-4);
+                assert_approx_eq!(measured.iy, exact.iy, 1e-4);
+                assert_approx_eq!(measured.ixy, 0.0, 1e-4);
+                if let Outline::Polygon(points) = &mut outline {
+                    points.reverse();
+                }
+            }
+        }
+
+        // Turned a quarter, the axes swap, and sheared the product appears
+        let turned = rectangle.clone().transformed(Transform::rotation(PI / 2.0));
+        let moments = turned.second_moment_of_area();
+        assert_approx_eq!(moments.ix, 32.0 / 3.0, 1e-4);
+        assert_approx_eq!(moments.iy, 8.0 / 3.0, 1e-4);
+        let sheared = triangle.clone().transformed(Transform::shear(0.5, 0.0));
+        let exact = sheared.second_moment_of_area();
+        let measured = sheared.outline().second_moments();
+        assert_approx_eq!(exact.ix, measured.ix, 1e-4);
+        assert_approx_eq!(exact.iy, measured.iy, 1e-4);
+        assert_approx_eq!(exact.ixy, measured.ixy, 1e-4);
+        assert!(exact.ixy.abs() > 1.0);
+        assert_approx_eq!(
+            Circle {
+                origin: Coord::default(),
+                radius: 2.0,
+            }
+            .polar_moment_of_area(),
+            8.0 * PI
+        );
+    }
+
+    #[cfg(feature = "std")]
+    proptest! {
+        #[test]
+        fn outline_properties(shape in shapes(), spots in vec((0.0f32..1.0, 0.0f32..1.0), 32)) {
+            let outline = shape.outline();
+            let bounds = outline.bounding_box();
+            let center = outline.centroid();
+            let (min, max) = (bounds.min(), bounds.max());
+            prop_assert!((min.x..=max.x).contains(&center.x) && (min.y..=max.y).contains(&center.y));
+
+            // Inside exactly when no distance away, for points in and around
+            // the bounding box
+            let (width, height) = (bounds.side_a * 1.5, bounds.side_b * 1.5);
+            let corner = bounds.origin - Coord::new(width / 2.0, height / 2.0);
+            for (u, v) in spots {
+                let point = corner + Coord::new(u * width, v * height);
+                let distance = shape.distance_to(point);
+                prop_assert!(distance >= 0.0);
+                prop_assert_eq!(outline.contains(point), distance == 0.0, "{:?}", point);
+            }
+        }
+    }
+
+    #[test]
+    fn properties_by_name() {
+        let mut ellipse = Ellipse {
+            origin: Coord::default(),
+            radius_x: 2.0,
+            radius_y: 1.0,
+        };
+        assert_eq!(ellipse.properties(), ["radius_x", "radius_y"]);
+        ellipse.set_property("radius_y", 3.0).unwrap();
+        assert_eq!(ellipse.get_property("radius_y"), Some(3.0));
+        assert_eq!(
+            ellipse.set_property("radius_x", -1.0),
+            Err(ShapeError::InvalidDimension {
+                name: "radius_x",
+                value: -1.0
+            })
+        );
+        assert_eq!(ellipse.radius_x, 2.0);
+
+        // Transforms pass them through to the shape underneath
+        let mut square = Square {
+            origin: Coord::default(),
+            side: 1.0,
+        }
+        .transformed(Transform::scale(2.0, 2.0));
+        square.set_property("side", 4.0).unwrap();
+        assert_eq!(square.get_area(), 64.0);
+        assert_eq!(square.get_property("radius"), None);
+        assert_eq!(
+            square.set_property("radius", 1.0),
+            Err(ShapeError::UnknownProperty("radius".into()))
+        );
+        assert!(Polygon::from(Triangle {
+            origin: Coord::default(),
+            base: 1.0,
+            height: 1.0
+        })
+        .properties()
+        .is_empty());
+    }
+
+    #[test]
+    fn simplify_polygon() {
+        // A square with redundant and slightly jittered points along its edges
+        let polygon = Polygon {
+            origin: Coord::new(5.0, 5.0),
+            points: vec![
+                Coord::new(0.0, 0.0),
+                Coord::new(1.0, 0.01),
+                Coord::new(2.0, 0.0),
+                Coord::new(2.0, 1.0),
+                Coord::new(2.0, 2.0),
+                Coord::new(1.0, 2.0),
+                Coord::new(0.0, 2.0),
+                Coord::new(-0.01, 1.0),
+            ],
+        };
+
+        let simplified = polygon.simplify(0.1);
+        assert_eq!(
+            simplified.points,
+            vec![
+                Coord::new(0.0, 0.0),
+                Coord::new(2.0, 0.0),
+                Coord::new(2.0, 2.0),
+                Coord::new(0.0, 2.0),
+            ]
+        );
+        assert_eq!(simplified.origin, polygon.origin);
+        assert_eq!(polygon.simplify(0.001).points.len(), 6);
+    }
+}
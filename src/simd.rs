@@ -0,0 +1,160 @@
+// Batch kernels over the columns of SoaCanvas. On x86_64 they use SSE2,
+// which every x86_64 CPU has, so there is no runtime detection. Elsewhere
+// they fall back to fixed width arrays, a shape the compiler vectorises
+// well. Sums are accumulated per lane, so they can differ from a serial sum
+// in the last few bits.
+use std::{array, ops::Add};
+
+use crate::storage::{area_coefficient, ShapeKind};
+
+const LANES: usize = 4;
+
+#[cfg(target_arch = "x86_64")]
+mod lanes {
+    use std::{
+        arch::x86_64::*,
+        ops::{Add, Mul},
+    };
+
+    use super::LANES;
+
+    // SAFETY, for every intrinsic here: SSE and SSE2 are part of the
+    // x86_64 baseline, so they are always available
+    #[derive(Clone, Copy)]
+    pub struct Lanes(__m128);
+
+    impl Lanes {
+        pub fn splat(value: f32) -> Lanes {
+            Lanes(unsafe { _mm_set1_ps(value) })
+        }
+        pub fn load(values: &[f32]) -> Lanes {
+            assert!(values.len() >= LANES);
+            // SAFETY: the slice has at least four values, and an unaligned
+            // load has no alignment requirement
+            Lanes(unsafe { _mm_loadu_ps(values.as_ptr()) })
+        }
+        pub fn store(self, values: &mut [f32]) {
+            assert!(values.len() >= LANES);
+            // SAFETY: as for load
+            unsafe { _mm_storeu_ps(values.as_mut_ptr(), self.0) }
+        }
+        pub fn to_array(self) -> [f32; LANES] {
+            let mut values = [0.0; LANES];
+            self.store(&mut values);
+            values
+        }
+    }
+
+    impl Add for Lanes {
+        type Output = Lanes;
+        fn add(self, other: Lanes) -> Lanes {
+            Lanes(unsafe { _mm_add_ps(self.0, other.0) })
+        }
+    }
+    impl Mul for Lanes {
+        type Output = Lanes;
+        fn mul(self, other: Lanes) -> Lanes {
+            Lanes(unsafe { _mm_mul_ps(self.0, other.0) })
+        }
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+mod lanes {
+    use std::ops::{Add, Mul};
+
+    use super::LANES;
+
+    #[derive(Clone, Copy)]
+    pub struct Lanes([f32; LANES]);
+
+    impl Lanes {
+        pub fn splat(value: f32) -> Lanes {
+            Lanes([value; LANES])
+        }
+        pub fn load(values: &[f32]) -> Lanes {
+            Lanes(values[..LANES].try_into().unwrap())
+        }
+        pub fn store(self, values: &mut [f32]) {
+            values[..LANES].copy_from_slice(&self.0);
+        }
+        pub fn to_array(self) -> [f32; LANES] {
+            self.0
+        }
+    }
+
+    impl Add for Lanes {
+        type Output = Lanes;
+        fn add(self, other: Lanes) -> Lanes {
+            Lanes(std::array::from_fn(|i| self.0[i] + other.0[i]))
+        }
+    }
+    impl Mul for Lanes {
+        type Output = Lanes;
+        fn mul(self, other: Lanes) -> Lanes {
+            Lanes(std::array::from_fn(|i| self.0[i] * other.0[i]))
+        }
+    }
+}
+
+use lanes::Lanes;
+
+// Sum of coefficient(kind) * a * b over the columns
+pub fn sum_areas(kinds: &[ShapeKind], a: &[f32], b: &[f32]) -> f32 {
+    let len = kinds.len().min(a.len()).min(b.len());
+    let split = len - len % LANES;
+    let mut total = Lanes::splat(0.0);
+    for start in (0..split).step_by(LANES) {
+        let coefficients: [f32; LANES] = array::from_fn(|i| area_coefficient(kinds[start + i]));
+        let coefficients = Lanes::load(&coefficients);
+        total = total + coefficients * Lanes::load(&a[start..]) * Lanes::load(&b[start..]);
+    }
+    let tail: f32 = (split..len)
+        .map(|i| area_coefficient(kinds[i]) * a[i] * b[i])
+        .sum();
+    total.to_array().into_iter().fold(tail, Add::add)
+}
+
+// Adds offset to every value
+pub fn add_all(values: &mut [f32], offset: f32) {
+    let offset_lanes = Lanes::splat(offset);
+    let mut chunks = values.chunks_exact_mut(LANES);
+    for chunk in &mut chunks {
+        (Lanes::load(chunk) + offset_lanes).store(chunk);
+    }
+    for value in chunks.into_remainder() {
+        *value += offset;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    #[test]
+    fn kernels_match_scalar() {
+        let kinds = [
+            ShapeKind::Circle,
+            ShapeKind::Rectangle,
+            ShapeKind::Triangle,
+            ShapeKind::Polygon,
+            ShapeKind::Square,
+            ShapeKind::Ellipse,
+        ];
+        // Lengths either side of a whole number of lanes
+        for len in [0, 3, 8, 11] {
+            let kinds: Vec<ShapeKind> = (0..len).map(|i| kinds[i % kinds.len()]).collect();
+            let a: Vec<f32> = (0..len).map(|i| i as f32 + 1.0).collect();
+            let b: Vec<f32> = (0..len).map(|i| 2.0 - i as f32 * 0.1).collect();
+            let scalar: f32 = (0..len)
+                .map(|i| area_coefficient(kinds[i]) * a[i] * b[i])
+                .sum();
+            assert_approx_eq!(sum_areas(&kinds, &a, &b), scalar);
+
+            let mut moved = a.clone();
+            add_all(&mut moved, 0.5);
+            assert!(moved.iter().zip(&a).all(|(m, a)| *m == a + 0.5));
+        }
+    }
+}
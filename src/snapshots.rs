@@ -0,0 +1,442 @@
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    command::Session,
+    error::{CanvasError, CommandError},
+};
+
+// Named checkpoints of a session, as much of it as a script holds, the way
+// the journal's snapshots are. Each is kept as the edit from the one taken
+// before it to itself, so a long run of them costs what changed between
+// them rather than a copy of the canvas each, and only the latest is held
+// whole. Restoring one replays the edits from the first up to it.
+//
+// Kept in a directory, each is a file numbered in the order taken:
+//     # snapshot before-experiment
+//     = 12
+//     - 1
+//     + add circle 2 at 10 0
+// keeping, dropping or adding lines of the script before it.
+#[derive(Default)]
+pub(crate) struct Snapshots {
+    taken: Vec<(String, Vec<Edit>)>,
+    latest: Vec<String>,
+    dir: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Edit {
+    Keep(usize),
+    Remove(usize),
+    Insert(Vec<String>),
+}
+
+// A line of one snapshot's script that the other doesn't have
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Added(String),
+    Removed(String),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added(line) => write!(f, "+ {line}"),
+            Change::Removed(line) => write!(f, "- {line}"),
+        }
+    }
+}
+
+impl Snapshots {
+    fn position(&self, name: &str) -> Result<usize, CommandError> {
+        self.taken
+            .iter()
+            .position(|(taken, _)| taken == name)
+            .ok_or_else(|| CanvasError::UnknownName(name.into()).into())
+    }
+
+    // The script of the snapshot at the position, rebuilt from the first
+    fn script(&self, position: usize) -> Vec<String> {
+        if position + 1 == self.taken.len() {
+            return self.latest.clone();
+        }
+        self.taken[..=position]
+            .iter()
+            .fold(Vec::new(), |script, (_, edits)| {
+                apply(&script, edits).unwrap()
+            })
+    }
+
+    fn write(&self, dir: &Path, position: usize) -> io::Result<()> {
+        let (name, edits) = &self.taken[position];
+        let mut text = format!("# snapshot {name}\n");
+        for edit in edits {
+            match edit {
+                Edit::Keep(n) => text += &format!("= {n}\n"),
+                Edit::Remove(n) => text += &format!("- {n}\n"),
+                Edit::Insert(lines) => {
+                    for line in lines {
+                        text += &format!("+ {line}\n");
+                    }
+                }
+            }
+        }
+        let mut file = fs::File::create(dir.join(format!("{}.snapshot", position + 1)))?;
+        file.write_all(text.as_bytes())?;
+        file.sync_all()
+    }
+
+    // The snapshots kept in the directory, each checked against the one
+    // before it
+    fn read(dir: &Path) -> io::Result<Snapshots> {
+        let invalid = |path: &Path| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} isn't a snapshot of the one before", path.display()),
+            )
+        };
+        let mut numbered: Vec<(usize, PathBuf)> = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "snapshot")
+            {
+                let number = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str()?.parse().ok());
+                numbered.push((number.ok_or_else(|| invalid(&path))?, path));
+            }
+        }
+        numbered.sort();
+        let mut snapshots = Snapshots {
+            dir: Some(dir.to_path_buf()),
+            ..Snapshots::default()
+        };
+        for (i, (number, path)) in numbered.into_iter().enumerate() {
+            let text = fs::read_to_string(&path)?;
+            let mut lines = text.lines();
+            let name = lines
+                .next()
+                .and_then(|line| line.strip_prefix("# snapshot "));
+            let (Some(name), true) = (name, number == i + 1) else {
+                return Err(invalid(&path));
+            };
+            let mut edits = Vec::new();
+            for line in lines {
+                let edit = match line.split_once(' ') {
+                    Some(("=", n)) => n.parse().map(Edit::Keep),
+                    Some(("-", n)) => n.parse().map(Edit::Remove),
+                    Some(("+", line)) => Ok(Edit::Insert(vec![line.into()])),
+                    _ => return Err(invalid(&path)),
+                };
+                push(&mut edits, edit.map_err(|_| invalid(&path))?);
+            }
+            snapshots.latest = apply(&snapshots.latest, &edits).ok_or_else(|| invalid(&path))?;
+            snapshots.taken.push((name.into(), edits));
+        }
+        Ok(snapshots)
+    }
+}
+
+impl Session {
+    // Snapshots are written to the directory as they're taken, from now on,
+    // and those already there are taken up, replacing any in the session
+    pub fn keep_snapshots_in(&mut self, dir: impl AsRef<Path>) -> Result<(), CommandError> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        self.snapshots = Snapshots::read(dir)?;
+        Ok(())
+    }
+
+    // Names are single words and can't be taken twice
+    pub fn take_snapshot(&mut self, name: &str) -> Result<(), CommandError> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(CommandError::Usage("snapshot <name>, the name one word"));
+        }
+        if self.snapshots.position(name).is_ok() {
+            return Err(CanvasError::NameTaken(name.into()).into());
+        }
+        let script: Vec<String> = self.snapshot()?.lines().map(String::from).collect();
+        let snapshots = &mut self.snapshots;
+        let edits = diff(&snapshots.latest, &script);
+        snapshots.taken.push((name.into(), edits));
+        snapshots.latest = script;
+        if let Some(dir) = &snapshots.dir {
+            snapshots.write(dir, snapshots.taken.len() - 1)?;
+        }
+        Ok(())
+    }
+
+    // In the order taken
+    pub fn list_snapshots(&self) -> Vec<&str> {
+        self.snapshots
+            .taken
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    // Puts the canvas back as it was, shape numbers and all. Snapshots,
+    // macros and the journal, which is rewritten to match, carry on.
+    pub fn restore(&mut self, name: &str) -> Result<(), CommandError> {
+        let script = self.snapshots.script(self.snapshots.position(name)?);
+        let mut restored = Session::new();
+        restored.run(&script.join("\n"))?;
+        self.replace_canvas(restored);
+        if let Some(mut journal) = self.journal.take() {
+            let rewritten = journal.snapshot(self);
+            self.journal = Some(journal);
+            rewritten?;
+        }
+        Ok(())
+    }
+
+    // The lines of a's script that b's drops and those it adds, in script
+    // order
+    pub fn diff_snapshots(&self, a: &str, b: &str) -> Result<Vec<Change>, CommandError> {
+        let old = self.snapshots.script(self.snapshots.position(a)?);
+        let new = self.snapshots.script(self.snapshots.position(b)?);
+        let (mut at, mut changes) = (0, Vec::new());
+        for edit in diff(&old, &new) {
+            match edit {
+                Edit::Keep(n) => at += n,
+                Edit::Remove(n) => {
+                    changes.extend(old[at..at + n].iter().cloned().map(Change::Removed));
+                    at += n;
+                }
+                Edit::Insert(lines) => changes.extend(lines.into_iter().map(Change::Added)),
+            }
+        }
+        Ok(changes)
+    }
+}
+
+// Adds the edit to the end, merged into the last one if it's the same kind
+fn push(edits: &mut Vec<Edit>, edit: Edit) {
+    match (edits.last_mut(), edit) {
+        (_, Edit::Keep(0) | Edit::Remove(0)) => {}
+        (Some(Edit::Keep(n)), Edit::Keep(more)) | (Some(Edit::Remove(n)), Edit::Remove(more)) => {
+            *n += more;
+        }
+        (Some(Edit::Insert(lines)), Edit::Insert(more)) => lines.extend(more),
+        (_, edit) => edits.push(edit),
+    }
+}
+
+// None if the edits run past the end of the script
+fn apply(script: &[String], edits: &[Edit]) -> Option<Vec<String>> {
+    let (mut out, mut at) = (Vec::new(), 0);
+    for edit in edits {
+        match edit {
+            Edit::Keep(n) => {
+                out.extend_from_slice(script.get(at..at + n)?);
+                at += n;
+            }
+            Edit::Remove(n) => {
+                script.get(at..at + n)?;
+                at += n;
+            }
+            Edit::Insert(lines) => out.extend(lines.iter().cloned()),
+        }
+    }
+    out.extend_from_slice(script.get(at..)?);
+    Some(out)
+}
+
+// The shortest edit from old to new, by Myers' algorithm, after setting
+// aside the lines they start and end with in common
+fn diff(old: &[String], new: &[String]) -> Vec<Edit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let (old_rest, new_rest) = (&old[prefix..], &new[prefix..]);
+    let suffix = old_rest
+        .iter()
+        .rev()
+        .zip(new_rest.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old_rest[..old_rest.len() - suffix],
+        &new_rest[..new_rest.len() - suffix],
+    );
+
+    // The furthest x reached along each diagonal k = x - y for each number
+    // of edits d, kept for d - 1's diagonals only, to walk back through
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let offset = n + m;
+    let mut v = vec![0; 2 * offset as usize + 2];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    'search: for d in 0..=offset {
+        trace.push(match d {
+            0 => Vec::new(),
+            _ => v[(offset - d + 1) as usize..(offset + d) as usize].to_vec(),
+        });
+        for k in (-d..=d).step_by(2) {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+                v[i + 1]
+            } else {
+                v[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[i] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    // Walked back from the end, so in reverse
+    let mut steps: Vec<Edit> = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, window) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let reached = |k: isize| window[(k + d - 1) as usize];
+        let k = x - y;
+        let from = if k == -d || (k != d && reached(k - 1) < reached(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let (from_x, from_y) = match d {
+            0 => (0, 0),
+            _ => (reached(from), reached(from) - from),
+        };
+        while x > from_x && y > from_y {
+            steps.push(Edit::Keep(1));
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            if x == from_x {
+                steps.push(Edit::Insert(vec![b[y as usize - 1].clone()]));
+                y -= 1;
+            } else {
+                steps.push(Edit::Remove(1));
+                x -= 1;
+            }
+        }
+    }
+
+    let mut edits = Vec::new();
+    push(&mut edits, Edit::Keep(prefix));
+    for step in steps.into_iter().rev() {
+        push(&mut edits, step);
+    }
+    push(&mut edits, Edit::Keep(suffix));
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn edits_rebuild_the_new_script() {
+        let lines =
+            |text: &str| -> Vec<String> { text.split_whitespace().map(String::from).collect() };
+        for (old, new) in [
+            ("a b c a b b a", "c b a b a c"),
+            ("", "x y"),
+            ("x y", ""),
+            ("a b c", "a b c"),
+            ("a b c d e", "a x c e f"),
+        ] {
+            let (old, new) = (lines(old), lines(new));
+            let edits = diff(&old, &new);
+            assert_eq!(apply(&old, &edits).unwrap(), new);
+        }
+        // The shortest edit, five lines for Myers' own example
+        let edits = diff(&lines("a b c a b b a"), &lines("c b a b a c"));
+        let changed: usize = edits
+            .iter()
+            .map(|edit| match edit {
+                Edit::Keep(_) => 0,
+                Edit::Remove(n) => *n,
+                Edit::Insert(lines) => lines.len(),
+            })
+            .sum();
+        assert_eq!(changed, 5);
+    }
+
+    #[test]
+    fn take_restore_and_diff() {
+        let dir = env::temp_dir().join(format!("kaleidoscope-snapshots-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut session = Session::new();
+        session.keep_snapshots_in(&dir).unwrap();
+        session.execute("add circle 1 at 0 0").unwrap();
+        session.execute("add rectangle 2 2 at 5 5").unwrap();
+        session.take_snapshot("before-experiment").unwrap();
+        session.execute("remove 1").unwrap();
+        session.execute("add triangle 1 1 at 9 9").unwrap();
+        session.take_snapshot("experiment").unwrap();
+        assert!(matches!(
+            session.take_snapshot("experiment"),
+            Err(CommandError::Canvas(CanvasError::NameTaken(_)))
+        ));
+        assert_eq!(
+            session.list_snapshots(),
+            ["before-experiment", "experiment"]
+        );
+
+        // Only what changed is stored for the second
+        let second = fs::read_to_string(dir.join("2.snapshot")).unwrap();
+        assert_eq!(
+            second,
+            "# snapshot experiment\n\
+             - 1\n\
+             + skip 1\n\
+             = 1\n\
+             + add triangle 1 1 at 9 9\n"
+        );
+        let changes: Vec<String> = session
+            .diff_snapshots("before-experiment", "experiment")
+            .unwrap()
+            .iter()
+            .map(Change::to_string)
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                "- add circle 1 at 0 0",
+                "+ skip 1",
+                "+ add triangle 1 1 at 9 9"
+            ]
+        );
+
+        assert_eq!(
+            session
+                .execute("diff experiment before-experiment")
+                .unwrap(),
+            "- skip 1\n+ add circle 1 at 0 0\n- add triangle 1 1 at 9 9"
+        );
+
+        session.execute("restore before-experiment").unwrap();
+        assert_eq!(session.canvas.len(), 2);
+        assert!(session.shape(3).is_none());
+        session.execute("move 1 to 2 2").unwrap();
+
+        // Taken up again from the directory by a later session
+        let mut later = Session::new();
+        later.keep_snapshots_in(&dir).unwrap();
+        later.restore("experiment").unwrap();
+        assert!(later.shape(1).is_none());
+        assert_eq!(later.execute("area 3").unwrap(), "0.5");
+        assert!(matches!(
+            later.restore("after"),
+            Err(CommandError::Canvas(CanvasError::UnknownName(_)))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
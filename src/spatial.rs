@@ -0,0 +1,308 @@
+use std::{collections::HashMap, mem::size_of};
+
+use crate::{arena::ShapeId, Coord, Rectangle};
+
+// Finds shapes by bounding box. Results are candidates whose bounds touch
+// the region, in no particular order; exact geometry is left to the caller.
+pub trait SpatialIndex: Send {
+    fn insert(&mut self, id: ShapeId, bounds: &Rectangle);
+    fn remove(&mut self, id: ShapeId);
+    fn query_region(&self, region: &Rectangle) -> Vec<ShapeId>;
+    // Bytes allocated for the index, roughly, for memory reports
+    fn heap_bytes(&self) -> usize {
+        0
+    }
+}
+
+// Buckets a hash map has room for, with their control bytes
+fn map_bytes<K, V>(map: &HashMap<K, V>) -> usize {
+    map.capacity() * (size_of::<K>() + size_of::<V>() + 1)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum IndexKind {
+    // Adapts to clustered shapes. Shapes outside the bounds still work, but
+    // are all checked on every query.
+    Quadtree { bounds: Rectangle },
+    // Best for shapes spread evenly and of similar size, with cells a little
+    // bigger than a typical shape
+    SpatialHash { cell_size: f32 },
+}
+
+impl IndexKind {
+    pub fn build(&self) -> Box<dyn SpatialIndex> {
+        match self {
+            IndexKind::Quadtree { bounds } => Box::new(Quadtree::new(bounds.clone())),
+            IndexKind::SpatialHash { cell_size } => Box::new(SpatialHash::new(*cell_size)),
+        }
+    }
+}
+
+fn overlaps(a: &Rectangle, b: &Rectangle) -> bool {
+    a.distance_to_box(b) == 0.0
+}
+
+fn contains(outer: &Rectangle, inner: &Rectangle) -> bool {
+    let (min, max) = (outer.min(), outer.max());
+    let (inner_min, inner_max) = (inner.min(), inner.max());
+    inner_min.x >= min.x && inner_min.y >= min.y && inner_max.x <= max.x && inner_max.y <= max.y
+}
+
+const NODE_CAPACITY: usize = 8;
+const MAX_DEPTH: u32 = 10;
+
+#[derive(Clone, Debug)]
+struct Node {
+    bounds: Rectangle,
+    // Shapes that don't fit inside a single child
+    items: Vec<(ShapeId, Rectangle)>,
+    children: Option<Box<[Node; 4]>>,
+}
+
+impl Node {
+    fn new(bounds: Rectangle) -> Node {
+        Node {
+            bounds,
+            items: Vec::new(),
+            children: None,
+        }
+    }
+
+    fn insert(&mut self, id: ShapeId, bounds: &Rectangle, depth: u32) {
+        if let Some(child) = self.child_containing(bounds) {
+            child.insert(id, bounds, depth + 1);
+            return;
+        }
+        self.items.push((id, bounds.clone()));
+        if self.children.is_none() && self.items.len() > NODE_CAPACITY && depth < MAX_DEPTH {
+            self.split(depth);
+        }
+    }
+
+    fn split(&mut self, depth: u32) {
+        let (min, max) = (self.bounds.min(), self.bounds.max());
+        let mid = self.bounds.origin;
+        self.children = Some(Box::new([
+            Node::new(Rectangle::from_corners(min, mid)),
+            Node::new(Rectangle::from_corners(
+                Coord::new(mid.x, min.y),
+                Coord::new(max.x, mid.y),
+            )),
+            Node::new(Rectangle::from_corners(
+                Coord::new(min.x, mid.y),
+                Coord::new(mid.x, max.y),
+            )),
+            Node::new(Rectangle::from_corners(mid, max)),
+        ]));
+        for (id, bounds) in std::mem::take(&mut self.items) {
+            self.insert(id, &bounds, depth);
+        }
+    }
+
+    fn child_containing(&mut self, bounds: &Rectangle) -> Option<&mut Node> {
+        self.children
+            .as_mut()?
+            .iter_mut()
+            .find(|child| contains(&child.bounds, bounds))
+    }
+
+    // Follows the same path insert took
+    fn remove(&mut self, id: ShapeId, bounds: &Rectangle) {
+        if let Some(position) = self.items.iter().position(|(item, _)| *item == id) {
+            self.items.swap_remove(position);
+        } else if let Some(child) = self.child_containing(bounds) {
+            child.remove(id, bounds);
+        }
+    }
+
+    // Children are counted here, the node itself by its parent
+    fn heap_bytes(&self) -> usize {
+        let children = self.children.iter().flat_map(|c| c.iter());
+        self.items.capacity() * size_of::<(ShapeId, Rectangle)>()
+            + children
+                .map(|child| size_of::<Node>() + child.heap_bytes())
+                .sum::<usize>()
+    }
+
+    fn query(&self, region: &Rectangle, found: &mut Vec<ShapeId>) {
+        found.extend(
+            self.items
+                .iter()
+                .filter(|(_, bounds)| overlaps(bounds, region))
+                .map(|(id, _)| *id),
+        );
+        for child in self.children.iter().flat_map(|c| c.iter()) {
+            if overlaps(&child.bounds, region) {
+                child.query(region, found);
+            }
+        }
+    }
+}
+
+// Each shape sits in the smallest node that fully contains its bounds
+#[derive(Clone, Debug)]
+pub struct Quadtree {
+    root: Node,
+    bounds: HashMap<ShapeId, Rectangle>,
+}
+
+impl Quadtree {
+    pub fn new(bounds: Rectangle) -> Quadtree {
+        Quadtree {
+            root: Node::new(bounds),
+            bounds: HashMap::new(),
+        }
+    }
+}
+
+impl SpatialIndex for Quadtree {
+    fn insert(&mut self, id: ShapeId, bounds: &Rectangle) {
+        self.remove(id);
+        self.root.insert(id, bounds, 0);
+        self.bounds.insert(id, bounds.clone());
+    }
+    fn remove(&mut self, id: ShapeId) {
+        if let Some(bounds) = self.bounds.remove(&id) {
+            self.root.remove(id, &bounds);
+        }
+    }
+    fn query_region(&self, region: &Rectangle) -> Vec<ShapeId> {
+        let mut found = Vec::new();
+        self.root.query(region, &mut found);
+        found
+    }
+    fn heap_bytes(&self) -> usize {
+        self.root.heap_bytes() + map_bytes(&self.bounds)
+    }
+}
+
+// Uniform grid of square cells, each listing the shapes whose bounds touch it
+#[derive(Clone, Debug)]
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<ShapeId>>,
+    bounds: HashMap<ShapeId, Rectangle>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> SpatialHash {
+        SpatialHash {
+            cell_size,
+            cells: HashMap::new(),
+            bounds: HashMap::new(),
+        }
+    }
+
+    fn cells(&self, bounds: &Rectangle) -> impl Iterator<Item = (i32, i32)> {
+        let cell = |point: Coord| {
+            (
+                (point.x / self.cell_size).floor() as i32,
+                (point.y / self.cell_size).floor() as i32,
+            )
+        };
+        let (min, max) = (cell(bounds.min()), cell(bounds.max()));
+        (min.0..=max.0).flat_map(move |x| (min.1..=max.1).map(move |y| (x, y)))
+    }
+}
+
+impl SpatialIndex for SpatialHash {
+    fn insert(&mut self, id: ShapeId, bounds: &Rectangle) {
+        self.remove(id);
+        for cell in self.cells(bounds).collect::<Vec<_>>() {
+            self.cells.entry(cell).or_default().push(id);
+        }
+        self.bounds.insert(id, bounds.clone());
+    }
+    fn remove(&mut self, id: ShapeId) {
+        let Some(bounds) = self.bounds.remove(&id) else {
+            return;
+        };
+        for cell in self.cells(&bounds).collect::<Vec<_>>() {
+            if let Some(ids) = self.cells.get_mut(&cell) {
+                ids.retain(|other| *other != id);
+                if ids.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+    fn query_region(&self, region: &Rectangle) -> Vec<ShapeId> {
+        let mut found: Vec<ShapeId> = self
+            .cells(region)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .filter(|id| overlaps(&self.bounds[id], region))
+            .collect();
+        // Shapes spanning several cells are listed in each
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+    fn heap_bytes(&self) -> usize {
+        let lists: usize = self
+            .cells
+            .values()
+            .map(|ids| ids.capacity() * size_of::<ShapeId>())
+            .sum();
+        map_bytes(&self.cells) + lists + map_bytes(&self.bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::Arena;
+
+    #[test]
+    fn indexes_agree_with_brute_force() {
+        let mut arena = Arena::default();
+        let boxes: Vec<(ShapeId, Rectangle)> = (0..200)
+            .map(|i| {
+                let bounds = Rectangle {
+                    origin: Coord::new((i * 37 % 100) as f32, (i * 61 % 100) as f32),
+                    side_a: 1.0 + (i % 7) as f32,
+                    side_b: 1.0 + (i % 5) as f32,
+                };
+                (arena.insert(()), bounds)
+            })
+            .collect();
+        let area = Rectangle {
+            origin: Coord::new(50.0, 50.0),
+            side_a: 100.0,
+            side_b: 100.0,
+        };
+        let regions = [
+            Rectangle::from_corners(Coord::new(10.0, 10.0), Coord::new(30.0, 25.0)),
+            Rectangle::from_corners(Coord::new(-50.0, -50.0), Coord::new(0.5, 0.5)),
+            Rectangle::from_corners(Coord::new(42.0, 42.0), Coord::new(42.0, 42.0)),
+        ];
+
+        for kind in [
+            IndexKind::Quadtree { bounds: area },
+            IndexKind::SpatialHash { cell_size: 8.0 },
+        ] {
+            let mut index = kind.build();
+            for (id, bounds) in &boxes {
+                index.insert(*id, bounds);
+            }
+            // Removed and moved shapes are found only where they are now
+            index.remove(boxes[0].0);
+            let moved = Rectangle::from_corners(Coord::new(20.0, 20.0), Coord::new(21.0, 21.0));
+            index.insert(boxes[1].0, &moved);
+            let mut current = boxes[1..].to_vec();
+            current[0].1 = moved;
+
+            for region in &regions {
+                let mut found = index.query_region(region);
+                found.sort();
+                let expected: Vec<ShapeId> = current
+                    .iter()
+                    .filter(|(_, bounds)| overlaps(bounds, region))
+                    .map(|(id, _)| *id)
+                    .collect();
+                assert_eq!(found, expected, "{kind:?}");
+            }
+        }
+    }
+}
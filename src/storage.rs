@@ -0,0 +1,563 @@
+use std::sync::Arc;
+
+#[cfg(feature = "simd")]
+use crate::simd;
+use crate::{
+    arena::ShapeId,
+    builder::coordinate,
+    error::{CanvasError, ShapeError},
+    geometry::Outline,
+    Canvas, Circle, Coord, Ellipse, Polygon, Rectangle, SecondMoments, Shape, ShapeCell,
+    ShapeObject, Square, Triangle,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShapeKind {
+    Circle,
+    Rectangle,
+    Triangle,
+    Polygon,
+    Square,
+    Ellipse,
+}
+
+// Concrete shapes stored inline, for batch work where the pointer chasing
+// and per-shape locking of ShapeObject dominate
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShapeEnum {
+    Circle(Circle),
+    Rectangle(Rectangle),
+    Triangle(Triangle),
+    Polygon(Polygon),
+    Square(Square),
+    Ellipse(Ellipse),
+}
+
+impl ShapeEnum {
+    pub fn kind(&self) -> ShapeKind {
+        match self {
+            ShapeEnum::Circle(_) => ShapeKind::Circle,
+            ShapeEnum::Rectangle(_) => ShapeKind::Rectangle,
+            ShapeEnum::Triangle(_) => ShapeKind::Triangle,
+            ShapeEnum::Polygon(_) => ShapeKind::Polygon,
+            ShapeEnum::Square(_) => ShapeKind::Square,
+            ShapeEnum::Ellipse(_) => ShapeKind::Ellipse,
+        }
+    }
+
+    fn shape(&self) -> &dyn Shape {
+        match self {
+            ShapeEnum::Circle(s) => s,
+            ShapeEnum::Rectangle(s) => s,
+            ShapeEnum::Triangle(s) => s,
+            ShapeEnum::Polygon(s) => s,
+            ShapeEnum::Square(s) => s,
+            ShapeEnum::Ellipse(s) => s,
+        }
+    }
+    fn shape_mut(&mut self) -> &mut dyn Shape {
+        match self {
+            ShapeEnum::Circle(s) => s,
+            ShapeEnum::Rectangle(s) => s,
+            ShapeEnum::Triangle(s) => s,
+            ShapeEnum::Polygon(s) => s,
+            ShapeEnum::Square(s) => s,
+            ShapeEnum::Ellipse(s) => s,
+        }
+    }
+
+    pub fn into_object(self) -> ShapeObject {
+        match self {
+            ShapeEnum::Circle(s) => Arc::new(ShapeCell::new(s)),
+            ShapeEnum::Rectangle(s) => Arc::new(ShapeCell::new(s)),
+            ShapeEnum::Triangle(s) => Arc::new(ShapeCell::new(s)),
+            ShapeEnum::Polygon(s) => Arc::new(ShapeCell::new(s)),
+            ShapeEnum::Square(s) => Arc::new(ShapeCell::new(s)),
+            ShapeEnum::Ellipse(s) => Arc::new(ShapeCell::new(s)),
+        }
+    }
+}
+
+impl Shape for ShapeEnum {
+    fn origin(&self) -> Coord {
+        self.shape().origin()
+    }
+
+    fn set_origin(&mut self, origin: Coord) {
+        self.shape_mut().set_origin(origin);
+    }
+
+    // Matched directly rather than through shape() so the common kinds inline
+    fn get_area(&self) -> f32 {
+        match self {
+            ShapeEnum::Circle(s) => s.get_area(),
+            ShapeEnum::Rectangle(s) => s.get_area(),
+            ShapeEnum::Triangle(s) => s.get_area(),
+            ShapeEnum::Polygon(s) => s.get_area(),
+            ShapeEnum::Square(s) => s.get_area(),
+            ShapeEnum::Ellipse(s) => s.get_area(),
+        }
+    }
+
+    fn outline(&self) -> Outline {
+        self.shape().outline()
+    }
+    fn second_moment_of_area(&self) -> SecondMoments {
+        self.shape().second_moment_of_area()
+    }
+
+    fn name(&self) -> &'static str {
+        self.shape().name()
+    }
+    fn properties(&self) -> &'static [&'static str] {
+        self.shape().properties()
+    }
+    fn get_property(&self, name: &str) -> Option<f32> {
+        self.shape().get_property(name)
+    }
+    fn set_property(&mut self, name: &str, value: f32) -> Result<(), ShapeError> {
+        self.shape_mut().set_property(name, value)
+    }
+    fn heap_bytes(&self) -> usize {
+        self.shape().heap_bytes()
+    }
+
+    fn duplicate(&self) -> ShapeObject {
+        self.clone().into_object()
+    }
+
+    fn validate(&self) -> Result<(), ShapeError> {
+        self.shape().validate()
+    }
+}
+
+impl From<Circle> for ShapeEnum {
+    fn from(shape: Circle) -> ShapeEnum {
+        ShapeEnum::Circle(shape)
+    }
+}
+impl From<Rectangle> for ShapeEnum {
+    fn from(shape: Rectangle) -> ShapeEnum {
+        ShapeEnum::Rectangle(shape)
+    }
+}
+impl From<Triangle> for ShapeEnum {
+    fn from(shape: Triangle) -> ShapeEnum {
+        ShapeEnum::Triangle(shape)
+    }
+}
+impl From<Polygon> for ShapeEnum {
+    fn from(shape: Polygon) -> ShapeEnum {
+        ShapeEnum::Polygon(shape)
+    }
+}
+impl From<Square> for ShapeEnum {
+    fn from(shape: Square) -> ShapeEnum {
+        ShapeEnum::Square(shape)
+    }
+}
+impl From<Ellipse> for ShapeEnum {
+    fn from(shape: Ellipse) -> ShapeEnum {
+        ShapeEnum::Ellipse(shape)
+    }
+}
+
+// A canvas storing shapes by value. Mutation needs &mut, so sharing between
+// threads is left to the caller, in exchange for lock free reads.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EnumCanvas {
+    shapes: Vec<ShapeEnum>,
+}
+
+impl EnumCanvas {
+    pub fn add(&mut self, shape: impl Into<ShapeEnum>) -> usize {
+        self.shapes.push(shape.into());
+        self.shapes.len() - 1
+    }
+    pub fn get(&self, index: usize) -> Option<&ShapeEnum> {
+        self.shapes.get(index)
+    }
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut ShapeEnum> {
+        self.shapes.get_mut(index)
+    }
+    pub fn remove(&mut self, index: usize) -> Result<ShapeEnum, CanvasError> {
+        if index >= self.len() {
+            return Err(CanvasError::UnknownIndex(index));
+        }
+        Ok(self.shapes.remove(index))
+    }
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &ShapeEnum> {
+        self.shapes.iter()
+    }
+    pub fn get_area(&self, index: usize) -> Option<f32> {
+        self.get(index).map(Shape::get_area)
+    }
+    pub fn set_origin(
+        &mut self,
+        index: usize,
+        origin: impl Into<Coord>,
+    ) -> Result<(), CanvasError> {
+        let shape = self
+            .get_mut(index)
+            .ok_or(CanvasError::UnknownIndex(index))?;
+        shape.set_origin(coordinate("origin", origin.into())?);
+        debug_check(shape);
+        Ok(())
+    }
+    pub fn total_area(&self) -> f32 {
+        self.shapes.iter().map(Shape::get_area).sum()
+    }
+    pub fn translate_all(&mut self, offset: Coord) {
+        for shape in &mut self.shapes {
+            let origin = shape.origin();
+            shape.set_origin(origin + offset);
+        }
+    }
+}
+
+impl FromIterator<ShapeEnum> for EnumCanvas {
+    fn from_iter<I: IntoIterator<Item = ShapeEnum>>(shapes: I) -> EnumCanvas {
+        EnumCanvas {
+            shapes: shapes.into_iter().collect(),
+        }
+    }
+}
+
+// Shapes split into one array per field, so bulk operations only stream
+// through the fields they need. The dimension columns are set up so every
+// area is a per kind coefficient times a times b: a holds radius, side_a,
+// base, side and radius_x, b holds the radius again, side_b, height, the
+// side again and radius_y. Polygons keep their
+// area in a, since moving them can't change it, and their points in a side
+// table.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SoaCanvas {
+    kinds: Vec<ShapeKind>,
+    x: Vec<f32>,
+    y: Vec<f32>,
+    a: Vec<f32>,
+    b: Vec<f32>,
+    points: Vec<Vec<Coord>>,
+}
+
+impl SoaCanvas {
+    pub fn add(&mut self, shape: impl Into<ShapeEnum>) -> usize {
+        let shape = shape.into();
+        let (kind, origin) = (shape.kind(), shape.origin());
+        let (a, b, points) = match shape {
+            ShapeEnum::Circle(s) => (s.radius, s.radius, Vec::new()),
+            ShapeEnum::Rectangle(s) => (s.side_a, s.side_b, Vec::new()),
+            ShapeEnum::Triangle(s) => (s.base, s.height, Vec::new()),
+            ShapeEnum::Polygon(s) => (s.get_area(), 1.0, s.points),
+            ShapeEnum::Square(s) => (s.side, s.side, Vec::new()),
+            ShapeEnum::Ellipse(s) => (s.radius_x, s.radius_y, Vec::new()),
+        };
+        self.kinds.push(kind);
+        self.x.push(origin.x);
+        self.y.push(origin.y);
+        self.a.push(a);
+        self.b.push(b);
+        self.points.push(points);
+        self.kinds.len() - 1
+    }
+    pub fn get(&self, index: usize) -> Option<ShapeEnum> {
+        let origin = self.origin(index)?;
+        let (a, b) = (self.a[index], self.b[index]);
+        Some(match self.kinds[index] {
+            ShapeKind::Circle => ShapeEnum::Circle(Circle { origin, radius: a }),
+            ShapeKind::Rectangle => ShapeEnum::Rectangle(Rectangle {
+                origin,
+                side_a: a,
+                side_b: b,
+            }),
+            ShapeKind::Triangle => ShapeEnum::Triangle(Triangle {
+                origin,
+                base: a,
+                height: b,
+            }),
+            ShapeKind::Polygon => ShapeEnum::Polygon(Polygon {
+                origin,
+                points: self.points[index].clone(),
+            }),
+            ShapeKind::Square => ShapeEnum::Square(Square { origin, side: a }),
+            ShapeKind::Ellipse => ShapeEnum::Ellipse(Ellipse {
+                origin,
+                radius_x: a,
+                radius_y: b,
+            }),
+        })
+    }
+    pub fn remove(&mut self, index: usize) -> Result<ShapeEnum, CanvasError> {
+        let shape = self.get(index).ok_or(CanvasError::UnknownIndex(index))?;
+        self.kinds.remove(index);
+        self.x.remove(index);
+        self.y.remove(index);
+        self.a.remove(index);
+        self.b.remove(index);
+        self.points.remove(index);
+        Ok(shape)
+    }
+    pub fn len(&self) -> usize {
+        self.kinds.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty()
+    }
+    pub fn origin(&self, index: usize) -> Option<Coord> {
+        Some(Coord::new(*self.x.get(index)?, self.y[index]))
+    }
+    pub fn get_area(&self, index: usize) -> Option<f32> {
+        let kind = *self.kinds.get(index)?;
+        Some(area_coefficient(kind) * self.a[index] * self.b[index])
+    }
+    pub fn set_origin(
+        &mut self,
+        index: usize,
+        origin: impl Into<Coord>,
+    ) -> Result<(), CanvasError> {
+        if index >= self.len() {
+            return Err(CanvasError::UnknownIndex(index));
+        }
+        let origin = coordinate("origin", origin.into())?;
+        self.x[index] = origin.x;
+        self.y[index] = origin.y;
+        if cfg!(debug_assertions) {
+            debug_check(&self.get(index).unwrap());
+        }
+        Ok(())
+    }
+    #[cfg(not(feature = "simd"))]
+    pub fn total_area(&self) -> f32 {
+        self.kinds
+            .iter()
+            .zip(&self.a)
+            .zip(&self.b)
+            .map(|((kind, a), b)| area_coefficient(*kind) * a * b)
+            .sum()
+    }
+    #[cfg(feature = "simd")]
+    pub fn total_area(&self) -> f32 {
+        simd::sum_areas(&self.kinds, &self.a, &self.b)
+    }
+    #[cfg(not(feature = "simd"))]
+    pub fn translate_all(&mut self, offset: Coord) {
+        self.x.iter_mut().for_each(|x| *x += offset.x);
+        self.y.iter_mut().for_each(|y| *y += offset.y);
+    }
+    #[cfg(feature = "simd")]
+    pub fn translate_all(&mut self, offset: Coord) {
+        simd::add_all(&mut self.x, offset.x);
+        simd::add_all(&mut self.y, offset.y);
+    }
+}
+
+// The same check as Canvas runs after changes, in debug builds
+fn debug_check(shape: &ShapeEnum) {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    if let Err(error) = shape.validate() {
+        panic!("invalid shape in storage: {error}");
+    }
+}
+
+pub fn area_coefficient(kind: ShapeKind) -> f32 {
+    match kind {
+        ShapeKind::Circle | ShapeKind::Ellipse => std::f32::consts::PI,
+        ShapeKind::Rectangle | ShapeKind::Polygon | ShapeKind::Square => 1.0,
+        ShapeKind::Triangle => 0.5,
+    }
+}
+
+impl FromIterator<ShapeEnum> for SoaCanvas {
+    fn from_iter<I: IntoIterator<Item = ShapeEnum>>(shapes: I) -> SoaCanvas {
+        let mut canvas = SoaCanvas::default();
+        for shape in shapes {
+            canvas.add(shape);
+        }
+        canvas
+    }
+}
+
+// The operations every storage mode supports, so code can be written once
+// against whichever suits its workload
+pub trait ShapeStorage {
+    type Id: Copy;
+    fn add_shape(&mut self, shape: ShapeEnum) -> Self::Id;
+    // In the order they were added
+    fn shape_ids(&self) -> Vec<Self::Id>;
+    fn shape_origin(&self, id: Self::Id) -> Option<Coord>;
+    fn shape_area(&self, id: Self::Id) -> Option<f32>;
+    fn move_shape(&mut self, id: Self::Id, origin: Coord) -> Result<(), CanvasError>;
+    fn total_area(&self) -> f32;
+    fn translate_all(&mut self, offset: Coord) -> Result<(), CanvasError>;
+}
+
+impl ShapeStorage for Canvas {
+    type Id = ShapeId;
+    fn add_shape(&mut self, shape: ShapeEnum) -> ShapeId {
+        self.add(shape.into_object())
+    }
+    fn shape_ids(&self) -> Vec<ShapeId> {
+        self.ids().to_vec()
+    }
+    fn shape_origin(&self, id: ShapeId) -> Option<Coord> {
+        Some(self.get(id)?.lock().ok()?.origin())
+    }
+    fn shape_area(&self, id: ShapeId) -> Option<f32> {
+        self.get_area(id)
+    }
+    fn move_shape(&mut self, id: ShapeId, origin: Coord) -> Result<(), CanvasError> {
+        self.set_origin(id, origin)
+    }
+    fn total_area(&self) -> f32 {
+        Canvas::total_area(self)
+    }
+    fn translate_all(&mut self, offset: Coord) -> Result<(), CanvasError> {
+        Canvas::translate_all(self, offset)
+    }
+}
+
+impl ShapeStorage for EnumCanvas {
+    type Id = usize;
+    fn add_shape(&mut self, shape: ShapeEnum) -> usize {
+        self.add(shape)
+    }
+    fn shape_ids(&self) -> Vec<usize> {
+        (0..self.len()).collect()
+    }
+    fn shape_origin(&self, index: usize) -> Option<Coord> {
+        self.get(index).map(Shape::origin)
+    }
+    fn shape_area(&self, index: usize) -> Option<f32> {
+        self.get_area(index)
+    }
+    fn move_shape(&mut self, index: usize, origin: Coord) -> Result<(), CanvasError> {
+        self.set_origin(index, origin)
+    }
+    fn total_area(&self) -> f32 {
+        EnumCanvas::total_area(self)
+    }
+    fn translate_all(&mut self, offset: Coord) -> Result<(), CanvasError> {
+        EnumCanvas::translate_all(self, offset);
+        Ok(())
+    }
+}
+
+impl ShapeStorage for SoaCanvas {
+    type Id = usize;
+    fn add_shape(&mut self, shape: ShapeEnum) -> usize {
+        self.add(shape)
+    }
+    fn shape_ids(&self) -> Vec<usize> {
+        (0..self.len()).collect()
+    }
+    fn shape_origin(&self, index: usize) -> Option<Coord> {
+        self.origin(index)
+    }
+    fn shape_area(&self, index: usize) -> Option<f32> {
+        self.get_area(index)
+    }
+    fn move_shape(&mut self, index: usize, origin: Coord) -> Result<(), CanvasError> {
+        self.set_origin(index, origin)
+    }
+    fn total_area(&self) -> f32 {
+        SoaCanvas::total_area(self)
+    }
+    fn translate_all(&mut self, offset: Coord) -> Result<(), CanvasError> {
+        SoaCanvas::translate_all(self, offset);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_approx_eq, testutil::Scene};
+
+    #[test]
+    fn enum_canvas_matches_canvas() {
+        let shapes = Scene::default().shapes(3);
+        let mut canvas = Canvas::default();
+        for shape in shapes.clone() {
+            canvas.add(shape.into_object());
+        }
+        let mut enum_canvas: EnumCanvas = shapes.into_iter().collect();
+
+        assert_eq!(enum_canvas.len(), canvas.len());
+        assert_approx_eq!(enum_canvas.total_area(), canvas.total_area());
+
+        let offset = Coord::new(1.5, -2.0);
+        enum_canvas.translate_all(offset);
+        canvas.translate_all(offset).unwrap();
+        for (shape, id) in enum_canvas.iter().zip(canvas.ids()) {
+            let origin = canvas.get(*id).unwrap().lock().unwrap().origin();
+            assert_eq!(shape.origin(), origin);
+        }
+    }
+
+    fn exercise<S: ShapeStorage + Default>(shapes: &[ShapeEnum]) -> (f32, Vec<Coord>) {
+        let mut storage = S::default();
+        let ids: Vec<S::Id> = shapes
+            .iter()
+            .map(|shape| storage.add_shape(shape.clone()))
+            .collect();
+        storage.translate_all(Coord::new(1.0, 2.0)).unwrap();
+        storage.move_shape(ids[3], Coord::new(-5.0, -5.0)).unwrap();
+        let origins = storage
+            .shape_ids()
+            .into_iter()
+            .filter_map(|id| storage.shape_origin(id))
+            .collect();
+        (storage.total_area(), origins)
+    }
+
+    #[test]
+    fn storage_modes_agree() {
+        let shapes = Scene::of(200).shapes(11);
+        let (area, origins) = exercise::<Canvas>(&shapes);
+
+        for (other_area, other_origins) in [
+            exercise::<EnumCanvas>(&shapes),
+            exercise::<SoaCanvas>(&shapes),
+        ] {
+            assert!((area - other_area).abs() < 1e-2 * area.abs().max(1.0));
+            assert_eq!(origins, other_origins);
+        }
+
+        let mut soa: SoaCanvas = shapes.iter().cloned().collect();
+        assert_eq!(soa.get(5).as_ref(), shapes.get(5));
+        assert_eq!(soa.remove(0).unwrap(), shapes[0]);
+        assert_eq!(soa.len(), 199);
+        assert!(matches!(
+            soa.remove(199),
+            Err(CanvasError::UnknownIndex(199))
+        ));
+        // Squares and ellipses keep their kind through the columns
+        let square = ShapeEnum::from(Square {
+            origin: Coord::new(1.0, 2.0),
+            side: 3.0,
+        });
+        let ellipse = ShapeEnum::from(Ellipse {
+            origin: Coord::new(-1.0, 0.5),
+            radius_x: 2.0,
+            radius_y: 0.5,
+        });
+        let soa: SoaCanvas = [square.clone(), ellipse.clone()].into_iter().collect();
+        assert_eq!(soa.get(0), Some(square));
+        assert_eq!(soa.get(1), Some(ellipse));
+        assert_approx_eq!(soa.total_area(), 9.0 + core::f32::consts::PI);
+
+        let mut enums: EnumCanvas = shapes.iter().cloned().collect();
+        assert!(matches!(
+            enums.remove(200),
+            Err(CanvasError::UnknownIndex(200))
+        ));
+    }
+}
@@ -0,0 +1,282 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    geometry::{outline_points, point_in_polygon, Outline},
+    mesh::MITER_LIMIT,
+    style::{Dash, LineCap, LineJoin, Style},
+    Coord,
+};
+
+// The area a stroke covers, broken into a band along each segment and a
+// patch at every corner and dash end, so whether a point is covered comes
+// down to a few point in polygon tests
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StrokeArea {
+    half_width: f32,
+    // Exact for undashed circles, which need no pieces
+    ring: Option<(Coord, f32)>,
+    polygons: Vec<Vec<Coord>>,
+    // Round joins and caps, half the width across
+    discs: Vec<Coord>,
+}
+
+impl StrokeArea {
+    pub fn new(outline: &Outline, style: &Style) -> StrokeArea {
+        let half_width = style.stroke_width / 2.0;
+        let mut area = StrokeArea {
+            half_width,
+            ..StrokeArea::default()
+        };
+        if let (Outline::Circle { center, radius }, None) = (outline, style.dash) {
+            area.ring = Some((*center, *radius));
+            return area;
+        }
+        let points = outline_points(outline.clone());
+        let chains = match style.dash {
+            None => vec![(points, true)],
+            Some(dash) => dashes(&points, dash)
+                .into_iter()
+                .map(|chain| (chain, false))
+                .collect(),
+        };
+        for (chain, closed) in chains {
+            area.add_chain(&chain, closed, style.cap, style.join);
+        }
+        area
+    }
+
+    pub fn contains(&self, point: Coord) -> bool {
+        if let Some((center, radius)) = self.ring {
+            return (center.distance(point) - radius).abs() <= self.half_width;
+        }
+        self.discs
+            .iter()
+            .any(|disc| disc.distance(point) <= self.half_width)
+            || self
+                .polygons
+                .iter()
+                .any(|polygon| point_in_polygon(point, polygon))
+    }
+
+    fn add_chain(&mut self, chain: &[Coord], closed: bool, cap: LineCap, join: LineJoin) {
+        let mut points: Vec<Coord> = Vec::with_capacity(chain.len());
+        for &point in chain {
+            if points.last() != Some(&point) {
+                points.push(point);
+            }
+        }
+        if closed && points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        let hw = self.half_width;
+        // A dash too short to have a direction is just its caps
+        if points.len() < 2 {
+            if let (Some(&point), LineCap::Round) = (points.first(), cap) {
+                self.discs.push(point);
+            }
+            return;
+        }
+
+        let n = points.len();
+        let segments = if closed { n } else { n - 1 };
+        let direction = |i: usize| {
+            let edge = points[(i + 1) % n] - points[i];
+            edge * (1.0 / edge.length())
+        };
+        for i in 0..segments {
+            let (mut a, mut b) = (points[i], points[(i + 1) % n]);
+            let d = direction(i);
+            if !closed && cap == LineCap::Square {
+                if i == 0 {
+                    a = a - d * hw;
+                }
+                if i == segments - 1 {
+                    b = b + d * hw;
+                }
+            }
+            let side = Coord::new(d.y, -d.x) * hw;
+            self.polygons
+                .push(vec![a + side, b + side, b - side, a - side]);
+        }
+
+        let corners = if closed { 0..n } else { 1..n - 1 };
+        for i in corners {
+            let (before, after) = (direction((i + n - 1) % n), direction(i));
+            self.add_join(points[i], before, after, join);
+        }
+        if !closed && cap == LineCap::Round {
+            self.discs.push(points[0]);
+            self.discs.push(points[n - 1]);
+        }
+    }
+
+    // Fills the wedge opened up on the outside of a corner
+    fn add_join(&mut self, vertex: Coord, before: Coord, after: Coord, join: LineJoin) {
+        let turn = before.cross(after);
+        if turn.abs() <= f32::EPSILON && before.dot(after) > 0.0 {
+            return;
+        }
+        if join == LineJoin::Round {
+            self.discs.push(vertex);
+            return;
+        }
+        // Right hand normals, flipped to the outside of a left turn's corner
+        let outside = if turn > 0.0 { 1.0 } else { -1.0 } * self.half_width;
+        let (n0, n1) = (
+            Coord::new(before.y, -before.x) * outside,
+            Coord::new(after.y, -after.x) * outside,
+        );
+        let mut wedge = vec![vertex, vertex + n0];
+        let cosine = n0.dot(n1) / (self.half_width * self.half_width);
+        if join == LineJoin::Miter && 1.0 + cosine > f32::EPSILON {
+            let tip = (n0 + n1) * (1.0 / (1.0 + cosine));
+            if tip.length() <= MITER_LIMIT * self.half_width {
+                wedge.push(vertex + tip);
+            }
+        }
+        wedge.push(vertex + n1);
+        self.polygons.push(wedge);
+    }
+}
+
+// The dashes along a closed chain of points, starting from its first point.
+// A dash running over the start joins up with the one ending there.
+pub fn dashes(points: &[Coord], dash: Dash) -> Vec<Vec<Coord>> {
+    let period = dash.on + dash.off;
+    let mut chains: Vec<Vec<Coord>> = Vec::new();
+    let mut position = dash.offset % period;
+    if position < 0.0 {
+        position += period;
+    }
+    let starts_on = position < dash.on;
+    let mut current: Option<Vec<Coord>> = None;
+    let n = points.len();
+    for i in 0..n {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        let length = a.distance(b);
+        if length == 0.0 {
+            continue;
+        }
+        let along = |t: f32| match t < length {
+            true => a + (b - a) * (t / length),
+            false => b,
+        };
+        let mut t = 0.0;
+        while t < length {
+            let on = position < dash.on;
+            let remaining = if on { dash.on } else { period } - position;
+            let step = remaining.min(length - t);
+            if on {
+                current.get_or_insert_with(|| vec![along(t)]);
+            }
+            t += step;
+            position += step;
+            if on {
+                let chain = current.as_mut().unwrap();
+                chain.push(along(t));
+                if step == remaining {
+                    chains.extend(current.take());
+                }
+            } else if step == remaining {
+                position = 0.0;
+            }
+        }
+    }
+    if let Some(last) = current {
+        match chains.first_mut() {
+            Some(first) if starts_on => {
+                let mut joined = last;
+                joined.extend_from_slice(&first[1..]);
+                *first = joined;
+            }
+            _ => chains.push(last),
+        }
+    }
+    chains
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Rectangle, Shape};
+
+    fn square() -> Outline {
+        Rectangle {
+            origin: Coord::new(5.0, 5.0),
+            side_a: 10.0,
+            side_b: 10.0,
+        }
+        .outline()
+    }
+
+    #[test]
+    fn dash_along_outline() {
+        let dash = Dash {
+            on: 4.0,
+            off: 1.0,
+            offset: 0.0,
+        };
+        let chains = dashes(&outline_points(square()), dash);
+        // 40 round, in eight periods of five
+        assert_eq!(chains.len(), 8);
+        let length = |chain: &Vec<Coord>| {
+            chain
+                .windows(2)
+                .map(|pair| pair[0].distance(pair[1]))
+                .sum::<f32>()
+        };
+        assert!(chains
+            .iter()
+            .all(|chain| (length(chain) - 4.0).abs() < 1e-4));
+
+        // Shifted so the first dash wraps round the corner it starts from
+        let shifted = dashes(
+            &outline_points(square()),
+            Dash {
+                offset: 2.0,
+                ..dash
+            },
+        );
+        assert_eq!(shifted.len(), 8);
+        assert!(shifted
+            .iter()
+            .all(|chain| (length(chain) - 4.0).abs() < 1e-4));
+        assert_eq!(shifted[0].len(), 3);
+    }
+
+    #[test]
+    fn caps_and_joins() {
+        let corner = Coord::new(10.9, 10.9);
+        let style = |join| Style {
+            stroke_width: 2.0,
+            join,
+            ..Style::default()
+        };
+        // Past the corner, reached only by the mitre
+        let miter = StrokeArea::new(&square(), &style(LineJoin::Miter));
+        assert!(miter.contains(corner));
+        assert!(miter.contains(Coord::new(5.0, 10.9)));
+        assert!(!miter.contains(Coord::new(5.0, 5.0)));
+        for join in [LineJoin::Round, LineJoin::Bevel] {
+            let area = StrokeArea::new(&square(), &style(join));
+            assert!(!area.contains(corner), "{join:?}");
+            assert!(area.contains(Coord::new(10.6, 10.2)), "{join:?}");
+        }
+
+        // Along the bottom edge, the gap between dashes at x = 4.5
+        let dashed = |cap| Style {
+            dash: Some(Dash {
+                on: 4.0,
+                off: 1.0,
+                offset: 0.0,
+            }),
+            cap,
+            ..style(LineJoin::Miter)
+        };
+        let gap = Coord::new(4.5, 0.0);
+        assert!(!StrokeArea::new(&square(), &dashed(LineCap::Butt)).contains(gap));
+        assert!(StrokeArea::new(&square(), &dashed(LineCap::Round)).contains(gap));
+        assert!(StrokeArea::new(&square(), &dashed(LineCap::Square)).contains(gap));
+        assert!(StrokeArea::new(&square(), &dashed(LineCap::Butt)).contains(Coord::new(2.0, 0.5)));
+    }
+}
@@ -0,0 +1,462 @@
+use alloc::{format, string::String};
+use core::str::FromStr;
+
+#[cfg(not(any(feature = "std", test)))]
+use crate::math::Float;
+use crate::{
+    builder::dimension,
+    error::{ParseColorError, ShapeError},
+    Coord,
+};
+
+// 8 bit RGBA, not premultiplied
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    pub const TRANSPARENT: Color = Color::rgba(0, 0, 0, 0);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color::rgba(r, g, b, 255)
+    }
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+    // Hue in degrees, saturation and lightness from 0 to 1
+    pub fn hsl(hue: f32, saturation: f32, lightness: f32) -> Color {
+        Color::hsla(hue, saturation, lightness, 1.0)
+    }
+    pub fn hsla(hue: f32, saturation: f32, lightness: f32, alpha: f32) -> Color {
+        let (s, l) = (saturation.clamp(0.0, 1.0), lightness.clamp(0.0, 1.0));
+        // Sixths of the way round the colour wheel
+        let hue = match hue % 360.0 {
+            hue if hue < 0.0 => hue + 360.0,
+            hue => hue,
+        } / 60.0;
+        let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = l - chroma / 2.0;
+        Color::rgba(
+            channel(r + m),
+            channel(g + m),
+            channel(b + m),
+            channel(alpha),
+        )
+    }
+
+    // Alpha scaled by the opacity, from 0 to 1
+    pub fn with_opacity(self, opacity: f32) -> Color {
+        Color {
+            a: channel(self.a as f32 / 255.0 * opacity),
+            ..self
+        }
+    }
+    // Source over compositing of this colour onto another
+    pub fn over(self, under: Color) -> Color {
+        let (top, bottom) = (self.a as f32 / 255.0, under.a as f32 / 255.0);
+        let alpha = top + bottom * (1.0 - top);
+        if alpha == 0.0 {
+            return Color::TRANSPARENT;
+        }
+        let mix = |over: u8, under: u8| {
+            channel((over as f32 * top + under as f32 * bottom * (1.0 - top)) / alpha / 255.0)
+        };
+        Color::rgba(
+            mix(self.r, under.r),
+            mix(self.g, under.g),
+            mix(self.b, under.b),
+            channel(alpha),
+        )
+    }
+
+    // #rrggbb, with the alpha only when it isn't opaque
+    pub fn to_hex(self) -> String {
+        match self.a {
+            255 => format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b),
+            a => format!("#{:02x}{:02x}{:02x}{a:02x}", self.r, self.g, self.b),
+        }
+    }
+}
+
+// From 0 to 1, clamped, to the nearest 8 bit value
+fn channel(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
+
+// #rgb, #rgba, #rrggbb or #rrggbbaa, the # optional
+impl FromStr for Color {
+    type Err = ParseColorError;
+    fn from_str(text: &str) -> Result<Color, ParseColorError> {
+        let error = || ParseColorError {
+            input: String::from(text),
+        };
+        let digits = text.strip_prefix('#').unwrap_or(text);
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(error());
+        }
+        let value =
+            |range: core::ops::Range<usize>| u8::from_str_radix(&digits[range], 16).unwrap();
+        let (r, g, b, a) = match digits.len() {
+            3 | 4 => {
+                let short = |i: usize| value(i..i + 1) * 17;
+                let a = if digits.len() == 4 { short(3) } else { 255 };
+                (short(0), short(1), short(2), a)
+            }
+            6 | 8 => {
+                let a = if digits.len() == 8 { value(6..8) } else { 255 };
+                (value(0..2), value(2..4), value(4..6), a)
+            }
+            _ => return Err(error()),
+        };
+        Ok(Color::rgba(r, g, b, a))
+    }
+}
+
+impl From<Color> for [u8; 4] {
+    fn from(color: Color) -> [u8; 4] {
+        [color.r, color.g, color.b, color.a]
+    }
+}
+impl From<[u8; 4]> for Color {
+    fn from([r, g, b, a]: [u8; 4]) -> Color {
+        Color { r, g, b, a }
+    }
+}
+
+// What an area is filled with. Positions and sizes are in canvas units, so
+// a gradient or pattern spans every shape sharing the style the same way.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Paint {
+    Solid(Color),
+    // From the first colour at start to the second at end, constant along
+    // lines square to the one between them and clamped past either end
+    Linear {
+        start: Coord,
+        end: Coord,
+        colors: [Color; 2],
+    },
+    // From the first colour at the centre to the second at the radius
+    Radial {
+        center: Coord,
+        radius: f32,
+        colors: [Color; 2],
+    },
+    // Bands of each colour in turn, each size wide, running along the angle
+    // in radians from the x axis
+    Stripes {
+        angle: f32,
+        size: f32,
+        colors: [Color; 2],
+    },
+    // A chequerboard of size wide squares lined up with the axes
+    Checks {
+        size: f32,
+        colors: [Color; 2],
+    },
+}
+
+impl Paint {
+    pub fn at(&self, point: Coord) -> Color {
+        match *self {
+            Paint::Solid(color) => color,
+            Paint::Linear { start, end, colors } => {
+                let (dx, dy) = (end.x - start.x, end.y - start.y);
+                let length = dx * dx + dy * dy;
+                if length == 0.0 {
+                    return colors[1];
+                }
+                let t = ((point.x - start.x) * dx + (point.y - start.y) * dy) / length;
+                mix(colors, t)
+            }
+            Paint::Radial {
+                center,
+                radius,
+                colors,
+            } => mix(
+                colors,
+                (point.x - center.x).hypot(point.y - center.y) / radius,
+            ),
+            Paint::Stripes {
+                angle,
+                size,
+                colors,
+            } => {
+                // Distance across the bands
+                let (sin, cos) = angle.sin_cos();
+                let across = point.y * cos - point.x * sin;
+                colors[(across / size).floor() as i64 as usize & 1]
+            }
+            Paint::Checks { size, colors } => {
+                let cell = |value: f32| (value / size).floor() as i64;
+                colors[(cell(point.x) + cell(point.y)) as usize & 1]
+            }
+        }
+    }
+
+    // The solid colour, or the first one for paints that vary, for outputs
+    // that can only show a flat fill
+    pub fn color(&self) -> Color {
+        match *self {
+            Paint::Solid(color) => color,
+            Paint::Linear { colors, .. }
+            | Paint::Radial { colors, .. }
+            | Paint::Stripes { colors, .. }
+            | Paint::Checks { colors, .. } => colors[0],
+        }
+    }
+
+    pub fn with_opacity(self, opacity: f32) -> Paint {
+        let fade = |colors: [Color; 2]| colors.map(|color| color.with_opacity(opacity));
+        match self {
+            Paint::Solid(color) => Paint::Solid(color.with_opacity(opacity)),
+            Paint::Linear { start, end, colors } => Paint::Linear {
+                start,
+                end,
+                colors: fade(colors),
+            },
+            Paint::Radial {
+                center,
+                radius,
+                colors,
+            } => Paint::Radial {
+                center,
+                radius,
+                colors: fade(colors),
+            },
+            Paint::Stripes {
+                angle,
+                size,
+                colors,
+            } => Paint::Stripes {
+                angle,
+                size,
+                colors: fade(colors),
+            },
+            Paint::Checks { size, colors } => Paint::Checks {
+                size,
+                colors: fade(colors),
+            },
+        }
+    }
+
+    // The radius or size it's scaled by, for paints that have one
+    pub fn size(&self) -> Option<(&'static str, f32)> {
+        match *self {
+            Paint::Radial { radius, .. } => Some(("radius", radius)),
+            Paint::Stripes { size, .. } | Paint::Checks { size, .. } => Some(("size", size)),
+            Paint::Solid(_) | Paint::Linear { .. } => None,
+        }
+    }
+}
+
+impl From<Color> for Paint {
+    fn from(color: Color) -> Paint {
+        Paint::Solid(color)
+    }
+}
+
+// Interpolated channel by channel, t clamped to between the two
+fn mix([from, to]: [Color; 2], t: f32) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    let lerp = |a: u8, b: u8| channel((a as f32 + (b as f32 - a as f32) * t) / 255.0);
+    Color::rgba(
+        lerp(from.r, to.r),
+        lerp(from.g, to.g),
+        lerp(from.b, to.b),
+        lerp(from.a, to.a),
+    )
+}
+
+// Dashes of the stroke, in canvas units along the outline, starting offset
+// into the pattern from the outline's first point
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dash {
+    pub on: f32,
+    pub off: f32,
+    pub offset: f32,
+}
+
+impl Dash {
+    // Neither length negative and the two together positive
+    pub fn validate(&self) -> Result<(), ShapeError> {
+        for (name, value) in [("dash on", self.on), ("dash off", self.off)] {
+            if value.is_nan() || value < 0.0 {
+                return Err(ShapeError::InvalidDimension { name, value });
+            }
+        }
+        dimension("dash period", Some(self.on + self.off))?;
+        if !self.offset.is_finite() {
+            return Err(ShapeError::InvalidDimension {
+                name: "dash offset",
+                value: self.offset,
+            });
+        }
+        Ok(())
+    }
+}
+
+// How the ends of dashes are drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum LineCap {
+    // Flat at the end
+    #[default]
+    Butt,
+    Round,
+    // Flat, half the stroke width past the end
+    Square,
+}
+
+// How the stroke turns corners
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum LineJoin {
+    // Sharp, or bevelled past mesh::MITER_LIMIT
+    #[default]
+    Miter,
+    Round,
+    Bevel,
+}
+
+// How a shape is painted. Either part can be left out; the stroke is
+// centred on the outline, its width in canvas units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Style {
+    pub fill: Option<Paint>,
+    pub stroke: Option<Color>,
+    pub stroke_width: f32,
+    // Solid when there are none
+    pub dash: Option<Dash>,
+    pub cap: LineCap,
+    pub join: LineJoin,
+    // Applied to the fill and stroke on top of their own alpha, from 0 to 1
+    pub opacity: f32,
+}
+
+// Filled black and not stroked, which is how shapes were always drawn
+impl Default for Style {
+    fn default() -> Style {
+        Style {
+            fill: Some(Paint::Solid(Color::BLACK)),
+            stroke: None,
+            stroke_width: 1.0,
+            dash: None,
+            cap: LineCap::Butt,
+            join: LineJoin::Miter,
+            opacity: 1.0,
+        }
+    }
+}
+
+impl Style {
+    // What to paint with, the opacity applied
+    pub fn fill_paint(&self) -> Option<Paint> {
+        self.fill.map(|paint| paint.with_opacity(self.opacity))
+    }
+    pub fn fill_color(&self) -> Option<Color> {
+        self.fill_paint().map(|paint| paint.color())
+    }
+    pub fn stroke_color(&self) -> Option<Color> {
+        self.stroke.map(|color| color.with_opacity(self.opacity))
+    }
+    // How far the stroke can reach past the outline, zero without one
+    pub fn stroke_reach(&self) -> f32 {
+        if self.stroke.is_none() {
+            return 0.0;
+        }
+        let corners: f32 = match (self.join, self.cap) {
+            (LineJoin::Miter, _) => crate::mesh::MITER_LIMIT,
+            (_, LineCap::Square) if self.dash.is_some() => core::f32::consts::SQRT_2,
+            _ => 1.0,
+        };
+        corners * self.stroke_width / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_convert() {
+        assert_eq!("#ff8000".parse(), Ok(Color::rgb(255, 128, 0)));
+        assert_eq!("0f08".parse(), Ok(Color::rgba(0, 255, 0, 136)));
+        assert_eq!("#11223380".parse(), Ok(Color::rgba(0x11, 0x22, 0x33, 0x80)));
+        for bad in ["", "#12", "#12345", "#gg0000", "#ff00a"] {
+            assert!(bad.parse::<Color>().is_err(), "{bad}");
+        }
+        assert_eq!(Color::rgba(1, 2, 254, 128).to_hex(), "#0102fe80");
+        assert_eq!(Color::WHITE.to_hex(), "#ffffff");
+
+        assert_eq!(Color::hsl(0.0, 1.0, 0.5), Color::rgb(255, 0, 0));
+        assert_eq!(Color::hsl(120.0, 1.0, 0.25), Color::rgb(0, 128, 0));
+        assert_eq!(Color::hsl(-120.0, 1.0, 0.5), Color::rgb(0, 0, 255));
+        assert_eq!(Color::hsl(200.0, 0.0, 1.0), Color::WHITE);
+    }
+
+    #[test]
+    fn compositing() {
+        let half_red = Color::rgb(255, 0, 0).with_opacity(0.5);
+        assert_eq!(half_red.a, 128);
+        assert_eq!(half_red.over(Color::WHITE), Color::rgb(255, 127, 127));
+        // Onto nothing, a colour stays as it is
+        assert_eq!(half_red.over(Color::TRANSPARENT), half_red);
+        assert_eq!(Color::TRANSPARENT.over(Color::BLACK), Color::BLACK);
+        let twice = half_red.over(half_red);
+        assert_eq!((twice.r, twice.a), (255, 192));
+    }
+
+    #[test]
+    fn paints() {
+        let colors = [Color::BLACK, Color::WHITE];
+        let linear = Paint::Linear {
+            start: Coord::new(0.0, 0.0),
+            end: Coord::new(10.0, 0.0),
+            colors,
+        };
+        assert_eq!(linear.at(Coord::new(-5.0, 3.0)), Color::BLACK);
+        assert_eq!(linear.at(Coord::new(5.0, 8.0)), Color::rgb(128, 128, 128));
+        assert_eq!(linear.at(Coord::new(20.0, 0.0)), Color::WHITE);
+
+        let radial = Paint::Radial {
+            center: Coord::new(1.0, 1.0),
+            radius: 2.0,
+            colors,
+        };
+        assert_eq!(radial.at(Coord::new(1.0, 1.0)), Color::BLACK);
+        assert_eq!(radial.at(Coord::new(1.0, 4.0)), Color::WHITE);
+
+        // Horizontal bands a unit tall
+        let stripes = Paint::Stripes {
+            angle: 0.0,
+            size: 1.0,
+            colors,
+        };
+        assert_eq!(stripes.at(Coord::new(7.0, 0.5)), Color::BLACK);
+        assert_eq!(stripes.at(Coord::new(7.0, 1.5)), Color::WHITE);
+        assert_eq!(stripes.at(Coord::new(7.0, -0.5)), Color::WHITE);
+
+        let checks = Paint::Checks { size: 2.0, colors };
+        assert_eq!(checks.at(Coord::new(1.0, 1.0)), Color::BLACK);
+        assert_eq!(checks.at(Coord::new(3.0, 1.0)), Color::WHITE);
+        assert_eq!(checks.at(Coord::new(-1.0, 1.0)), Color::WHITE);
+        assert_eq!(checks.size(), Some(("size", 2.0)));
+
+        let faded = checks.with_opacity(0.5);
+        assert_eq!(faded.at(Coord::new(3.0, 3.0)).a, 128);
+        assert_eq!(faded.color(), Color::BLACK.with_opacity(0.5));
+    }
+}
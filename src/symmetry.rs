@@ -0,0 +1,303 @@
+use std::{f32::consts::PI, sync::Arc};
+
+use crate::{
+    cell::ShapeGuard, error::CanvasError, geometry::Outline, Circle, Coord, Polygon, Shape,
+    ShapeCell, ShapeObject,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymmetryGroup {
+    // n-fold rotation
+    Cyclic(u32),
+    // n-fold rotation plus n mirror lines, the first along the x axis
+    Dihedral(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Symmetry {
+    pub group: SymmetryGroup,
+    pub center: Coord,
+}
+
+// An infinite line through a point, used as a mirror
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MirrorLine {
+    pub point: Coord,
+    pub direction: Coord,
+}
+
+// A distance preserving map of the plane: an optional mirror in the x axis,
+// then a rotation about the zero origin, then a translation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Element {
+    pub angle: f32,
+    pub mirrored: bool,
+    pub offset: Coord,
+}
+
+impl Element {
+    pub fn rotation(center: Coord, angle: f32) -> Element {
+        Element::about(center, angle, false)
+    }
+    pub fn reflection(line: &MirrorLine) -> Element {
+        let angle = line.direction.y.atan2(line.direction.x);
+        Element::about(line.point, 2.0 * angle, true)
+    }
+    // Maps center onto itself
+    fn about(center: Coord, angle: f32, mirrored: bool) -> Element {
+        let linear = Element {
+            angle,
+            mirrored,
+            offset: Coord::default(),
+        };
+        Element {
+            offset: center - linear.map(center),
+            ..linear
+        }
+    }
+
+    pub fn map(&self, point: Coord) -> Coord {
+        let point = if self.mirrored {
+            Coord::new(point.x, -point.y)
+        } else {
+            point
+        };
+        point.rotate(self.angle) + self.offset
+    }
+    pub fn inverse(&self) -> Element {
+        // A mirror then a rotation is its own linear inverse
+        let linear = Element {
+            angle: if self.mirrored {
+                self.angle
+            } else {
+                -self.angle
+            },
+            mirrored: self.mirrored,
+            offset: Coord::default(),
+        };
+        Element {
+            offset: -linear.map(self.offset),
+            ..linear
+        }
+    }
+    // The element applying `first` and then `self`
+    pub fn after(&self, first: &Element) -> Element {
+        Element {
+            // Mirroring reverses the direction of the first rotation
+            angle: self.angle
+                + if self.mirrored {
+                    -first.angle
+                } else {
+                    first.angle
+                },
+            mirrored: self.mirrored != first.mirrored,
+            offset: self.map(first.offset),
+        }
+    }
+
+    // A transformed copy of the shape. Circles stay circles, anything else
+    // becomes a polygon since shapes have no rotation of their own.
+    pub fn image(&self, shape: &dyn Shape) -> ShapeObject {
+        match shape.outline() {
+            Outline::Circle { center, radius } => Arc::new(ShapeCell::new(Circle {
+                origin: self.map(center),
+                radius,
+            })),
+            Outline::Polygon(points) => {
+                let origin = self.map(shape.origin());
+                Arc::new(ShapeCell::new(Polygon {
+                    origin,
+                    points: points
+                        .into_iter()
+                        .map(|point| self.map(point) - origin)
+                        .collect(),
+                }))
+            }
+        }
+    }
+}
+
+// The most images a symmetry may give each shape, as one add makes them
+// all at once
+pub const MAX_ORDER: u32 = 360;
+
+impl Symmetry {
+    pub fn order(&self) -> u32 {
+        match self.group {
+            SymmetryGroup::Cyclic(n) | SymmetryGroup::Dihedral(n) => n,
+        }
+    }
+    pub fn validate(&self) -> Result<(), CanvasError> {
+        match self.order() {
+            1..=MAX_ORDER => Ok(()),
+            n => Err(CanvasError::InvalidSymmetryOrder(n)),
+        }
+    }
+
+    // Every group element other than the identity
+    pub fn elements(&self) -> Vec<Element> {
+        let (n, mirrors) = match self.group {
+            SymmetryGroup::Cyclic(n) => (n.max(1), false),
+            SymmetryGroup::Dihedral(n) => (n.max(1), true),
+        };
+        let rotations = (0..n).map(|k| 2.0 * PI * k as f32 / n as f32);
+        let mut elements: Vec<Element> = rotations
+            .clone()
+            .skip(1)
+            .map(|angle| Element::rotation(self.center, angle))
+            .collect();
+        if mirrors {
+            elements.extend(rotations.map(|angle| {
+                Element::reflection(&MirrorLine {
+                    point: self.center,
+                    direction: Coord::new(1.0, 0.0).rotate(angle / 2.0),
+                })
+            }));
+        }
+        elements
+    }
+}
+
+// A seed shape and the copies generated from it, each with the element that
+// maps the seed onto it
+pub struct Orbit {
+    pub seed: ShapeObject,
+    pub images: Vec<(Element, ShapeObject)>,
+}
+
+impl Orbit {
+    pub fn new(seed: ShapeObject, elements: Vec<Element>) -> Result<Orbit, CanvasError> {
+        let mut orbit = Orbit {
+            seed,
+            images: Vec::new(),
+        };
+        for element in elements {
+            orbit.add_image(element)?;
+        }
+        Ok(orbit)
+    }
+
+    pub fn add_image(&mut self, element: Element) -> Result<ShapeObject, CanvasError> {
+        let image = element.image(&*self.seed.lock()?);
+        self.images.push((element, image.clone()));
+        Ok(image)
+    }
+
+    // The seed, then its images
+    pub fn members(&self) -> impl Iterator<Item = &ShapeObject> {
+        std::iter::once(&self.seed).chain(self.images.iter().map(|(_, image)| image))
+    }
+
+    pub fn contains(&self, shape: &ShapeObject) -> bool {
+        Arc::ptr_eq(&self.seed, shape) || self.images.iter().any(|(_, s)| Arc::ptr_eq(s, shape))
+    }
+
+    // The element mapping the seed onto a member of the orbit
+    pub fn element_of(&self, member: &ShapeObject) -> Option<Element> {
+        if Arc::ptr_eq(&self.seed, member) {
+            return Some(Element::rotation(Coord::default(), 0.0));
+        }
+        self.images
+            .iter()
+            .find(|(_, image)| Arc::ptr_eq(image, member))
+            .map(|(element, _)| *element)
+    }
+
+    // Moves the given member of the orbit and everything else with it. The
+    // seed stays locked until the images have moved too, so moves from
+    // different threads can't interleave and leave the copies out of step.
+    pub fn set_origin(&self, member: &ShapeObject, origin: Coord) -> Result<(), CanvasError> {
+        let mut seed = self.seed.lock()?;
+        self.move_locked(&mut seed, member, origin)
+    }
+    // Moves the orbit only if the member is still at the expected origin
+    pub fn compare_and_set_origin(
+        &self,
+        member: &ShapeObject,
+        expected: Coord,
+        origin: Coord,
+    ) -> Result<bool, CanvasError> {
+        let mut seed = self.seed.lock()?;
+        let current = match Arc::ptr_eq(&self.seed, member) {
+            true => seed.origin(),
+            false => member.lock()?.origin(),
+        };
+        if current != expected {
+            return Ok(false);
+        }
+        self.move_locked(&mut seed, member, origin)?;
+        Ok(true)
+    }
+    // Images whose locks are poisoned are left behind, and the rest moved
+    // before saying so
+    fn move_locked(
+        &self,
+        seed: &mut ShapeGuard<'_, dyn Shape + Send + Sync>,
+        member: &ShapeObject,
+        origin: Coord,
+    ) -> Result<(), CanvasError> {
+        let seed_origin = self
+            .element_of(member)
+            .map_or(origin, |element| element.inverse().map(origin));
+        seed.set_origin(seed_origin);
+        let mut moved = Ok(());
+        for (element, image) in &self.images {
+            match image.lock() {
+                Ok(mut image) => image.set_origin(element.map(seed_origin)),
+                Err(_) => moved = Err(CanvasError::Poisoned),
+            }
+        }
+        moved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    #[test]
+    fn dihedral_elements() {
+        let symmetry = Symmetry {
+            group: SymmetryGroup::Dihedral(4),
+            center: Coord::new(1.0, 1.0),
+        };
+        let elements = symmetry.elements();
+        assert_eq!(elements.len(), 7);
+
+        let point = Coord::new(3.0, 2.0);
+        for element in &elements {
+            let mapped = element.map(point);
+            assert_approx_eq!(mapped.distance(symmetry.center), 5.0f32.sqrt());
+            assert!(element.inverse().map(mapped).distance(point) < 1e-5);
+        }
+    }
+
+    #[test]
+    fn compose_reflections() {
+        let vertical = Element::reflection(&MirrorLine {
+            point: Coord::new(1.0, 0.0),
+            direction: Coord::new(0.0, 1.0),
+        });
+        let horizontal = Element::reflection(&MirrorLine {
+            point: Coord::new(0.0, 2.0),
+            direction: Coord::new(3.0, 0.0),
+        });
+        assert!(
+            vertical
+                .map(Coord::new(3.0, 5.0))
+                .distance(Coord::new(-1.0, 5.0))
+                < 1e-5
+        );
+
+        // Two perpendicular mirrors make a half turn about where they cross
+        let half_turn = horizontal.after(&vertical);
+        assert!(!half_turn.mirrored);
+        assert!(
+            half_turn
+                .map(Coord::new(3.0, 5.0))
+                .distance(Coord::new(-1.0, -1.0))
+                < 1e-5
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::ops::Range;
+
+use crate::{
+    generator::{random_canvas, random_shape, random_shapes, GeneratorConfig, Placement, Rng},
+    storage::{ShapeEnum, ShapeKind},
+    Canvas, Coord, Rectangle, Shape,
+};
+
+pub const KINDS: [ShapeKind; 6] = [
+    ShapeKind::Circle,
+    ShapeKind::Rectangle,
+    ShapeKind::Triangle,
+    ShapeKind::Square,
+    ShapeKind::Ellipse,
+    ShapeKind::Polygon,
+];
+
+// The scenes the tests, property tests and benchmarks are run over, so they
+// all measure the same thing. Density is the share of the area around the
+// origin the shapes would cover if none overlapped, so canvases of any size
+// with the same density are as crowded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scene {
+    pub count: usize,
+    pub density: f32,
+    // Range for each dimension: radius, sides, base and height
+    pub size: Range<f32>,
+    pub kinds: Vec<ShapeKind>,
+}
+
+impl Default for Scene {
+    fn default() -> Scene {
+        Scene {
+            count: 100,
+            density: 0.5,
+            size: 1.0..5.0,
+            kinds: KINDS.to_vec(),
+        }
+    }
+}
+
+impl Scene {
+    pub fn of(count: usize) -> Scene {
+        Scene {
+            count,
+            ..Scene::default()
+        }
+    }
+
+    // The square the shapes are placed in, centred on the origin
+    pub fn bounds(&self) -> Rectangle {
+        // The mean area over a fixed sample of the kinds and sizes
+        let mut rng = Rng::new(0);
+        let mean = (0..64)
+            .filter_map(|i| self.kinds.get(i % self.kinds.len()))
+            .map(|&kind| {
+                random_shape(&mut rng, kind, Coord::default(), self.size.clone()).get_area()
+            })
+            .sum::<f32>()
+            / 64.0;
+        let side = (self.count as f32 * mean / self.density)
+            .sqrt()
+            .max(self.size.end);
+        Rectangle {
+            origin: Coord::default(),
+            side_a: side,
+            side_b: side,
+        }
+    }
+    pub fn generator(&self) -> GeneratorConfig {
+        GeneratorConfig {
+            count: self.count,
+            weights: self.kinds.iter().map(|&kind| (kind, 1.0)).collect(),
+            size: self.size.clone(),
+            placement: Placement::Uniform {
+                bounds: self.bounds(),
+            },
+        }
+    }
+
+    pub fn shapes(&self, seed: u64) -> Vec<ShapeEnum> {
+        random_shapes(seed, &self.generator())
+    }
+    pub fn canvas(&self, seed: u64) -> Canvas {
+        random_canvas(seed, &self.generator())
+    }
+}
+
+// One shape of any kind, anywhere from -50 to 50 and from tiny to large,
+// for checking properties that should hold for every shape
+pub fn shape(rng: &mut Rng) -> ShapeEnum {
+    let kind = KINDS[rng.below(KINDS.len())];
+    let origin = Coord::new(rng.range(-50.0..50.0), rng.range(-50.0..50.0));
+    random_shape(rng, kind, origin, 0.1..20.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scenes_are_repeatable() {
+        let scene = Scene::of(400);
+        assert_eq!(scene.shapes(7), scene.shapes(7));
+        assert_ne!(scene.shapes(7), scene.shapes(8));
+        assert_eq!(scene.canvas(7).len(), 400);
+        assert_eq!(shape(&mut Rng::new(3)), shape(&mut Rng::new(3)));
+
+        // Covering about the same share of the bounds at any size
+        for count in [100, 10_000] {
+            let scene = Scene::of(count);
+            let bounds = scene.bounds();
+            let covered: f32 = scene.shapes(1).iter().map(|shape| shape.get_area()).sum();
+            let density = covered / bounds.get_area();
+            assert!((0.4..0.6).contains(&density), "{count} shapes: {density}");
+        }
+        let sparse = Scene {
+            density: 0.05,
+            ..Scene::of(100)
+        };
+        assert!(sparse.bounds().side_a > Scene::of(100).bounds().side_a * 3.0);
+    }
+}
@@ -0,0 +1,236 @@
+use std::f32::consts::PI;
+
+use crate::{
+    error::CanvasError,
+    symmetry::{Element, MirrorLine},
+    Canvas, Coord, Rectangle, ShapeObject,
+};
+
+// Translations generated by two basis vectors
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lattice {
+    pub a: Coord,
+    pub b: Coord,
+}
+
+impl Lattice {
+    pub fn rectangular(width: f32, height: f32) -> Lattice {
+        Lattice {
+            a: Coord::new(width, 0.0),
+            b: Coord::new(0.0, height),
+        }
+    }
+    pub fn square(size: f32) -> Lattice {
+        Lattice::rectangular(size, size)
+    }
+    // Basis vectors 60 degrees apart
+    pub fn hexagonal(size: f32) -> Lattice {
+        Lattice {
+            a: Coord::new(size, 0.0),
+            b: Coord::new(size, 0.0).rotate(PI / 3.0),
+        }
+    }
+
+    // Lattice coordinates of a point
+    fn coordinates(&self, point: Coord) -> Coord {
+        let determinant = self.a.cross(self.b);
+        Coord::new(
+            point.cross(self.b) / determinant,
+            self.a.cross(point) / determinant,
+        )
+    }
+}
+
+// The 17 plane symmetry groups, in international notation. The rectangular
+// groups (pm to cmm) expect a lattice with a along x and b along y, the
+// square groups a square lattice and the trigonal and hexagonal groups a
+// hexagonal one. p1 and p2 work with any lattice.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WallpaperGroup {
+    P1,
+    P2,
+    Pm,
+    Pg,
+    Cm,
+    Pmm,
+    Pmg,
+    Pgg,
+    Cmm,
+    P4,
+    P4m,
+    P4g,
+    P3,
+    P3m1,
+    P31m,
+    P6,
+    P6m,
+}
+
+impl WallpaperGroup {
+    // The symmetries within a single lattice cell, the identity first
+    pub fn elements(&self, lattice: &Lattice) -> Vec<Element> {
+        let (a, b) = (lattice.a, lattice.b);
+        let identity = rotations(1);
+        let mirror =
+            |point: Coord, direction: Coord| Element::reflection(&MirrorLine { point, direction });
+        // Reflect in the line, then slide along it
+        let glide = |point: Coord, direction: Coord, shift: Coord| {
+            translation(shift).after(&mirror(point, direction))
+        };
+        let with = |mut elements: Vec<Element>, extra: Vec<Element>| {
+            let composed: Vec<Element> = extra
+                .iter()
+                .flat_map(|e| elements.iter().map(move |base| e.after(base)))
+                .collect();
+            elements.extend(composed);
+            elements
+        };
+        let centred = |elements: Vec<Element>| with(elements, vec![translation((a + b) * 0.5)]);
+
+        match self {
+            WallpaperGroup::P1 => identity,
+            WallpaperGroup::P2 => rotations(2),
+            WallpaperGroup::Pm => with(identity, vec![mirror(Coord::default(), b)]),
+            WallpaperGroup::Pg => with(identity, vec![glide(Coord::default(), b, b * 0.5)]),
+            WallpaperGroup::Cm => centred(with(identity, vec![mirror(Coord::default(), b)])),
+            WallpaperGroup::Pmm => with(rotations(2), vec![mirror(Coord::default(), a)]),
+            // Mirrors a quarter cell away from the two-fold centres
+            WallpaperGroup::Pmg => with(rotations(2), vec![mirror(a * 0.25, b)]),
+            WallpaperGroup::Pgg => with(rotations(2), vec![glide(b * 0.25, a, a * 0.5)]),
+            WallpaperGroup::Cmm => centred(with(rotations(2), vec![mirror(Coord::default(), a)])),
+            WallpaperGroup::P4 => rotations(4),
+            WallpaperGroup::P4m => with(rotations(4), vec![mirror(Coord::default(), a)]),
+            // Diagonal mirrors through the two-fold centres only
+            WallpaperGroup::P4g => with(rotations(4), vec![mirror(a * 0.5, a + b)]),
+            WallpaperGroup::P3 => rotations(3),
+            // Mirrors through every three-fold centre
+            WallpaperGroup::P3m1 => with(rotations(3), vec![mirror(Coord::default(), a + b)]),
+            WallpaperGroup::P31m => with(rotations(3), vec![mirror(Coord::default(), a)]),
+            WallpaperGroup::P6 => rotations(6),
+            WallpaperGroup::P6m => with(rotations(6), vec![mirror(Coord::default(), a)]),
+        }
+    }
+}
+
+fn rotations(n: u32) -> Vec<Element> {
+    (0..n)
+        .map(|k| Element::rotation(Coord::default(), 2.0 * PI * k as f32 / n as f32))
+        .collect()
+}
+
+fn translation(offset: Coord) -> Element {
+    Element {
+        angle: 0.0,
+        mirrored: false,
+        offset,
+    }
+}
+
+// Copies of the motif under every symmetry of the group and every lattice
+// translation, keeping those whose bounds reach into the target. Fails
+// with Poisoned if a motif shape's lock is.
+pub fn tile(
+    motif: &[ShapeObject],
+    group: WallpaperGroup,
+    lattice: &Lattice,
+    target: &Rectangle,
+) -> Result<Canvas, CanvasError> {
+    let elements = group.elements(lattice);
+    let motif: Vec<(ShapeObject, Rectangle)> = motif
+        .iter()
+        .map(|shape| Ok((shape.clone(), shape.lock()?.bounding_box())))
+        .collect::<Result<_, CanvasError>>()?;
+
+    // Cells far enough out that no image can reach back into the target
+    let reach = motif
+        .iter()
+        .map(|(_, bounds)| bounds.min().length().max(bounds.max().length()))
+        .fold(0.0, f32::max)
+        + lattice.a.length()
+        + lattice.b.length();
+    let padding = Coord::new(reach, reach);
+    let (min, max) = (target.min() - padding, target.max() + padding);
+    let corners = [min, Coord::new(max.x, min.y), max, Coord::new(min.x, max.y)]
+        .map(|corner| lattice.coordinates(corner));
+    let range = |axis: fn(&Coord) -> f32| {
+        let low = corners.iter().map(axis).fold(f32::INFINITY, f32::min);
+        let high = corners.iter().map(axis).fold(f32::NEG_INFINITY, f32::max);
+        low.floor() as i32..=high.ceil() as i32
+    };
+
+    let mut canvas = Canvas::default();
+    for i in range(|c| c.x) {
+        for j in range(|c| c.y) {
+            let shift = translation(lattice.a * i as f32 + lattice.b * j as f32);
+            for element in &elements {
+                let element = shift.after(element);
+                for (shape, _) in &motif {
+                    let image = element.image(&*shape.lock()?);
+                    let bounds = image.lock()?.bounding_box();
+                    if bounds.distance_to_box(target) == 0.0 {
+                        canvas.add(image);
+                    }
+                }
+            }
+        }
+    }
+    Ok(canvas)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{assert_approx_eq, Circle, ShapeCell, Triangle};
+
+    #[test]
+    fn groups_have_expected_order() {
+        let rectangular = Lattice::rectangular(2.0, 1.0);
+        let square = Lattice::square(1.0);
+        let hexagonal = Lattice::hexagonal(1.0);
+        let orders = [
+            (WallpaperGroup::P1, rectangular, 1),
+            (WallpaperGroup::Pg, rectangular, 2),
+            (WallpaperGroup::Cmm, rectangular, 8),
+            (WallpaperGroup::Pgg, rectangular, 4),
+            (WallpaperGroup::P4g, square, 8),
+            (WallpaperGroup::P31m, hexagonal, 6),
+            (WallpaperGroup::P6m, hexagonal, 12),
+        ];
+        for (group, lattice, order) in orders {
+            assert_eq!(group.elements(&lattice).len(), order, "{group:?}");
+        }
+    }
+
+    #[test]
+    fn tile_fills_target() {
+        let motif: Vec<ShapeObject> = vec![Arc::new(ShapeCell::new(Circle {
+            origin: Coord::new(0.25, 0.25),
+            radius: 0.1,
+        }))];
+        let target = Rectangle {
+            origin: Coord::new(2.0, 2.0),
+            side_a: 4.0,
+            side_b: 4.0,
+        };
+
+        // A circle in each quarter of each of the 16 unit cells
+        let canvas = tile(&motif, WallpaperGroup::Pmm, &Lattice::square(1.0), &target).unwrap();
+        assert_eq!(canvas.len(), 64);
+
+        let motif: Vec<ShapeObject> = vec![Arc::new(ShapeCell::new(Triangle {
+            origin: Coord::new(0.3, 0.1),
+            base: 0.2,
+            height: 0.1,
+        }))];
+        let canvas = tile(&motif, WallpaperGroup::P4, &Lattice::square(1.0), &target).unwrap();
+        let total: f32 = canvas
+            .ids()
+            .iter()
+            .filter_map(|&id| canvas.get_area(id))
+            .sum();
+        assert_approx_eq!(total, canvas.len() as f32 * 0.01);
+        assert!(canvas.len() >= 64);
+    }
+}
@@ -0,0 +1,114 @@
+// span!(LEVEL, "name", fields...) enters a span until the end of the
+// enclosing block, and event!(LEVEL, fields..., "message") records an
+// event, both through the tracing crate with the tracing feature on. Without
+// it they expand to nothing, so their fields mustn't be the only use of a
+// variable.
+
+#[cfg(feature = "tracing")]
+macro_rules! span {
+    ($level:ident, $($arg:tt)+) => {
+        let _span = tracing::span!(tracing::Level::$level, $($arg)+).entered();
+    };
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! span {
+    ($($arg:tt)+) => {};
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {
+        tracing::event!(tracing::Level::$level, $($arg)+)
+    };
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! event {
+    ($($arg:tt)+) => {};
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Barrier, Mutex,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata, Subscriber,
+    };
+
+    use crate::{Canvas, Circle, Coord, ShapeCell};
+
+    // Keeps the message of every event, and counts spans
+    #[derive(Default)]
+    struct Messages {
+        messages: Mutex<Vec<String>>,
+        spans: AtomicUsize,
+    }
+
+    struct Message<'a>(&'a mut String);
+
+    impl Visit for Message<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                *self.0 = format!("{value:?}");
+            }
+        }
+    }
+
+    impl Subscriber for Messages {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(self.spans.fetch_add(1, Ordering::Relaxed) as u64 + 1)
+        }
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut message = String::new();
+            event.record(&mut Message(&mut message));
+            self.messages.lock().unwrap().push(message);
+        }
+        fn enter(&self, _: &span::Id) {}
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    #[test]
+    fn changes_and_lock_waits_are_traced() {
+        let messages = Arc::new(Messages::default());
+        tracing::subscriber::with_default(messages.clone(), || {
+            let mut canvas = Canvas::default();
+            let shape = Arc::new(ShapeCell::new(Circle {
+                origin: Coord::default(),
+                radius: 1.0,
+            }));
+            let id = canvas.add(shape.clone());
+            canvas.translate_all((1.0, 0.0)).unwrap();
+
+            // Held by one thread while this one waits for it
+            let barrier = Arc::new(Barrier::new(2));
+            let holder = {
+                let (shape, barrier) = (shape.clone(), barrier.clone());
+                thread::spawn(move || {
+                    let _guard = shape.lock().unwrap();
+                    barrier.wait();
+                    thread::sleep(Duration::from_millis(20));
+                })
+            };
+            barrier.wait();
+            canvas.set_origin(id, (2.0, 0.0)).unwrap();
+            holder.join().unwrap();
+        });
+        assert!(messages.spans.load(Ordering::Relaxed) > 0);
+        let messages = messages.messages.lock().unwrap();
+        for message in ["added shape", "moving shape", "waited for shape lock"] {
+            assert!(messages.iter().any(|m| m == message), "{messages:?}");
+        }
+    }
+}
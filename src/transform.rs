@@ -0,0 +1,311 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+#[cfg(not(any(feature = "std", test)))]
+use crate::math::Float;
+use crate::{
+    builder::coordinate,
+    error::ShapeError,
+    geometry::{outline_points, Outline},
+    Coord, SecondMoments, Shape,
+};
+#[cfg(feature = "std")]
+use crate::{
+    cell::{ShapeCell, ShapeObject},
+    Circle, Polygon,
+};
+
+// An affine map of the plane, given by where it sends the unit x and y
+// vectors and the zero origin
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub x_axis: Coord,
+    pub y_axis: Coord,
+    pub offset: Coord,
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        x_axis: Coord { x: 1.0, y: 0.0 },
+        y_axis: Coord { x: 0.0, y: 1.0 },
+        offset: Coord { x: 0.0, y: 0.0 },
+    };
+
+    pub fn translation(offset: Coord) -> Transform {
+        Transform {
+            offset,
+            ..Transform::IDENTITY
+        }
+    }
+    // Counter-clockwise about the zero origin
+    pub fn rotation(angle: f32) -> Transform {
+        let (sin, cos) = angle.sin_cos();
+        Transform {
+            x_axis: Coord::new(cos, sin),
+            y_axis: Coord::new(-sin, cos),
+            ..Transform::IDENTITY
+        }
+    }
+    pub fn scale(x: f32, y: f32) -> Transform {
+        Transform {
+            x_axis: Coord::new(x, 0.0),
+            y_axis: Coord::new(0.0, y),
+            ..Transform::IDENTITY
+        }
+    }
+    // Moves x by x_factor * y, and y by y_factor * x
+    pub fn shear(x_factor: f32, y_factor: f32) -> Transform {
+        Transform {
+            x_axis: Coord::new(1.0, y_factor),
+            y_axis: Coord::new(x_factor, 1.0),
+            ..Transform::IDENTITY
+        }
+    }
+    // The same map, but fixing center rather than the zero origin
+    pub fn about(&self, center: Coord) -> Transform {
+        Transform::translation(center)
+            .after(self)
+            .after(&Transform::translation(-center))
+    }
+
+    pub fn map(&self, point: Coord) -> Coord {
+        self.map_vector(point) + self.offset
+    }
+    // Ignores the translation, for directions and offsets
+    pub fn map_vector(&self, vector: Coord) -> Coord {
+        self.x_axis * vector.x + self.y_axis * vector.y
+    }
+    // The transform applying `first` and then `self`
+    pub fn after(&self, first: &Transform) -> Transform {
+        Transform {
+            x_axis: self.map_vector(first.x_axis),
+            y_axis: self.map_vector(first.y_axis),
+            offset: self.map(first.offset),
+        }
+    }
+    // How areas scale, negative when the map mirrors
+    pub fn determinant(&self) -> f32 {
+        self.x_axis.cross(self.y_axis)
+    }
+    // None for maps that flatten the plane onto a line or point
+    pub fn inverse(&self) -> Option<Transform> {
+        let det = self.determinant();
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        let linear = Transform {
+            x_axis: Coord::new(self.y_axis.y, -self.x_axis.y) * (1.0 / det),
+            y_axis: Coord::new(-self.y_axis.x, self.x_axis.x) * (1.0 / det),
+            offset: Coord::default(),
+        };
+        Some(Transform {
+            offset: -linear.map_vector(self.offset),
+            ..linear
+        })
+    }
+    // Finite and invertible
+    pub fn validate(&self) -> Result<(), ShapeError> {
+        for axis in [self.x_axis, self.y_axis, self.offset] {
+            coordinate("transform", axis)?;
+        }
+        match self.inverse() {
+            Some(_) => Ok(()),
+            None => Err(ShapeError::SingularTransform),
+        }
+    }
+    // Circles stay circles under conformal maps and are sampled otherwise
+    pub fn map_outline(&self, outline: Outline) -> Outline {
+        if let (Outline::Circle { center, radius }, true) = (&outline, self.is_conformal()) {
+            return Outline::Circle {
+                center: self.map(*center),
+                radius: radius * self.x_axis.length(),
+            };
+        }
+        let mut points: Vec<Coord> = outline_points(outline)
+            .into_iter()
+            .map(|point| self.map(point))
+            .collect();
+        // Mirroring turns the winding around
+        if self.determinant() < 0.0 {
+            points.reverse();
+        }
+        Outline::Polygon(points)
+    }
+    // Second moments of the shape's image, about its centroid, which the
+    // map takes to the image's centroid
+    pub fn map_moments(&self, moments: SecondMoments) -> SecondMoments {
+        let (u, v) = (self.x_axis, self.y_axis);
+        let SecondMoments { ix, iy, ixy } = moments;
+        let det = self.determinant().abs();
+        SecondMoments {
+            ix: det * (u.y * u.y * iy + 2.0 * u.y * v.y * ixy + v.y * v.y * ix),
+            iy: det * (u.x * u.x * iy + 2.0 * u.x * v.x * ixy + v.x * v.x * ix),
+            ixy: det * (u.x * u.y * iy + (u.x * v.y + v.x * u.y) * ixy + v.x * v.y * ix),
+        }
+    }
+    // A transformed copy of the shape, a circle or polygon as with
+    // symmetry::Element::image
+    #[cfg(feature = "std")]
+    pub fn image(&self, shape: &dyn Shape) -> ShapeObject {
+        match self.map_outline(shape.outline()) {
+            Outline::Circle { center, radius } => Arc::new(ShapeCell::new(Circle {
+                origin: center,
+                radius,
+            })),
+            Outline::Polygon(points) => {
+                let origin = self.map(shape.origin());
+                Arc::new(ShapeCell::new(Polygon {
+                    origin,
+                    points: points.into_iter().map(|point| point - origin).collect(),
+                }))
+            }
+        }
+    }
+    // Rotations, mirrors, translations and uniform scales, which map circles
+    // onto circles
+    pub fn is_conformal(&self) -> bool {
+        let (x, y) = (self.x_axis, self.y_axis);
+        let scale = x.dot(x).max(y.dot(y));
+        (x.dot(y)).abs() <= 1e-6 * scale && (x.dot(x) - y.dot(y)).abs() <= 1e-6 * scale
+    }
+}
+
+// A shape drawn through a transform. The wrapped shape keeps its own
+// coordinates as they were; everything reported is after the transform.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transformed<S> {
+    pub shape: S,
+    pub transform: Transform,
+}
+
+impl<S: Shape> Transformed<S> {
+    pub fn new(shape: S, transform: Transform) -> Transformed<S> {
+        Transformed { shape, transform }
+    }
+}
+
+impl<S: Shape + Clone + Send + Sync + 'static> Shape for Transformed<S> {
+    fn origin(&self) -> Coord {
+        self.transform.map(self.shape.origin())
+    }
+    // Moves the transform rather than the shape under it
+    fn set_origin(&mut self, origin: Coord) {
+        self.transform.offset = self.transform.offset + (origin - self.origin());
+    }
+    fn get_area(&self) -> f32 {
+        self.shape.get_area() * self.transform.determinant().abs()
+    }
+    fn outline(&self) -> Outline {
+        self.transform.map_outline(self.shape.outline())
+    }
+    fn second_moment_of_area(&self) -> SecondMoments {
+        self.transform
+            .map_moments(self.shape.second_moment_of_area())
+    }
+    fn name(&self) -> &'static str {
+        self.shape.name()
+    }
+    // Those of the shape under the transform
+    fn properties(&self) -> &'static [&'static str] {
+        self.shape.properties()
+    }
+    fn get_property(&self, name: &str) -> Option<f32> {
+        self.shape.get_property(name)
+    }
+    fn set_property(&mut self, name: &str, value: f32) -> Result<(), ShapeError> {
+        self.shape.set_property(name, value)
+    }
+    fn heap_bytes(&self) -> usize {
+        self.shape.heap_bytes()
+    }
+    fn validate(&self) -> Result<(), ShapeError> {
+        self.shape.validate()?;
+        self.transform.validate()
+    }
+
+    #[cfg(feature = "std")]
+    fn duplicate(&self) -> ShapeObject {
+        Arc::new(ShapeCell::new(self.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    use super::*;
+    use crate::{assert_approx_eq, geometry, Circle, Rectangle};
+
+    fn close(a: Coord, b: Coord) -> bool {
+        a.distance(b) < 1e-5
+    }
+
+    #[test]
+    fn compose_and_invert() {
+        let point = Coord::new(2.0, -1.0);
+        let rotation = Transform::rotation(FRAC_PI_2).about(Coord::new(1.0, 1.0));
+        assert!(close(
+            rotation.map(Coord::new(2.0, 1.0)),
+            Coord::new(1.0, 2.0)
+        ));
+
+        let skewed = Transform::shear(0.5, 0.0).after(&Transform::scale(2.0, 3.0));
+        assert_eq!(skewed.map(point), Coord::new(2.5, -3.0));
+        assert_eq!(skewed.determinant(), 6.0);
+        let both = rotation.after(&skewed);
+        assert!(close(both.map(point), rotation.map(skewed.map(point))));
+        assert!(close(both.inverse().unwrap().map(both.map(point)), point));
+        assert_eq!(Transform::scale(1.0, 0.0).inverse(), None);
+        assert!(rotation.is_conformal() && !skewed.is_conformal());
+    }
+
+    #[test]
+    fn shapes_follow_transform() {
+        let square = Rectangle {
+            origin: Coord::default(),
+            side_a: 2.0,
+            side_b: 2.0,
+        };
+        // A diamond twice the size, centred on (5, 0)
+        let mut diamond = Transformed::new(
+            square,
+            Transform::translation(Coord::new(5.0, 0.0))
+                .after(&Transform::rotation(FRAC_PI_4))
+                .after(&Transform::scale(2.0, 2.0)),
+        );
+        assert!(close(diamond.origin(), Coord::new(5.0, 0.0)));
+        assert_approx_eq!(diamond.get_area(), 16.0);
+        let bounds = diamond.bounding_box();
+        assert_approx_eq!(bounds.side_a, 4.0 * 2f32.sqrt());
+        // Inside the diamond, though outside the square it came from
+        assert_eq!(diamond.distance_to(Coord::new(7.5, 0.0)), 0.0);
+        assert!(diamond.distance_to(Coord::new(7.0, 2.0)) > 0.0);
+
+        diamond.set_origin(Coord::new(0.0, 1.0));
+        assert!(close(diamond.origin(), Coord::new(0.0, 1.0)));
+        assert_eq!(diamond.shape.origin, Coord::default());
+
+        let circle = Circle {
+            origin: Coord::new(1.0, 0.0),
+            radius: 1.0,
+        };
+        let mirrored = circle.clone().transformed(Transform::scale(-3.0, 3.0));
+        assert!(matches!(mirrored.outline(), Outline::Circle { radius, .. } if radius == 3.0));
+        let stretched = Transformed::new(circle, Transform::scale(3.0, -1.0));
+        assert_approx_eq!(stretched.get_area(), 3.0 * PI);
+        assert!(
+            matches!(stretched.outline(), Outline::Polygon(p) if geometry::signed_area(&p) > 0.0)
+        );
+        assert_eq!(
+            Transformed::new(stretched.shape, Transform::scale(0.0, 1.0)).validate(),
+            Err(ShapeError::SingularTransform)
+        );
+    }
+}
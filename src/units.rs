@@ -0,0 +1,85 @@
+// Canvas coordinates are in pixels. A resolution in dots (pixels) per inch
+// ties them to physical sizes for print and cutting.
+pub const MM_PER_INCH: f32 = 25.4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unit {
+    Px,
+    Mm,
+    In,
+}
+
+impl Unit {
+    // How many pixels one of this unit is at the given resolution
+    pub fn pixels(self, dpi: Dpi) -> f32 {
+        match self {
+            Unit::Px => 1.0,
+            Unit::Mm => dpi.0 / MM_PER_INCH,
+            Unit::In => dpi.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dpi(pub f32);
+
+// The CSS reference resolution
+impl Default for Dpi {
+    fn default() -> Dpi {
+        Dpi(96.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Length {
+    pub value: f32,
+    pub unit: Unit,
+}
+
+impl Length {
+    pub fn new(value: f32, unit: Unit) -> Length {
+        Length { value, unit }
+    }
+    pub fn px(value: f32) -> Length {
+        Length::new(value, Unit::Px)
+    }
+    pub fn mm(value: f32) -> Length {
+        Length::new(value, Unit::Mm)
+    }
+    pub fn inches(value: f32) -> Length {
+        Length::new(value, Unit::In)
+    }
+
+    pub fn to_px(self, dpi: Dpi) -> f32 {
+        self.value * self.unit.pixels(dpi)
+    }
+    pub fn convert(self, unit: Unit, dpi: Dpi) -> Length {
+        Length::new(self.to_px(dpi) / unit.pixels(dpi), unit)
+    }
+}
+
+// An area in canvas pixels, in square units
+pub fn convert_area(area: f32, unit: Unit, dpi: Dpi) -> f32 {
+    let scale = unit.pixels(dpi);
+    area / (scale * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    #[test]
+    fn convert_lengths() {
+        let dpi = Dpi(300.0);
+        assert_eq!(Length::inches(2.0).to_px(dpi), 600.0);
+        assert_eq!(
+            Length::px(150.0).convert(Unit::In, dpi),
+            Length::inches(0.5)
+        );
+        let mm = Length::inches(1.0).convert(Unit::Mm, dpi);
+        assert_approx_eq!(mm.value, 25.4);
+        // 96 pixels square is one square inch at the default resolution
+        assert_eq!(convert_area(96.0 * 96.0, Unit::In, Dpi::default()), 1.0);
+    }
+}
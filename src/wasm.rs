@@ -0,0 +1,156 @@
+// Bindings for a browser front end through wasm-bindgen, which generates
+// the JavaScript glue: WasmCanvas is a class there, with a constructor and
+// the methods below. Shape ids cross as u64 (BigInt on the JavaScript
+// side), and methods that can fail say so with a bool or undefined.
+//
+// draw returns the canvas as a Float32Array of draw commands. Each command
+// is a kind (0 for a circle, 1 for a polygon), then five values shared by
+// both kinds
+//     id low bits, id high bits, fill, stroke, stroke width
+// and then the geometry
+//     center x, center y, radius    (circle)
+//     n, x0, y0, ..., xn-1, yn-1    (polygon)
+// The id halves are integers stored in f32 bit patterns, and so are the
+// colours, as 0xRRGGBBAA with zero for no fill or stroke. Gradient and
+// pattern fills come through as their first colour.
+use std::sync::Arc;
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{
+    error::ShapeError, geometry::Outline, style::Color, Canvas, Circle, Coord, Rectangle,
+    ShapeCell, ShapeId, ShapeObject, Triangle,
+};
+
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct WasmCanvas {
+    canvas: Canvas,
+}
+
+#[wasm_bindgen]
+impl WasmCanvas {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmCanvas {
+        WasmCanvas::default()
+    }
+
+    // Each add returns the new shape's id, or undefined if the sizes are
+    // invalid
+    pub fn add_circle(&mut self, x: f32, y: f32, radius: f32) -> Option<u64> {
+        let circle = Circle::builder()
+            .origin(Coord::new(x, y))
+            .radius(radius)
+            .build();
+        self.add(circle.map(|circle| Arc::new(ShapeCell::new(circle)) as ShapeObject))
+    }
+    pub fn add_rectangle(&mut self, x: f32, y: f32, side_a: f32, side_b: f32) -> Option<u64> {
+        let rectangle = Rectangle::builder()
+            .origin(Coord::new(x, y))
+            .side_a(side_a)
+            .side_b(side_b)
+            .build();
+        self.add(rectangle.map(|rectangle| Arc::new(ShapeCell::new(rectangle)) as ShapeObject))
+    }
+    pub fn add_triangle(&mut self, x: f32, y: f32, base: f32, height: f32) -> Option<u64> {
+        let triangle = Triangle::builder()
+            .origin(Coord::new(x, y))
+            .base(base)
+            .height(height)
+            .build();
+        self.add(triangle.map(|triangle| Arc::new(ShapeCell::new(triangle)) as ShapeObject))
+    }
+
+    pub fn remove(&mut self, id: u64) -> bool {
+        self.canvas.remove(ShapeId::from_bits(id)).is_some()
+    }
+    pub fn set_origin(&mut self, id: u64, x: f32, y: f32) -> bool {
+        self.canvas
+            .set_origin(ShapeId::from_bits(id), Coord::new(x, y))
+            .is_ok()
+    }
+    pub fn translate_all(&mut self, dx: f32, dy: f32) -> bool {
+        self.canvas.translate_all(Coord::new(dx, dy)).is_ok()
+    }
+    pub fn len(&self) -> usize {
+        self.canvas.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.canvas.is_empty()
+    }
+
+    // The topmost shape at the point
+    pub fn hit_test(&self, x: f32, y: f32) -> Option<u64> {
+        let hits = self.canvas.hit_test(Coord::new(x, y));
+        hits.first().map(|id| id.to_bits())
+    }
+
+    pub fn draw(&self) -> Vec<f32> {
+        let mut buffer = Vec::new();
+        for command in self.canvas.draw_commands() {
+            let bits = command.id.to_bits();
+            let color = |color: Option<Color>| {
+                f32::from_bits(color.map_or(0, |c| u32::from_be_bytes(c.into())))
+            };
+            let style = command.style;
+            let header = [
+                f32::from_bits(bits as u32),
+                f32::from_bits((bits >> 32) as u32),
+                color(style.fill_color()),
+                color(style.stroke_color()),
+                style.stroke_width,
+            ];
+            match command.outline {
+                Outline::Circle { center, radius } => {
+                    buffer.push(0.0);
+                    buffer.extend(header);
+                    buffer.extend([center.x, center.y, radius]);
+                }
+                Outline::Polygon(points) => {
+                    buffer.push(1.0);
+                    buffer.extend(header);
+                    buffer.push(points.len() as f32);
+                    buffer.extend(points.iter().flat_map(|p| [p.x, p.y]));
+                }
+            }
+        }
+        buffer
+    }
+}
+
+impl WasmCanvas {
+    fn add(&mut self, shape: Result<ShapeObject, ShapeError>) -> Option<u64> {
+        Some(self.canvas.add(shape.ok()?).to_bits())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drive_canvas_through_bindings() {
+        let mut canvas = WasmCanvas::new();
+        let circle = canvas.add_circle(0.0, 0.0, 1.0).unwrap();
+        let square = canvas.add_rectangle(5.0, 0.0, 2.0, 2.0).unwrap();
+        assert_eq!(canvas.add_triangle(0.0, 0.0, -1.0, 1.0), None);
+        assert_eq!(canvas.len(), 2);
+
+        assert_eq!(canvas.hit_test(5.5, 0.5), Some(square));
+        assert!(canvas.set_origin(circle, 5.0, 0.0));
+        // The circle was added first, so is painted under the square
+        assert_eq!(canvas.hit_test(5.0, 0.0), Some(square));
+        assert!(canvas.remove(square));
+        assert_eq!(canvas.hit_test(5.0, 0.0), Some(circle));
+        assert_eq!(canvas.hit_test(50.0, 0.0), None);
+
+        let buffer = canvas.draw();
+        let id = buffer[1].to_bits() as u64 | (buffer[2].to_bits() as u64) << 32;
+        assert_eq!(buffer.len(), 9);
+        assert_eq!(buffer[0], 0.0);
+        assert_eq!(id, circle);
+        assert_eq!(buffer[3].to_bits(), 0x0000_00ff);
+        assert_eq!(buffer[4].to_bits(), 0);
+        assert_eq!(buffer[6..], [5.0, 0.0, 1.0]);
+    }
+}